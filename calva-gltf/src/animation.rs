@@ -1,9 +1,27 @@
-use gltf::animation::util::ReadOutputs;
+use gltf::animation::{util::ReadOutputs, Interpolation};
 use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
-trait Interpolate {
+trait Interpolate: Copy + std::ops::Add<Output = Self> + std::ops::Mul<f32, Output = Self> {
     fn interpolate(a: Self, b: Self, alpha: f32) -> Self;
+
+    fn normalize(self) -> Self {
+        self
+    }
+
+    // https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#interpolation-cubic
+    fn cubic_spline(a: &Keyframe<Self>, b: &Keyframe<Self>, alpha: f32, delta: f32) -> Self {
+        let t = alpha;
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        let value = a.value * (2.0 * t3 - 3.0 * t2 + 1.0)
+            + a.out_tangent * (delta * (t3 - 2.0 * t2 + t))
+            + b.value * (-2.0 * t3 + 3.0 * t2)
+            + b.in_tangent * (delta * (t3 - t2));
+
+        value.normalize()
+    }
 }
 
 impl Interpolate for glam::Vec3 {
@@ -16,28 +34,112 @@ impl Interpolate for glam::Quat {
     fn interpolate(a: Self, b: Self, alpha: f32) -> Self {
         glam::Quat::slerp(if glam::Quat::dot(a, b) < 0.0 { -a } else { a }, b, alpha)
     }
+
+    fn normalize(self) -> Self {
+        glam::Quat::normalize(self)
+    }
+}
+
+/// Rate at which animations are baked into joints matrices, see
+/// [`AnimationsManager`](renderer::AnimationsManager) for the memory cost
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimationSampleRate {
+    /// Frames sampled a fixed number of times per second
+    Fixed(f32),
+    /// Frames sampled at the shortest interval between two source keyframes,
+    /// so that evenly spaced keyframes are baked as is. Capped to
+    /// [`AnimationSampleRate::MAX_KEYFRAMES_SAMPLES_PER_SEC`].
+    Keyframes,
+}
+
+impl AnimationSampleRate {
+    pub const MAX_KEYFRAMES_SAMPLES_PER_SEC: f32 = 60.0;
+}
+
+impl Default for AnimationSampleRate {
+    fn default() -> Self {
+        Self::Fixed(renderer::AnimationsManager::DEFAULT_SAMPLES_PER_SEC)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Keyframe<T> {
+    in_tangent: T,
+    value: T,
+    out_tangent: T,
+}
+
+impl<T: Interpolate> Keyframe<T> {
+    fn new(value: T) -> Self {
+        Self {
+            in_tangent: value * 0.0,
+            value,
+            out_tangent: value * 0.0,
+        }
+    }
 }
 
-struct ChannelSampler<T>(BTreeMap<Duration, T>);
+struct ChannelSampler<T> {
+    interpolation: Interpolation,
+    keyframes: BTreeMap<Duration, Keyframe<T>>,
+}
+
+impl<T: Interpolate> ChannelSampler<T> {
+    fn new(
+        interpolation: Interpolation,
+        times: impl IntoIterator<Item = Duration>,
+        values: impl IntoIterator<Item = T>,
+    ) -> Self {
+        let keyframes = if interpolation == Interpolation::CubicSpline {
+            // Cubic spline outputs are stored as (in-tangent, value, out-tangent) triplets
+            let values = values.into_iter().collect::<Vec<_>>();
+
+            times
+                .into_iter()
+                .zip(values.chunks_exact(3).map(|triplet| Keyframe {
+                    in_tangent: triplet[0],
+                    value: triplet[1],
+                    out_tangent: triplet[2],
+                }))
+                .collect()
+        } else {
+            times
+                .into_iter()
+                .zip(values.into_iter().map(Keyframe::new))
+                .collect()
+        };
 
-impl<T: Interpolate + Copy> ChannelSampler<T> {
-    fn first(&self) -> (&Duration, &T) {
-        self.0.range(..).next().unwrap()
+        Self {
+            interpolation,
+            keyframes,
+        }
+    }
+
+    fn linear(keyframes: impl IntoIterator<Item = (Duration, T)>) -> Self {
+        let (times, values): (Vec<_>, Vec<_>) = keyframes.into_iter().unzip();
+        Self::new(Interpolation::Linear, times, values)
+    }
+
+    fn first(&self) -> (&Duration, &Keyframe<T>) {
+        self.keyframes.range(..).next().unwrap()
     }
 
-    fn last(&self) -> (&Duration, &T) {
-        self.0.range(..).rev().next().unwrap()
+    fn last(&self) -> (&Duration, &Keyframe<T>) {
+        self.keyframes.range(..).next_back().unwrap()
     }
 
-    fn closest_before(&self, time: &Duration) -> (&Duration, &T) {
-        self.0
-            .range(..time)
+    fn closest_before(&self, time: &Duration) -> (&Duration, &Keyframe<T>) {
+        self.keyframes
+            .range(..=time)
             .next_back()
             .unwrap_or_else(|| self.first())
     }
 
-    fn closest_after(&self, time: &Duration) -> (&Duration, &T) {
-        self.0.range(time..).next().unwrap_or_else(|| self.last())
+    fn closest_after(&self, time: &Duration) -> (&Duration, &Keyframe<T>) {
+        self.keyframes
+            .range(time..)
+            .next()
+            .unwrap_or_else(|| self.last())
     }
 
     pub fn get_value(&self, time: &Duration) -> T {
@@ -45,18 +147,30 @@ impl<T: Interpolate + Copy> ChannelSampler<T> {
         let after = self.closest_after(time);
 
         if before.0 == after.0 {
-            return *before.1;
+            return before.1.value;
         }
 
-        let alpha = (time.as_secs_f32() - before.0.as_secs_f32())
-            / (after.0.as_secs_f32() - before.0.as_secs_f32());
+        let delta = after.0.as_secs_f32() - before.0.as_secs_f32();
+        let alpha = (time.as_secs_f32() - before.0.as_secs_f32()) / delta;
 
-        T::interpolate(*before.1, *after.1, alpha)
+        match self.interpolation {
+            Interpolation::Step => before.1.value,
+            Interpolation::Linear => T::interpolate(before.1.value, after.1.value, alpha),
+            Interpolation::CubicSpline => T::cubic_spline(before.1, after.1, alpha, delta),
+        }
     }
 
     pub fn get_time_range(&self) -> (Duration, Duration) {
         (*self.first().0, *self.last().0)
     }
+
+    fn min_interval(&self) -> Option<Duration> {
+        self.keyframes
+            .keys()
+            .zip(self.keyframes.keys().skip(1))
+            .map(|(a, b)| *b - *a)
+            .min()
+    }
 }
 
 struct NodeSampler {
@@ -74,9 +188,9 @@ impl NodeSampler {
         let scale = glam::Vec3::from(scale);
 
         Self {
-            translations: ChannelSampler([(Duration::default(), translation)].into()),
-            rotations: ChannelSampler([(Duration::default(), rotation)].into()),
-            scales: ChannelSampler([(Duration::default(), scale)].into()),
+            translations: ChannelSampler::linear([(Duration::default(), translation)]),
+            rotations: ChannelSampler::linear([(Duration::default(), rotation)]),
+            scales: ChannelSampler::linear([(Duration::default(), scale)]),
         }
     }
 
@@ -100,6 +214,17 @@ impl NodeSampler {
             |acc, (start, end)| (acc.0.min(start), acc.1.max(end)),
         )
     }
+
+    fn min_interval(&self) -> Option<Duration> {
+        [
+            self.translations.min_interval(),
+            self.rotations.min_interval(),
+            self.scales.min_interval(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
 }
 
 pub struct AnimationSampler {
@@ -120,43 +245,31 @@ impl AnimationSampler {
                 .map(Duration::from_secs_f32)
                 .collect::<Vec<_>>();
 
+            let interpolation = channel.sampler().interpolation();
+
             let target_node = channel.target().node();
-            let mut sampler = samplers
+            let sampler = samplers
                 .entry(target_node.index())
                 .or_insert_with(|| NodeSampler::from_node_default(target_node));
 
             match reader.read_outputs().unwrap() {
                 ReadOutputs::Translations(translations) => {
-                    sampler.translations = ChannelSampler(
-                        keyframes
-                            .iter()
-                            .copied()
-                            .zip(translations.map(glam::Vec3::from))
-                            .collect(),
+                    sampler.translations = ChannelSampler::new(
+                        interpolation,
+                        keyframes,
+                        translations.map(glam::Vec3::from),
                     );
                 }
                 ReadOutputs::Rotations(rotations) => {
-                    let it = match rotations {
-                        gltf::animation::util::Rotations::F32(it) => it,
-                        _ => unimplemented!(),
-                    };
-
-                    sampler.rotations = ChannelSampler(
-                        keyframes
-                            .iter()
-                            .copied()
-                            .zip(it.map(glam::Quat::from_array))
-                            .collect(),
+                    sampler.rotations = ChannelSampler::new(
+                        interpolation,
+                        keyframes,
+                        rotations.into_f32().map(glam::Quat::from_array),
                     );
                 }
                 ReadOutputs::Scales(scales) => {
-                    sampler.scales = ChannelSampler(
-                        keyframes
-                            .iter()
-                            .copied()
-                            .zip(scales.map(glam::Vec3::from))
-                            .collect(),
-                    );
+                    sampler.scales =
+                        ChannelSampler::new(interpolation, keyframes, scales.map(glam::Vec3::from));
                 }
                 _ => unimplemented!(),
             }
@@ -215,6 +328,35 @@ impl AnimationSampler {
                 |acc, (start, end)| (acc.0.min(start), acc.1.max(end)),
             )
     }
+
+    pub fn samples_per_sec(&self, rate: AnimationSampleRate) -> f32 {
+        match rate {
+            AnimationSampleRate::Fixed(samples_per_sec) => samples_per_sec,
+            AnimationSampleRate::Keyframes => self
+                .samplers
+                .values()
+                .filter_map(NodeSampler::min_interval)
+                .min()
+                .filter(|interval| !interval.is_zero())
+                .map_or(
+                    renderer::AnimationsManager::DEFAULT_SAMPLES_PER_SEC,
+                    |interval| 1.0 / interval.as_secs_f32(),
+                )
+                .min(AnimationSampleRate::MAX_KEYFRAMES_SAMPLES_PER_SEC),
+        }
+    }
+
+    /// Times at which frames are baked, from the start to the end of the
+    /// animation included
+    pub fn sample_times(&self, samples_per_sec: f32) -> impl Iterator<Item = Duration> {
+        let (start, end) = self.get_time_range();
+
+        // Tolerates rounding errors so that the end keyframe is not dropped
+        let frames = ((end - start).as_secs_f32() * samples_per_sec + 1e-3).floor() as u32 + 1;
+
+        (0..frames)
+            .map(move |frame| start + Duration::from_secs_f32(frame as f32 / samples_per_sec))
+    }
 }
 
 #[cfg(test)]
@@ -225,15 +367,12 @@ mod tests {
     #[test]
     fn it_works() {
         let sampler = NodeSampler {
-            translations: ChannelSampler(
-                [
-                    (Duration::from_secs_f32(1.0), glam::Vec3::X * 10.0),
-                    (Duration::from_secs_f32(2.0), glam::Vec3::X * 20.0),
-                ]
-                .into(),
-            ),
-            rotations: ChannelSampler([(Duration::default(), glam::Quat::IDENTITY)].into()),
-            scales: ChannelSampler([(Duration::default(), glam::Vec3::ONE)].into()),
+            translations: ChannelSampler::linear([
+                (Duration::from_secs_f32(1.0), glam::Vec3::X * 10.0),
+                (Duration::from_secs_f32(2.0), glam::Vec3::X * 20.0),
+            ]),
+            rotations: ChannelSampler::linear([(Duration::default(), glam::Quat::IDENTITY)]),
+            scales: ChannelSampler::linear([(Duration::default(), glam::Vec3::ONE)]),
         };
 
         assert_eq!(
@@ -251,4 +390,78 @@ mod tests {
             glam::Mat4::from_translation(glam::Vec3::X * 20.0)
         );
     }
+
+    #[test]
+    fn step_interpolation() {
+        let a = glam::Quat::IDENTITY;
+        let b = glam::Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+
+        let sampler = ChannelSampler::new(
+            Interpolation::Step,
+            [Duration::from_secs_f32(0.0), Duration::from_secs_f32(1.0)],
+            [a, b],
+        );
+
+        assert_eq!(sampler.get_value(&Duration::from_secs_f32(0.5)), a);
+        assert_eq!(sampler.get_value(&Duration::from_secs_f32(0.99)), a);
+        assert_eq!(sampler.get_value(&Duration::from_secs_f32(1.0)), b);
+    }
+
+    #[test]
+    fn sample_rates() {
+        let keyframes = |times: &[f32]| {
+            ChannelSampler::linear(
+                times
+                    .iter()
+                    .map(|&t| (Duration::from_secs_f32(t), glam::Vec3::ONE)),
+            )
+        };
+
+        let sampler = AnimationSampler {
+            samplers: HashMap::from([(
+                0,
+                NodeSampler {
+                    translations: keyframes(&[0.0, 0.1, 0.2, 0.3, 0.4]),
+                    rotations: ChannelSampler::linear([(
+                        Duration::default(),
+                        glam::Quat::IDENTITY,
+                    )]),
+                    scales: keyframes(&[0.0, 0.4]),
+                },
+            )]),
+        };
+
+        let rate = sampler.samples_per_sec(AnimationSampleRate::Keyframes);
+        assert!((rate - 10.0).abs() < 1e-3);
+        assert_eq!(sampler.sample_times(rate).count(), 5);
+
+        assert_eq!(
+            sampler.samples_per_sec(AnimationSampleRate::Fixed(5.0)),
+            5.0
+        );
+        assert_eq!(sampler.sample_times(5.0).count(), 3);
+        assert_eq!(
+            sampler.sample_times(5.0).last(),
+            Some(Duration::from_secs_f32(0.4))
+        );
+
+        // Keyframes closer than the cap are resampled
+        let dense = AnimationSampler {
+            samplers: HashMap::from([(
+                0,
+                NodeSampler {
+                    translations: keyframes(&[0.0, 0.001, 1.0]),
+                    rotations: ChannelSampler::linear([(
+                        Duration::default(),
+                        glam::Quat::IDENTITY,
+                    )]),
+                    scales: keyframes(&[0.0]),
+                },
+            )]),
+        };
+        assert_eq!(
+            dense.samples_per_sec(AnimationSampleRate::Keyframes),
+            AnimationSampleRate::MAX_KEYFRAMES_SAMPLES_PER_SEC
+        );
+    }
 }
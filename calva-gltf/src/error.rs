@@ -0,0 +1,95 @@
+/// Error raised while importing a glTF model
+#[derive(Debug)]
+pub enum GltfError {
+    /// An external file could not be read
+    Io(std::io::Error),
+    /// The document itself is malformed
+    Gltf(gltf::Error),
+    /// An embedded or external image could not be decoded
+    Image(image::ImageError),
+    /// A base64 data URI could not be decoded
+    Base64(base64::DecodeError),
+    /// The model relies on something the loader doesn't handle
+    UnsupportedFormat(String),
+    /// An external reference is not a relative path inside the asset
+    /// directory, or is not valid percent-encoded UTF-8
+    InvalidUri(String),
+    /// A buffer references the glb binary chunk, but there is none
+    MissingBinaryChunk,
+    /// The buffer at this index holds less bytes than declared
+    BufferTooShort(usize),
+    /// A texture references an image that doesn't exist
+    InvalidImageIndex(usize),
+    /// The image at this index points outside of its buffer view
+    InvalidImageData(usize),
+    /// A mesh primitive lacks a vertex attribute required by the renderer
+    MissingSemantic {
+        mesh: String,
+        semantic: gltf::Semantic,
+    },
+    /// The positions accessor of a mesh primitive has no valid min/max
+    MissingAccessorBounds { mesh: String },
+    /// Textures could not be uploaded
+    Texture(anyhow::Error),
+}
+
+impl std::fmt::Display for GltfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Cannot read file: {error}"),
+            Self::Gltf(error) => write!(f, "Invalid glTF: {error}"),
+            Self::Image(error) => write!(f, "Cannot decode image: {error}"),
+            Self::Base64(error) => write!(f, "Cannot decode data URI: {error}"),
+            Self::UnsupportedFormat(format) => write!(f, "Unsupported {format}"),
+            Self::InvalidUri(uri) => write!(f, "Invalid external reference: {uri}"),
+            Self::MissingBinaryChunk => write!(f, "Missing glb binary chunk"),
+            Self::BufferTooShort(index) => write!(f, "Buffer {index} is too short"),
+            Self::InvalidImageIndex(index) => write!(f, "Invalid image index {index}"),
+            Self::InvalidImageData(index) => write!(f, "Invalid image {index} buffer"),
+            Self::MissingSemantic { mesh, semantic } => {
+                write!(f, "Mesh [{mesh}] missing [{semantic:?}]")
+            }
+            Self::MissingAccessorBounds { mesh } => {
+                write!(f, "Mesh [{mesh}] missing positions accessor min/max")
+            }
+            Self::Texture(error) => write!(f, "Cannot create texture: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Gltf(error) => Some(error),
+            Self::Image(error) => Some(error),
+            Self::Base64(error) => Some(error),
+            Self::Texture(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GltfError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(error: gltf::Error) -> Self {
+        Self::Gltf(error)
+    }
+}
+
+impl From<image::ImageError> for GltfError {
+    fn from(error: image::ImageError) -> Self {
+        Self::Image(error)
+    }
+}
+
+impl From<base64::DecodeError> for GltfError {
+    fn from(error: base64::DecodeError) -> Self {
+        Self::Base64(error)
+    }
+}
@@ -1,58 +1,185 @@
 #![warn(clippy::all)]
 
-use anyhow::{anyhow, Result};
 use renderer::{
-    wgpu, AnimationId, AnimationsManager, Engine, Instance, Material, MaterialId, MaterialsManager,
-    MeshId, MeshesManager, PointLight, Renderer, SkinsManager, TextureId, TexturesManager,
+    wgpu, AlphaMode, AnimationId, AnimationsManager, Camera, Engine, Instance, Material,
+    MaterialId, MaterialsManager, MeshId, MeshesManager, PhotometricUnits, PointLight, Renderer,
+    SamplerConfig, Skeleton, SkeletonId, SkinsManager, TextureId, TextureTransform,
+    TexturesManager,
 };
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, HashMap, HashSet},
     io::Read,
-    time::Duration,
+    path::Path,
+    sync::Mutex,
 };
 
 mod animation;
+mod error;
+mod root_motion;
+
+pub use animation::AnimationSampleRate;
 use animation::*;
+pub use error::GltfError;
+pub use root_motion::RootMotion;
+
+/// Commonly used types, `use calva_gltf::prelude::*;`
+pub mod prelude {
+    pub use crate::{AnimationSampleRate, GltfError, GltfModel, GltfOptions, RootMotion};
+}
+
+/// Settings of a single model load, see [`GltfModel::new_with_options`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GltfOptions {
+    /// Lower rates save VRAM, e.g. for background characters
+    pub animation_sample_rate: AnimationSampleRate,
+    /// Name of the joint (usually the hips) whose ground motion is extracted
+    /// into [`GltfModel::root_motions`], animations then play in place
+    pub root_motion_node: Option<String>,
+}
 
+/// A loaded model is immutable: the document and the instances built from it
+/// are never modified after loading, which allows caching per scene data.
 pub struct GltfModel {
     pub doc: gltf::Document,
 
     meshes_instances: Vec<Vec<Instance>>,
+    /// `EXT_mesh_gpu_instancing` local transforms, by node index
+    gpu_instances: HashMap<usize, Vec<glam::Mat4>>,
     pub animations: HashMap<String, AnimationId>,
+    /// Extracted when [`GltfOptions::root_motion_node`] is set, by animation
+    /// name
+    pub root_motions: HashMap<String, RootMotion>,
+    /// Joints hierarchy of the animated skin, see
+    /// [`SkeletonDebugPass`](renderer::SkeletonDebugPass)
+    pub skeleton: Option<SkeletonId>,
+    /// Instances and lights of each scene relative to its root, by scene
+    /// index. Filled on first use by [`GltfModel::scene_instances`].
+    scenes_data: Mutex<HashMap<usize, SceneData>>,
+}
+
+/// Instances and point lights of a scene
+type SceneData = (Vec<Instance>, Vec<PointLight>);
+
+/// Baked animations of a skin
+#[derive(Default)]
+struct SkinAnimations {
+    skeleton: Option<SkeletonId>,
+    animations: HashMap<String, AnimationId>,
+    root_motions: HashMap<String, RootMotion>,
 }
 
 impl GltfModel {
-    pub fn from_path(renderer: &Renderer, engine: &mut Engine, path: &str) -> Result<Self> {
-        Self::from_reader(renderer, engine, &mut std::fs::File::open(path)?)
+    /// External buffers and images are resolved relative to the file directory
+    pub fn from_path(
+        renderer: &Renderer,
+        engine: &mut Engine,
+        path: &str,
+    ) -> Result<Self, GltfError> {
+        let path = Path::new(path);
+        let base = path.parent().unwrap_or_else(|| Path::new("./"));
+
+        Self::from_slice_with_base(renderer, engine, &std::fs::read(path)?, base)
     }
 
+    /// Only self-contained assets (glb or embedded base64) can be loaded, see
+    /// [`GltfModel::from_gltf`] for external references
     pub fn from_reader(
         renderer: &Renderer,
         engine: &mut Engine,
         reader: &mut dyn Read,
-    ) -> Result<Self> {
+    ) -> Result<Self, GltfError> {
         let mut gltf_buffer = Vec::new();
         reader.read_to_end(&mut gltf_buffer)?;
 
-        let (doc, buffers, images) = gltf::import_slice(&gltf_buffer)?;
+        Self::from_gltf(
+            renderer,
+            engine,
+            gltf::Gltf::from_slice(&gltf_buffer)?,
+            |uri| {
+                Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Unresolved external reference: {uri}"),
+                ))
+            },
+        )
+    }
 
-        Self::new(renderer, engine, doc, &buffers, &images)
+    /// External buffers and images are resolved relative to `base`
+    pub fn from_slice_with_base(
+        renderer: &Renderer,
+        engine: &mut Engine,
+        bytes: &[u8],
+        base: impl AsRef<Path>,
+    ) -> Result<Self, GltfError> {
+        Self::from_gltf(renderer, engine, gltf::Gltf::from_slice(bytes)?, |uri| {
+            std::fs::read(base.as_ref().join(uri))
+        })
+    }
+
+    /// `resolve` returns the content of the external buffers and images URIs,
+    /// e.g. fetched over the network. Data URIs and the glb binary chunk are
+    /// handled directly.
+    ///
+    /// KTX2 images (`KHR_texture_basisu`) are uploaded compressed when the
+    /// device supports their format, textures fall back to their `source`
+    /// image otherwise. Basis Universal payloads are not transcoded.
+    pub fn from_gltf(
+        renderer: &Renderer,
+        engine: &mut Engine,
+        gltf: gltf::Gltf,
+        resolve: impl FnMut(&str) -> std::io::Result<Vec<u8>>,
+    ) -> Result<Self, GltfError> {
+        let (doc, buffers, images) = import(gltf, resolve)?;
+
+        Self::from_images(renderer, engine, doc, &buffers, &images, Default::default())
     }
 
+    /// Images are already decoded, e.g. by [`gltf::import`] which doesn't
+    /// handle KTX2 images. Load from the asset bytes to upload their compressed
+    /// blocks instead, see [`GltfModel::from_gltf`].
     pub fn new(
         renderer: &Renderer,
         engine: &mut Engine,
         doc: gltf::Document,
         buffers: &[gltf::buffer::Data],
         images: &[gltf::image::Data],
-    ) -> Result<Self> {
+    ) -> Result<Self, GltfError> {
+        Self::new_with_options(renderer, engine, doc, buffers, images, Default::default())
+    }
+
+    pub fn new_with_options(
+        renderer: &Renderer,
+        engine: &mut Engine,
+        doc: gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: &[gltf::image::Data],
+        options: GltfOptions,
+    ) -> Result<Self, GltfError> {
+        let images = images
+            .iter()
+            .map(|image| Image::Decoded(Cow::Borrowed(image)))
+            .collect::<Vec<_>>();
+
+        Self::from_images(renderer, engine, doc, buffers, &images, options)
+    }
+
+    fn from_images(
+        renderer: &Renderer,
+        engine: &mut Engine,
+        doc: gltf::Document,
+        buffers: &[gltf::buffer::Data],
+        images: &[Image],
+        options: GltfOptions,
+    ) -> Result<Self, GltfError> {
         let textures = Self::build_textures(renderer, engine, &doc, images)?;
 
         let materials = Self::build_materials(renderer, engine, &doc, &textures)?;
 
         let meshes = Self::build_meshes(renderer, engine, &doc, buffers)?;
 
-        let skins_animations = Self::build_skin_animations(renderer, engine, &doc, buffers);
+        let skins_animations =
+            Self::build_skin_animations(renderer, engine, &doc, buffers, &options);
 
         let meshes_instances = doc
             .meshes()
@@ -67,20 +194,29 @@ impl GltfModel {
                             .and_then(|index| materials.get(index).copied())
                             .unwrap_or_default();
 
-                        Instance {
-                            mesh: mesh_id,
-                            material: material_id,
-                            ..Default::default()
-                        }
+                        Instance::new(glam::Mat4::IDENTITY)
+                            .with_mesh(mesh_id)
+                            .with_material(material_id)
                     })
                     .collect()
             })
             .collect();
 
+        let gpu_instances = doc
+            .nodes()
+            .filter_map(|node| Some((node.index(), node_gpu_instances(&doc, &node, buffers)?)))
+            .collect();
+
+        let skin_animations = skins_animations.into_iter().next().unwrap_or_default();
+
         Ok(Self {
             doc,
             meshes_instances,
-            animations: skins_animations.get(0).cloned().unwrap_or_default(),
+            gpu_instances,
+            animations: skin_animations.animations,
+            root_motions: skin_animations.root_motions,
+            skeleton: skin_animations.skeleton,
+            scenes_data: Default::default(),
         })
     }
 
@@ -88,130 +224,212 @@ impl GltfModel {
         renderer: &Renderer,
         engine: &mut Engine,
         doc: &gltf::Document,
-        images: &[gltf::image::Data],
-    ) -> Result<Vec<TextureId>> {
-        let textures = doc
+        images: &[Image],
+    ) -> Result<Vec<TextureIds>, GltfError> {
+        let usages = images_usages(doc);
+        let textures_manager = engine.resources.get::<TexturesManager>();
+
+        // All the images are uploaded with a single submit, and their views
+        // added at once
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("GltfModel textures upload"),
+            });
+        let mut views = vec![];
+
+        // Converted to RGBA in parallel, uploaded serially
+        let rgba_images = par_map(images.iter().collect(), |image| match image {
+            Image::Decoded(data) => rgba_image(data),
+            Image::Ktx2(_) => None,
+        });
+
+        let images_views = doc
             .images()
-            .map(|image| {
-                let image_data = images
+            .zip(usages)
+            .map(|(image, usage)| {
+                let texture = match images
                     .get(image.index())
-                    .ok_or_else(|| anyhow!("Invalid image index"))?;
-
-                // 3 channels texture formats are not supported by WebGPU
-                // https://github.com/gpuweb/gpuweb/issues/66
-                let buf = if image_data.format == gltf::image::Format::R8G8B8 {
-                    image::ImageBuffer::from_raw(
-                        image_data.width,
-                        image_data.height,
-                        image_data.pixels.clone(),
-                    )
-                    .map(image::DynamicImage::ImageRgb8)
-                } else {
-                    image::ImageBuffer::from_raw(
-                        image_data.width,
-                        image_data.height,
-                        image_data.pixels.clone(),
-                    )
-                    .map(image::DynamicImage::ImageRgba8)
-                }
-                .ok_or_else(|| anyhow!("Invalid image buffer"))?;
+                    .ok_or(GltfError::InvalidImageIndex(image.index()))?
+                {
+                    Image::Ktx2(bytes) => {
+                        let reader = ktx2::Reader::new(&bytes[..]);
+                        let ktx2 = reader
+                            .as_ref()
+                            .map_err(ToString::to_string)
+                            .and_then(|reader| ktx2_image(reader, renderer.device.features()));
+
+                        match ktx2 {
+                            Ok(ktx2) => Self::upload_ktx2(renderer, image.name(), &ktx2),
+                            // Only fails the load when a texture has no fallback
+                            Err(reason) => return Ok(Err(reason)),
+                        }
+                    }
+                    Image::Decoded(_) => {
+                        let buf = rgba_images
+                            .get(image.index())
+                            .ok_or(GltfError::InvalidImageIndex(image.index()))?
+                            .as_ref()
+                            .ok_or(GltfError::InvalidImageData(image.index()))?;
+
+                        let size = wgpu::Extent3d {
+                            width: buf.width(),
+                            height: buf.height(),
+                            depth_or_array_layers: 1,
+                        };
 
-                let size = wgpu::Extent3d {
-                    width: buf.width(),
-                    height: buf.height(),
-                    depth_or_array_layers: 1,
-                };
+                        let dimension = wgpu::TextureDimension::D2;
+                        let desc = wgpu::TextureDescriptor {
+                            label: image.name(),
+                            size,
+                            mip_level_count: size.max_mips(dimension),
+                            sample_count: 1,
+                            dimension,
+                            format: usage.format(),
+                            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                                | wgpu::TextureUsages::COPY_DST,
+                            view_formats: &[
+                                wgpu::TextureFormat::Rgba8Unorm,
+                                wgpu::TextureFormat::Rgba8UnormSrgb,
+                            ],
+                        };
 
-                let dimension = wgpu::TextureDimension::D2;
-                let desc = wgpu::TextureDescriptor {
-                    label: image.name(),
-                    size,
-                    mip_level_count: size.max_mips(dimension),
-                    sample_count: 1,
-                    dimension,
-                    format: wgpu::TextureFormat::Rgba8Unorm,
-                    usage: wgpu::TextureUsages::TEXTURE_BINDING
-                        | wgpu::TextureUsages::RENDER_ATTACHMENT
-                        | wgpu::TextureUsages::COPY_DST,
-                    view_formats: &[wgpu::TextureFormat::Rgba8Unorm],
-                };
+                        let texture = renderer.device.create_texture(&desc);
+
+                        renderer.queue.write_texture(
+                            texture.as_image_copy(),
+                            buf,
+                            wgpu::ImageDataLayout {
+                                offset: 0,
+                                bytes_per_row: Some(4 * size.width),
+                                rows_per_image: None,
+                            },
+                            size,
+                        );
 
-                let texture = renderer.device.create_texture(&desc);
+                        textures_manager
+                            .get()
+                            .encode_mipmaps(&renderer.device, &mut encoder, &texture, &desc)
+                            .map_err(GltfError::Texture)?;
 
-                renderer.queue.write_texture(
-                    texture.as_image_copy(),
-                    &buf.to_rgba8(),
-                    wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: Some(4 * size.width),
-                        rows_per_image: None,
-                    },
-                    size,
-                );
+                        texture
+                    }
+                };
 
-                engine
-                    .ressources
-                    .get::<TexturesManager>()
-                    .get()
-                    .generate_mipmaps(&renderer.device, &renderer.queue, &texture, &desc)?;
+                // A view for each color space the image is sampled in, as
+                // indices in `views`
+                let mut add_view = |format| {
+                    views.push(texture.create_view(&wgpu::TextureViewDescriptor {
+                        format: Some(format),
+                        ..Default::default()
+                    }));
+                    views.len() - 1
+                };
 
-                Ok(engine
-                    .ressources
-                    .get::<TexturesManager>()
-                    .get_mut()
-                    .add(&renderer.device, texture.create_view(&Default::default())))
+                let format = texture.format();
+                let srgb = usage.srgb.then(|| add_view(format.add_srgb_suffix()));
+                let linear =
+                    (usage.linear || !usage.srgb).then(|| add_view(format.remove_srgb_suffix()));
+
+                Ok(Ok((srgb, linear)))
+            })
+            .collect::<Result<Vec<_>, GltfError>>()?;
+
+        renderer.queue.submit(Some(encoder.finish()));
+
+        let ids = textures_manager.get_mut().add_all(views);
+        let id = |view: Option<usize>| view.map(|view| ids[view]).unwrap_or_default();
+        let textures = images_views
+            .into_iter()
+            .map(|views| {
+                views.map(|(srgb, linear)| TextureIds {
+                    srgb: id(srgb),
+                    linear: id(linear),
+                })
             })
-            .collect::<Result<Vec<_>>>()?;
+            .collect::<Vec<_>>();
 
         doc.textures()
             .map(|texture| {
-                textures
-                    .get(texture.source().index())
-                    .copied()
-                    .ok_or_else(|| anyhow!("Invalid texture image index"))
+                let image = |index: usize| {
+                    textures
+                        .get(index)
+                        .ok_or(GltfError::InvalidImageIndex(index))
+                };
+
+                // The KHR_texture_basisu image when it could be uploaded, the
+                // fallback source otherwise
+                let ids = match basisu_source(&texture).map(image).transpose()? {
+                    Some(Ok(ids)) => ids,
+                    _ => {
+                        let index = texture.source().index();
+                        image(index)?.as_ref().map_err(|reason| {
+                            GltfError::UnsupportedFormat(format!("KTX2 image {index}, {reason}"))
+                        })?
+                    }
+                };
+
+                let sampler = textures_manager
+                    .get_mut()
+                    .sampler(&renderer.device, sampler_config(&texture.sampler()));
+
+                Ok(TextureIds {
+                    srgb: ids.srgb.with_sampler(sampler),
+                    linear: ids.linear.with_sampler(sampler),
+                })
             })
             .collect()
     }
 
+    /// Texture with the KTX2 blocks and mip levels as is
+    fn upload_ktx2(renderer: &Renderer, label: Option<&str>, ktx2: &Ktx2Image) -> wgpu::Texture {
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: ktx2.size,
+            mip_level_count: ktx2.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ktx2.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[
+                ktx2.format.remove_srgb_suffix(),
+                ktx2.format.add_srgb_suffix(),
+            ],
+        });
+
+        for (mip_level, level) in ktx2.levels.iter().enumerate() {
+            renderer.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    mip_level: mip_level as u32,
+                    ..texture.as_image_copy()
+                },
+                level.data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(level.bytes_per_row),
+                    rows_per_image: None,
+                },
+                level.size,
+            );
+        }
+
+        texture
+    }
+
     fn build_materials(
         renderer: &Renderer,
         engine: &mut Engine,
         doc: &gltf::Document,
-        textures: &[TextureId],
-    ) -> Result<Vec<MaterialId>> {
+        textures: &[TextureIds],
+    ) -> Result<Vec<MaterialId>, GltfError> {
         doc.materials()
             .map(|material| {
-                let albedo = material
-                    .pbr_metallic_roughness()
-                    .base_color_texture()
-                    .and_then(|t| textures.get(t.texture().index()).copied())
-                    .unwrap_or_default();
-
-                let normal = material
-                    .normal_texture()
-                    .and_then(|t| textures.get(t.texture().index()).copied())
-                    .unwrap_or_default();
-
-                let metallic_roughness = material
-                    .pbr_metallic_roughness()
-                    .metallic_roughness_texture()
-                    .and_then(|t| textures.get(t.texture().index()).copied())
-                    .unwrap_or_default();
-
-                let emissive = material
-                    .emissive_texture()
-                    .and_then(|t| textures.get(t.texture().index()).copied())
-                    .unwrap_or_default();
-
-                Ok(engine.ressources.get::<MaterialsManager>().get().add(
-                    &renderer.queue,
-                    Material {
-                        albedo,
-                        normal,
-                        metallic_roughness,
-                        emissive,
-                    },
-                ))
+                Ok(engine
+                    .resources
+                    .get::<MaterialsManager>()
+                    .get()
+                    .add(&renderer.queue, material_data(&material, textures)))
             })
             .collect()
     }
@@ -221,7 +439,7 @@ impl GltfModel {
         engine: &mut Engine,
         doc: &gltf::Document,
         buffers: &[gltf::buffer::Data],
-    ) -> Result<Vec<Vec<MeshId>>> {
+    ) -> Result<Vec<Vec<MeshId>>, GltfError> {
         doc.meshes()
             .map(|mesh| {
                 let mesh_name = mesh.name().unwrap_or("?");
@@ -232,49 +450,72 @@ impl GltfModel {
                             buffers.get(buffer.index()).map(std::ops::Deref::deref)
                         };
 
-                        let get_accessor_data = |accessor: gltf::Accessor| -> Option<&[u8]> {
-                            let view = accessor.view()?;
-
-                            let start = view.offset();
-                            let end = start + view.length();
+                        let get_data = |semantic: &gltf::Semantic| -> Option<Cow<[u8]>> {
+                            primitive
+                                .get(semantic)
+                                .and_then(|accessor| accessor_data(&accessor, buffers))
+                        };
 
-                            let buffer = get_buffer_data(view.buffer())?;
+                        let missing_semantic =
+                            |semantic: &gltf::Semantic| GltfError::MissingSemantic {
+                                mesh: mesh_name.to_string(),
+                                semantic: semantic.clone(),
+                            };
 
-                            Some(&buffer[start..end])
+                        let get_data_res = |semantic: &gltf::Semantic| {
+                            get_data(semantic).ok_or_else(|| missing_semantic(semantic))
                         };
 
-                        let get_data = |semantic: &gltf::Semantic| -> Option<&[u8]> {
-                            primitive.get(semantic).and_then(get_accessor_data)
+                        let reader = primitive.reader(get_buffer_data);
+
+                        let indices = primitive_indices(&primitive, buffers)?;
+
+                        // Missing normals and tangents are generated, most
+                        // exporters omit tangents
+                        let positions = || -> Result<Vec<glam::Vec3>, GltfError> {
+                            Ok(reader
+                                .read_positions()
+                                .ok_or_else(|| missing_semantic(&gltf::Semantic::Positions))?
+                                .map(glam::Vec3::from)
+                                .collect())
                         };
 
-                        let get_data_res = |semantic: &gltf::Semantic| -> Result<&[u8]> {
-                            get_data(semantic)
-                                .ok_or_else(|| anyhow!("Mesh [{mesh_name}] missing [{semantic:?}]"))
+                        let normals = match reader.read_normals() {
+                            Some(normals) => normals.map(glam::Vec3::from).collect(),
+                            None => generate_normals(&positions()?, &indices),
                         };
 
-                        let indices = primitive
-                            .reader(get_buffer_data)
-                            .read_indices()
-                            .unwrap()
-                            .into_u32()
-                            .collect::<Vec<_>>();
+                        let tangents = match reader.read_tangents() {
+                            Some(tangents) => tangents.map(glam::Vec4::from).collect(),
+                            None => {
+                                let uvs = reader
+                                    .read_tex_coords(0)
+                                    .ok_or_else(|| missing_semantic(&gltf::Semantic::TexCoords(0)))?
+                                    .into_f32()
+                                    .map(glam::Vec2::from)
+                                    .collect::<Vec<_>>();
+
+                                generate_tangents(&positions()?, &normals, &uvs, &indices)
+                            }
+                        };
 
                         let bounding_sphere = {
-                            let positions_accessor =
-                                primitive.get(&gltf::Semantic::Positions).ok_or_else(|| {
-                                    anyhow!("Mesh [{mesh_name}] Missing positions accessor",)
-                                })?;
-
-                            let min = serde_json::from_value::<glam::Vec3>(
-                                positions_accessor.min().ok_or_else(|| {
-                                    anyhow!("Mesh [{mesh_name}] Missing positions accessor min")
-                                })?,
-                            )?;
-                            let max = serde_json::from_value::<glam::Vec3>(
-                                positions_accessor.max().ok_or_else(|| {
-                                    anyhow!("Mesh [{mesh_name}] Missing positions accessor max")
-                                })?,
-                            )?;
+                            let positions_accessor = primitive
+                                .get(&gltf::Semantic::Positions)
+                                .ok_or_else(|| missing_semantic(&gltf::Semantic::Positions))?;
+
+                            let bound = |value: Option<gltf::json::Value>| {
+                                serde_json::from_value::<glam::Vec3>(value?).ok()
+                            };
+                            let (min, max) = Option::zip(
+                                bound(positions_accessor.min()),
+                                bound(positions_accessor.max()),
+                            )
+                            .ok_or_else(|| {
+                                GltfError::MissingAccessorBounds {
+                                    mesh: mesh_name.to_string(),
+                                }
+                            })?;
 
                             let center = (min + max) / 2.0;
                             let radius = (max - center).length();
@@ -287,27 +528,27 @@ impl GltfModel {
                             get_data(&gltf::Semantic::Weights(0)),
                         )
                         .map(|(joints, weights)| {
-                            engine.ressources.get::<SkinsManager>().get_mut().add(
+                            engine.resources.get::<SkinsManager>().get_mut().add(
                                 &renderer.queue,
-                                joints,
-                                weights,
+                                &joints,
+                                &weights,
                             )
                         });
 
-                        let mesh = engine.ressources.get::<MeshesManager>().get().add(
+                        let mesh = engine.resources.get::<MeshesManager>().get().add(
                             &renderer.queue,
                             bounding_sphere,
-                            get_data_res(&gltf::Semantic::Positions)?,
-                            get_data_res(&gltf::Semantic::Normals)?,
-                            get_data_res(&gltf::Semantic::Tangents)?,
-                            get_data_res(&gltf::Semantic::TexCoords(0))?,
+                            &get_data_res(&gltf::Semantic::Positions)?,
+                            bytemuck::cast_slice(&normals),
+                            bytemuck::cast_slice(&tangents),
+                            &get_data_res(&gltf::Semantic::TexCoords(0))?,
                             bytemuck::cast_slice(&indices),
                             skin,
                         );
 
                         Ok(mesh)
                     })
-                    .collect::<Result<_>>()
+                    .collect::<Result<_, GltfError>>()
             })
             .collect()
     }
@@ -317,7 +558,8 @@ impl GltfModel {
         engine: &mut Engine,
         doc: &gltf::Document,
         buffers: &[gltf::buffer::Data],
-    ) -> Vec<HashMap<String, AnimationId>> {
+        options: &GltfOptions,
+    ) -> Vec<SkinAnimations> {
         let nodes_transforms = {
             let children_nodes = doc
                 .nodes()
@@ -372,43 +614,54 @@ impl GltfModel {
                     .map(|arr| glam::Mat4::from_cols_array_2d(&arr))
                     .collect::<Vec<_>>();
 
-                let animation_ids = animations_samplers.iter().map(|sampler| {
-                    let (start, end) = sampler.get_time_range();
-
-                    let mut animation: Vec<Vec<glam::Mat4>> = Vec::new();
-                    let mut time = start;
-
-                    while time <= end {
-                        let animated_nodes_transforms = sampler
-                            .get_nodes_transforms(&time, doc.default_scene().unwrap().nodes());
-
-                        let frame: Vec<glam::Mat4> = skin
-                            .joints()
-                            .zip(&inverse_bind_matrices)
-                            .map(|(node, &inverse_bind_matrix)| {
-                                let global_joint_transform =
-                                    animated_nodes_transforms[&node.index()];
-                                inv_mesh_transform * global_joint_transform * inverse_bind_matrix
-                            })
-                            .collect();
+                let skeleton = engine
+                    .resources
+                    .get::<SkinsManager>()
+                    .get_mut()
+                    .add_skeleton(skin_skeleton(doc, &skin, &inverse_bind_matrices));
+
+                let root_node = options
+                    .root_motion_node
+                    .as_deref()
+                    .and_then(|name| skin.joints().find(|joint| joint.name() == Some(name)));
+
+                // Each animation is baked on its own worker, in order
+                let animations = par_map(animations_samplers.iter().collect(), |sampler| {
+                    bake_animation(
+                        doc,
+                        &skin,
+                        inv_mesh_transform,
+                        &inverse_bind_matrices,
+                        sampler,
+                        sampler.samples_per_sec(options.animation_sample_rate),
+                        root_node.as_ref(),
+                    )
+                });
 
-                        animation.push(frame);
+                let mut animation_ids = HashMap::new();
+                let mut root_motions = HashMap::new();
 
-                        // time += AnimationsManager::SAMPLE_RATE;
-                        time += Duration::from_secs_f32(1.0 / AnimationsManager::SAMPLES_PER_SEC);
-                    }
+                for (animation, baked) in doc.animations().zip(animations) {
+                    let name = animation.name().unwrap_or_default().to_owned();
 
-                    engine.ressources.get::<AnimationsManager>().get_mut().add(
+                    let animation_id = engine.resources.get::<AnimationsManager>().get_mut().add(
                         &renderer.device,
                         &renderer.queue,
-                        animation,
-                    )
-                });
+                        baked.frames,
+                        baked.samples_per_sec,
+                    );
+
+                    animation_ids.insert(name.clone(), animation_id);
+                    if let Some(root_motion) = baked.root_motion {
+                        root_motions.insert(name, root_motion);
+                    }
+                }
 
-                doc.animations()
-                    .map(|animation| animation.name().unwrap_or_default().to_owned())
-                    .zip(animation_ids)
-                    .collect::<HashMap<_, _>>()
+                SkinAnimations {
+                    skeleton: Some(skeleton),
+                    animations: animation_ids,
+                    root_motions,
+                }
             })
             .collect()
     }
@@ -432,10 +685,17 @@ impl GltfModel {
                     .mesh()
                     .and_then(|mesh| self.meshes_instances.get(mesh.index()));
                 if let Some(mesh_instances) = mesh_instances {
-                    instances.extend(mesh_instances.iter().map(|&instance| Instance {
-                        transform,
-                        animation: animation.unwrap_or_default().into(),
-                        ..instance
+                    let transforms = match self.gpu_instances.get(&node.index()) {
+                        Some(locals) => locals.iter().map(|local| transform * *local).collect(),
+                        None => vec![transform],
+                    };
+
+                    instances.extend(transforms.into_iter().flat_map(|transform| {
+                        mesh_instances.iter().map(move |&instance| Instance {
+                            transform,
+                            animation: animation.unwrap_or_default().into(),
+                            ..instance
+                        })
                     }))
                 }
 
@@ -448,27 +708,18 @@ impl GltfModel {
                         Kind::Point => {
                             let position = transform.transform_point3(glam::Vec3::ZERO);
 
-                            const WATTS_TO_LUMENS: f32 = 683.0;
-                            // Luminous intensity in candela (lm/sr) ; multiplied by 4π to get luminous power (lumens) ; converted to watts
-                            let intensity =
-                                light.intensity() * (4.0 * std::f32::consts::PI) / WATTS_TO_LUMENS;
+                            let color = glam::Vec3::from(light.color())
+                                * PhotometricUnits::candela_to_radiant(light.intensity());
 
-                            let color = glam::Vec3::from(light.color()) * intensity;
-
-                            let radius = light.range().unwrap_or_else(|| {
-                                const ATTENUATION_MAX: f32 = 1.0 - (5.0 / 256.0);
-                                (color.max_element() * ATTENUATION_MAX).sqrt()
-                            });
-
-                            // There must be an error in blender export, removing the 4π factor will give the exact
-                            // same result as blender renders when using the same exposure algorithm, but we also
-                            // need to keep it for radius computation to get a somewhat similar range :/
-                            let color = color / (4.0 * std::f32::consts::PI);
+                            let radius = light
+                                .range()
+                                .unwrap_or_else(|| PhotometricUnits::point_light_range(color));
 
                             point_lights.push(PointLight {
                                 position,
                                 radius,
                                 color,
+                                cast_shadows: false,
                             });
                         }
                         Kind::Spot { .. } => {
@@ -503,24 +754,107 @@ impl GltfModel {
         transform: glam::Mat4,
         animation: Option<AnimationId>,
     ) -> (Vec<Instance>, Vec<PointLight>) {
-        self.nodes_data(scene.nodes(), transform, animation)
+        let mut scenes_data = self.scenes_data.lock().unwrap();
+        let (instances, point_lights) = scenes_data
+            .entry(scene.index())
+            .or_insert_with(|| self.nodes_data(scene.nodes(), glam::Mat4::IDENTITY, None));
+
+        let instances = instances
+            .iter()
+            .map(|instance| Instance {
+                transform: transform * instance.transform,
+                animation: animation.unwrap_or_default().into(),
+                ..*instance
+            })
+            .collect();
+
+        let point_lights = point_lights
+            .iter()
+            .map(|point_light| PointLight {
+                position: transform.transform_point3(point_light.position),
+                ..*point_light
+            })
+            .collect();
+
+        (instances, point_lights)
     }
 
+    /// Instances and point lights of the scene named `scene_name`, or of the
+    /// default scene. The nodes tree of each scene is only walked once.
     pub fn scene_instances(
         &self,
         scene_name: Option<&str>,
         transform: Option<glam::Mat4>,
         animation: Option<AnimationId>,
     ) -> Option<(Vec<Instance>, Vec<PointLight>)> {
-        let scene = if let Some(scene_name) = scene_name {
+        let scene = self.get_scene(scene_name)?;
+
+        Some(self.scene_data(scene, transform.unwrap_or_default(), animation))
+    }
+
+    pub fn scene_bounds(&self, scene_name: Option<&str>) -> Option<(glam::Vec3, glam::Vec3)> {
+        let scene = self.get_scene(scene_name)?;
+
+        let mut bounds: Option<(glam::Vec3, glam::Vec3)> = None;
+
+        traverse_nodes_tree(
+            scene.nodes(),
+            &mut |parent_transform: &glam::Mat4, node| {
+                let transform =
+                    *parent_transform * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+
+                // Instanced meshes are bound by each of their instances
+                let transforms = match self.gpu_instances.get(&node.index()) {
+                    Some(locals) => locals.iter().map(|local| transform * *local).collect(),
+                    None => vec![transform],
+                };
+
+                for primitive in node.mesh().iter().flat_map(|mesh| mesh.primitives()) {
+                    let bounding_box = primitive.bounding_box();
+                    let min = glam::Vec3::from(bounding_box.min);
+                    let max = glam::Vec3::from(bounding_box.max);
+
+                    for transform in &transforms {
+                        for i in 0..8 {
+                            let corner = glam::Vec3::select(
+                                glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                                max,
+                                min,
+                            );
+                            let p = transform.transform_point3(corner);
+
+                            bounds = Some(match bounds {
+                                Some((min, max)) => (min.min(p), max.max(p)),
+                                None => (p, p),
+                            });
+                        }
+                    }
+                }
+
+                Some(transform)
+            },
+            glam::Mat4::IDENTITY,
+        );
+
+        bounds
+    }
+
+    /// Camera of the default scene named `name`, or its first camera.
+    ///
+    /// Cameras are matched by node or camera name, `aspect` is used when the
+    /// glTF camera doesn't specify one.
+    pub fn camera(&self, name: Option<&str>, aspect: f32) -> Option<Camera> {
+        scene_camera(self.get_scene(None)?, name, aspect)
+    }
+
+    fn get_scene(&self, scene_name: Option<&str>) -> Option<gltf::Scene<'_>> {
+        if let Some(scene_name) = scene_name {
             self.doc
                 .scenes()
-                .find(|scene| scene.name() == Some(scene_name))?
+                .find(|scene| scene.name() == Some(scene_name))
         } else {
-            self.doc.default_scene()?
-        };
-
-        Some(self.scene_data(scene, transform.unwrap_or_default(), animation))
+            self.doc.default_scene()
+        }
     }
 
     pub fn get_node(&self, name: &str) -> Option<gltf::Node> {
@@ -529,6 +863,470 @@ impl GltfModel {
     pub fn get_animation(&self, name: &str) -> Option<AnimationId> {
         self.animations.get(name).copied()
     }
+
+    /// Names of the named scenes, in document order
+    pub fn scene_names(&self) -> impl Iterator<Item = &str> {
+        self.doc.scenes().filter_map(|scene| scene.name())
+    }
+
+    /// Scene used when no scene name is given, if the document sets one and
+    /// it is named
+    pub fn default_scene_name(&self) -> Option<&str> {
+        self.doc.default_scene()?.name()
+    }
+
+    pub fn node_names(&self) -> impl Iterator<Item = &str> {
+        self.doc.nodes().filter_map(|node| node.name())
+    }
+
+    pub fn animation_names(&self) -> impl Iterator<Item = &str> {
+        self.animations.keys().map(String::as_str)
+    }
+
+    /// Instances of the node mesh primitives, without the node transform
+    pub fn node_mesh_instances(&self, name: &str) -> Option<&[Instance]> {
+        let mesh = self.get_node(name)?.mesh()?;
+
+        self.meshes_instances.get(mesh.index()).map(Vec::as_slice)
+    }
+}
+
+/// Texture views of a glTF texture, one per color space
+#[derive(Debug, Default, Clone, Copy)]
+struct TextureIds {
+    srgb: TextureId,
+    linear: TextureId,
+}
+
+/// Color spaces an image is sampled in, from the material slots using it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ImageUsage {
+    /// Base color and emissive
+    srgb: bool,
+    /// Normal and metallic roughness
+    linear: bool,
+}
+
+impl ImageUsage {
+    /// Color images are stored as sRGB so that mipmaps are filtered in linear
+    /// space, unused images default to linear
+    fn format(&self) -> wgpu::TextureFormat {
+        if self.srgb {
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        } else {
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+/// KTX2 image of a texture, from the `KHR_texture_basisu` extension
+fn basisu_source(texture: &gltf::Texture) -> Option<usize> {
+    let index = texture
+        .extension_value("KHR_texture_basisu")?
+        .get("source")?
+        .as_u64()?;
+
+    Some(index as usize)
+}
+
+fn images_usages(doc: &gltf::Document) -> Vec<ImageUsage> {
+    let mut usages = vec![ImageUsage::default(); doc.images().len()];
+
+    let mut mark = |texture: gltf::Texture, srgb: bool| {
+        let sources = std::iter::once(texture.source().index()).chain(basisu_source(&texture));
+
+        for index in sources {
+            if let Some(usage) = usages.get_mut(index) {
+                if srgb {
+                    usage.srgb = true;
+                } else {
+                    usage.linear = true;
+                }
+            }
+        }
+    };
+
+    for material in doc.materials() {
+        let pbr = material.pbr_metallic_roughness();
+
+        if let Some(info) = pbr.base_color_texture() {
+            mark(info.texture(), true);
+        }
+        if let Some(info) = material.emissive_texture() {
+            mark(info.texture(), true);
+        }
+        if let Some(info) = material.normal_texture() {
+            mark(info.texture(), false);
+        }
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            mark(info.texture(), false);
+        }
+        if let Some(info) = material.occlusion_texture() {
+            mark(info.texture(), false);
+        }
+    }
+
+    usages
+}
+
+fn sampler_config(sampler: &gltf::texture::Sampler) -> SamplerConfig {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    use wgpu::{AddressMode, FilterMode};
+
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => AddressMode::Repeat,
+    };
+
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => FilterMode::Nearest,
+        Some(MagFilter::Linear) | None => FilterMode::Linear,
+    };
+
+    // Textures always have mipmaps, filters without mipmaps sample them the
+    // same way as the texels
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest | MinFilter::NearestMipmapNearest) => {
+            (FilterMode::Nearest, FilterMode::Nearest)
+        }
+        Some(MinFilter::NearestMipmapLinear) => (FilterMode::Nearest, FilterMode::Linear),
+        Some(MinFilter::LinearMipmapNearest) => (FilterMode::Linear, FilterMode::Nearest),
+        Some(MinFilter::Linear | MinFilter::LinearMipmapLinear) | None => {
+            (FilterMode::Linear, FilterMode::Linear)
+        }
+    };
+
+    SamplerConfig {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+    }
+}
+
+fn material_data(material: &gltf::Material, textures: &[TextureIds]) -> Material {
+    let pbr = material.pbr_metallic_roughness();
+
+    let texture_id = |texture: gltf::Texture, srgb: bool| {
+        textures
+            .get(texture.index())
+            .map(|ids| if srgb { ids.srgb } else { ids.linear })
+            .unwrap_or_default()
+    };
+
+    let texture_transform = |info: &gltf::texture::Info| {
+        info.texture_transform()
+            .map(|transform| TextureTransform {
+                offset: transform.offset(),
+                rotation: transform.rotation(),
+                scale: transform.scale(),
+            })
+            .unwrap_or_default()
+    };
+
+    let albedo = pbr.base_color_texture();
+    let metallic_roughness = pbr.metallic_roughness_texture();
+    let emissive = material.emissive_texture();
+    let occlusion = material.occlusion_texture();
+
+    let albedo_transform = albedo.as_ref().map(texture_transform).unwrap_or_default();
+
+    Material {
+        albedo: albedo
+            .as_ref()
+            .map(|t| texture_id(t.texture(), true))
+            .unwrap_or_default(),
+        normal: material
+            .normal_texture()
+            .map(|t| texture_id(t.texture(), false))
+            .unwrap_or_default(),
+        metallic_roughness: metallic_roughness
+            .as_ref()
+            .map(|t| texture_id(t.texture(), false))
+            .unwrap_or_default(),
+        emissive: emissive
+            .as_ref()
+            .map(|t| texture_id(t.texture(), true))
+            .unwrap_or_default(),
+        emissive_factor: material.emissive_factor(),
+        emissive_strength: material.emissive_strength().unwrap_or(1.0),
+        base_color_factor: pbr.base_color_factor(),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        albedo_transform,
+        normal_transform: material
+            .normal_texture()
+            .map(|t| extension_texture_transform(t.extension_value(KHR_TEXTURE_TRANSFORM)))
+            .unwrap_or_default(),
+        metallic_roughness_transform: metallic_roughness
+            .as_ref()
+            .map(texture_transform)
+            .unwrap_or_default(),
+        emissive_transform: emissive.as_ref().map(texture_transform).unwrap_or_default(),
+        // Packed occlusion roughness metallic images resolve to the same view
+        occlusion: occlusion
+            .as_ref()
+            .map(|t| texture_id(t.texture(), false))
+            .unwrap_or_default(),
+        occlusion_strength: occlusion.as_ref().map_or(1.0, |t| t.strength()),
+        occlusion_transform: occlusion
+            .as_ref()
+            .map(|t| extension_texture_transform(t.extension_value(KHR_TEXTURE_TRANSFORM)))
+            .unwrap_or_default(),
+    }
+}
+
+const KHR_TEXTURE_TRANSFORM: &str = "KHR_texture_transform";
+
+/// Normal and occlusion textures only expose `KHR_texture_transform` as a raw
+/// extension value, unlike the other texture slots
+fn extension_texture_transform(value: Option<&serde_json::Value>) -> TextureTransform {
+    use gltf::json::extensions::texture::TextureTransform as Json;
+
+    value
+        .and_then(|value| serde_json::from_value::<Json>(value.clone()).ok())
+        .map(|transform| TextureTransform {
+            offset: transform.offset.0,
+            rotation: transform.rotation.0,
+            scale: transform.scale.0,
+        })
+        .unwrap_or_default()
+}
+
+/// Image of the document, see [`GltfModel::build_textures`]
+enum Image<'a> {
+    Decoded(Cow<'a, gltf::image::Data>),
+    /// Kept encoded so that its compressed blocks are uploaded as is
+    Ktx2(Vec<u8>),
+}
+
+type Import = (gltf::Document, Vec<gltf::buffer::Data>, Vec<Image<'static>>);
+
+/// Percent-decodes a relative reference, rejecting the ones escaping the
+/// asset directory
+fn decode_uri(uri: &str) -> Result<Cow<'_, str>, GltfError> {
+    use std::path::Component;
+
+    let invalid = || GltfError::InvalidUri(uri.to_string());
+    let decoded = urlencoding::decode(uri).map_err(|_| invalid())?;
+
+    let mut depth = 0usize;
+    for component in Path::new(decoded.as_ref()).components() {
+        depth = match component {
+            Component::Prefix(_) | Component::RootDir => return Err(invalid()),
+            Component::ParentDir => depth.checked_sub(1).ok_or_else(invalid)?,
+            Component::CurDir => depth,
+            Component::Normal(_) => depth + 1,
+        };
+    }
+
+    Ok(decoded)
+}
+
+/// Same as [`gltf::import_slice`] with external references read by `resolve`,
+/// percent-decoded and relative to the asset directory
+fn import(
+    gltf::Gltf { document, mut blob }: gltf::Gltf,
+    mut resolve: impl FnMut(&str) -> std::io::Result<Vec<u8>>,
+) -> Result<Import, GltfError> {
+    let mut read_uri = |uri: &str| -> Result<Vec<u8>, GltfError> {
+        match uri.strip_prefix("data:") {
+            Some(data) => {
+                let (_, base64) = data
+                    .split_once(";base64,")
+                    .ok_or_else(|| GltfError::UnsupportedFormat(format!("data URI {uri}")))?;
+                Ok(base64::decode(base64)?)
+            }
+            None => Ok(resolve(&decode_uri(uri)?)?),
+        }
+    };
+
+    let buffers = document
+        .buffers()
+        .map(|buffer| {
+            let mut data = match buffer.source() {
+                gltf::buffer::Source::Bin => blob.take().ok_or(GltfError::MissingBinaryChunk)?,
+                gltf::buffer::Source::Uri(uri) => read_uri(uri)?,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(GltfError::BufferTooShort(buffer.index()));
+            }
+
+            data.resize((data.len() + 3) & !3, 0);
+
+            Ok(gltf::buffer::Data(data))
+        })
+        .collect::<Result<Vec<_>, GltfError>>()?;
+
+    let encoded_images = document
+        .images()
+        .map(|image| match image.source() {
+            gltf::image::Source::View { view, .. } => {
+                let start = view.offset();
+                let bytes = buffers[view.buffer().index()]
+                    .get(start..start + view.length())
+                    .ok_or(GltfError::InvalidImageData(image.index()))?;
+                Ok(Cow::Borrowed(bytes))
+            }
+            gltf::image::Source::Uri { uri, .. } => Ok(Cow::Owned(read_uri(uri)?)),
+        })
+        .collect::<Result<Vec<_>, GltfError>>()?;
+
+    // Decoding is the slow part, read_uri is sequential
+    let images = par_map(encoded_images, |bytes| {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            return Ok(Image::Ktx2(bytes.into_owned()));
+        }
+
+        let image = image::load_from_memory(&bytes)?.into_rgba8();
+
+        Ok(Image::Decoded(Cow::Owned(gltf::image::Data {
+            width: image.width(),
+            height: image.height(),
+            format: gltf::image::Format::R8G8B8A8,
+            pixels: image.into_raw(),
+        })))
+    })
+    .into_iter()
+    .collect::<Result<Vec<_>, GltfError>>()?;
+
+    Ok((document, buffers, images))
+}
+
+/// Local transforms of the instances of a node mesh, from the
+/// `EXT_mesh_gpu_instancing` TRANSLATION, ROTATION and SCALE attributes
+fn node_gpu_instances(
+    doc: &gltf::Document,
+    node: &gltf::Node,
+    buffers: &[gltf::buffer::Data],
+) -> Option<Vec<glam::Mat4>> {
+    use gltf::{accessor::DataType, accessor::Iter, animation::util::Rotations};
+
+    let attributes = node
+        .extension_value("EXT_mesh_gpu_instancing")?
+        .get("attributes")?;
+
+    let accessor = |name: &str| {
+        let index = attributes.get(name)?.as_u64()?;
+        doc.accessors().nth(index as usize)
+    };
+    let get_buffer_data =
+        |buffer: gltf::Buffer| buffers.get(buffer.index()).map(std::ops::Deref::deref);
+
+    let count = ["TRANSLATION", "ROTATION", "SCALE"]
+        .into_iter()
+        .filter_map(accessor)
+        .map(|accessor| accessor.count())
+        .max()?;
+
+    let translations = match accessor("TRANSLATION") {
+        Some(accessor) => Iter::<[f32; 3]>::new(accessor, get_buffer_data)?
+            .map(glam::Vec3::from)
+            .collect(),
+        None => vec![glam::Vec3::ZERO; count],
+    };
+
+    let rotations = match accessor("ROTATION") {
+        Some(accessor) => match accessor.data_type() {
+            DataType::I8 => Rotations::I8(Iter::new(accessor, get_buffer_data)?),
+            DataType::U8 => Rotations::U8(Iter::new(accessor, get_buffer_data)?),
+            DataType::I16 => Rotations::I16(Iter::new(accessor, get_buffer_data)?),
+            DataType::U16 => Rotations::U16(Iter::new(accessor, get_buffer_data)?),
+            DataType::F32 => Rotations::F32(Iter::new(accessor, get_buffer_data)?),
+            DataType::U32 => return None,
+        }
+        .into_f32()
+        .map(glam::Quat::from_array)
+        .collect(),
+        None => vec![glam::Quat::IDENTITY; count],
+    };
+
+    let scales = match accessor("SCALE") {
+        Some(accessor) => Iter::<[f32; 3]>::new(accessor, get_buffer_data)?
+            .map(glam::Vec3::from)
+            .collect(),
+        None => vec![glam::Vec3::ONE; count],
+    };
+
+    Some(
+        translations
+            .into_iter()
+            .zip(rotations)
+            .zip(scales)
+            .map(|((translation, rotation), scale)| {
+                glam::Mat4::from_scale_rotation_translation(scale, rotation, translation)
+            })
+            .collect(),
+    )
+}
+
+fn scene_camera(scene: gltf::Scene, name: Option<&str>, aspect: f32) -> Option<Camera> {
+    let mut found: Option<Camera> = None;
+
+    traverse_nodes_tree(
+        scene.nodes(),
+        &mut |parent_transform: &glam::Mat4, node| {
+            if found.is_some() {
+                return None;
+            }
+
+            let transform =
+                *parent_transform * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+
+            if let Some(camera) = node.camera() {
+                if name.is_none() || node.name() == name || camera.name() == name {
+                    found = Some(Camera {
+                        view: transform.inverse(),
+                        proj: camera_projection(&camera, aspect),
+                    });
+                }
+            }
+
+            Some(transform)
+        },
+        glam::Mat4::IDENTITY,
+    );
+
+    found
+}
+
+fn camera_projection(camera: &gltf::Camera, aspect: f32) -> glam::Mat4 {
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => {
+            let aspect = perspective.aspect_ratio().unwrap_or(aspect);
+
+            match perspective.zfar() {
+                Some(zfar) => glam::Mat4::perspective_rh(
+                    perspective.yfov(),
+                    aspect,
+                    perspective.znear(),
+                    zfar,
+                ),
+                None => glam::Mat4::perspective_infinite_rh(
+                    perspective.yfov(),
+                    aspect,
+                    perspective.znear(),
+                ),
+            }
+        }
+        gltf::camera::Projection::Orthographic(orthographic) => glam::Mat4::orthographic_rh(
+            -orthographic.xmag(),
+            orthographic.xmag(),
+            -orthographic.ymag(),
+            orthographic.ymag(),
+            orthographic.znear(),
+            orthographic.zfar(),
+        ),
+    }
 }
 
 pub fn traverse_nodes_tree<'a, T>(
@@ -542,3 +1340,1441 @@ pub fn traverse_nodes_tree<'a, T>(
         }
     }
 }
+
+/// Returns the tightly packed elements of an accessor.
+///
+/// Sparse accessors are materialized into a dense array: the base data (or
+/// zeros when the accessor has no buffer view) is copied, then the elements
+/// referenced by the sparse indices are overwritten with the sparse values.
+pub fn accessor_data<'a>(
+    accessor: &gltf::Accessor,
+    buffers: &'a [gltf::buffer::Data],
+) -> Option<Cow<'a, [u8]>> {
+    let get_view_data = |view: gltf::buffer::View, offset: usize, len: usize| {
+        let buffer = buffers.get(view.buffer().index())?;
+        let start = view.offset() + offset;
+
+        buffer.get(start..start + len)
+    };
+
+    let element_size = accessor.size();
+    let len = accessor.count() * element_size;
+
+    let data = match accessor.view() {
+        Some(view) => Cow::Borrowed(get_view_data(view, accessor.offset(), len)?),
+        None => Cow::Owned(vec![0; len]),
+    };
+
+    let Some(sparse) = accessor.sparse() else {
+        return Some(data);
+    };
+
+    let indices = sparse.indices();
+    let index_type = indices.index_type();
+    let index_size = index_type.size();
+    let indices_data = get_view_data(
+        indices.view(),
+        indices.offset(),
+        sparse.count() * index_size,
+    )?;
+
+    let values = sparse.values();
+    let values_data = get_view_data(
+        values.view(),
+        values.offset(),
+        sparse.count() * element_size,
+    )?;
+
+    let mut data = data.into_owned();
+
+    for (index, value) in indices_data
+        .chunks_exact(index_size)
+        .map(|bytes| {
+            use gltf::accessor::sparse::IndexType;
+            match index_type {
+                IndexType::U8 => bytes[0] as usize,
+                IndexType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+                IndexType::U32 => {
+                    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+                }
+            }
+        })
+        .zip(values_data.chunks_exact(element_size))
+    {
+        let start = index * element_size;
+        data.get_mut(start..start + element_size)?
+            .copy_from_slice(value);
+    }
+
+    Some(Cow::Owned(data))
+}
+
+/// Maps `items` in order, on the rayon thread pool with the `rayon` feature
+fn par_map<T, R>(items: Vec<T>, f: impl Fn(T) -> R + Send + Sync) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        items.into_par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        items.into_iter().map(f).collect()
+    }
+}
+
+/// Pixels of an image as RGBA, `None` when they don't match its size
+fn rgba_image(data: &gltf::image::Data) -> Option<image::RgbaImage> {
+    // 3 channels texture formats are not supported by WebGPU
+    // https://github.com/gpuweb/gpuweb/issues/66
+    if data.format == gltf::image::Format::R8G8B8 {
+        image::RgbImage::from_raw(data.width, data.height, data.pixels.clone())
+            .map(|image| image::DynamicImage::ImageRgb8(image).into_rgba8())
+    } else {
+        image::RgbaImage::from_raw(data.width, data.height, data.pixels.clone())
+    }
+}
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// KTX2 formats uploaded as is, Basis Universal images would need transcoding
+const KTX2_FORMATS: &[(ktx2::Format, wgpu::TextureFormat)] = {
+    use ktx2::Format as Ktx2;
+    use wgpu::{AstcBlock, AstcChannel, TextureFormat as Wgpu};
+
+    &[
+        (Ktx2::R8G8B8A8_UNORM, Wgpu::Rgba8Unorm),
+        (Ktx2::R8G8B8A8_SRGB, Wgpu::Rgba8UnormSrgb),
+        (Ktx2::BC1_RGBA_UNORM_BLOCK, Wgpu::Bc1RgbaUnorm),
+        (Ktx2::BC1_RGBA_SRGB_BLOCK, Wgpu::Bc1RgbaUnormSrgb),
+        (Ktx2::BC3_UNORM_BLOCK, Wgpu::Bc3RgbaUnorm),
+        (Ktx2::BC3_SRGB_BLOCK, Wgpu::Bc3RgbaUnormSrgb),
+        (Ktx2::BC4_UNORM_BLOCK, Wgpu::Bc4RUnorm),
+        (Ktx2::BC5_UNORM_BLOCK, Wgpu::Bc5RgUnorm),
+        (Ktx2::BC7_UNORM_BLOCK, Wgpu::Bc7RgbaUnorm),
+        (Ktx2::BC7_SRGB_BLOCK, Wgpu::Bc7RgbaUnormSrgb),
+        (Ktx2::ETC2_R8G8B8_UNORM_BLOCK, Wgpu::Etc2Rgb8Unorm),
+        (Ktx2::ETC2_R8G8B8_SRGB_BLOCK, Wgpu::Etc2Rgb8UnormSrgb),
+        (Ktx2::ETC2_R8G8B8A8_UNORM_BLOCK, Wgpu::Etc2Rgba8Unorm),
+        (Ktx2::ETC2_R8G8B8A8_SRGB_BLOCK, Wgpu::Etc2Rgba8UnormSrgb),
+        (Ktx2::EAC_R11_UNORM_BLOCK, Wgpu::EacR11Unorm),
+        (Ktx2::EAC_R11G11_UNORM_BLOCK, Wgpu::EacRg11Unorm),
+        (
+            Ktx2::ASTC_4x4_UNORM_BLOCK,
+            Wgpu::Astc {
+                block: AstcBlock::B4x4,
+                channel: AstcChannel::Unorm,
+            },
+        ),
+        (
+            Ktx2::ASTC_4x4_SRGB_BLOCK,
+            Wgpu::Astc {
+                block: AstcBlock::B4x4,
+                channel: AstcChannel::UnormSrgb,
+            },
+        ),
+    ]
+};
+
+/// Mip level of a KTX2 image, `size` is rounded up to whole blocks
+struct Ktx2Level<'a> {
+    size: wgpu::Extent3d,
+    bytes_per_row: u32,
+    data: &'a [u8],
+}
+
+struct Ktx2Image<'a> {
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    levels: Vec<Ktx2Level<'a>>,
+}
+
+/// Blocks of a KTX2 image, or why they can't be uploaded to a device with
+/// `features`
+fn ktx2_image<'a>(
+    reader: &'a ktx2::Reader<&[u8]>,
+    features: wgpu::Features,
+) -> Result<Ktx2Image<'a>, String> {
+    let header = reader.header();
+
+    match header.supercompression_scheme {
+        None => {}
+        Some(ktx2::SupercompressionScheme::BasisLZ) => {
+            return Err("Basis Universal needs transcoding".to_string())
+        }
+        Some(scheme) => return Err(format!("{scheme:?} supercompression")),
+    }
+
+    // UASTC has no Vulkan format either
+    let Some(vk_format) = header.format else {
+        return Err("Basis Universal needs transcoding".to_string());
+    };
+
+    if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count > 1 {
+        return Err("3D, array and cube textures are not handled".to_string());
+    }
+
+    let format = KTX2_FORMATS
+        .iter()
+        .find_map(|&(ktx2_format, format)| (ktx2_format == vk_format).then_some(format))
+        .ok_or_else(|| format!("{vk_format:?} format"))?;
+
+    let missing = format.required_features() - features;
+    if !missing.is_empty() {
+        return Err(format!("{vk_format:?} needs {missing:?}"));
+    }
+
+    let size = wgpu::Extent3d {
+        width: header.pixel_width,
+        height: header.pixel_height.max(1),
+        depth_or_array_layers: 1,
+    };
+
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_size(None)
+        .ok_or_else(|| format!("{vk_format:?} format"))?;
+    if !size.width.is_multiple_of(block_width) || !size.height.is_multiple_of(block_height) {
+        return Err(format!(
+            "{}x{} is not a multiple of the {block_width}x{block_height} blocks",
+            size.width, size.height
+        ));
+    }
+
+    let dimension = wgpu::TextureDimension::D2;
+    if reader.levels().len() as u32 > size.max_mips(dimension) {
+        return Err(format!("{} mip levels", reader.levels().len()));
+    }
+
+    let levels = reader
+        .levels()
+        .enumerate()
+        .map(|(level, data)| {
+            let size = size
+                .mip_level_size(level as u32, dimension)
+                .physical_size(format);
+            let bytes_per_row = size.width / block_width * block_size;
+
+            let length = (bytes_per_row * size.height / block_height) as usize;
+            if data.len() != length {
+                return Err(format!("mip level {level} is not {length} bytes"));
+            }
+
+            Ok(Ktx2Level {
+                size,
+                bytes_per_row,
+                data,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(Ktx2Image {
+        format,
+        size,
+        levels,
+    })
+}
+
+/// Indices of a primitive as a triangle list, the only topology of the
+/// geometry pipeline. Non-indexed primitives get sequential indices, strips
+/// and fans are unrolled.
+fn primitive_indices(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Vec<u32>, GltfError> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+
+    let mut indices = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => {
+            let count = primitive
+                .get(&gltf::Semantic::Positions)
+                .map(|accessor| accessor.count())
+                .unwrap_or(0);
+
+            (0..count as u32).collect::<Vec<_>>()
+        }
+    };
+
+    let triangles = indices.len().saturating_sub(2);
+
+    match primitive.mode() {
+        gltf::mesh::Mode::Triangles => {
+            indices.truncate(indices.len() / 3 * 3);
+            Ok(indices)
+        }
+        gltf::mesh::Mode::TriangleStrip => Ok((0..triangles)
+            .flat_map(|i| match i % 2 {
+                0 => [indices[i], indices[i + 1], indices[i + 2]],
+                _ => [indices[i], indices[i + 2], indices[i + 1]],
+            })
+            .collect()),
+        gltf::mesh::Mode::TriangleFan => Ok((0..triangles)
+            .flat_map(|i| [indices[i + 1], indices[i + 2], indices[0]])
+            .collect()),
+        mode => Err(GltfError::UnsupportedFormat(format!(
+            "primitive mode {mode:?}"
+        ))),
+    }
+}
+
+/// Valid triangles of an indexed triangle list
+fn triangles<'a>(indices: &'a [u32], len: usize) -> impl Iterator<Item = [usize; 3]> + 'a {
+    indices
+        .chunks_exact(3)
+        .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+        .filter(move |t| t.iter().all(|&i| i < len))
+}
+
+/// Smooth vertex normals, accumulated from the faces weighted by their area
+fn generate_normals(positions: &[glam::Vec3], indices: &[u32]) -> Vec<glam::Vec3> {
+    let mut normals = vec![glam::Vec3::ZERO; positions.len()];
+
+    for [a, b, c] in triangles(indices, positions.len()) {
+        // Cross product length is twice the triangle area
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+
+    normals
+        .into_iter()
+        .map(|normal| normal.try_normalize().unwrap_or(glam::Vec3::Y))
+        .collect()
+}
+
+/// Vertex tangents from the UV0 derivatives of the faces, orthogonalized
+/// against the normals. The `w` component holds the bitangent sign, as for
+/// glTF tangents.
+fn generate_tangents(
+    positions: &[glam::Vec3],
+    normals: &[glam::Vec3],
+    uvs: &[glam::Vec2],
+    indices: &[u32],
+) -> Vec<glam::Vec4> {
+    let len = positions.len().min(normals.len()).min(uvs.len());
+    let mut tangents = vec![glam::Vec3::ZERO; positions.len()];
+    let mut bitangents = vec![glam::Vec3::ZERO; positions.len()];
+
+    for [a, b, c] in triangles(indices, len) {
+        let (e1, e2) = (positions[b] - positions[a], positions[c] - positions[a]);
+        let (d1, d2) = (uvs[b] - uvs[a], uvs[c] - uvs[a]);
+
+        let det = d1.perp_dot(d2);
+        if det.abs() <= f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (e1 * d2.y - e2 * d1.y) / det;
+        let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+
+        for i in [a, b, c] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    (0..positions.len())
+        .map(|i| {
+            let normal = normals.get(i).copied().unwrap_or(glam::Vec3::Y);
+
+            let tangent = (tangents[i] - normal * normal.dot(tangents[i]))
+                .try_normalize()
+                .unwrap_or_else(|| normal.any_orthonormal_vector());
+
+            let sign = if normal.cross(tangent).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            tangent.extend(sign)
+        })
+        .collect()
+}
+
+/// Parents are the closest ancestors belonging to the skin, bind positions
+/// are taken from the inverse bind matrices
+fn skin_skeleton(
+    doc: &gltf::Document,
+    skin: &gltf::Skin,
+    inverse_bind_matrices: &[glam::Mat4],
+) -> Skeleton {
+    let node_parents = doc
+        .nodes()
+        .flat_map(|node| {
+            node.children()
+                .map(move |child| (child.index(), node.index()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let joints = skin
+        .joints()
+        .enumerate()
+        .map(|(joint, node)| (node.index(), joint as u32))
+        .collect::<HashMap<_, _>>();
+
+    let parents = skin
+        .joints()
+        .map(|node| {
+            std::iter::successors(node_parents.get(&node.index()), |parent| {
+                node_parents.get(parent)
+            })
+            .find_map(|parent| joints.get(parent).copied())
+        })
+        .collect();
+
+    let bind_positions = inverse_bind_matrices
+        .iter()
+        .map(|inverse_bind_matrix| {
+            inverse_bind_matrix
+                .inverse()
+                .transform_point3(glam::Vec3::ZERO)
+        })
+        .collect();
+
+    Skeleton {
+        parents,
+        bind_positions,
+    }
+}
+
+struct BakedAnimation {
+    frames: Vec<Vec<glam::Mat4>>,
+    samples_per_sec: f32,
+    root_motion: Option<RootMotion>,
+}
+
+/// Joints matrices of `skin` at each sampled time, the ground motion of
+/// `root_node` being removed from the frames when set
+fn bake_animation(
+    doc: &gltf::Document,
+    skin: &gltf::Skin,
+    inv_mesh_transform: glam::Mat4,
+    inverse_bind_matrices: &[glam::Mat4],
+    sampler: &AnimationSampler,
+    samples_per_sec: f32,
+    root_node: Option<&gltf::Node>,
+) -> BakedAnimation {
+    let nodes_transforms = sampler
+        .sample_times(samples_per_sec)
+        .map(|time| sampler.get_nodes_transforms(&time, doc.default_scene().unwrap().nodes()))
+        .collect::<Vec<_>>();
+
+    let (root_motion, removals) = match root_node {
+        Some(root_node) => {
+            let root_transforms = nodes_transforms
+                .iter()
+                .map(|transforms| inv_mesh_transform * transforms[&root_node.index()])
+                .collect::<Vec<_>>();
+
+            let (root_motion, removals) = RootMotion::extract(samples_per_sec, &root_transforms);
+            (Some(root_motion), removals)
+        }
+        None => (None, vec![glam::Mat4::IDENTITY; nodes_transforms.len()]),
+    };
+
+    let frames = nodes_transforms
+        .iter()
+        .zip(removals)
+        .map(|(transforms, removal)| {
+            skin.joints()
+                .zip(inverse_bind_matrices)
+                .map(|(node, &inverse_bind_matrix)| {
+                    removal * inv_mesh_transform * transforms[&node.index()] * inverse_bind_matrix
+                })
+                .collect()
+        })
+        .collect();
+
+    BakedAnimation {
+        frames,
+        samples_per_sec,
+        root_motion,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize((json.len() + 3) & !3, b' ');
+
+        let mut bin = bin.to_vec();
+        bin.resize((bin.len() + 3) & !3, 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+
+        glb
+    }
+
+    #[test]
+    fn sparse_accessor() {
+        let base = [glam::Vec3::ZERO; 4];
+        let indices: [u16; 2] = [1, 3];
+        let values = [glam::vec3(1.0, 2.0, 3.0), glam::vec3(4.0, 5.0, 6.0)];
+
+        let mut bin = vec![];
+        bin.extend_from_slice(bytemuck::cast_slice(&base));
+        bin.extend_from_slice(bytemuck::cast_slice(&indices));
+        bin.extend_from_slice(bytemuck::cast_slice(&values));
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": {} }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 48 }},
+                    {{ "buffer": 0, "byteOffset": 48, "byteLength": 4 }},
+                    {{ "buffer": 0, "byteOffset": 52, "byteLength": 24 }}
+                ],
+                "accessors": [{{
+                    "bufferView": 0,
+                    "componentType": 5126,
+                    "count": 4,
+                    "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0],
+                    "max": [4.0, 5.0, 6.0],
+                    "sparse": {{
+                        "count": 2,
+                        "indices": {{ "bufferView": 1, "componentType": 5123 }},
+                        "values": {{ "bufferView": 2 }}
+                    }}
+                }}]
+            }}"#,
+            bin.len()
+        );
+
+        let (doc, buffers, _) = gltf::import_slice(make_glb(&json, &bin)).unwrap();
+        let accessor = doc.accessors().next().unwrap();
+
+        let data = accessor_data(&accessor, &buffers).unwrap();
+        let positions: &[glam::Vec3] = bytemuck::cast_slice(&data[..]);
+
+        assert_eq!(
+            positions,
+            &[glam::Vec3::ZERO, values[0], glam::Vec3::ZERO, values[1]]
+        );
+    }
+
+    #[test]
+    fn import_external_buffer() {
+        let bin = [1u8, 2, 3, 4, 5, 6];
+
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "buffers": [{ "uri": "data.bin", "byteLength": 6 }]
+        }"#;
+
+        let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        let (_, buffers, _) = import(gltf, |uri| {
+            assert_eq!(uri, "data.bin");
+            Ok(bin.to_vec())
+        })
+        .unwrap();
+
+        assert_eq!(&buffers[0][..6], &bin);
+
+        let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        assert!(matches!(
+            import(gltf, |_| Ok(vec![0; 2])),
+            Err(GltfError::BufferTooShort(0))
+        ));
+
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "buffers": [{ "uri": "data:application/octet-stream;base64,AQIDBAUG", "byteLength": 6 }]
+        }"#;
+
+        let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        let (_, buffers, _) = import(gltf, |_| unreachable!()).unwrap();
+
+        assert_eq!(&buffers[0][..6], &bin);
+
+        let import_uri = |uri: &str| {
+            let json = format!(
+                r#"{{
+                    "asset": {{ "version": "2.0" }},
+                    "buffers": [{{ "uri": "{uri}", "byteLength": 6 }}]
+                }}"#
+            );
+            let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+
+            let mut resolved = None;
+            import(gltf, |uri| {
+                resolved = Some(uri.to_string());
+                Ok(bin.to_vec())
+            })
+            .map(|_| resolved.unwrap())
+        };
+
+        assert_eq!(
+            import_uri("my%20buffers/data%2Ebin").unwrap(),
+            "my buffers/data.bin"
+        );
+        assert_eq!(import_uri("a/../data.bin").unwrap(), "a/../data.bin");
+
+        for uri in [
+            "../data.bin",
+            "%2E%2E/data.bin",
+            "a/../../data.bin",
+            "/data.bin",
+            "%2Fdata.bin",
+            "%FF.bin",
+        ] {
+            assert!(
+                matches!(import_uri(uri), Err(GltfError::InvalidUri(_))),
+                "{uri}"
+            );
+        }
+    }
+
+    #[test]
+    fn primitive_topologies() {
+        let positions = [glam::Vec3::ZERO; 5];
+        let indices: [u16; 4] = [4, 2, 3, 0];
+
+        let mut bin = vec![];
+        bin.extend_from_slice(bytemuck::cast_slice(&positions));
+        bin.extend_from_slice(bytemuck::cast_slice(&indices));
+
+        let primitive = |mode: u32, indices: bool| {
+            let indices = if indices { r#", "indices": 1"# } else { "" };
+            format!(r#"{{ "attributes": {{ "POSITION": 0 }}, "mode": {mode}{indices} }}"#)
+        };
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ "byteLength": {} }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 60 }},
+                    {{ "buffer": 0, "byteOffset": 60, "byteLength": 8 }}
+                ],
+                "accessors": [
+                    {{
+                        "bufferView": 0,
+                        "componentType": 5126,
+                        "count": 5,
+                        "type": "VEC3",
+                        "min": [0.0, 0.0, 0.0],
+                        "max": [0.0, 0.0, 0.0]
+                    }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 4, "type": "SCALAR" }}
+                ],
+                "meshes": [{{ "primitives": [{}, {}, {}, {}, {}] }}]
+            }}"#,
+            bin.len(),
+            primitive(4, false),
+            primitive(5, false),
+            primitive(6, false),
+            primitive(5, true),
+            primitive(1, false),
+        );
+
+        let (doc, buffers, _) = gltf::import_slice(make_glb(&json, &bin)).unwrap();
+        let mut primitives = doc.meshes().next().unwrap().primitives();
+        let mut next = || primitive_indices(&primitives.next().unwrap(), &buffers);
+
+        assert_eq!(next().unwrap(), [0, 1, 2]);
+        assert_eq!(next().unwrap(), [0, 1, 2, 1, 3, 2, 2, 3, 4]);
+        assert_eq!(next().unwrap(), [1, 2, 0, 2, 3, 0, 3, 4, 0]);
+        assert_eq!(next().unwrap(), [4, 2, 3, 2, 0, 3]);
+        assert!(matches!(next(), Err(GltfError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn par_map_order() {
+        let items = (0..1000).collect::<Vec<u32>>();
+        let expected = items.iter().map(|i| i * i).collect::<Vec<_>>();
+
+        assert_eq!(par_map(items, |i| i * i), expected);
+    }
+
+    #[test]
+    fn generated_tangents() {
+        // Unwelded cube, UVs go along `u` and `v` on each face
+        let (mut positions, mut uvs, mut indices) = (vec![], vec![], vec![]);
+        let mut faces = vec![];
+        for normal in [
+            glam::Vec3::X,
+            glam::Vec3::NEG_X,
+            glam::Vec3::Y,
+            glam::Vec3::NEG_Y,
+            glam::Vec3::Z,
+            glam::Vec3::NEG_Z,
+        ] {
+            let u = normal.any_orthonormal_vector();
+            let v = normal.cross(u);
+
+            let start = positions.len() as u32;
+            for uv in [
+                glam::vec2(0.0, 0.0),
+                glam::vec2(1.0, 0.0),
+                glam::vec2(1.0, 1.0),
+                glam::vec2(0.0, 1.0),
+            ] {
+                positions.push(normal + (uv.x * 2.0 - 1.0) * u + (uv.y * 2.0 - 1.0) * v);
+                uvs.push(uv);
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| start + i));
+            faces.push((normal, u));
+        }
+
+        let normals = generate_normals(&positions, &indices);
+        let tangents = generate_tangents(&positions, &normals, &uvs, &indices);
+
+        for (i, (normal, u)) in faces.into_iter().enumerate() {
+            for vertex in i * 4..i * 4 + 4 {
+                assert!(normals[vertex].abs_diff_eq(normal, 1e-6));
+
+                let tangent = tangents[vertex];
+                assert!(tangent.truncate().dot(normals[vertex]).abs() < 1e-6);
+                assert!(tangent.truncate().abs_diff_eq(u, 1e-6));
+                assert_eq!(tangent.w, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn images_color_spaces() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "images": [{ "uri": "albedo.png" }, { "uri": "normal.png" }, { "uri": "shared.png" }],
+            "textures": [{ "source": 0 }, { "source": 1 }, { "source": 2 }],
+            "materials": [{
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": { "index": 0 },
+                    "metallicRoughnessTexture": { "index": 2 }
+                },
+                "normalTexture": { "index": 1 },
+                "emissiveTexture": { "index": 2 }
+            }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let usages = images_usages(&doc);
+
+        assert_eq!(usages[0].format(), wgpu::TextureFormat::Rgba8UnormSrgb);
+        assert_eq!(usages[1].format(), wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(
+            usages[2],
+            ImageUsage {
+                srgb: true,
+                linear: true
+            }
+        );
+    }
+
+    fn make_ktx2(vk_format: u32, scheme: u32, size: (u32, u32), levels: &[&[u8]]) -> Vec<u8> {
+        let index_length = 24 * levels.len() as u32;
+        // Empty data format descriptor, only its total length
+        let dfd_offset = 80 + index_length;
+        let mut level_offset = dfd_offset as u64 + 4;
+
+        let mut ktx2 = KTX2_MAGIC.to_vec();
+        for value in [
+            vk_format,
+            1,
+            size.0,
+            size.1,
+            0,
+            0,
+            1,
+            levels.len() as u32,
+            scheme,
+        ] {
+            ktx2.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [dfd_offset, 4, 0, 0] {
+            ktx2.extend_from_slice(&value.to_le_bytes());
+        }
+        ktx2.extend_from_slice(&[0; 16]);
+
+        for level in levels {
+            let length = level.len() as u64;
+            for value in [level_offset, length, length] {
+                ktx2.extend_from_slice(&value.to_le_bytes());
+            }
+            level_offset += length;
+        }
+
+        ktx2.extend_from_slice(&4u32.to_le_bytes());
+        for level in levels {
+            ktx2.extend_from_slice(level);
+        }
+
+        ktx2
+    }
+
+    #[test]
+    fn ktx2_images() {
+        const BC7_SRGB: u32 = 146;
+        let bc = wgpu::Features::TEXTURE_COMPRESSION_BC;
+
+        // The last two levels are smaller than a block
+        let bytes = make_ktx2(
+            BC7_SRGB,
+            0,
+            (8, 8),
+            &[&[0; 64], &[0; 16], &[0; 16], &[0; 16]],
+        );
+        let reader = ktx2::Reader::new(&bytes[..]).unwrap();
+        let ktx2 = ktx2_image(&reader, bc).unwrap();
+
+        assert_eq!(ktx2.format, wgpu::TextureFormat::Bc7RgbaUnormSrgb);
+        assert_eq!((ktx2.size.width, ktx2.size.height), (8, 8));
+        let levels = ktx2
+            .levels
+            .iter()
+            .map(|level| (level.size.width, level.bytes_per_row, level.data.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(levels, [(8, 32, 64), (4, 16, 16), (4, 16, 16), (4, 16, 16)]);
+
+        let error = ktx2_image(&reader, wgpu::Features::empty()).err().unwrap();
+        assert!(error.contains("TEXTURE_COMPRESSION_BC"), "{error}");
+
+        let bytes = make_ktx2(BC7_SRGB, 0, (8, 8), &[&[0; 48]]);
+        let reader = ktx2::Reader::new(&bytes[..]).unwrap();
+        assert!(ktx2_image(&reader, bc).is_err());
+
+        let bytes = make_ktx2(BC7_SRGB, 0, (6, 8), &[&[0; 64]]);
+        let reader = ktx2::Reader::new(&bytes[..]).unwrap();
+        assert!(ktx2_image(&reader, bc).is_err());
+
+        // BasisLZ (ETC1S) and UASTC payloads
+        for scheme in [1, 0] {
+            let bytes = make_ktx2(0, scheme, (8, 8), &[&[0; 64]]);
+            let reader = ktx2::Reader::new(&bytes[..]).unwrap();
+            let error = ktx2_image(&reader, bc).err().unwrap();
+            assert!(error.contains("Basis Universal"), "{error}");
+        }
+    }
+
+    #[test]
+    fn import_basisu_texture() {
+        let mut png = vec![];
+        image::RgbaImage::new(1, 1)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+        let ktx2 = make_ktx2(146, 0, (4, 4), &[&[0; 16]]);
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "images": [
+                    {{ "uri": "data:image/png;base64,{}" }},
+                    {{ "uri": "data:image/ktx2;base64,{}" }}
+                ],
+                "textures": [{{
+                    "source": 0,
+                    "extensions": {{ "KHR_texture_basisu": {{ "source": 1 }} }}
+                }}],
+                "materials": [{{ "normalTexture": {{ "index": 0 }} }}]
+            }}"#,
+            base64::encode(&png),
+            base64::encode(&ktx2),
+        );
+
+        let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        let (doc, _, images) = import(gltf, |_| unreachable!()).unwrap();
+
+        // The KTX2 image is kept encoded instead of failing to decode
+        assert!(matches!(&images[0], Image::Decoded(data) if data.width == 1));
+        assert!(matches!(&images[1], Image::Ktx2(bytes) if *bytes == ktx2));
+
+        let texture = doc.textures().next().unwrap();
+        assert_eq!(basisu_source(&texture), Some(1));
+        assert_eq!(texture.source().index(), 0);
+
+        // Both sampled as a normal map
+        let usages = images_usages(&doc);
+        assert!(usages.iter().all(|usage| usage.linear && !usage.srgb));
+    }
+
+    #[test]
+    fn texture_samplers() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "images": [{ "uri": "floor.png" }],
+            "samplers": [{
+                "wrapS": 33648,
+                "wrapT": 33071,
+                "magFilter": 9728,
+                "minFilter": 9986
+            }],
+            "textures": [{ "source": 0 }, { "source": 0, "sampler": 0 }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let configs = doc
+            .textures()
+            .map(|texture| sampler_config(&texture.sampler()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(configs[0], SamplerConfig::default());
+        assert_eq!(
+            configs[1],
+            SamplerConfig {
+                address_mode_u: wgpu::AddressMode::MirrorRepeat,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Nearest,
+                mipmap_filter: wgpu::FilterMode::Linear,
+            }
+        );
+    }
+
+    #[test]
+    fn material_factors() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "materials": [{
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": [0.5, 0.25, 1.0, 0.75],
+                    "metallicFactor": 0.1,
+                    "roughnessFactor": 0.9
+                },
+                "alphaMode": "MASK",
+                "alphaCutoff": 0.3
+            }, {}]
+        }"#;
+
+        let (doc, _, _) = gltf::import_slice(make_glb(json, &[])).unwrap();
+        let materials = doc
+            .materials()
+            .map(|material| material_data(&material, &[]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(materials[0].base_color_factor, [0.5, 0.25, 1.0, 0.75]);
+        assert_eq!(materials[0].metallic_factor, 0.1);
+        assert_eq!(materials[0].roughness_factor, 0.9);
+        assert_eq!(materials[0].alpha_mode, AlphaMode::Mask);
+        assert_eq!(materials[0].alpha_cutoff, 0.3);
+
+        // glTF defaults match the default white material
+        let default = Material::default();
+        assert_eq!(materials[1].base_color_factor, default.base_color_factor);
+        assert_eq!(materials[1].metallic_factor, default.metallic_factor);
+        assert_eq!(materials[1].roughness_factor, default.roughness_factor);
+        assert_eq!(materials[1].alpha_mode, default.alpha_mode);
+    }
+
+    #[test]
+    fn material_occlusion() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "images": [{ "uri": "orm.png" }],
+            "textures": [{ "source": 0 }],
+            "materials": [{
+                "pbrMetallicRoughness": { "metallicRoughnessTexture": { "index": 0 } },
+                "occlusionTexture": { "index": 0, "strength": 0.5 }
+            }, {}]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+
+        // Packed occlusion roughness metallic is uploaded once
+        assert_eq!(
+            images_usages(&doc),
+            vec![ImageUsage {
+                srgb: false,
+                linear: true
+            }]
+        );
+
+        let materials = doc
+            .materials()
+            .map(|material| material_data(&material, &[]))
+            .collect::<Vec<_>>();
+
+        assert_eq!(materials[0].occlusion_strength, 0.5);
+        assert_eq!(materials[0].occlusion, materials[0].metallic_roughness);
+        assert_eq!(materials[1].occlusion_strength, 1.0);
+    }
+
+    #[test]
+    fn material_texture_transform() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "extensionsUsed": ["KHR_texture_transform"],
+            "images": [{ "uri": "albedo.png" }],
+            "textures": [{ "source": 0 }],
+            "materials": [{
+                "pbrMetallicRoughness": {
+                    "baseColorTexture": {
+                        "index": 0,
+                        "extensions": { "KHR_texture_transform": { "scale": [2.0, 2.0] } }
+                    },
+                    "metallicRoughnessTexture": { "index": 0 }
+                },
+                "normalTexture": {
+                    "index": 0,
+                    "extensions": { "KHR_texture_transform": { "offset": [0.5, 0.0] } }
+                },
+                "occlusionTexture": { "index": 0 }
+            }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let material = material_data(&doc.materials().next().unwrap(), &[]);
+
+        assert_eq!(material.albedo_transform.scale, [2.0, 2.0]);
+        assert_eq!(
+            material.albedo_transform.matrix(),
+            [2.0, 0.0, 0.0, 2.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            material.metallic_roughness_transform,
+            TextureTransform::default()
+        );
+
+        // Each slot reads its own transform
+        assert_eq!(material.normal_transform.offset, [0.5, 0.0]);
+        assert_eq!(material.normal_transform.scale, [1.0, 1.0]);
+        assert_eq!(material.occlusion_transform, TextureTransform::default());
+    }
+
+    #[test]
+    fn scene_cameras() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scene": 0,
+            "scenes": [{ "nodes": [0, 2] }],
+            "nodes": [
+                { "name": "rig", "translation": [0.0, 5.0, 0.0], "children": [1] },
+                { "name": "main", "translation": [0.0, 0.0, 10.0], "camera": 0 },
+                { "name": "top", "camera": 1 }
+            ],
+            "cameras": [{
+                "type": "perspective",
+                "perspective": { "yfov": 1.0, "znear": 0.1, "zfar": 100.0 }
+            }, {
+                "name": "ortho",
+                "type": "orthographic",
+                "orthographic": { "xmag": 2.0, "ymag": 1.0, "znear": 0.1, "zfar": 50.0 }
+            }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let scene = doc.default_scene().unwrap();
+
+        // Parent transform is applied, aspect falls back to the given one
+        let main = scene_camera(scene.clone(), None, 2.0).unwrap();
+        assert!(main
+            .view
+            .transform_point3(glam::vec3(0.0, 5.0, 10.0))
+            .abs_diff_eq(glam::Vec3::ZERO, 1e-5));
+        assert_eq!(main.proj, glam::Mat4::perspective_rh(1.0, 2.0, 0.1, 100.0));
+
+        let top = scene_camera(scene.clone(), Some("ortho"), 2.0).unwrap();
+        assert_eq!(top.view, glam::Mat4::IDENTITY);
+        assert_eq!(
+            top.proj,
+            glam::Mat4::orthographic_rh(-2.0, 2.0, -1.0, 1.0, 0.1, 50.0)
+        );
+
+        assert!(scene_camera(scene, Some("missing"), 2.0).is_none());
+    }
+
+    #[test]
+    fn root_motion_extraction() {
+        // Hips walk 2 units forward along X in 1 second, bobbing along Y
+        let times: [f32; 3] = [0.0, 0.5, 1.0];
+        let translations = [
+            glam::vec3(0.0, 1.0, 0.0),
+            glam::vec3(1.0, 1.2, 0.0),
+            glam::vec3(2.0, 1.0, 0.0),
+        ];
+        let inverse_bind_matrices = [glam::Mat4::IDENTITY; 2];
+
+        let mut bin = Vec::new();
+        bin.extend_from_slice(bytemuck::cast_slice(&times));
+        bin.extend_from_slice(bytemuck::cast_slice(&translations));
+        bin.extend_from_slice(bytemuck::cast_slice(&inverse_bind_matrices));
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0, 1] }}],
+                "nodes": [
+                    {{ "name": "hips", "children": [2] }},
+                    {{ "mesh": 0, "skin": 0 }},
+                    {{ "name": "leg", "translation": [0.0, -0.5, 0.0] }}
+                ],
+                "meshes": [{{ "primitives": [] }}],
+                "skins": [{{ "joints": [0, 2], "inverseBindMatrices": 2 }}],
+                "animations": [{{
+                    "name": "walk",
+                    "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "translation" }} }}],
+                    "samplers": [{{ "input": 0, "output": 1 }}]
+                }}],
+                "buffers": [{{ "byteLength": {} }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteLength": 12 }},
+                    {{ "buffer": 0, "byteOffset": 12, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 48, "byteLength": 128 }}
+                ],
+                "accessors": [
+                    {{
+                        "bufferView": 0,
+                        "componentType": 5126,
+                        "count": 3,
+                        "type": "SCALAR",
+                        "min": [0.0],
+                        "max": [1.0]
+                    }},
+                    {{ "bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3" }},
+                    {{ "bufferView": 2, "componentType": 5126, "count": 2, "type": "MAT4" }}
+                ]
+            }}"#,
+            bin.len()
+        );
+
+        let (doc, buffers, _) = gltf::import_slice(make_glb(&json, &bin)).unwrap();
+        let skin = doc.skins().next().unwrap();
+        let hips = doc.nodes().next().unwrap();
+        let sampler = AnimationSampler::new(doc.animations().next().unwrap(), &buffers);
+
+        let baked = bake_animation(
+            &doc,
+            &skin,
+            glam::Mat4::IDENTITY,
+            &inverse_bind_matrices,
+            &sampler,
+            10.0,
+            Some(&hips),
+        );
+        assert_eq!(baked.frames.len(), 11);
+
+        // In place frames keep the hips over the origin, vertical motion aside
+        for frame in &baked.frames {
+            let hips = frame[0].transform_point3(glam::Vec3::ZERO);
+            let leg = frame[1].transform_point3(glam::Vec3::ZERO);
+
+            assert!(hips.x.abs() < 1e-5 && hips.z.abs() < 1e-5);
+            assert!((leg - hips - glam::vec3(0.0, -0.5, 0.0)).length() < 1e-5);
+        }
+        let mid_hips = baked.frames[5][0].transform_point3(glam::Vec3::ZERO);
+        assert!((mid_hips.y - 1.2).abs() < 1e-5);
+
+        let root_motion = baked.root_motion.unwrap();
+        assert_eq!(root_motion.deltas.len(), 11);
+
+        let position = |motion: glam::Mat4| motion.transform_point3(glam::Vec3::ZERO);
+        assert!((position(root_motion.at(1.0)) - glam::vec3(2.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((position(root_motion.at(0.25)) - glam::vec3(0.5, 0.0, 0.0)).length() < 1e-4);
+        assert!(
+            (position(root_motion.delta(0.25, 0.75)) - glam::vec3(1.0, 0.0, 0.0)).length() < 1e-4
+        );
+
+        // Wrapping around accumulates the end of the cycle and its start
+        assert!(
+            (position(root_motion.delta(0.75, 0.25)) - glam::vec3(1.0, 0.0, 0.0)).length() < 1e-4
+        );
+    }
+
+    #[test]
+    fn skin_skeleton_hierarchy() {
+        // Nodes outside of the skin are skipped when looking for parents
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [
+                { "name": "armature", "children": [1] },
+                { "name": "hips", "children": [2] },
+                { "name": "spine", "children": [3] },
+                { "name": "head" },
+                { "name": "hand" }
+            ],
+            "skins": [{ "joints": [3, 1, 4] }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let skin = doc.skins().next().unwrap();
+
+        let bind_positions = [
+            glam::vec3(0.0, 2.0, 0.0),
+            glam::vec3(0.0, 1.0, 0.0),
+            glam::vec3(0.5, 1.5, 0.0),
+        ];
+        let inverse_bind_matrices =
+            bind_positions.map(|position| glam::Mat4::from_translation(position).inverse());
+
+        let skeleton = skin_skeleton(&doc, &skin, &inverse_bind_matrices);
+        assert_eq!(skeleton.parents, [Some(1), None, None]);
+        assert_eq!(skeleton.bind_positions, bind_positions);
+    }
+
+    #[test]
+    fn gpu_instancing() {
+        let translations = [
+            glam::vec3(1.0, 0.0, 0.0),
+            glam::vec3(2.0, 0.0, 0.0),
+            glam::vec3(3.0, 0.0, 0.0),
+        ];
+        let bin = bytemuck::cast_slice(&translations);
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "extensionsUsed": ["EXT_mesh_gpu_instancing"],
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [{{
+                    "mesh": 0,
+                    "translation": [0.0, 10.0, 0.0],
+                    "extensions": {{
+                        "EXT_mesh_gpu_instancing": {{ "attributes": {{ "TRANSLATION": 0 }} }}
+                    }}
+                }}],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                "buffers": [{{ "byteLength": {} }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": {} }}],
+                "accessors": [{{
+                    "bufferView": 0,
+                    "componentType": 5126,
+                    "count": 3,
+                    "type": "VEC3",
+                    "min": [1.0, 0.0, 0.0],
+                    "max": [3.0, 0.0, 0.0]
+                }}]
+            }}"#,
+            bin.len(),
+            bin.len()
+        );
+
+        let (doc, buffers, _) = gltf::import_slice(make_glb(&json, bin)).unwrap();
+        let gpu_instances = doc
+            .nodes()
+            .filter_map(|node| Some((node.index(), node_gpu_instances(&doc, &node, &buffers)?)))
+            .collect();
+        let model = GltfModel {
+            doc,
+            meshes_instances: vec![vec![Instance::default()]],
+            gpu_instances,
+            animations: Default::default(),
+            root_motions: Default::default(),
+            skeleton: None,
+            scenes_data: Default::default(),
+        };
+
+        let (instances, _) = model.scene_instances(None, None, None).unwrap();
+        assert_eq!(instances.len(), 3);
+
+        // Instance transforms are applied on top of the node transform
+        for (instance, translation) in instances.iter().zip(translations) {
+            let position = instance.transform.transform_point3(glam::Vec3::ZERO);
+            assert_eq!(position, translation + glam::vec3(0.0, 10.0, 0.0));
+        }
+
+        // Each instance moves the mesh box, spanning 1 to 3 on x
+        assert_eq!(
+            model.scene_bounds(None),
+            Some((glam::vec3(2.0, 10.0, 0.0), glam::vec3(6.0, 10.0, 0.0)))
+        );
+    }
+
+    #[test]
+    fn scene_bounds() {
+        let positions = [
+            glam::vec3(-1.0, 0.0, -1.0),
+            glam::vec3(1.0, 0.0, -1.0),
+            glam::vec3(0.0, 2.0, 1.0),
+        ];
+        let bin = bytemuck::cast_slice(&positions);
+
+        let json = format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [
+                    {{ "name": "root", "translation": [10.0, 0.0, 0.0], "children": [1] }},
+                    {{ "name": "mesh", "translation": [0.0, 0.0, 5.0], "mesh": 0 }}
+                ],
+                "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                "buffers": [{{ "byteLength": {} }}],
+                "bufferViews": [{{ "buffer": 0, "byteLength": {} }}],
+                "accessors": [{{
+                    "bufferView": 0,
+                    "componentType": 5126,
+                    "count": 3,
+                    "type": "VEC3",
+                    "min": [-1.0, 0.0, -1.0],
+                    "max": [1.0, 2.0, 1.0]
+                }}]
+            }}"#,
+            bin.len(),
+            bin.len()
+        );
+
+        let (doc, _, _) = gltf::import_slice(make_glb(&json, bin)).unwrap();
+        let model = GltfModel {
+            doc,
+            meshes_instances: vec![],
+            gpu_instances: Default::default(),
+            animations: Default::default(),
+            root_motions: Default::default(),
+            skeleton: None,
+            scenes_data: Default::default(),
+        };
+
+        let (min, max) = model.scene_bounds(None).unwrap();
+        assert_eq!(min, glam::vec3(9.0, 0.0, 4.0));
+        assert_eq!(max, glam::vec3(11.0, 2.0, 6.0));
+
+        let node_position = glam::vec3(10.0, 0.0, 5.0);
+        assert!(node_position.cmpge(min).all() && node_position.cmple(max).all());
+
+        assert!(model.scene_bounds(Some("missing")).is_none());
+    }
+
+    #[test]
+    fn dungeon_scene_bounds() {
+        // Demo assets are not versioned
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../demo/assets/dungeon.glb");
+        if !Path::new(path).exists() {
+            return;
+        }
+
+        // Bounds only need the accessors min and max, no buffer is read
+        let doc = gltf::Gltf::open(path).unwrap().document;
+
+        // First tile used by the demo world generation
+        let mut tile_position = None;
+        traverse_nodes_tree(
+            doc.default_scene().unwrap().nodes(),
+            &mut |parent_transform: &glam::Mat4, node| {
+                let transform =
+                    *parent_transform * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+                if node.name() == Some("module01") {
+                    tile_position = Some(transform.transform_point3(glam::Vec3::ZERO));
+                }
+                Some(transform)
+            },
+            glam::Mat4::IDENTITY,
+        );
+        let tile_position = tile_position.expect("module01 node in the default scene");
+
+        let model = GltfModel {
+            doc,
+            meshes_instances: vec![],
+            gpu_instances: Default::default(),
+            animations: Default::default(),
+            root_motions: Default::default(),
+            skeleton: None,
+            scenes_data: Default::default(),
+        };
+
+        let (min, max) = model.scene_bounds(None).unwrap();
+        assert!(
+            tile_position.cmpge(min - 1e-4).all() && tile_position.cmple(max + 1e-4).all(),
+            "{tile_position} outside of {min}..{max}"
+        );
+    }
+
+    #[test]
+    fn node_lookups() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{ "name": "root", "children": [1] }, { "name": "mesh", "mesh": 0 }, {}],
+            "meshes": [{ "primitives": [] }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let model = GltfModel {
+            doc,
+            meshes_instances: vec![vec![Instance::default(); 2]],
+            gpu_instances: Default::default(),
+            animations: Default::default(),
+            root_motions: Default::default(),
+            skeleton: None,
+            scenes_data: Default::default(),
+        };
+
+        assert_eq!(model.node_names().collect::<Vec<_>>(), ["root", "mesh"]);
+        assert_eq!(model.animation_names().count(), 0);
+
+        assert_eq!(model.node_mesh_instances("mesh").map(<[_]>::len), Some(2));
+        assert!(model.node_mesh_instances("root").is_none());
+        assert!(model.node_mesh_instances("missing").is_none());
+    }
+
+    #[test]
+    fn multiple_scenes() {
+        let json = r#"{
+            "asset": { "version": "2.0" },
+            "scene": 1,
+            "scenes": [
+                { "name": "open", "nodes": [0] },
+                { "name": "closed", "nodes": [1] },
+                { "nodes": [] }
+            ],
+            "nodes": [
+                { "mesh": 0, "translation": [1.0, 0.0, 0.0] },
+                { "mesh": 0, "translation": [0.0, 2.0, 0.0] }
+            ],
+            "meshes": [{ "primitives": [] }]
+        }"#;
+
+        let doc = gltf::Gltf::from_slice(json.as_bytes()).unwrap().document;
+        let model = GltfModel {
+            doc,
+            meshes_instances: vec![vec![Instance::default()]],
+            gpu_instances: Default::default(),
+            animations: Default::default(),
+            root_motions: Default::default(),
+            skeleton: None,
+            scenes_data: Default::default(),
+        };
+
+        assert_eq!(model.scene_names().collect::<Vec<_>>(), ["open", "closed"]);
+        assert_eq!(model.default_scene_name(), Some("closed"));
+
+        let position =
+            |instances: &[Instance]| instances[0].transform.transform_point3(glam::Vec3::ZERO);
+
+        let (default, _) = model.scene_instances(None, None, None).unwrap();
+        assert_eq!(position(&default), glam::vec3(0.0, 2.0, 0.0));
+
+        // Cached scenes are still placed by the given transform
+        let transform = glam::Mat4::from_translation(glam::vec3(0.0, 0.0, 3.0));
+        for _ in 0..2 {
+            let (open, _) = model
+                .scene_instances(Some("open"), Some(transform), None)
+                .unwrap();
+            assert_eq!(open.len(), 1);
+            assert_eq!(position(&open), glam::vec3(1.0, 0.0, 3.0));
+        }
+        assert_eq!(model.scenes_data.lock().unwrap().len(), 2);
+
+        assert!(model.scene_instances(Some("missing"), None, None).is_none());
+    }
+}
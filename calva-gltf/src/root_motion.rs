@@ -0,0 +1,93 @@
+/// Motion of a skeleton root node, extracted from an animation so that the
+/// baked frames play in place.
+///
+/// Motion is restricted to the ground plane, i.e. the root translation along
+/// X/Z and its rotation around Y, vertical bobbing being kept in the pose.
+/// Transforms are expressed in the model space: moving an instance by the
+/// motion over a time span is `transform * root_motion.delta(from, to)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootMotion {
+    pub samples_per_sec: f32,
+    /// Motion from the previous baked frame to this one, the first one being
+    /// the identity
+    pub deltas: Vec<glam::Mat4>,
+}
+
+impl RootMotion {
+    /// `root_transforms` are the root node transforms at each baked frame,
+    /// returns the motion along with the transform removing it from each frame
+    pub(crate) fn extract(
+        samples_per_sec: f32,
+        root_transforms: &[glam::Mat4],
+    ) -> (Self, Vec<glam::Mat4>) {
+        let grounds = root_transforms
+            .iter()
+            .map(|transform| {
+                let (_, rotation, translation) = transform.to_scale_rotation_translation();
+                let forward = rotation * glam::Vec3::Z;
+
+                glam::Mat4::from_rotation_translation(
+                    glam::Quat::from_rotation_y(forward.x.atan2(forward.z)),
+                    translation * glam::vec3(1.0, 0.0, 1.0),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let deltas = std::iter::once(glam::Mat4::IDENTITY)
+            .chain(grounds.windows(2).map(|w| w[0].inverse() * w[1]))
+            .collect();
+
+        // Every frame is brought back on the first frame ground transform
+        let removals = grounds
+            .iter()
+            .map(|ground| grounds[0] * ground.inverse())
+            .collect();
+
+        (
+            Self {
+                samples_per_sec,
+                deltas,
+            },
+            removals,
+        )
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.deltas.len().saturating_sub(1) as f32 / self.samples_per_sec
+    }
+
+    /// Motion accumulated from the animation start to `time`, clamped to the
+    /// animation duration. Deltas are interpolated between baked frames.
+    pub fn at(&self, time: f32) -> glam::Mat4 {
+        let frame = (time * self.samples_per_sec).clamp(0.0, self.deltas.len() as f32 - 1.0);
+        let index = frame.floor() as usize;
+
+        let motion = self.deltas[..=index]
+            .iter()
+            .fold(glam::Mat4::IDENTITY, |acc, delta| acc * *delta);
+
+        match self.deltas.get(index + 1) {
+            Some(next) => {
+                let (_, rotation, translation) = next.to_scale_rotation_translation();
+                let alpha = frame.fract();
+
+                motion
+                    * glam::Mat4::from_rotation_translation(
+                        glam::Quat::IDENTITY.slerp(rotation, alpha),
+                        translation * alpha,
+                    )
+            }
+            None => motion,
+        }
+    }
+
+    /// Motion between two times of the animation, `to` being before `from`
+    /// when a looping animation wrapped around
+    pub fn delta(&self, from: f32, to: f32) -> glam::Mat4 {
+        if to >= from {
+            self.at(from).inverse() * self.at(to)
+        } else {
+            self.at(from).inverse() * self.at(self.duration()) * self.at(to)
+        }
+    }
+}
@@ -1,40 +1,326 @@
+use std::sync::mpsc;
+
 use crate::{
-    AmbientLightPass, AmbientLightPassInputs, AnimatePass, CameraManager, DirectionalLightPass,
-    DirectionalLightPassInputs, FxaaPass, FxaaPassInputs, GeometryPass, HierarchicalDepthPass,
-    HierarchicalDepthPassInputs, PointLightsPass, PointLightsPassInputs, RenderContext, Renderer,
-    RessourcesManager, SkyboxPass, SkyboxPassInputs, SsaoPass, SsaoPassInputs, ToneMappingPass,
-    ToneMappingPassInputs,
+    AmbientLightConfig, AmbientLightPass, AmbientLightPassInputs, AnimatePass, AutoExposurePass,
+    AutoExposurePassInputs, BloomConfig, BloomPass, BloomPassInputs, Camera, CameraManager,
+    DebugBlitPass, DebugBlitPassInputs, DebugShapesPass, DebugShapesPassInputs, DecalsPass,
+    DecalsPassInputs, DepthConfig, DirectionalLightPass, DirectionalLightPassInputs, DofConfig,
+    DofPass, DofPassInputs, FogConfig, FogPass, FogPassInputs, FxaaPass, FxaaPassInputs,
+    GeometryPass, HierarchicalDepthPass, HierarchicalDepthPassInputs, InstanceId, InstancesManager,
+    NormalsDebugPass, PointLightsConfig, PointLightsPass, PointLightsPassInputs, RenderContext,
+    Renderer, ResourcesManager, ScreenEffectsPass, SkeletonDebugPass, SkyboxPass, SkyboxPassInputs,
+    SsaoConfig, SsaoPass, SsaoPassInputs, SsrConfig, SsrPass, SsrPassInputs, TaaPass,
+    TaaPassInputs, TexturesManager, ToneMappingConfig, ToneMappingPass, ToneMappingPassInputs,
+    Viewport,
 };
 
+/// Anti-aliasing of the lit HDR color, see [`Engine::anti_aliasing`].
+/// Switching is free, every method keeps its resources.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AntiAliasing {
+    /// The lit color is tone mapped as is
+    Off,
+    #[default]
+    Fxaa,
+    /// Temporal anti-aliasing, jitters the camera. Only the main view keeps a
+    /// history, other viewports fall back to FXAA. The history is not updated
+    /// while another method is selected, switching back to TAA ghosts for a
+    /// few frames.
+    Taa,
+}
+
+impl AntiAliasing {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Fxaa, Self::Taa];
+}
+
+impl std::fmt::Display for AntiAliasing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Off => "Off",
+            Self::Fxaa => "FXAA",
+            Self::Taa => "TAA",
+        })
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut AntiAliasing {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::ComboBox::from_label("Anti-aliasing")
+            .selected_text(self.to_string())
+            .show_ui(ui, |ui| {
+                for anti_aliasing in AntiAliasing::ALL {
+                    ui.selectable_value(self, anti_aliasing, anti_aliasing.to_string());
+                }
+            })
+            .response
+    }
+}
+
+/// Construction time choices of an [`Engine`], see [`Engine::builder`].
+///
+/// Passes left out by [`EngineBuilder::ssao`] or [`EngineBuilder::shadows`]
+/// are not created: their render targets and shadow maps are not allocated
+/// and their pipelines not compiled. The `*_enabled` settings are only the
+/// initial values of the engine toggles, which can be flipped at any time.
+#[derive(Debug, Clone)]
+pub struct EngineBuilder {
+    depth: DepthConfig,
+    ssao: bool,
+    shadows: bool,
+    directional_shadow_size: u32,
+
+    anti_aliasing: AntiAliasing,
+    dof_enabled: bool,
+    fog_enabled: bool,
+    screen_effects_enabled: bool,
+    skybox_enabled: bool,
+    directional_light_enabled: bool,
+    point_lights_enabled: bool,
+
+    ambient_light_config: AmbientLightConfig,
+    point_lights_config: PointLightsConfig,
+    ssao_config: SsaoConfig,
+    ssr_config: SsrConfig,
+    fog_config: FogConfig,
+    bloom_config: BloomConfig,
+    dof_config: DofConfig,
+    tone_mapping_config: ToneMappingConfig,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self {
+            depth: Default::default(),
+            ssao: true,
+            shadows: true,
+            directional_shadow_size: DirectionalLightPass::DEFAULT_SHADOW_SIZE,
+
+            anti_aliasing: Default::default(),
+            dof_enabled: false,
+            fog_enabled: false,
+            screen_effects_enabled: false,
+            skybox_enabled: true,
+            directional_light_enabled: true,
+            point_lights_enabled: true,
+
+            ambient_light_config: Default::default(),
+            point_lights_config: Default::default(),
+            ssao_config: Default::default(),
+            ssr_config: Default::default(),
+            fog_config: Default::default(),
+            bloom_config: Default::default(),
+            dof_config: Default::default(),
+            tone_mapping_config: Default::default(),
+        }
+    }
+}
+
+impl EngineBuilder {
+    /// Depth buffer of the gbuffer, e.g. [`DepthConfig::reversed_z`] for large
+    /// draw distances. Every pass testing against the gbuffer depth is built
+    /// for it, it can't be changed afterward.
+    pub fn depth(mut self, depth: DepthConfig) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Creates the [`SsaoPass`], [`Engine::ssao`] is `None` otherwise
+    pub fn ssao(mut self, ssao: bool) -> Self {
+        self.ssao = ssao;
+        self
+    }
+
+    /// Allocates the point lights and directional light shadow maps, see
+    /// [`PointLightsPass::without_shadows`] and
+    /// [`DirectionalLightPass::without_shadows`]
+    pub fn shadows(mut self, shadows: bool) -> Self {
+        self.shadows = shadows;
+        self
+    }
+
+    /// Resolution of the directional light shadow map, see
+    /// [`DirectionalLightPass::with_shadow_size`]. Ignored without
+    /// [`EngineBuilder::shadows`].
+    pub fn directional_shadow_size(mut self, size: u32) -> Self {
+        self.directional_shadow_size = size;
+        self
+    }
+
+    pub fn anti_aliasing(mut self, anti_aliasing: AntiAliasing) -> Self {
+        self.anti_aliasing = anti_aliasing;
+        self
+    }
+
+    pub fn dof_enabled(mut self, enabled: bool) -> Self {
+        self.dof_enabled = enabled;
+        self
+    }
+
+    pub fn fog_enabled(mut self, enabled: bool) -> Self {
+        self.fog_enabled = enabled;
+        self
+    }
+
+    pub fn screen_effects_enabled(mut self, enabled: bool) -> Self {
+        self.screen_effects_enabled = enabled;
+        self
+    }
+
+    pub fn skybox_enabled(mut self, enabled: bool) -> Self {
+        self.skybox_enabled = enabled;
+        self
+    }
+
+    pub fn directional_light_enabled(mut self, enabled: bool) -> Self {
+        self.directional_light_enabled = enabled;
+        self
+    }
+
+    pub fn point_lights_enabled(mut self, enabled: bool) -> Self {
+        self.point_lights_enabled = enabled;
+        self
+    }
+
+    pub fn ambient_light_config(mut self, config: AmbientLightConfig) -> Self {
+        self.ambient_light_config = config;
+        self
+    }
+
+    pub fn point_lights_config(mut self, config: PointLightsConfig) -> Self {
+        self.point_lights_config = config;
+        self
+    }
+
+    /// Ignored without [`EngineBuilder::ssao`]
+    pub fn ssao_config(mut self, config: SsaoConfig) -> Self {
+        self.ssao_config = config;
+        self
+    }
+
+    pub fn ssr_config(mut self, config: SsrConfig) -> Self {
+        self.ssr_config = config;
+        self
+    }
+
+    pub fn fog_config(mut self, config: FogConfig) -> Self {
+        self.fog_config = config;
+        self
+    }
+
+    pub fn bloom_config(mut self, config: BloomConfig) -> Self {
+        self.bloom_config = config;
+        self
+    }
+
+    pub fn dof_config(mut self, config: DofConfig) -> Self {
+        self.dof_config = config;
+        self
+    }
+
+    pub fn tone_mapping_config(mut self, config: ToneMappingConfig) -> Self {
+        self.tone_mapping_config = config;
+        self
+    }
+
+    pub fn build(&self, renderer: &Renderer) -> Engine {
+        Engine::from_builder(renderer, self)
+    }
+
+    /// Same as [`EngineBuilder::build`], with shader and pipeline validation
+    /// errors returned instead of panicking
+    pub async fn try_build(&self, renderer: &Renderer) -> Result<Engine, wgpu::Error> {
+        renderer.with_error_capture(|| self.build(renderer)).await
+    }
+}
+
 pub struct Engine {
-    pub ressources: RessourcesManager,
+    pub resources: ResourcesManager,
 
     size: (u32, u32),
 
+    pub anti_aliasing: AntiAliasing,
+    pub dof_enabled: bool,
+    pub fog_enabled: bool,
+    /// Vignette and chromatic aberration
+    pub screen_effects_enabled: bool,
+    /// Geometry edges overlay, ignored when the device doesn't support it
+    pub wireframe_enabled: bool,
+    /// When disabled the ambient occlusion is cleared to white, i.e. no
+    /// occlusion. Ignored without [`Engine::ssao`].
+    pub ssao_enabled: bool,
+    /// Point light shadow maps, lights are shaded unshadowed when disabled.
+    /// Ignored when built without [`EngineBuilder::shadows`].
+    pub shadows_enabled: bool,
+    /// When disabled the background is [`GeometryPass::clear_color`]
+    pub skybox_enabled: bool,
+    /// Sun light and its shadows, see [`DirectionalLightPass`]
+    pub directional_light_enabled: bool,
+    pub point_lights_enabled: bool,
+
     pub animate: AnimatePass,
     pub geometry: GeometryPass,
+    pub decals: DecalsPass,
     pub hierarchical_depth: HierarchicalDepthPass,
     pub ambient_light: AmbientLightPass,
     pub directional_light: DirectionalLightPass,
     pub point_lights: PointLightsPass,
-    pub ssao: SsaoPass<640, 480>,
+    /// `None` when built without [`EngineBuilder::ssao`]
+    pub ssao: Option<SsaoPass<640, 480>>,
     pub skybox: SkyboxPass,
+    pub ssr: SsrPass,
+    pub fog: FogPass,
+    pub bloom: BloomPass,
     pub fxaa: FxaaPass,
+    pub taa: TaaPass,
+    pub dof: DofPass,
+    pub auto_exposure: AutoExposurePass,
     pub tone_mapping: ToneMappingPass,
+    pub screen_effects: ScreenEffectsPass,
+    pub debug_blit: DebugBlitPass,
+    pub debug_shapes: DebugShapesPass,
+    pub skeleton_debug: SkeletonDebugPass,
+    pub normals_debug: NormalsDebugPass,
 }
 
 impl Engine {
+    /// Engine with every pass, same as `Engine::builder().build(renderer)`
     pub fn new(renderer: &Renderer) -> Self {
-        let ressources = RessourcesManager::new(renderer.device.clone());
+        Self::builder().build(renderer)
+    }
+
+    /// Engine with another depth buffer, see [`EngineBuilder::depth`]
+    pub fn with_depth(renderer: &Renderer, depth: DepthConfig) -> Self {
+        Self::builder().depth(depth).build(renderer)
+    }
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder::default()
+    }
+
+    fn from_builder(renderer: &Renderer, builder: &EngineBuilder) -> Self {
+        let resources = ResourcesManager::new(renderer.device.clone());
+        let depth = builder.depth;
+        resources.get_or_insert_with(|| CameraManager::new(&renderer.device, depth));
 
         let size = (
             renderer.surface_config.width,
             renderer.surface_config.height,
         );
 
-        let animate = AnimatePass::new(&renderer.device, &ressources);
+        let animate = AnimatePass::new(&renderer.device, &resources);
 
-        let geometry = GeometryPass::new(&renderer.device, &renderer.surface_config, &ressources);
+        let geometry = GeometryPass::new(&renderer.device, &renderer.surface_config, &resources);
+
+        let decals = DecalsPass::new(
+            &renderer.device,
+            &resources,
+            DecalsPassInputs {
+                albedo_metallic: &geometry.outputs.albedo_metallic,
+                normal_roughness: &geometry.outputs.normal_roughness,
+                depth: &geometry.outputs.depth,
+            },
+        );
 
         let hierarchical_depth = HierarchicalDepthPass::new(
             &renderer.device,
@@ -45,43 +331,85 @@ impl Engine {
 
         let ambient_light = AmbientLightPass::new(
             &renderer.device,
+            &resources,
             AmbientLightPassInputs {
                 albedo: &geometry.outputs.albedo_metallic,
+                normal_roughness: &geometry.outputs.normal_roughness,
+                depth: &geometry.outputs.depth,
                 emissive: &geometry.outputs.emissive,
             },
         );
 
-        let directional_light = DirectionalLightPass::new(
+        let directional_light_inputs = DirectionalLightPassInputs {
+            albedo_metallic: &geometry.outputs.albedo_metallic,
+            normal_roughness: &geometry.outputs.normal_roughness,
+            depth: &geometry.outputs.depth,
+            hierarchical_depth: &hierarchical_depth.outputs.output,
+            output: &ambient_light.outputs.output,
+        };
+        let directional_light = if builder.shadows {
+            DirectionalLightPass::with_shadow_size(
+                &renderer.device,
+                &resources,
+                directional_light_inputs,
+                builder.directional_shadow_size,
+            )
+        } else {
+            DirectionalLightPass::without_shadows(
+                &renderer.device,
+                &resources,
+                directional_light_inputs,
+            )
+        };
+
+        let point_lights_inputs = PointLightsPassInputs {
+            albedo_metallic: &geometry.outputs.albedo_metallic,
+            normal_roughness: &geometry.outputs.normal_roughness,
+            depth: &geometry.outputs.depth,
+            output: &ambient_light.outputs.output,
+        };
+        let point_lights = if builder.shadows {
+            PointLightsPass::new(&renderer.device, &resources, point_lights_inputs)
+        } else {
+            PointLightsPass::without_shadows(&renderer.device, &resources, point_lights_inputs)
+        };
+
+        let skybox = SkyboxPass::new(
             &renderer.device,
-            &ressources,
-            DirectionalLightPassInputs {
-                albedo_metallic: &geometry.outputs.albedo_metallic,
-                normal_roughness: &geometry.outputs.normal_roughness,
+            &resources,
+            SkyboxPassInputs {
                 depth: &geometry.outputs.depth,
                 output: &ambient_light.outputs.output,
             },
         );
 
-        let point_lights = PointLightsPass::new(
+        let ssr = SsrPass::new(
             &renderer.device,
-            &ressources,
-            PointLightsPassInputs {
-                albedo_metallic: &geometry.outputs.albedo_metallic,
+            &resources,
+            SsrPassInputs {
                 normal_roughness: &geometry.outputs.normal_roughness,
                 depth: &geometry.outputs.depth,
+                hierarchical_depth: &hierarchical_depth.outputs.output,
                 output: &ambient_light.outputs.output,
             },
         );
 
-        let skybox = SkyboxPass::new(
+        let fog = FogPass::new(
             &renderer.device,
-            &ressources,
-            SkyboxPassInputs {
+            &resources,
+            FogPassInputs {
                 depth: &geometry.outputs.depth,
                 output: &ambient_light.outputs.output,
             },
         );
 
+        let bloom = BloomPass::new(
+            &renderer.device,
+            BloomPassInputs {
+                input: &ambient_light.outputs.output,
+            },
+        );
+
         let fxaa = FxaaPass::new(
             &renderer.device,
             FxaaPassInputs {
@@ -89,40 +417,136 @@ impl Engine {
             },
         );
 
-        let ssao = SsaoPass::new(
+        let taa = TaaPass::new(
             &renderer.device,
-            &ressources,
-            SsaoPassInputs {
-                normal: &geometry.outputs.normal_roughness,
+            TaaPassInputs {
+                input: &ambient_light.outputs.output,
+                velocity: &geometry.outputs.velocity,
+                output: &fxaa.outputs.output,
+            },
+        );
+
+        let ssao = builder.ssao.then(|| {
+            SsaoPass::new(
+                &renderer.device,
+                &resources,
+                SsaoPassInputs {
+                    normal: &geometry.outputs.normal_roughness,
+                    depth: &geometry.outputs.depth,
+                    output: &fxaa.outputs.output,
+                },
+            )
+        });
+
+        let dof = DofPass::new(
+            &renderer.device,
+            &resources,
+            DofPassInputs {
                 depth: &geometry.outputs.depth,
                 output: &fxaa.outputs.output,
             },
         );
 
+        let auto_exposure = AutoExposurePass::new(
+            &renderer.device,
+            AutoExposurePassInputs {
+                input: &fxaa.outputs.output,
+            },
+        );
+
         let tone_mapping = ToneMappingPass::new(
             &renderer.device,
             ToneMappingPassInputs {
-                format: renderer.surface_config.format,
                 input: &fxaa.outputs.output,
+                exposure: &auto_exposure.outputs.exposure,
             },
         );
 
-        Self {
-            ressources,
+        let screen_effects = ScreenEffectsPass::new(&renderer.device);
+
+        let debug_blit = DebugBlitPass::new(
+            &renderer.device,
+            &resources,
+            DebugBlitPassInputs {
+                albedo_metallic: &geometry.outputs.albedo_metallic,
+                normal_roughness: &geometry.outputs.normal_roughness,
+                emissive: &geometry.outputs.emissive,
+                depth: &geometry.outputs.depth,
+                ssao: ssao.as_ref().map(|ssao| &ssao.outputs.output),
+            },
+        );
+
+        let debug_shapes = DebugShapesPass::new(
+            &renderer.device,
+            &resources,
+            DebugShapesPassInputs {
+                depth: &geometry.outputs.depth,
+            },
+        );
+
+        let skeleton_debug = SkeletonDebugPass::new(&renderer.device, &resources);
+
+        let normals_debug = NormalsDebugPass::new(&renderer.device, &resources);
+
+        let mut engine = Self {
+            resources,
 
             size,
 
+            anti_aliasing: builder.anti_aliasing,
+            dof_enabled: builder.dof_enabled,
+            fog_enabled: builder.fog_enabled,
+            screen_effects_enabled: builder.screen_effects_enabled,
+            wireframe_enabled: false,
+            ssao_enabled: builder.ssao,
+            shadows_enabled: builder.shadows,
+            skybox_enabled: builder.skybox_enabled,
+            directional_light_enabled: builder.directional_light_enabled,
+            point_lights_enabled: builder.point_lights_enabled,
+
             animate,
             geometry,
+            decals,
             hierarchical_depth,
             ambient_light,
             directional_light,
             point_lights,
             ssao,
             skybox,
+            ssr,
+            fog,
+            bloom,
             fxaa,
+            taa,
+            dof,
+            auto_exposure,
             tone_mapping,
+            screen_effects,
+            debug_blit,
+            debug_shapes,
+            skeleton_debug,
+            normals_debug,
+        };
+
+        // Uploaded on the next update
+        *engine.ambient_light.config = builder.ambient_light_config;
+        *engine.point_lights.config = builder.point_lights_config;
+        if let Some(ssao) = &mut engine.ssao {
+            *ssao.config = builder.ssao_config;
         }
+        *engine.ssr.config = builder.ssr_config;
+        *engine.fog.config = builder.fog_config;
+        *engine.bloom.config = builder.bloom_config;
+        *engine.dof.config = builder.dof_config;
+        *engine.tone_mapping.config = builder.tone_mapping_config;
+
+        engine
+    }
+
+    /// Same as [`Engine::new`], with shader and pipeline validation errors
+    /// returned instead of panicking
+    pub async fn try_new(renderer: &Renderer) -> Result<Self, wgpu::Error> {
+        renderer.with_error_capture(|| Self::new(renderer)).await
     }
 
     pub fn resize(&mut self, renderer: &Renderer) {
@@ -139,6 +563,15 @@ impl Engine {
         self.geometry
             .resize(&renderer.device, &renderer.surface_config);
 
+        self.decals.rebind(
+            &renderer.device,
+            DecalsPassInputs {
+                albedo_metallic: &self.geometry.outputs.albedo_metallic,
+                normal_roughness: &self.geometry.outputs.normal_roughness,
+                depth: &self.geometry.outputs.depth,
+            },
+        );
+
         self.hierarchical_depth.rebind(
             &renderer.device,
             HierarchicalDepthPassInputs {
@@ -150,6 +583,8 @@ impl Engine {
             &renderer.device,
             AmbientLightPassInputs {
                 albedo: &self.geometry.outputs.albedo_metallic,
+                normal_roughness: &self.geometry.outputs.normal_roughness,
+                depth: &self.geometry.outputs.depth,
                 emissive: &self.geometry.outputs.emissive,
             },
         );
@@ -160,6 +595,7 @@ impl Engine {
                 albedo_metallic: &self.geometry.outputs.albedo_metallic,
                 normal_roughness: &self.geometry.outputs.normal_roughness,
                 depth: &self.geometry.outputs.depth,
+                hierarchical_depth: &self.hierarchical_depth.outputs.output,
                 output: &self.ambient_light.outputs.output,
             },
         );
@@ -179,6 +615,31 @@ impl Engine {
             output: &self.ambient_light.outputs.output,
         });
 
+        self.ssr.rebind(
+            &renderer.device,
+            SsrPassInputs {
+                normal_roughness: &self.geometry.outputs.normal_roughness,
+                depth: &self.geometry.outputs.depth,
+                hierarchical_depth: &self.hierarchical_depth.outputs.output,
+                output: &self.ambient_light.outputs.output,
+            },
+        );
+
+        self.fog.rebind(
+            &renderer.device,
+            FogPassInputs {
+                depth: &self.geometry.outputs.depth,
+                output: &self.ambient_light.outputs.output,
+            },
+        );
+
+        self.bloom.rebind(
+            &renderer.device,
+            BloomPassInputs {
+                input: &self.ambient_light.outputs.output,
+            },
+        );
+
         self.fxaa.rebind(
             &renderer.device,
             FxaaPassInputs {
@@ -186,47 +647,1163 @@ impl Engine {
             },
         );
 
-        self.ssao.rebind(
+        self.taa.rebind(
+            &renderer.device,
+            TaaPassInputs {
+                input: &self.ambient_light.outputs.output,
+                velocity: &self.geometry.outputs.velocity,
+                output: &self.fxaa.outputs.output,
+            },
+        );
+
+        if let Some(ssao) = &mut self.ssao {
+            ssao.rebind(
+                &renderer.device,
+                SsaoPassInputs {
+                    normal: &self.geometry.outputs.normal_roughness,
+                    depth: &self.geometry.outputs.depth,
+                    output: &self.fxaa.outputs.output,
+                },
+            );
+        }
+
+        self.dof.rebind(
             &renderer.device,
-            SsaoPassInputs {
-                normal: &self.geometry.outputs.normal_roughness,
+            DofPassInputs {
                 depth: &self.geometry.outputs.depth,
                 output: &self.fxaa.outputs.output,
             },
         );
 
+        self.auto_exposure.rebind(
+            &renderer.device,
+            AutoExposurePassInputs {
+                input: &self.fxaa.outputs.output,
+            },
+        );
+
         self.tone_mapping.rebind(
             &renderer.device,
             ToneMappingPassInputs {
-                format: renderer.surface_config.format,
                 input: &self.fxaa.outputs.output,
+                exposure: &self.auto_exposure.outputs.exposure,
             },
         );
+
+        self.debug_blit.rebind(
+            &renderer.device,
+            DebugBlitPassInputs {
+                albedo_metallic: &self.geometry.outputs.albedo_metallic,
+                normal_roughness: &self.geometry.outputs.normal_roughness,
+                emissive: &self.geometry.outputs.emissive,
+                depth: &self.geometry.outputs.depth,
+                ssao: self.ssao.as_ref().map(|ssao| &ssao.outputs.output),
+            },
+        );
+
+        self.debug_shapes.rebind(DebugShapesPassInputs {
+            depth: &self.geometry.outputs.depth,
+        });
     }
 
     pub fn update(&mut self, renderer: &Renderer) {
-        self.ressources
-            .get::<CameraManager>()
+        {
+            let instances = self.resources.get::<InstancesManager>();
+            let mut instances = instances.get_mut();
+
+            instances.flush(&renderer.queue);
+            instances.update_animations(self.animate.uniform.as_secs_f32());
+        }
+        self.resources
+            .get::<TexturesManager>()
             .get_mut()
-            .update(&renderer.queue);
+            .flush(&renderer.device);
+
+        // The instances buffer is reallocated when it grows
+        self.animate.rebind_instances(&renderer.device);
+        self.geometry.rebind_instances(&renderer.device);
+        self.directional_light.rebind_instances(&renderer.device);
+        self.point_lights.rebind_instances(&renderer.device);
+
+        {
+            let camera = self.resources.get::<CameraManager>();
+            let mut camera = camera.get_mut();
+
+            camera.set_jitter((self.anti_aliasing == AntiAliasing::Taa).then_some(self.size));
+            camera.update(&renderer.queue);
+        }
 
         self.animate.update(&renderer.queue);
+        self.geometry.update(&renderer.queue);
         self.directional_light.update(&renderer.queue);
         self.ambient_light.update(&renderer.queue);
-        self.ssao.update(&renderer.queue);
+        self.point_lights
+            .update(&renderer.queue, self.shadows_enabled);
+        if let Some(ssao) = &mut self.ssao {
+            ssao.update(&renderer.queue);
+        }
+        self.ssr.update(&renderer.queue);
+        self.fog.update(&renderer.queue);
+        self.bloom.update(&renderer.queue);
+        self.fxaa.update(&renderer.queue);
+        self.taa.update(&renderer.queue);
+        self.dof.update(&renderer.queue);
+        self.auto_exposure
+            .update(&renderer.queue, **self.animate.uniform);
         self.tone_mapping.update(&renderer.queue);
+        self.screen_effects.update(&renderer.queue);
+        self.debug_blit.update(&renderer.queue);
+    }
+
+    /// Instance under the pixel at `x`, `y` of the last rendered frame, see
+    /// [`GeometryPass::pick`]. Picking has to be enabled beforehand with
+    /// [`GeometryPass::set_picking_enabled`].
+    pub fn pick(&self, renderer: &Renderer, x: u32, y: u32) -> mpsc::Receiver<Option<InstanceId>> {
+        self.geometry.pick(&renderer.device, &renderer.queue, x, y)
+    }
+
+    /// Renders the frame to `target` instead of the context frame, `target`
+    /// can use any renderable color format. The scene is rendered at the
+    /// renderer surface resolution and isn't scaled to a target of another
+    /// size. Can be combined with [`Engine::render`] in the same frame,
+    /// animations are only stepped once.
+    pub fn render_to(&self, ctx: &mut RenderContext, target: &wgpu::Texture) {
+        let view = target.create_view(&Default::default());
+        self.render(&mut ctx.with_frame(&view, target.format(), (target.width(), target.height())));
+    }
+
+    /// Renders the scene seen by `camera` to a region of the frame, the rest of
+    /// the frame is left untouched so that it can be called once per view for
+    /// split-screen or a minimap.
+    ///
+    /// Each view runs the whole graph at the frame resolution, with FXAA in
+    /// place of temporal anti-aliasing and without wireframe or debug shapes
+    /// overlays. Animations are only stepped by the first render following
+    /// [`Engine::update`].
+    pub fn render_viewport(&self, ctx: &mut RenderContext, camera: &Camera, viewport: Viewport) {
+        let camera_manager = self.resources.get::<CameraManager>();
+
+        // Uniforms written to the queue apply to the whole next submission
+        ctx.submit();
+        camera_manager.get().write_view(ctx.queue, camera);
+
+        let main_viewport = ctx.viewport.replace(viewport);
+        self.render(ctx);
+        ctx.viewport = main_viewport;
+
+        ctx.submit();
+        camera_manager.get().restore(ctx.queue);
     }
 
     pub fn render(&self, ctx: &mut RenderContext) {
         self.animate.render(ctx);
         self.geometry.render(ctx);
+        self.decals.render(ctx);
         self.hierarchical_depth.render(ctx);
         self.ambient_light.render(ctx);
-        // self.directional_light.render(ctx);
-        self.point_lights.render(ctx);
-        self.skybox.render(ctx);
-        self.fxaa.render(ctx);
-        self.ssao.render(ctx);
-        self.tone_mapping.render(ctx);
+        if self.directional_light_enabled {
+            self.directional_light.render(ctx);
+        }
+        if self.point_lights_enabled {
+            self.point_lights.render(ctx);
+        }
+        if self.skybox_enabled {
+            self.skybox.render(ctx);
+        }
+        self.ssr.render(ctx);
+        if self.fog_enabled {
+            self.fog.render(ctx);
+        }
+        self.bloom.render(ctx);
+        match self.anti_aliasing {
+            AntiAliasing::Off => self.copy_without_anti_aliasing(ctx),
+            // History only holds the main view
+            AntiAliasing::Taa if ctx.viewport.is_none() => self.taa.render(ctx),
+            AntiAliasing::Fxaa | AntiAliasing::Taa => self.fxaa.render(ctx),
+        }
+        if let Some(ssao) = &self.ssao {
+            if self.ssao_enabled {
+                ssao.render(ctx);
+            } else {
+                // Keeps the debug view from showing a stale occlusion
+                ssao.clear(ctx);
+            }
+        }
+        if self.dof_enabled {
+            self.dof.render(ctx);
+        }
+        // Adapts to the main view only
+        if ctx.viewport.is_none() {
+            self.auto_exposure.render(ctx);
+        }
+        if self.screen_effects_enabled {
+            self.screen_effects
+                .render(ctx, |ctx| self.tone_mapping.render(ctx));
+        } else {
+            self.tone_mapping.render(ctx);
+        }
+        self.debug_blit.render(ctx);
+        // Gbuffer depth doesn't match the viewport
+        if ctx.viewport.is_none() {
+            ctx.overlay(Some(&self.geometry.outputs.depth), |ctx| {
+                if self.wireframe_enabled {
+                    self.geometry.render_wireframe(ctx);
+                }
+                self.debug_shapes.render(ctx);
+                self.skeleton_debug.render(ctx);
+                self.normals_debug.render(ctx);
+            });
+        }
+    }
+
+    /// Anti-aliased color is read from the FXAA output by the next passes
+    fn copy_without_anti_aliasing(&self, ctx: &mut RenderContext) {
+        let input = &self.ambient_light.outputs.output;
+        let output = &self.fxaa.outputs.output;
+
+        let (origin, size) = match ctx.viewport {
+            Some(viewport) => (
+                wgpu::Origin3d {
+                    x: viewport.x,
+                    y: viewport.y,
+                    z: 0,
+                },
+                wgpu::Extent3d {
+                    width: viewport.width,
+                    height: viewport.height,
+                    depth_or_array_layers: 1,
+                },
+            ),
+            None => (wgpu::Origin3d::ZERO, output.size()),
+        };
+
+        ctx.encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTexture {
+                origin,
+                ..input.as_image_copy()
+            },
+            wgpu::ImageCopyTexture {
+                origin,
+                ..output.as_image_copy()
+            },
+            size,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+    use crate::{
+        AlphaMode, AnimationPlayback, AnimationState, AnimationsManager, DebugShape, DebugView,
+        Decal, DecalsManager, Instance, InstancesManager, Material, MaterialId, MaterialsManager,
+        MeshId, MeshVertices, MeshesManager, NormalsDebug, Skeleton, SkeletonDebug, SkinsManager,
+        TexturesManager,
+    };
+
+    #[test]
+    fn render_to_other_format() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.wireframe_enabled = true;
+        engine.screen_effects_enabled = true;
+        engine.fog_enabled = true;
+        engine.ssao_enabled = false;
+        engine.shadows_enabled = false;
+        engine.skybox_enabled = false;
+        engine.debug_blit.config.view = DebugView::Normals;
+        engine.debug_shapes.update(
+            &renderer.queue,
+            &[
+                DebugShape::Sphere {
+                    center: glam::Vec3::ZERO,
+                    radius: 1.0,
+                    color: [1.0, 0.0, 0.0],
+                },
+                DebugShape::Aabb {
+                    min: glam::Vec3::NEG_ONE,
+                    max: glam::Vec3::ONE,
+                    color: [0.0, 1.0, 0.0],
+                },
+            ],
+        );
+        let skeleton = engine
+            .resources
+            .get::<SkinsManager>()
+            .get_mut()
+            .add_skeleton(Skeleton {
+                parents: vec![None, Some(0)],
+                bind_positions: vec![glam::Vec3::ZERO, glam::Vec3::Y],
+            });
+        engine.skeleton_debug.update(
+            &renderer.queue,
+            Some(SkeletonDebug {
+                skeleton,
+                transform: glam::Mat4::IDENTITY,
+                animation: Default::default(),
+                time: 0.0,
+                color: [0.0, 0.0, 1.0],
+            }),
+        );
+        let animated = animated_instance(&renderer, &engine);
+        **engine.animate.uniform = std::time::Duration::from_millis(100);
+        engine.update(&renderer);
+
+        let format = wgpu::TextureFormat::Rgba16Float;
+        assert_ne!(format, renderer.surface_config.format);
+
+        let target = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 64,
+                height: 64,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        renderer
+            .render(|ctx| {
+                engine.render_to(ctx, &target);
+                engine.render(ctx);
+            })
+            .unwrap();
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+
+        // Both renders share the frame animation step
+        let time = animation_time(&renderer, &engine, animated);
+        assert!((time - 0.1).abs() < 1e-5, "{time}");
+    }
+
+    #[test]
+    fn render_to_other_size() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::builder()
+            .ssao(false)
+            .skybox_enabled(false)
+            .screen_effects_enabled(true)
+            .build(&renderer);
+        engine.screen_effects.config.vignette_intensity = 1.0;
+        engine.screen_effects.config.vignette_radius = 0.0;
+        view_quad(&renderer, &engine, None);
+        engine.update(&renderer);
+
+        let (width, height) = (32, 32);
+        let target = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let bytes_per_row = wgpu::util::align_to(width * 4, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (bytes_per_row * height) as _,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        // Screen effects input goes from the frame size to the target size
+        renderer
+            .render(|ctx| {
+                engine.render(ctx);
+                engine.render_to(ctx, &target);
+                ctx.encoder.copy_texture_to_buffer(
+                    target.as_image_copy(),
+                    wgpu::ImageCopyBuffer {
+                        buffer: &readback,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: Some(bytes_per_row),
+                            rows_per_image: Some(height),
+                        },
+                    },
+                    target.size(),
+                );
+            })
+            .unwrap();
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        renderer.device.poll(wgpu::Maintain::Wait);
+
+        let data = slice.get_mapped_range();
+        let pixel = |x: u32, y: u32| {
+            let offset = (y * bytes_per_row + x * 4) as usize;
+            data[offset]
+        };
+
+        // Vignette is centered on the target
+        let (center, corner) = (pixel(width / 2, height / 2), pixel(width - 1, height - 1));
+        assert!(center > 0, "{center}");
+        assert!(corner < center, "{corner} >= {center}");
+    }
+
+    #[test]
+    fn render_reversed_z() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::with_depth(&renderer, DepthConfig::reversed_z(&renderer.device));
+        engine.wireframe_enabled = true;
+        engine.fog_enabled = true;
+        engine.update(&renderer);
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
+
+    #[test]
+    fn build_without_optional_passes() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut fog_config = FogConfig::default();
+        fog_config.density = 0.5;
+
+        let mut engine = Engine::builder()
+            .ssao(false)
+            .shadows(false)
+            .anti_aliasing(AntiAliasing::Off)
+            .fog_enabled(true)
+            .fog_config(fog_config)
+            .build(&renderer);
+
+        assert!(engine.ssao.is_none());
+        assert!(!engine.point_lights.has_shadow_maps());
+        assert!(!engine.directional_light.has_shadow_map());
+        assert_eq!(engine.directional_light.shadow_size(), 1);
+        assert!(!engine.shadows_enabled);
+        assert_eq!(engine.anti_aliasing, AntiAliasing::Off);
+        assert_eq!(*engine.fog.config, fog_config);
+
+        // Toggling the skipped passes back on is ignored
+        engine.ssao_enabled = true;
+        engine.shadows_enabled = true;
+        engine.debug_blit.config.view = DebugView::Ssao;
+        engine.update(&renderer);
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
+
+    #[test]
+    fn switch_anti_aliasing() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+
+        let camera = view_camera();
+        let viewport = Viewport {
+            x: 32,
+            y: 0,
+            width: 32,
+            height: 32,
+        };
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        for anti_aliasing in AntiAliasing::ALL {
+            engine.anti_aliasing = anti_aliasing;
+            engine.update(&renderer);
+
+            renderer
+                .render(|ctx| {
+                    engine.render(ctx);
+                    engine.render_viewport(ctx, &camera, viewport);
+                })
+                .unwrap();
+        }
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
+
+    #[test]
+    fn render_viewport_keeps_rest_of_frame() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.update(&renderer);
+
+        let camera = view_camera();
+        let viewport = Viewport {
+            x: 32,
+            y: 0,
+            width: 32,
+            height: 32,
+        };
+
+        renderer
+            .render(|ctx| {
+                ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: ctx.frame,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+
+                engine.render_viewport(ctx, &camera, viewport);
+            })
+            .unwrap();
+
+        let image = renderer.capture().unwrap();
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let inside = x >= 32 && y < 32;
+            assert_eq!(pixel.0 == [255, 0, 0, 255], !inside, "({x}, {y})");
+        }
+    }
+
+    /// Instance playing a one second, looping animation
+    fn animated_instance(renderer: &Renderer, engine: &Engine) -> InstanceId {
+        let animation = engine.resources.get::<AnimationsManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            vec![vec![glam::Mat4::IDENTITY]; 10],
+            10.0,
+        );
+
+        engine.resources.get::<InstancesManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            [Instance::new(glam::Mat4::IDENTITY).with_animation(animation)],
+        )[0]
+    }
+
+    fn animation_time(renderer: &Renderer, engine: &Engine, id: InstanceId) -> f32 {
+        gpu_animation(renderer, engine, id).tracks[0].time
+    }
+
+    /// Animation state as advanced by the animate pass
+    fn gpu_animation(renderer: &Renderer, engine: &Engine, id: InstanceId) -> AnimationState {
+        let instances = engine.resources.get::<InstancesManager>();
+        let data = test_utils::read_buffer(
+            &renderer.device,
+            &renderer.queue,
+            &instances.get().instances,
+        );
+
+        // Instances follow the count header
+        let offset =
+            std::mem::size_of::<[u32; 4]>() + u32::from(id) as usize * Instance::SIZE as usize;
+        let instance: Instance =
+            bytemuck::pod_read_unaligned(&data[offset..offset + Instance::SIZE as usize]);
+        instance.animation
+    }
+
+    #[test]
+    fn render_viewport_steps_animations_once() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        let id = animated_instance(&renderer, &engine);
+        **engine.animate.uniform = std::time::Duration::from_millis(100);
+        engine.update(&renderer);
+
+        let camera = view_camera();
+        let viewport = Viewport {
+            x: 0,
+            y: 0,
+            width: 16,
+            height: 16,
+        };
+
+        renderer
+            .render(|ctx| {
+                engine.render(ctx);
+                engine.render_viewport(ctx, &camera, viewport);
+            })
+            .unwrap();
+
+        let time = animation_time(&renderer, &engine, id);
+        assert!((time - 0.1).abs() < 1e-5, "{time}");
+
+        // Next frame steps again
+        engine.update(&renderer);
+        renderer
+            .render(|ctx| engine.render_viewport(ctx, &camera, viewport))
+            .unwrap();
+
+        let time = animation_time(&renderer, &engine, id);
+        assert!((time - 0.2).abs() < 1e-5, "{time}");
+    }
+
+    #[test]
+    fn crossfade_animations_of_different_lengths() {
+        let Some(renderer) = test_utils::renderer((16, 16)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        let id = animated_instance(&renderer, &engine);
+        // 0.4 seconds, looping
+        let short = engine.resources.get::<AnimationsManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            vec![vec![glam::Mat4::IDENTITY]; 4],
+            10.0,
+        );
+
+        let step = |engine: &mut Engine, secs: f32| {
+            **engine.animate.uniform = std::time::Duration::from_secs_f32(secs);
+            engine.update(&renderer);
+            renderer.render(|ctx| engine.render(ctx)).unwrap();
+        };
+
+        step(&mut engine, 0.5);
+        engine
+            .resources
+            .get::<InstancesManager>()
+            .get_mut()
+            .crossfade_animation(
+                &renderer.queue,
+                id,
+                short,
+                AnimationPlayback::default(),
+                0.3,
+            );
+
+        step(&mut engine, 0.2);
+        step(&mut engine, 0.25);
+
+        // Each track wraps around its own duration, the blend factor settles
+        let state = gpu_animation(&renderer, &engine, id);
+        assert!((state.tracks[0].time - 0.95).abs() < 1e-5, "{state:?}");
+        assert!((state.tracks[1].time - 0.05).abs() < 1e-5, "{state:?}");
+        assert_eq!(state.blend, 1.0);
+
+        // The CPU copy followed, fading back replaces the first track
+        engine
+            .resources
+            .get::<InstancesManager>()
+            .get_mut()
+            .crossfade_animation(
+                &renderer.queue,
+                id,
+                short,
+                AnimationPlayback::default(),
+                0.3,
+            );
+
+        step(&mut engine, 0.1);
+        let state = gpu_animation(&renderer, &engine, id);
+        assert!((state.tracks[0].time - 0.1).abs() < 1e-5, "{state:?}");
+        assert!((state.tracks[1].time - 0.15).abs() < 1e-5, "{state:?}");
+        assert!((state.blend - 2.0 / 3.0).abs() < 1e-5, "{state:?}");
+        assert_eq!(state.tracks[0].animation, short);
+        assert_eq!(state.tracks[1].animation, short);
+    }
+
+    /// Camera one unit away from the origin, looking down the Z axis
+    fn view_camera() -> Camera {
+        Camera {
+            view: glam::Mat4::look_at_rh(glam::Vec3::Z, glam::Vec3::ZERO, glam::Vec3::Y),
+            proj: glam::Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0),
+        }
+    }
+
+    fn set_view_camera(engine: &Engine) {
+        let Camera { view, proj } = view_camera();
+
+        let camera = engine.resources.get::<CameraManager>();
+        let mut camera = camera.get_mut();
+        camera.view = view;
+        camera.proj = proj;
+    }
+
+    /// Quad facing [`view_camera`], larger than the view, its texture
+    /// coordinates span the whole quad
+    fn quad_mesh(renderer: &Renderer, engine: &Engine) -> MeshId {
+        let vertices = [
+            [-1.0f32, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let normals = [[0.0f32, 0.0, 1.0]; 4];
+        let tangents = [[1.0f32, 0.0, 0.0, 1.0]; 4];
+        let tex_coords = [[0.0f32, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+        let indices = [0u32, 1, 2, 0, 2, 3];
+        engine.resources.get::<MeshesManager>().get().add(
+            &renderer.queue,
+            (glam::Vec3::ZERO, 1.5),
+            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(&normals),
+            bytemuck::cast_slice(&tangents),
+            bytemuck::cast_slice(&tex_coords),
+            bytemuck::cast_slice(&indices),
+            None,
+        )
+    }
+
+    /// Quad covering the view, with the default material when `material`
+    /// is `None`
+    fn view_quad(renderer: &Renderer, engine: &Engine, material: Option<MaterialId>) {
+        let mesh = quad_mesh(renderer, engine);
+        engine.resources.get::<InstancesManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            [Instance::new(glam::Mat4::IDENTITY)
+                .with_mesh(mesh)
+                .with_material(material.unwrap_or_default())],
+        );
+
+        set_view_camera(engine);
+    }
+
+    #[test]
+    fn directional_light() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::builder()
+            .ssao(false)
+            .skybox_enabled(false)
+            .point_lights_enabled(false)
+            .anti_aliasing(AntiAliasing::Off)
+            .directional_shadow_size(512)
+            .build(&renderer);
+        engine.ambient_light.config.strength = 0.0;
+
+        assert!(engine.directional_light.has_shadow_map());
+        assert_eq!(engine.directional_light.shadow_size(), 512);
+        engine.directional_light.uniform.light.direction = glam::Vec3::NEG_Z;
+
+        // Facing the sun
+        view_quad(&renderer, &engine, None);
+
+        let render = |engine: &mut Engine| {
+            engine.update(&renderer);
+
+            renderer
+                .device
+                .push_error_scope(wgpu::ErrorFilter::Validation);
+            renderer.render(|ctx| engine.render(ctx)).unwrap();
+            let error = pollster::block_on(renderer.device.pop_error_scope());
+            assert!(error.is_none(), "{error:?}");
+
+            renderer.capture().unwrap().get_pixel(32, 32).0
+        };
+
+        engine.directional_light_enabled = false;
+        let unlit = render(&mut engine);
+
+        engine.directional_light_enabled = true;
+        let lit = render(&mut engine);
+        assert!(lit[0] > unlit[0], "{lit:?} <= {unlit:?}");
+
+        // Nothing casts a shadow on the quad, culling can't change the result
+        engine.directional_light.uniform.occlusion_culling = false;
+        assert_eq!(render(&mut engine), lit);
+    }
+
+    #[test]
+    fn auto_exposure_black_frame() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        // Nothing in the scene, all pixels are black
+        let mut engine = Engine::new(&renderer);
+        engine.auto_exposure.config.enabled = true;
+        **engine.animate.uniform = std::time::Duration::from_millis(16);
+        engine.update(&renderer);
+
+        let size = engine.auto_exposure.outputs.exposure.size();
+        let readback = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        renderer
+            .render(|ctx| {
+                engine.render(ctx);
+                ctx.encoder.copy_buffer_to_buffer(
+                    &engine.auto_exposure.outputs.exposure,
+                    0,
+                    &readback,
+                    0,
+                    size,
+                );
+            })
+            .unwrap();
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        renderer.device.poll(wgpu::Maintain::Wait);
+
+        let values: Vec<f32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        let (adapted, exposure) = (values[0], values[1]);
+        assert!(exposure.is_finite(), "{exposure}");
+        assert!(exposure > 0.0, "{exposure}");
+        assert_eq!(adapted, exposure);
+    }
+
+    #[test]
+    fn alpha_mask_cutout() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.debug_blit.config.view = DebugView::Albedo;
+
+        // White texture, opaque on the left half and transparent on the right
+        let texels: Vec<u8> = (0..8)
+            .flat_map(|x| [255, 255, 255, if x < 4 { 255 } else { 0 }])
+            .collect();
+        let size = wgpu::Extent3d {
+            width: 8,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        renderer.queue.write_texture(
+            texture.as_image_copy(),
+            &texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: None,
+            },
+            size,
+        );
+        let albedo = engine
+            .resources
+            .get::<TexturesManager>()
+            .get_mut()
+            .add(texture.create_view(&Default::default()));
+
+        let material = engine.resources.get::<MaterialsManager>().get().add(
+            &renderer.queue,
+            Material {
+                albedo,
+                alpha_mode: AlphaMode::Mask,
+                ..Default::default()
+            },
+        );
+
+        view_quad(&renderer, &engine, Some(material));
+
+        engine.update(&renderer);
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let image = renderer.capture().unwrap();
+        assert_eq!(image.get_pixel(8, 32).0, [255, 255, 255, 255]);
+        assert_ne!(image.get_pixel(40, 32).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn non_uniform_scale_normals() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.debug_blit.config.view = DebugView::Normals;
+
+        // Quad facing the camera with tilted normals, stretched along y
+        let vertices = [
+            [-1.0f32, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let normal = glam::vec3(0.0, 1.0, 1.0).normalize();
+        let normals = [normal.to_array(); 4];
+        let tangents = [[1.0f32, 0.0, 0.0, 1.0]; 4];
+        let tex_coords = [[0.0f32; 2]; 4];
+        let indices = [0u32, 1, 2, 0, 2, 3];
+        let mesh = engine.resources.get::<MeshesManager>().get().add(
+            &renderer.queue,
+            (glam::Vec3::ZERO, 1.5),
+            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(&normals),
+            bytemuck::cast_slice(&tangents),
+            bytemuck::cast_slice(&tex_coords),
+            bytemuck::cast_slice(&indices),
+            None,
+        );
+
+        let instance =
+            Instance::new(glam::Mat4::from_scale(glam::vec3(1.0, 2.0, 1.0))).with_mesh(mesh);
+        engine.resources.get::<InstancesManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            [instance],
+        );
+
+        set_view_camera(&engine);
+
+        engine.update(&renderer);
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        // View space normals match world space ones, the camera is not rotated
+        let expected = (instance.normal_matrix() * normal).normalize();
+        let srgb = |c: f32| {
+            let c = c * 0.5 + 0.5;
+            let c = if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            };
+            (c * 255.0).round() as i32
+        };
+
+        let image = renderer.capture().unwrap();
+        let pixel = image.get_pixel(32, 32).0;
+        for (channel, expected) in pixel.iter().zip(expected.to_array().map(srgb)) {
+            assert!((*channel as i32 - expected).abs() <= 2, "{pixel:?}");
+        }
+    }
+
+    #[test]
+    fn normals_debug() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.skybox_enabled = false;
+
+        let vertices = [[-0.5f32, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
+        let normals = [[0.0f32, 0.0, 1.0]; 3];
+        // Last handedness is not a unit sign
+        let tangents = [
+            [1.0f32, 0.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, -1.0],
+            [1.0, 0.0, 0.0, 0.0],
+        ];
+        let tex_coords = [[0.0f32; 2]; 3];
+        let indices = [0u32, 1, 2];
+
+        let meshes = engine.resources.get::<MeshesManager>();
+        // Moves the triangle past the first vertices
+        meshes.get().add(
+            &renderer.queue,
+            (glam::Vec3::ZERO, 1.0),
+            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(&normals),
+            bytemuck::cast_slice(&[[1.0f32, 0.0, 0.0, 1.0]; 3]),
+            bytemuck::cast_slice(&tex_coords),
+            bytemuck::cast_slice(&indices),
+            None,
+        );
+        let mesh = meshes.get().add(
+            &renderer.queue,
+            (glam::Vec3::ZERO, 1.0),
+            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(&normals),
+            bytemuck::cast_slice(&tangents),
+            bytemuck::cast_slice(&tex_coords),
+            bytemuck::cast_slice(&indices),
+            None,
+        );
+
+        assert_eq!(
+            meshes.get().vertices(mesh),
+            MeshVertices {
+                offset: 3,
+                count: 3,
+                invalid_tangents: 1,
+            }
+        );
+
+        set_view_camera(&engine);
+
+        engine.normals_debug.update(
+            &renderer.queue,
+            Some(NormalsDebug {
+                mesh,
+                // Non uniform scale
+                transform: glam::Mat4::from_scale(glam::vec3(2.0, 1.0, 1.0)),
+                length: 0.5,
+                tangents: true,
+            }),
+        );
+        engine.update(&renderer);
+
+        renderer
+            .device
+            .push_error_scope(wgpu::ErrorFilter::Validation);
+
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let error = pollster::block_on(renderer.device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+    }
+
+    #[test]
+    fn pick_instance() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+
+        // No target until picking is enabled
+        let picked = engine.pick(&renderer, 16, 32);
+        assert_eq!(picked.try_recv(), Ok(None));
+
+        engine
+            .geometry
+            .set_picking_enabled(&renderer.device, &renderer.surface_config, true);
+
+        let material = engine
+            .resources
+            .get::<MaterialsManager>()
+            .get()
+            .add(&renderer.queue, Default::default());
+
+        let mesh = quad_mesh(&renderer, &engine);
+
+        // One quad on each half of the view
+        let instances = engine.resources.get::<InstancesManager>();
+        let ids = instances.get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            [-1.0, 1.0].map(|x| Instance {
+                transform: glam::Mat4::from_translation(glam::vec3(x, 0.0, 0.0)),
+                mesh,
+                material,
+                ..Default::default()
+            }),
+        );
+
+        set_view_camera(&engine);
+
+        engine.update(&renderer);
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let pick = |x, y| {
+            let picked = engine.pick(&renderer, x, y);
+            renderer.device.poll(wgpu::Maintain::Wait);
+            picked.try_recv().unwrap()
+        };
+
+        assert_eq!(pick(16, 32), Some(ids[0]));
+        assert_eq!(pick(48, 32), Some(ids[1]));
+        assert_eq!(pick(64, 32), None);
+
+        // Removed instances are not picked, even before the next frame
+        instances.get_mut().remove(&renderer.queue, &ids[1..]);
+        assert_eq!(pick(48, 32), None);
+    }
+
+    #[test]
+    fn project_decal() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+        engine.debug_blit.config.view = DebugView::Albedo;
+
+        let materials = engine.resources.get::<MaterialsManager>();
+        let white = materials.get().add(&renderer.queue, Default::default());
+        let red = materials.get().add(
+            &renderer.queue,
+            Material {
+                base_color_factor: [1.0, 0.0, 0.0, 1.0],
+                ..Default::default()
+            },
+        );
+
+        view_quad(&renderer, &engine, Some(white));
+
+        // Covers the left half of the quad
+        engine.resources.get::<DecalsManager>().get_mut().add(
+            &renderer.queue,
+            &[Decal {
+                transform: glam::Mat4::from_scale_rotation_translation(
+                    glam::vec3(1.0, 2.0, 1.0),
+                    glam::Quat::IDENTITY,
+                    glam::vec3(-0.5, 0.0, 0.0),
+                ),
+                material: red,
+                ..Default::default()
+            }],
+        );
+
+        engine.update(&renderer);
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let image = renderer.capture().unwrap();
+        assert_eq!(image.get_pixel(16, 32).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(48, 32).0, [255, 255, 255, 255]);
     }
 }
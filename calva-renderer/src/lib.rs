@@ -8,15 +8,33 @@ pub use egui;
 mod engine;
 mod passes;
 mod renderer;
-mod ressources;
+mod resources;
+#[cfg(test)]
+mod test_utils;
+mod time_of_day;
 mod uniform_buffer;
 
 pub use engine::*;
 pub use passes::*;
 pub use renderer::*;
-pub use ressources::*;
+pub use resources::*;
+pub use time_of_day::*;
 pub use uniform_buffer::*;
 
+/// Commonly used types, `use calva_renderer::prelude::*;`
+pub mod prelude {
+    pub use crate::{
+        wgpu, AmbientLightConfig, AnimationId, AnimationPlayback, AnimationsManager, AntiAliasing,
+        AutoExposureConfig, BloomConfig, Camera, CameraManager, Decal, DecalId, DecalsManager,
+        DepthConfig, DirectionalLight, DofConfig, Engine, EngineBuilder, FogConfig, FxaaConfig,
+        GeometryConfig, Instance, InstanceId, InstancesManager, LightsManager, Material,
+        MaterialId, MaterialsManager, MeshId, MeshesManager, PointLight, PointLightId,
+        PointLightsConfig, RenderContext, RenderError, Renderer, SkinsManager, SkyParams,
+        SkyboxManager, SsaoConfig, SsrConfig, TaaConfig, TextureId, TexturesManager, TimeOfDay,
+        ToneMappingConfig, Viewport,
+    };
+}
+
 pub mod util {
     pub mod icosphere;
 }
@@ -1,10 +1,15 @@
-use crate::{RenderContext, UniformBuffer};
+use crate::{
+    CameraManager, RenderContext, ResourceRef, ResourcesManager, SkyboxManager, UniformBuffer,
+    UniformData,
+};
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct AmbientLightConfig {
     pub color: [f32; 3],
     pub strength: f32,
+    /// Light the scene from the skybox irradiance and prefiltered specular
+    /// maps instead of the constant color
+    pub ibl: bool,
 }
 
 impl Default for AmbientLightConfig {
@@ -13,6 +18,29 @@ impl Default for AmbientLightConfig {
         Self {
             color: [0.05; 3],
             strength: 1.0,
+            ibl: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuAmbientLightConfig {
+    color: [f32; 3],
+    strength: f32,
+    ibl: u32,
+    _padding: [u32; 3],
+}
+
+impl UniformData for AmbientLightConfig {
+    type GpuType = GpuAmbientLightConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuAmbientLightConfig {
+            color: self.color,
+            strength: self.strength,
+            ibl: self.ibl as _,
+            _padding: [0; 3],
         }
     }
 }
@@ -29,6 +57,8 @@ impl egui::Widget for &mut AmbientLightConfig {
                 });
 
                 ui.add(egui::Slider::new(&mut self.strength, 0.0..=1.0).text("Strength"));
+
+                ui.checkbox(&mut self.ibl, "Image based lighting");
             })
             .header_response
     }
@@ -36,6 +66,8 @@ impl egui::Widget for &mut AmbientLightConfig {
 
 pub struct AmbientLightPassInputs<'a> {
     pub albedo: &'a wgpu::Texture,
+    pub normal_roughness: &'a wgpu::Texture,
+    pub depth: &'a wgpu::Texture,
     pub emissive: &'a wgpu::Texture,
 }
 
@@ -48,15 +80,25 @@ pub struct AmbientLightPass {
     pub outputs: AmbientLightPassOutputs,
     output_view: wgpu::TextureView,
 
+    camera: ResourceRef<CameraManager>,
+    skybox: ResourceRef<SkyboxManager>,
+
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
     pipeline: wgpu::RenderPipeline,
 }
 
 impl AmbientLightPass {
-    pub fn new(device: &wgpu::Device, inputs: AmbientLightPassInputs) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: AmbientLightPassInputs,
+    ) -> Self {
         let config = UniformBuffer::new(device, AmbientLightConfig::default());
 
+        let camera = resources.get::<CameraManager>();
+        let skybox = resources.get::<SkyboxManager>();
+
         let outputs = Self::make_outputs(device, &inputs);
         let output_view = outputs.output.create_view(&Default::default());
 
@@ -85,6 +127,28 @@ impl AmbientLightPass {
                     },
                     count: None,
                 },
+                // normal roughness
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                // depth
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -94,7 +158,12 @@ impl AmbientLightPass {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("AmbientLight pipeline layout"),
-            bind_group_layouts: &[&config.bind_group_layout, &bind_group_layout],
+            bind_group_layouts: &[
+                &config.bind_group_layout,
+                &bind_group_layout,
+                &camera.get().bind_group_layout,
+                &skybox.get().ibl_bind_group_layout,
+            ],
             push_constant_ranges: &[],
         });
 
@@ -126,6 +195,9 @@ impl AmbientLightPass {
             outputs,
             output_view,
 
+            camera,
+            skybox,
+
             bind_group_layout,
             bind_group,
             pipeline,
@@ -144,6 +216,9 @@ impl AmbientLightPass {
     }
 
     pub fn render(&self, ctx: &mut RenderContext) {
+        let camera = self.camera.get();
+        let skybox = self.skybox.get();
+
         let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("AmbientLight"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -160,6 +235,8 @@ impl AmbientLightPass {
         rpass.set_pipeline(&self.pipeline);
         rpass.set_bind_group(0, &self.config.bind_group, &[]);
         rpass.set_bind_group(1, &self.bind_group, &[]);
+        rpass.set_bind_group(2, &camera.bind_group, &[]);
+        rpass.set_bind_group(3, &skybox.ibl_bind_group, &[]);
 
         rpass.draw(0..3, 0..1);
     }
@@ -178,7 +255,10 @@ impl AmbientLightPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba16Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            // Copied as is when anti-aliasing is off
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[wgpu::TextureFormat::Rgba16Float],
         });
 
@@ -206,6 +286,21 @@ impl AmbientLightPass {
                         &inputs.emissive.create_view(&Default::default()),
                     ),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.normal_roughness.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&inputs.depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..Default::default()
+                        },
+                    )),
+                },
             ],
         })
     }
@@ -19,16 +19,94 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<
 struct Config {
     color: vec3<f32>,
     strength: f32,
+    ibl: u32,
 }
 @group(0) @binding(0) var<uniform> config: Config;
 
 @group(1) @binding(0) var t_albedo_metallic: texture_2d<f32>;
 @group(1) @binding(1) var t_emissive: texture_2d<f32>;
+@group(1) @binding(2) var t_normal_roughness: texture_2d<f32>;
+@group(1) @binding(3) var t_depth: texture_depth_2d;
+
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
+}
+@group(2) @binding(0) var<uniform> camera: Camera;
+
+@group(3) @binding(0) var t_irradiance: texture_cube<f32>;
+@group(3) @binding(1) var t_prefiltered: texture_cube<f32>;
+@group(3) @binding(2) var t_brdf_lut: texture_2d<f32>;
+@group(3) @binding(3) var t_ibl_sampler: sampler;
+
+fn fresnel_schlick_roughness(cos_theta: f32, F0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return F0 + (max(vec3<f32>(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn image_based_lighting(coord: vec2<i32>, albedo: vec3<f32>, metallic: f32) -> vec3<f32> {
+    let depth = textureLoad(t_depth, coord, 0);
+
+    // Sky
+    if depth == camera.far_depth { return vec3<f32>(0.0); }
+
+    let normal_roughness = textureLoad(t_normal_roughness, coord, 0);
+    let roughness = normal_roughness.a;
+
+    let size = vec2<f32>(textureDimensions(t_depth));
+    let uv = (vec2<f32>(coord) + 0.5) / size;
+    let ndc = (uv - 0.5) * vec2<f32>(2.0, -2.0);
+
+    let frag_pos_view4 = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+    let frag_pos_view = frag_pos_view4.xyz / frag_pos_view4.w;
+
+    // Rotation only, cubemaps are in world space
+    let inv_view = mat3x3<f32>(
+        camera.inv_view[0].xyz,
+        camera.inv_view[1].xyz,
+        camera.inv_view[2].xyz,
+    );
+
+    let N = normalize(normal_roughness.xyz);
+    let V = normalize(-frag_pos_view);
+    let R = reflect(-V, N);
+    let NdotV = max(dot(N, V), 0.0);
+
+    let F0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let kS = fresnel_schlick_roughness(NdotV, F0, roughness);
+    let kD = (1.0 - kS) * (1.0 - metallic);
+
+    let irradiance = textureSampleLevel(t_irradiance, t_ibl_sampler, inv_view * N, 0.0).rgb;
+    let diffuse = irradiance * albedo;
+
+    let max_lod = f32(textureNumLevels(t_prefiltered) - 1u);
+    let prefiltered = textureSampleLevel(t_prefiltered, t_ibl_sampler, inv_view * R, roughness * max_lod).rgb;
+    let brdf = textureSampleLevel(t_brdf_lut, t_ibl_sampler, vec2<f32>(NdotV, roughness), 0.0).rg;
+    let specular = prefiltered * (kS * brdf.x + brdf.y);
+
+    return kD * diffuse + specular;
+}
 
 @fragment
 fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
-    var ambient = textureLoad(t_albedo_metallic, vec2<i32>(position.xy), 0).rgb;
-    var emissive = textureLoad(t_emissive, vec2<i32>(position.xy), 0).rgb;
+    let coord = vec2<i32>(position.xy);
+
+    let albedo_metallic = textureLoad(t_albedo_metallic, coord, 0);
+    let emissive_occlusion = textureLoad(t_emissive, coord, 0);
+    let emissive = emissive_occlusion.rgb;
+    let occlusion = emissive_occlusion.a;
+
+    var ambient = config.color * albedo_metallic.rgb;
+    if config.ibl != 0u {
+        ambient = image_based_lighting(coord, albedo_metallic.rgb, albedo_metallic.a);
+    }
 
-    return vec4<f32>(config.color * config.strength * ambient + emissive, 1.0);
+    return vec4<f32>(ambient * occlusion * config.strength + emissive, 1.0);
 }
@@ -1,8 +1,8 @@
-use std::time::Duration;
+use std::{cell::Cell, time::Duration};
 
 use crate::{
-    Instance, InstancesManager, RenderContext, RessourceRef, RessourcesManager, UniformBuffer,
-    UniformData,
+    AnimationsManager, Instance, InstancesManager, RenderContext, ResourceRef, ResourcesManager,
+    UniformBuffer, UniformData,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
@@ -33,17 +33,24 @@ impl UniformData for AnimateUniform {
 pub struct AnimatePass {
     pub uniform: UniformBuffer<AnimateUniform>,
 
-    instances: RessourceRef<InstancesManager>,
+    instances: ResourceRef<InstancesManager>,
+    animations: ResourceRef<AnimationsManager>,
 
+    instances_capacity: usize,
+    /// Animations are stepped by the first render following an update, the
+    /// other renders of the frame (viewports, extra targets) reuse the state
+    stepped: Cell<bool>,
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
     pipeline: wgpu::ComputePipeline,
 }
 
 impl AnimatePass {
-    pub fn new(device: &wgpu::Device, ressources: &RessourcesManager) -> Self {
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager) -> Self {
         let uniform = UniformBuffer::new(device, AnimateUniform::default());
 
-        let instances = ressources.get::<InstancesManager>();
+        let instances = resources.get::<InstancesManager>();
+        let animations = resources.get::<AnimationsManager>();
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("AnimatePass bind group layout"),
@@ -61,20 +68,18 @@ impl AnimatePass {
             }],
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("AnimatePass bind group"),
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: instances.get().instances.as_entire_binding(),
-            }],
-        });
+        let instances_capacity = instances.get().capacity();
+        let bind_group = Self::make_bind_group(device, &bind_group_layout, &instances.get());
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("animate.wgsl"));
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("AnimatePass pipeline layout"),
-            bind_group_layouts: &[&bind_group_layout, &uniform.bind_group_layout],
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &uniform.bind_group_layout,
+                &animations.get().bind_group_layout,
+            ],
             push_constant_ranges: &[],
         });
 
@@ -89,17 +94,40 @@ impl AnimatePass {
             uniform,
 
             instances,
+            animations,
 
+            instances_capacity,
+            stepped: Cell::new(false),
+            bind_group_layout,
             bind_group,
             pipeline,
         }
     }
 
+    pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+        let instances = self.instances.get();
+        if instances.capacity() == self.instances_capacity {
+            return;
+        }
+
+        self.instances_capacity = instances.capacity();
+        self.bind_group = Self::make_bind_group(device, &self.bind_group_layout, &instances);
+    }
+
     pub fn update(&mut self, queue: &wgpu::Queue) {
         self.uniform.update(queue);
+        self.stepped.set(false);
     }
 
+    /// Advances the animations by the uniform duration, once per
+    /// [`AnimatePass::update`]
     pub fn render(&self, ctx: &mut RenderContext) {
+        if self.stepped.replace(true) {
+            return;
+        }
+
+        let animations = self.animations.get();
+
         let mut cpass = ctx
             .encoder
             .begin_compute_pass(&wgpu::ComputePassDescriptor {
@@ -109,11 +137,27 @@ impl AnimatePass {
         cpass.set_pipeline(&self.pipeline);
         cpass.set_bind_group(0, &self.bind_group, &[]);
         cpass.set_bind_group(1, &self.uniform.bind_group, &[]);
+        cpass.set_bind_group(2, &animations.bind_group, &[]);
 
         const WORKGROUP_SIZE: usize = 256;
         let workgroups_count =
-            (self.instances.get().count() as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
+            (self.instances.get().high_water_mark() as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
 
         cpass.dispatch_workgroups(workgroups_count, 1, 1);
     }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        instances: &InstancesManager,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("AnimatePass bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: instances.instances.as_entire_binding(),
+            }],
+        })
+    }
 }
@@ -1,6 +1,16 @@
-struct AnimationState {
+struct AnimationTrack {
     animation_id: u32,
     time: f32,
+    speed: f32,
+    looping: u32,
+}
+
+struct AnimationState {
+    tracks: array<AnimationTrack, 2>,
+    blend: f32,
+    blend_speed: f32,
+    additive: AnimationTrack,
+    additive_weight: f32,
 }
 
 struct Instance {
@@ -8,6 +18,9 @@ struct Instance {
     mesh_id: u32,
     material_id: u32,
     animation: AnimationState,
+    color_tint: vec4<f32>,
+    // See InstanceFlags
+    flags: u32,
 }
 struct Instances {
     count: u32,
@@ -20,9 +33,37 @@ var<storage, read_write> instances: Instances;
 @group(1) @binding(0)
 var<uniform> time: f32;
 
+@group(2) @binding(0) var animations: binding_array<texture_2d_array<f32>>;
+@group(2) @binding(2) var<storage, read> animations_samples_per_sec: array<f32>;
+
+fn track_time(track: AnimationTrack) -> f32 {
+    if track.animation_id == 0u { return 0.0; }
+
+    let frames = textureDimensions(animations[track.animation_id]).y;
+    let samples_per_sec = animations_samples_per_sec[track.animation_id];
+    var t = track.time + time * track.speed;
+
+    if track.looping != 0u {
+        // Skinning wraps around, last frame blends back into the first one
+        let duration = f32(frames) / samples_per_sec;
+        t -= floor(t / duration) * duration;
+    } else {
+        let duration = f32(frames - 1u) / samples_per_sec;
+        t = clamp(t, 0.0, duration);
+    }
+
+    return t;
+}
+
 @compute @workgroup_size(256)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     if global_id.x >= instances.count { return; }
 
-    instances.instances[global_id.x].animation.time += time;
+    let animation = &instances.instances[global_id.x].animation;
+
+    (*animation).tracks[0].time = track_time((*animation).tracks[0]);
+    (*animation).tracks[1].time = track_time((*animation).tracks[1]);
+    (*animation).additive.time = track_time((*animation).additive);
+
+    (*animation).blend = clamp((*animation).blend + time * (*animation).blend_speed, 0.0, 1.0);
 }
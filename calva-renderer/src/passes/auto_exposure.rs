@@ -0,0 +1,291 @@
+use std::time::Duration;
+
+use crate::{AnimateUniform, RenderContext, UniformBuffer, UniformData};
+
+/// Luminances are expressed in EV, i.e. `log2(luminance)`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AutoExposureConfig {
+    /// When disabled only the manual [`crate::ToneMappingConfig::exposure`]
+    /// is applied, otherwise it acts as an exposure compensation
+    pub enabled: bool,
+    /// Darkest average scene luminance adapted to
+    pub min_ev: f32,
+    /// Brightest average scene luminance adapted to
+    pub max_ev: f32,
+    /// Rate of the exponential adaptation, per second
+    pub adaptation_speed: f32,
+    /// Luminance the average scene luminance is exposed to
+    pub target: f32,
+}
+
+impl Default for AutoExposureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_ev: -8.0,
+            max_ev: 8.0,
+            adaptation_speed: 1.5,
+            target: 0.18,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuAutoExposureConfig {
+    enabled: u32,
+    min_ev: f32,
+    max_ev: f32,
+    adaptation_speed: f32,
+    target: f32,
+}
+
+impl UniformData for AutoExposureConfig {
+    type GpuType = GpuAutoExposureConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuAutoExposureConfig {
+            enabled: self.enabled as _,
+            min_ev: self.min_ev,
+            max_ev: self.max_ev,
+            adaptation_speed: self.adaptation_speed,
+            target: self.target,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut AutoExposureConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Auto exposure")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.enabled, "Enabled");
+                ui.add_enabled_ui(self.enabled, |ui| {
+                    ui.add(egui::Slider::new(&mut self.min_ev, -16.0..=0.0).text("Min EV"));
+                    ui.add(egui::Slider::new(&mut self.max_ev, 0.0..=16.0).text("Max EV"));
+                    ui.add(
+                        egui::Slider::new(&mut self.adaptation_speed, 0.1..=10.0)
+                            .text("Adaptation speed"),
+                    );
+                    ui.add(egui::Slider::new(&mut self.target, 0.01..=1.0).text("Target"));
+                });
+            })
+            .header_response
+    }
+}
+
+pub struct AutoExposurePassInputs<'a> {
+    pub input: &'a wgpu::Texture,
+}
+
+pub struct AutoExposurePassOutputs {
+    /// Exposure, in EV, to add to the manual exposure. Zero when disabled.
+    pub exposure: wgpu::Buffer,
+}
+
+/// Eye adaptation: builds a log luminance histogram of the HDR input and
+/// smoothly moves the exposure toward the one mapping its average to
+/// [`AutoExposureConfig::target`].
+///
+/// Black pixels are left out of the average, a black frame adapts to
+/// [`AutoExposureConfig::min_ev`].
+pub struct AutoExposurePass {
+    pub config: UniformBuffer<AutoExposureConfig>,
+    pub outputs: AutoExposurePassOutputs,
+
+    delta_time: UniformBuffer<AnimateUniform>,
+    size: (u32, u32),
+    histogram: wgpu::Buffer,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    histogram_pipeline: wgpu::ComputePipeline,
+    adapt_pipeline: wgpu::ComputePipeline,
+}
+
+impl AutoExposurePass {
+    const HISTOGRAM_BINS: u64 = 256;
+
+    pub fn new(device: &wgpu::Device, inputs: AutoExposurePassInputs) -> Self {
+        let config = UniformBuffer::new(device, AutoExposureConfig::default());
+        let delta_time = UniformBuffer::new(device, AnimateUniform::default());
+
+        let size = (inputs.input.width(), inputs.input.height());
+
+        let histogram = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("AutoExposure histogram"),
+            size: Self::HISTOGRAM_BINS * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let outputs = AutoExposurePassOutputs {
+            // Adapted and applied exposure, padded for uniform bindings
+            exposure: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("AutoExposure exposure"),
+                size: 4 * std::mem::size_of::<f32>() as u64,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::UNIFORM
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("AutoExposure bind group layout"),
+            entries: &[
+                // Input
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                // Histogram
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                // Exposure
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group =
+            Self::make_bind_group(device, &bind_group_layout, &histogram, &outputs, &inputs);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("auto_exposure.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("AutoExposure pipeline layout"),
+            bind_group_layouts: &[
+                &config.bind_group_layout,
+                &delta_time.bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let histogram_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("AutoExposure histogram pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "build_histogram",
+        });
+
+        let adapt_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("AutoExposure adapt pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "adapt",
+        });
+
+        Self {
+            config,
+            outputs,
+
+            delta_time,
+            size,
+            histogram,
+
+            bind_group_layout,
+            bind_group,
+            histogram_pipeline,
+            adapt_pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: AutoExposurePassInputs) {
+        self.size = (inputs.input.width(), inputs.input.height());
+
+        self.bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.histogram,
+            &self.outputs,
+            &inputs,
+        );
+    }
+
+    /// `dt` is the time elapsed since the previous frame
+    pub fn update(&mut self, queue: &wgpu::Queue, dt: Duration) {
+        self.config.update(queue);
+
+        **self.delta_time = dt;
+        self.delta_time.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        let mut cpass = ctx
+            .encoder
+            .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("AutoExposure"),
+            });
+
+        cpass.set_bind_group(0, &self.config.bind_group, &[]);
+        cpass.set_bind_group(1, &self.delta_time.bind_group, &[]);
+        cpass.set_bind_group(2, &self.bind_group, &[]);
+
+        if self.config.enabled {
+            cpass.set_pipeline(&self.histogram_pipeline);
+            cpass.dispatch_workgroups(
+                (self.size.0 as f32 / 16.0).ceil() as u32,
+                (self.size.1 as f32 / 16.0).ceil() as u32,
+                1,
+            );
+        }
+
+        // Also resets the exposure when disabled
+        cpass.set_pipeline(&self.adapt_pipeline);
+        cpass.dispatch_workgroups(1, 1, 1);
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        histogram: &wgpu::Buffer,
+        outputs: &AutoExposurePassOutputs,
+        inputs: &AutoExposurePassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("AutoExposure bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.input.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: histogram.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: outputs.exposure.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
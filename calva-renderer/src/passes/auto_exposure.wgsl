@@ -0,0 +1,107 @@
+struct Config {
+    enabled: u32,
+    min_ev: f32,
+    max_ev: f32,
+    adaptation_speed: f32,
+    target_luminance: f32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var<uniform> delta_time: f32;
+
+struct Exposure {
+    adapted: f32,
+    exposure: f32,
+}
+
+@group(2) @binding(0) var t_input: texture_2d<f32>;
+@group(2) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(2) @binding(2) var<storage, read_write> exposure: Exposure;
+
+// Bin 0 holds black pixels, left out of the average
+const BINS: f32 = 255.0;
+
+fn ev_range() -> f32 {
+    return max(config.max_ev - config.min_ev, 1e-3);
+}
+
+//
+// Histogram
+//
+
+var<workgroup> local_histogram: array<atomic<u32>, 256>;
+
+@compute @workgroup_size(16, 16, 1)
+fn build_histogram(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    atomicStore(&local_histogram[local_index], 0u);
+
+    workgroupBarrier();
+
+    let dims = vec2<u32>(textureDimensions(t_input));
+    if all(global_id.xy < dims) {
+        let color = textureLoad(t_input, vec2<i32>(global_id.xy), 0).rgb;
+        let luminance = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+
+        var bin = 0u;
+        if luminance > 1e-5 {
+            let t = saturate((log2(luminance) - config.min_ev) / ev_range());
+            bin = u32(t * (BINS - 1.0)) + 1u;
+        }
+
+        atomicAdd(&local_histogram[bin], 1u);
+    }
+
+    workgroupBarrier();
+
+    atomicAdd(&histogram[local_index], atomicLoad(&local_histogram[local_index]));
+}
+
+//
+// Adaptation
+//
+
+var<workgroup> counts: array<f32, 256>;
+var<workgroup> weights: array<f32, 256>;
+
+@compute @workgroup_size(256, 1, 1)
+fn adapt(@builtin(local_invocation_index) local_index: u32) {
+    // Ready for the next frame
+    let count = f32(atomicExchange(&histogram[local_index], 0u));
+
+    counts[local_index] = select(count, 0.0, local_index == 0u);
+    weights[local_index] = counts[local_index] * f32(local_index);
+
+    workgroupBarrier();
+
+    for (var stride = 128u; stride > 0u; stride = stride >> 1u) {
+        if local_index < stride {
+            counts[local_index] += counts[local_index + stride];
+            weights[local_index] += weights[local_index + stride];
+        }
+
+        workgroupBarrier();
+    }
+
+    if local_index == 0u && config.enabled != 0u {
+        // A black frame has no average, clamp it to the darkest luminance
+        var average_ev = config.min_ev;
+        if counts[0] > 0.0 {
+            let bin = weights[0] / counts[0];
+            average_ev += (bin - 1.0) / (BINS - 1.0) * ev_range();
+        }
+        average_ev = clamp(average_ev, config.min_ev, config.min_ev + ev_range());
+
+        let target_ev = log2(max(config.target_luminance, 1e-3)) - average_ev;
+        let t = 1.0 - exp(-delta_time * max(config.adaptation_speed, 0.0));
+
+        exposure.adapted = mix(exposure.adapted, target_ev, saturate(t));
+    }
+
+    if local_index == 0u {
+        // Adaptation resumes from where it was when re-enabled
+        exposure.exposure = select(0.0, exposure.adapted, config.enabled != 0u);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{RenderContext, UniformBuffer};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomConfig {
+    pub threshold: f32,
+    pub intensity: f32,
+    pub mip_count: u32,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.05,
+            mip_count: 5,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut BloomConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Bloom")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add(egui::Slider::new(&mut self.threshold, 0.0..=10.0).text("Threshold"));
+                ui.add(egui::Slider::new(&mut self.intensity, 0.0..=1.0).text("Intensity"));
+                ui.add(
+                    egui::Slider::new(&mut self.mip_count, 1..=BloomPass::MAX_MIPS)
+                        .text("Mip count"),
+                );
+            })
+            .header_response
+    }
+}
+
+pub struct BloomPassInputs<'a> {
+    pub input: &'a wgpu::Texture,
+}
+
+/// Blurs the bright parts of the HDR lighting result down a mip chain and
+/// adds them back, before tone mapping.
+///
+/// Only the input texture is read, no scene resource: like
+/// [`crate::FxaaPass`] and [`crate::ToneMappingPass`] it is built without the
+/// resources manager.
+pub struct BloomPass {
+    pub config: UniformBuffer<BloomConfig>,
+
+    input_view: wgpu::TextureView,
+    mips_views: Vec<wgpu::TextureView>,
+
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    input_bind_group: wgpu::BindGroup,
+    mips_bind_groups: Vec<wgpu::BindGroup>,
+
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl BloomPass {
+    pub const MAX_MIPS: u32 = 8;
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(device: &wgpu::Device, inputs: BloomPassInputs) -> Self {
+        let config = UniformBuffer::new(device, BloomConfig::default());
+
+        let input_view = inputs.input.create_view(&Default::default());
+        let mips_views = Self::make_mips_views(device, &inputs);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom bind group layout"),
+            entries: &[
+                // Sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Source
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let input_bind_group =
+            Self::make_bind_group(device, &bind_group_layout, &sampler, &input_view);
+        let mips_bind_groups = mips_views
+            .iter()
+            .map(|view| Self::make_bind_group(device, &bind_group_layout, &sampler, view))
+            .collect();
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("bloom.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom pipeline layout"),
+            bind_group_layouts: &[&config.bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let make_pipeline = |label: &str,
+                             entry_point: &str,
+                             format: wgpu::TextureFormat,
+                             blend: Option<wgpu::BlendState>| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+            })
+        };
+
+        let additive = Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        });
+
+        let prefilter_pipeline = make_pipeline(
+            "Bloom[prefilter] pipeline",
+            "fs_prefilter",
+            Self::FORMAT,
+            None,
+        );
+        let downsample_pipeline = make_pipeline(
+            "Bloom[downsample] pipeline",
+            "fs_downsample",
+            Self::FORMAT,
+            None,
+        );
+        let upsample_pipeline = make_pipeline(
+            "Bloom[upsample] pipeline",
+            "fs_upsample",
+            Self::FORMAT,
+            additive,
+        );
+        let composite_pipeline = make_pipeline(
+            "Bloom[composite] pipeline",
+            "fs_composite",
+            inputs.input.format(),
+            additive,
+        );
+
+        Self {
+            config,
+
+            input_view,
+            mips_views,
+
+            sampler,
+            bind_group_layout,
+            input_bind_group,
+            mips_bind_groups,
+
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: BloomPassInputs) {
+        self.input_view = inputs.input.create_view(&Default::default());
+        self.mips_views = Self::make_mips_views(device, &inputs);
+
+        self.input_bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.input_view,
+        );
+        self.mips_bind_groups = self
+            .mips_views
+            .iter()
+            .map(|view| Self::make_bind_group(device, &self.bind_group_layout, &self.sampler, view))
+            .collect();
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        ctx.encoder.profile_start("Bloom");
+
+        let mip_count = (self.config.mip_count as usize).clamp(1, self.mips_views.len());
+
+        let mut draw = |label: &str,
+                        pipeline: &wgpu::RenderPipeline,
+                        bind_group: &wgpu::BindGroup,
+                        view: &wgpu::TextureView,
+                        load: wgpu::LoadOp<wgpu::Color>| {
+            let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load, store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &self.config.bind_group, &[]);
+            rpass.set_bind_group(1, bind_group, &[]);
+
+            rpass.draw(0..3, 0..1);
+        };
+
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        draw(
+            "Bloom[prefilter]",
+            &self.prefilter_pipeline,
+            &self.input_bind_group,
+            &self.mips_views[0],
+            clear,
+        );
+
+        for mip in 1..mip_count {
+            draw(
+                "Bloom[downsample]",
+                &self.downsample_pipeline,
+                &self.mips_bind_groups[mip - 1],
+                &self.mips_views[mip],
+                clear,
+            );
+        }
+
+        for mip in (1..mip_count).rev() {
+            draw(
+                "Bloom[upsample]",
+                &self.upsample_pipeline,
+                &self.mips_bind_groups[mip],
+                &self.mips_views[mip - 1],
+                wgpu::LoadOp::Load,
+            );
+        }
+
+        draw(
+            "Bloom[composite]",
+            &self.composite_pipeline,
+            &self.mips_bind_groups[0],
+            &self.input_view,
+            wgpu::LoadOp::Load,
+        );
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_mips_views(device: &wgpu::Device, inputs: &BloomPassInputs) -> Vec<wgpu::TextureView> {
+        let size = wgpu::Extent3d {
+            width: (inputs.input.width() / 2).max(1),
+            height: (inputs.input.height() / 2).max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let mip_level_count = size
+            .max_mips(wgpu::TextureDimension::D2)
+            .min(Self::MAX_MIPS);
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Bloom mips"),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[Self::FORMAT],
+        });
+
+        (0..mip_level_count)
+            .map(|base_mip_level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Bloom mip view"),
+                    base_mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bloom bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+            ],
+        })
+    }
+}
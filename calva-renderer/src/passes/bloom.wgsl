@@ -0,0 +1,110 @@
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = out.position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    threshold: f32,
+    intensity: f32,
+    mip_count: u32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var t_sampler: sampler;
+@group(1) @binding(1) var t_source: texture_2d<f32>;
+
+fn sample(uv: vec2<f32>, offset: vec2<f32>) -> vec3<f32> {
+    return textureSample(t_source, t_sampler, uv + offset).rgb;
+}
+
+// 13 taps downsample filter
+// http://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let t = 1.0 / vec2<f32>(textureDimensions(t_source));
+
+    let a = sample(uv, t * vec2<f32>(-2.0, -2.0));
+    let b = sample(uv, t * vec2<f32>( 0.0, -2.0));
+    let c = sample(uv, t * vec2<f32>( 2.0, -2.0));
+
+    let d = sample(uv, t * vec2<f32>(-2.0,  0.0));
+    let e = sample(uv, t * vec2<f32>( 0.0,  0.0));
+    let f = sample(uv, t * vec2<f32>( 2.0,  0.0));
+
+    let g = sample(uv, t * vec2<f32>(-2.0,  2.0));
+    let h = sample(uv, t * vec2<f32>( 0.0,  2.0));
+    let i = sample(uv, t * vec2<f32>( 2.0,  2.0));
+
+    let j = sample(uv, t * vec2<f32>(-1.0, -1.0));
+    let k = sample(uv, t * vec2<f32>( 1.0, -1.0));
+    let l = sample(uv, t * vec2<f32>(-1.0,  1.0));
+    let m = sample(uv, t * vec2<f32>( 1.0,  1.0));
+
+    return e * 0.125
+        + (a + c + g + i) * 0.03125
+        + (b + d + f + h) * 0.0625
+        + (j + k + l + m) * 0.125;
+}
+
+// 3x3 tent upsample filter
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+    let t = 1.0 / vec2<f32>(textureDimensions(t_source));
+
+    return (
+        sample(uv, t * vec2<f32>(-1.0, -1.0)) * 1.0 +
+        sample(uv, t * vec2<f32>( 0.0, -1.0)) * 2.0 +
+        sample(uv, t * vec2<f32>( 1.0, -1.0)) * 1.0 +
+        sample(uv, t * vec2<f32>(-1.0,  0.0)) * 2.0 +
+        sample(uv, t * vec2<f32>( 0.0,  0.0)) * 4.0 +
+        sample(uv, t * vec2<f32>( 1.0,  0.0)) * 2.0 +
+        sample(uv, t * vec2<f32>(-1.0,  1.0)) * 1.0 +
+        sample(uv, t * vec2<f32>( 0.0,  1.0)) * 2.0 +
+        sample(uv, t * vec2<f32>( 1.0,  1.0)) * 1.0
+    ) / 16.0;
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(in.uv);
+
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - config.threshold, 0.0) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.uv), 1.0);
+}
+
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uv), 1.0);
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(in.uv) * config.intensity, 1.0);
+}
@@ -0,0 +1,338 @@
+use crate::{
+    CameraManager, FramePipelines, RenderContext, ResourceRef, ResourcesManager, UniformBuffer,
+    UniformData,
+};
+
+/// Intermediate texture shown instead of the final image
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum DebugView {
+    #[default]
+    Final,
+    Albedo,
+    Normals,
+    Roughness,
+    Metallic,
+    Depth,
+    Ssao,
+    Emissive,
+}
+
+impl DebugView {
+    pub const ALL: [Self; 8] = [
+        Self::Final,
+        Self::Albedo,
+        Self::Normals,
+        Self::Roughness,
+        Self::Metallic,
+        Self::Depth,
+        Self::Ssao,
+        Self::Emissive,
+    ];
+}
+
+impl std::fmt::Display for DebugView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct DebugBlitConfig {
+    pub view: DebugView,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuDebugBlitConfig {
+    view: u32,
+}
+
+impl UniformData for DebugBlitConfig {
+    type GpuType = GpuDebugBlitConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuDebugBlitConfig {
+            view: self.view as _,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut DebugBlitConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::ComboBox::from_label("Debug view")
+            .selected_text(self.view.to_string())
+            .show_ui(ui, |ui| {
+                for view in DebugView::ALL {
+                    ui.selectable_value(&mut self.view, view, view.to_string());
+                }
+            })
+            .response
+    }
+}
+
+pub struct DebugBlitPassInputs<'a> {
+    pub albedo_metallic: &'a wgpu::Texture,
+    pub normal_roughness: &'a wgpu::Texture,
+    pub emissive: &'a wgpu::Texture,
+    pub depth: &'a wgpu::Texture,
+    /// `None` without [`SsaoPass`](crate::SsaoPass), [`DebugView::Ssao`] then
+    /// shows black
+    pub ssao: Option<&'a wgpu::Texture>,
+}
+
+/// Replaces the final image with one of the gbuffer or intermediate textures,
+/// see [`DebugView`].
+///
+/// Normals are remapped from view space to 0..1 and depth is linearized
+/// between the camera planes.
+pub struct DebugBlitPass {
+    pub config: UniformBuffer<DebugBlitConfig>,
+
+    camera: ResourceRef<CameraManager>,
+
+    sampler: wgpu::Sampler,
+    /// Bound in place of a missing SSAO output
+    ssao_fallback: wgpu::Texture,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipelines: FramePipelines,
+}
+
+impl DebugBlitPass {
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DebugBlitPassInputs,
+    ) -> Self {
+        let config = UniformBuffer::new(device, DebugBlitConfig::default());
+
+        let camera = resources.get::<CameraManager>();
+
+        // SSAO is rendered at a lower resolution
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("DebugBlit sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_entry =
+            |binding: u32, sample_type: wgpu::TextureSampleType| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type,
+                },
+                count: None,
+            };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("DebugBlit bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Albedo/metallic
+                texture_entry(1, wgpu::TextureSampleType::Float { filterable: false }),
+                // Normal/roughness
+                texture_entry(2, wgpu::TextureSampleType::Float { filterable: false }),
+                // Emissive
+                texture_entry(3, wgpu::TextureSampleType::Float { filterable: false }),
+                // Depth
+                texture_entry(4, wgpu::TextureSampleType::Depth),
+                // SSAO
+                texture_entry(5, wgpu::TextureSampleType::Float { filterable: true }),
+            ],
+        });
+
+        let ssao_fallback = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("DebugBlit ssao fallback"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let bind_group = Self::make_bind_group(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &ssao_fallback,
+            &inputs,
+        );
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("debug_blit.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("DebugBlit pipeline layout"),
+            bind_group_layouts: &[
+                &camera.get().bind_group_layout,
+                &config.bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("DebugBlit pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        });
+
+        Self {
+            config,
+
+            camera,
+
+            sampler,
+            ssao_fallback,
+            bind_group_layout,
+            bind_group,
+            pipelines,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: DebugBlitPassInputs) {
+        self.bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.ssao_fallback,
+            &inputs,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        if self.config.view == DebugView::Final {
+            return;
+        }
+
+        ctx.encoder.profile_start("DebugBlit");
+
+        let camera = self.camera.get();
+        let pipeline = self.pipelines.get(ctx);
+        let viewport = ctx.viewport;
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("DebugBlit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        if let Some(viewport) = viewport {
+            viewport.apply(&mut rpass);
+        }
+
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_bind_group(1, &self.config.bind_group, &[]);
+        rpass.set_bind_group(2, &self.bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        ssao_fallback: &wgpu::Texture,
+        inputs: &DebugBlitPassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("DebugBlit bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.albedo_metallic.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.normal_roughness.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.emissive.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&inputs.depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..Default::default()
+                        },
+                    )),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs
+                            .ssao
+                            .unwrap_or(ssao_fallback)
+                            .create_view(&Default::default()),
+                    ),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,95 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = out.position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    view: u32,
+}
+@group(1) @binding(0) var<uniform> config: Config;
+
+@group(2) @binding(0) var t_sampler: sampler;
+@group(2) @binding(1) var t_albedo_metallic: texture_2d<f32>;
+@group(2) @binding(2) var t_normal_roughness: texture_2d<f32>;
+@group(2) @binding(3) var t_emissive: texture_2d<f32>;
+@group(2) @binding(4) var t_depth: texture_depth_2d;
+@group(2) @binding(5) var t_ssao: texture_2d<f32>;
+
+fn view_z(depth: f32) -> f32 {
+    let p = camera.inv_proj * vec4<f32>(0.0, 0.0, depth, 1.0);
+    return -p.z / p.w;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Relative to the viewport, which covers the whole gbuffer
+    let c = vec2<i32>(in.uv * vec2<f32>(textureDimensions(t_depth)));
+
+    switch config.view {
+        case 1u: { // Albedo
+            return vec4<f32>(textureLoad(t_albedo_metallic, c, 0).rgb, 1.0);
+        }
+        case 2u: { // Normals
+            let n = textureLoad(t_normal_roughness, c, 0).xyz;
+            return vec4<f32>(n * 0.5 + 0.5, 1.0);
+        }
+        case 3u: { // Roughness
+            return vec4<f32>(vec3<f32>(textureLoad(t_normal_roughness, c, 0).a), 1.0);
+        }
+        case 4u: { // Metallic
+            return vec4<f32>(vec3<f32>(textureLoad(t_albedo_metallic, c, 0).a), 1.0);
+        }
+        case 5u: { // Depth
+            let znear = view_z(1.0 - camera.far_depth);
+            let zfar = view_z(camera.far_depth);
+            let z = view_z(textureLoad(t_depth, c, 0));
+            return vec4<f32>(vec3<f32>(saturate((z - znear) / (zfar - znear))), 1.0);
+        }
+        case 6u: { // Ssao
+            let occlusion = textureSample(t_ssao, t_sampler, in.uv).r;
+            return vec4<f32>(vec3<f32>(1.0 - occlusion), 1.0);
+        }
+        case 7u: { // Emissive
+            return vec4<f32>(textureLoad(t_emissive, c, 0).rgb, 1.0);
+        }
+        default: {
+            return vec4<f32>(0.0);
+        }
+    }
+}
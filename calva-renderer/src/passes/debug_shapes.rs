@@ -0,0 +1,251 @@
+use crate::{
+    util::icosphere::Icosphere, CameraManager, FramePipelines, RenderContext, ResourceRef,
+    ResourcesManager,
+};
+
+/// Wireframe shape drawn by [`DebugShapesPass`], in world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugShape {
+    Line {
+        start: glam::Vec3,
+        end: glam::Vec3,
+        color: [f32; 3],
+    },
+    Sphere {
+        center: glam::Vec3,
+        radius: f32,
+        color: [f32; 3],
+    },
+    Aabb {
+        min: glam::Vec3,
+        max: glam::Vec3,
+        color: [f32; 3],
+    },
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DebugVertex {
+    position: glam::Vec3,
+    color: [f32; 3],
+}
+
+pub struct DebugShapesPassInputs<'a> {
+    pub depth: &'a wgpu::Texture,
+}
+
+/// Lines, spheres and boxes drawn over the final frame, hidden by the scene
+/// geometry. Useful to visualize culling volumes or navigation data.
+///
+/// Shapes are set with [`DebugShapesPass::update`] and kept until the next
+/// update.
+pub struct DebugShapesPass {
+    camera: ResourceRef<CameraManager>,
+
+    /// Edges of the unit sphere
+    sphere_edges: Vec<(glam::Vec3, glam::Vec3)>,
+    vertices: wgpu::Buffer,
+    vertex_count: u32,
+
+    depth_view: wgpu::TextureView,
+
+    pipelines: FramePipelines,
+}
+
+impl DebugShapesPass {
+    pub const MAX_VERTICES: usize = 1 << 16;
+
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DebugShapesPassInputs,
+    ) -> Self {
+        let camera = resources.get::<CameraManager>();
+
+        let icosphere = Icosphere::new(1);
+        let mut sphere_edges = icosphere
+            .indices
+            .chunks_exact(3)
+            .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect::<Vec<_>>();
+        sphere_edges.sort_unstable();
+        sphere_edges.dedup();
+        let sphere_edges = sphere_edges
+            .into_iter()
+            .map(|(a, b)| {
+                (
+                    icosphere.vertices[a as usize],
+                    icosphere.vertices[b as usize],
+                )
+            })
+            .collect();
+
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("DebugShapes vertices"),
+            size: (Self::MAX_VERTICES * std::mem::size_of::<DebugVertex>()) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let depth_view = inputs.depth.create_view(&Default::default());
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("debug_shapes.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("DebugShapes pipeline layout"),
+            bind_group_layouts: &[&camera.get().bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let depth = camera.get().depth();
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("DebugShapes pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<DebugVertex>() as _,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![
+                            0 => Float32x3,
+                            1 => Float32x3,
+                        ],
+                    }],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: depth.format,
+                    depth_write_enabled: false,
+                    depth_compare: depth.compare(wgpu::CompareFunction::LessEqual),
+                    stencil: Default::default(),
+                    bias: Default::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        });
+
+        Self {
+            camera,
+
+            sphere_edges,
+            vertices,
+            vertex_count: 0,
+
+            depth_view,
+
+            pipelines,
+        }
+    }
+
+    pub fn rebind(&mut self, inputs: DebugShapesPassInputs) {
+        self.depth_view = inputs.depth.create_view(&Default::default());
+    }
+
+    /// Replaces the drawn shapes, vertices above [`Self::MAX_VERTICES`] are
+    /// dropped
+    pub fn update(&mut self, queue: &wgpu::Queue, shapes: &[DebugShape]) {
+        let mut vertices = Vec::new();
+        let mut line = |start, end, color| {
+            vertices.push(DebugVertex {
+                position: start,
+                color,
+            });
+            vertices.push(DebugVertex {
+                position: end,
+                color,
+            });
+        };
+
+        for shape in shapes {
+            match *shape {
+                DebugShape::Line { start, end, color } => line(start, end, color),
+                DebugShape::Sphere {
+                    center,
+                    radius,
+                    color,
+                } => {
+                    for &(a, b) in &self.sphere_edges {
+                        line(center + a * radius, center + b * radius, color);
+                    }
+                }
+                DebugShape::Aabb { min, max, color } => {
+                    let corner = |i: usize| {
+                        glam::Vec3::select(
+                            glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                            max,
+                            min,
+                        )
+                    };
+
+                    // Edges link corners differing by a single axis
+                    for i in 0..8 {
+                        for axis in [1, 2, 4] {
+                            if i & axis == 0 {
+                                line(corner(i), corner(i | axis), color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        vertices.truncate(Self::MAX_VERTICES);
+        self.vertex_count = vertices.len() as _;
+
+        queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        let camera = self.camera.get();
+        let pipeline = self.pipelines.get(ctx);
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("DebugShapes"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.depth.unwrap_or(&self.depth_view),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+
+        rpass.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -0,0 +1,40 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//
+// Vertex shader
+//
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    return VertexOutput(
+        camera.view_proj * vec4<f32>(in.position, 1.0),
+        in.color,
+    );
+}
+
+//
+// Fragment shader
+//
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
@@ -0,0 +1,283 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    CameraManager, DecalsManager, GpuDecal, MaterialsManager, RenderContext, ResourceRef,
+    ResourcesManager, TexturesManager,
+};
+
+pub struct DecalsPassInputs<'a> {
+    pub albedo_metallic: &'a wgpu::Texture,
+    pub normal_roughness: &'a wgpu::Texture,
+    pub depth: &'a wgpu::Texture,
+}
+
+/// Projects the [`DecalsManager`] decals on the gbuffer, between the geometry
+/// and the lighting passes.
+///
+/// Each decal draws the back faces of its box, so that it still shows with
+/// the camera inside the volume, and reconstructs the covered surfaces from
+/// the depth buffer. Albedo and normal are blended over the gbuffer, metallic
+/// and roughness are left untouched.
+pub struct DecalsPass {
+    camera: ResourceRef<CameraManager>,
+    textures: ResourceRef<TexturesManager>,
+    materials: ResourceRef<MaterialsManager>,
+    decals: ResourceRef<DecalsManager>,
+
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+
+    albedo_metallic_view: wgpu::TextureView,
+    normal_roughness_view: wgpu::TextureView,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl DecalsPass {
+    #[rustfmt::skip]
+    const VERTICES: [[f32; 3]; 8] = [
+        [-0.5, -0.5, -0.5],
+        [ 0.5, -0.5, -0.5],
+        [ 0.5,  0.5, -0.5],
+        [-0.5,  0.5, -0.5],
+        [-0.5, -0.5,  0.5],
+        [ 0.5, -0.5,  0.5],
+        [ 0.5,  0.5,  0.5],
+        [-0.5,  0.5,  0.5],
+    ];
+
+    /// Counter-clockwise seen from outside the box
+    #[rustfmt::skip]
+    const INDICES: [u16; 36] = [
+        0, 3, 2, 0, 2, 1, // -Z
+        4, 5, 6, 4, 6, 7, // +Z
+        0, 4, 7, 0, 7, 3, // -X
+        1, 2, 6, 1, 6, 5, // +X
+        0, 1, 5, 0, 5, 4, // -Y
+        3, 7, 6, 3, 6, 2, // +Y
+    ];
+
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DecalsPassInputs,
+    ) -> Self {
+        let camera = resources.get::<CameraManager>();
+        let textures = resources.get::<TexturesManager>();
+        let materials = resources.get::<MaterialsManager>();
+        let decals = resources.get::<DecalsManager>();
+
+        let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Decals box vertices buffer"),
+            contents: bytemuck::cast_slice(&Self::VERTICES),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Decals box indices buffer"),
+            contents: bytemuck::cast_slice(&Self::INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let albedo_metallic_view = inputs.albedo_metallic.create_view(&Default::default());
+        let normal_roughness_view = inputs.normal_roughness.create_view(&Default::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Decals bind group layout"),
+            entries: &[
+                // depth
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = Self::make_bind_group(device, &bind_group_layout, &inputs);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("decals.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Decals pipeline layout"),
+            bind_group_layouts: &[
+                &camera.get().bind_group_layout,
+                &textures.get().bind_group_layout,
+                &materials.get().bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let blend = Some(wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: Default::default(),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Decals pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    // Decals instances
+                    wgpu::VertexBufferLayout {
+                        array_stride: GpuDecal::SIZE,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![
+                            // Transform
+                            0 => Float32x4,
+                            1 => Float32x4,
+                            2 => Float32x4,
+                            3 => Float32x4,
+                            // Inverse transform
+                            4 => Float32x4,
+                            5 => Float32x4,
+                            6 => Float32x4,
+                            7 => Float32x4,
+
+                            8 => Uint32,  // Material
+                            9 => Float32, // Normal threshold
+                        ],
+                    },
+                    // Box vertices
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<[f32; 3]>() as _,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![10 => Float32x3],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                // Metallic and roughness are kept
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: inputs.albedo_metallic.format(),
+                        blend,
+                        write_mask: wgpu::ColorWrites::COLOR,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: inputs.normal_roughness.format(),
+                        blend,
+                        write_mask: wgpu::ColorWrites::COLOR,
+                    }),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Front),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        });
+
+        Self {
+            camera,
+            textures,
+            materials,
+            decals,
+
+            vertices,
+            indices,
+
+            albedo_metallic_view,
+            normal_roughness_view,
+            bind_group_layout,
+            bind_group,
+
+            pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: DecalsPassInputs) {
+        self.bind_group = Self::make_bind_group(device, &self.bind_group_layout, &inputs);
+
+        self.albedo_metallic_view = inputs.albedo_metallic.create_view(&Default::default());
+        self.normal_roughness_view = inputs.normal_roughness.create_view(&Default::default());
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        let decals = self.decals.get();
+        if decals.count() == 0 {
+            return;
+        }
+
+        ctx.encoder.profile_start("Decals");
+
+        let camera = self.camera.get();
+        let textures = self.textures.get();
+        let materials = self.materials.get();
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Decals"),
+            color_attachments: &[&self.albedo_metallic_view, &self.normal_roughness_view].map(
+                |view| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })
+                },
+            ),
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_bind_group(1, &textures.bind_group, &[]);
+        rpass.set_bind_group(2, &materials.bind_group, &[]);
+        rpass.set_bind_group(3, &self.bind_group, &[]);
+
+        rpass.set_vertex_buffer(0, decals.decals.slice(..));
+        rpass.set_vertex_buffer(1, self.vertices.slice(..));
+        rpass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
+
+        rpass.draw_indexed(
+            0..Self::INDICES.len() as u32,
+            0,
+            0..decals.high_water_mark(),
+        );
+
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        inputs: &DecalsPassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Decals bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&inputs.depth.create_view(
+                    &wgpu::TextureViewDescriptor {
+                        aspect: wgpu::TextureAspect::DepthOnly,
+                        ..Default::default()
+                    },
+                )),
+            }],
+        })
+    }
+}
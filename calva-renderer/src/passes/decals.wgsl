@@ -0,0 +1,162 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+@group(1) @binding(0) var textures: binding_array<texture_2d<f32>>;
+@group(1) @binding(1) var textures_samplers: binding_array<sampler>;
+
+// Texture ids hold the view index in the low 24 bits and the sampler index
+// in the high 8 bits
+fn sample_texture(id: u32, uv: vec2<f32>) -> vec4<f32> {
+    return textureSample(textures[id & 0xffffffu], textures_samplers[id >> 24u], uv);
+}
+
+struct Material {
+    albedo: u32,
+    normal: u32,
+    metallic_roughness: u32,
+    emissive: u32,
+    emissive_factor: vec3<f32>,
+    emissive_strength: f32,
+    base_color_factor: vec4<f32>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    alpha_mode: u32,
+    alpha_cutoff: f32,
+    // Albedo, normal, metallic roughness, emissive and occlusion
+    uv_transforms: array<mat3x2<f32>, 5>,
+    occlusion: u32,
+    occlusion_strength: f32,
+}
+@group(2) @binding(0) var<storage, read> materials: array<Material>;
+
+@group(3) @binding(0) var t_depth: texture_depth_2d;
+
+//
+// Vertex shader
+//
+
+struct DecalInstance {
+    @location(0) transform_0: vec4<f32>,
+    @location(1) transform_1: vec4<f32>,
+    @location(2) transform_2: vec4<f32>,
+    @location(3) transform_3: vec4<f32>,
+    @location(4) inv_transform_0: vec4<f32>,
+    @location(5) inv_transform_1: vec4<f32>,
+    @location(6) inv_transform_2: vec4<f32>,
+    @location(7) inv_transform_3: vec4<f32>,
+    @location(8) material_id: u32,
+    @location(9) normal_threshold: f32,
+}
+
+struct VertexInput {
+    @location(10) position: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) inv_transform_0: vec4<f32>,
+    @location(1) @interpolate(flat) inv_transform_1: vec4<f32>,
+    @location(2) @interpolate(flat) inv_transform_2: vec4<f32>,
+    @location(3) @interpolate(flat) inv_transform_3: vec4<f32>,
+    // Decal axes in world space
+    @location(4) @interpolate(flat) tangent: vec3<f32>,
+    @location(5) @interpolate(flat) bitangent: vec3<f32>,
+    @location(6) @interpolate(flat) normal: vec3<f32>,
+    @location(7) @interpolate(flat) material_id: u32,
+    @location(8) @interpolate(flat) normal_threshold: f32,
+}
+
+@vertex
+fn vs_main(instance: DecalInstance, in: VertexInput) -> VertexOutput {
+    let transform = mat4x4<f32>(
+        instance.transform_0,
+        instance.transform_1,
+        instance.transform_2,
+        instance.transform_3,
+    );
+
+    var out: VertexOutput;
+    out.position = camera.view_proj * transform * vec4<f32>(in.position, 1.0);
+
+    out.inv_transform_0 = instance.inv_transform_0;
+    out.inv_transform_1 = instance.inv_transform_1;
+    out.inv_transform_2 = instance.inv_transform_2;
+    out.inv_transform_3 = instance.inv_transform_3;
+
+    out.tangent = normalize(transform[0].xyz);
+    out.bitangent = normalize(transform[1].xyz);
+    out.normal = normalize(transform[2].xyz);
+
+    out.material_id = instance.material_id;
+    out.normal_threshold = instance.normal_threshold;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct FragmentOutput {
+    // Alpha is the blend factor, metallic and roughness are write masked
+    @location(0) albedo_metallic: vec4<f32>,
+    @location(1) normal_roughness: vec4<f32>,
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let depth = textureLoad(t_depth, vec2<i32>(floor(in.position.xy)), 0);
+
+    let uv = in.position.xy / vec2<f32>(textureDimensions(t_depth));
+    let ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let view_position = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+    let world_position = (camera.inv_view * vec4<f32>(view_position.xyz / view_position.w, 1.0)).xyz;
+
+    // The gbuffer normal can't be read while written, the surface normal is
+    // rebuilt from the depth instead
+    let surface_normal = normalize(cross(dpdy(world_position), dpdx(world_position)));
+
+    let inv_transform = mat4x4<f32>(
+        in.inv_transform_0,
+        in.inv_transform_1,
+        in.inv_transform_2,
+        in.inv_transform_3,
+    );
+    let local_position = (inv_transform * vec4<f32>(world_position, 1.0)).xyz;
+    let decal_uv = vec3<f32>(local_position.x + 0.5, 0.5 - local_position.y, 1.0);
+
+    // Sampled before discarding, in uniform control flow
+    let material = materials[in.material_id];
+    let albedo_texture = sample_texture(material.albedo, material.uv_transforms[0] * decal_uv);
+    let normal_texture = sample_texture(material.normal, material.uv_transforms[1] * decal_uv).rgb;
+
+    let inside = all(abs(local_position) <= vec3<f32>(0.5));
+    let facing = dot(surface_normal, in.normal) >= in.normal_threshold;
+    if depth == camera.far_depth || !inside || !facing {
+        discard;
+    }
+
+    let albedo = material.base_color_factor
+        * select(vec4<f32>(1.0), albedo_texture, material.albedo != 0u);
+
+    let tbn = mat3x3<f32>(in.tangent, in.bitangent, in.normal);
+    let normal = normalize(tbn * (normal_texture * 2.0 - 1.0));
+    let view_normal = (camera.view * vec4<f32>(normal, 0.0)).xyz;
+
+    return FragmentOutput(
+        albedo,
+        // Without normal map the surface normal is kept
+        vec4<f32>(view_normal, select(0.0, albedo.a, material.normal != 0u)),
+    );
+}
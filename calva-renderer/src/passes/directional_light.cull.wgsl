@@ -5,6 +5,10 @@ struct Camera {
     inv_view: mat4x4<f32>,
     inv_proj: mat4x4<f32>,
     frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
 }
 @group(0) @binding(0) var<uniform> camera: Camera;
 
@@ -13,6 +17,10 @@ struct DirectionalLight {
     direction_world: vec4<f32>,
     direction_view: vec4<f32>,
     view_proj: mat4x4<f32>,
+    occlusion_culling: u32,
+    shadow_length: f32,
+    shadow_softness: f32,
+    shadows: u32,
 }
 @group(1) @binding(0) var<uniform> directional_light: DirectionalLight;
 
@@ -21,17 +29,38 @@ struct MeshBoundingSphere {
     radius: f32,
 }
 
+struct MeshLod {
+    vertex_count: u32,
+    base_index: u32,
+}
+
+struct MeshLods {
+    count: u32,
+    levels: array<MeshLod, 3>,
+}
+
 struct MeshInfo {
     vertex_count: u32,
     base_index: u32,
     vertex_offset: i32,
     skin_offset: i32,
     bounding_sphere: MeshBoundingSphere,
+    lods: MeshLods,
 }
 
-struct AnimationState {
+struct AnimationTrack {
     animation_id: u32,
     time: f32,
+    speed: f32,
+    looping: u32,
+}
+
+struct AnimationState {
+    tracks: array<AnimationTrack, 2>,
+    blend: f32,
+    blend_speed: f32,
+    additive: AnimationTrack,
+    additive_weight: f32,
 }
 
 struct Instance {
@@ -39,7 +68,14 @@ struct Instance {
     mesh_id: u32,
     material_id: u32,
     animation: AnimationState,
+    color_tint: vec4<f32>,
+    // See InstanceFlags
+    flags: u32,
 }
+// Mesh of the freed instance slots, see InstancesManager::remove
+const REMOVED_MESH: u32 = 0xFFFFFFFFu;
+const INSTANCE_HIDDEN: u32 = 1u;
+
 struct Instances {
     count: u32,
     instances: array<Instance>
@@ -54,7 +90,12 @@ struct DrawInstance {
     transform: mat4x4<f32>,
     material_id: u32,
     skin_offset: i32,
-    animation: AnimationState,
+    // Blended tracks and additive layer
+    animations_id: array<u32, 3>,
+    animations_time: array<f32, 3>,
+    // Tracks blend and additive weight
+    animations_blend: vec2<f32>,
+    _padding: vec2<u32>,
 }
 
 struct DrawIndexedIndirect {
@@ -84,6 +125,9 @@ var<storage, read_write> draw_instances: array<DrawInstance>;
 @group(2) @binding(4)
 var<storage, read_write> draw_indirects: DrawIndirects;
 
+@group(3) @binding(0)
+var t_hierarchical_depth: texture_2d<f32>;
+
 @compute @workgroup_size(32)
 fn reset(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let mesh_id = global_id.x;
@@ -140,6 +184,73 @@ fn sphere_visible(sphere: MeshBoundingSphere, transform: mat4x4<f32>) -> bool {
     return true;
 }
 
+// Largest screen area, in Hi-Z tiles, tested before giving up and keeping the caster
+const MAX_OCCLUSION_TILES: u32 = 64u;
+
+// A caster hidden from the camera can still throw a visible shadow, so the test
+// is done on the caster bounding sphere swept along the light direction over
+// the shadow depth range. When all of this volume is behind the visible
+// surfaces, none of them can receive the caster shadow.
+// Large volumes are never culled, this keeps the test cheap at the cost of
+// drawing some hidden casters close to the camera.
+fn shadow_occluded(center: vec3<f32>, radius: f32) -> bool {
+    let end = center + directional_light.direction_world.xyz * directional_light.shadow_length;
+
+    var ndc_min = vec3<f32>(1.0);
+    var ndc_max = vec3<f32>(-1.0);
+    for (var i = 0u; i < 16u; i++) {
+        let corner = vec3<f32>(
+            f32(i & 1u),
+            f32((i >> 1u) & 1u),
+            f32((i >> 2u) & 1u),
+        ) * 2.0 - 1.0;
+
+        let p = select(center, end, i >= 8u) + corner * radius;
+        let clip = camera.view_proj * vec4<f32>(p, 1.0);
+
+        // Crosses the camera plane, projected bounds are meaningless
+        if clip.w <= 0.0 {
+            return false;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        ndc_min = min(ndc_min, ndc);
+        ndc_max = max(ndc_max, ndc);
+    }
+
+    let size = vec2<i32>(textureDimensions(t_hierarchical_depth));
+    let uv_min = saturate(vec2<f32>(ndc_min.x, ndc_max.y) * vec2<f32>(0.5, -0.5) + 0.5);
+    let uv_max = saturate(vec2<f32>(ndc_max.x, ndc_min.y) * vec2<f32>(0.5, -0.5) + 0.5);
+
+    let tile_min = min(vec2<i32>(uv_min * vec2<f32>(size)), size - 1);
+    let tile_max = min(vec2<i32>(uv_max * vec2<f32>(size)), size - 1);
+
+    let tiles = tile_max - tile_min + 1;
+    if u32(tiles.x * tiles.y) > MAX_OCCLUSION_TILES {
+        return false;
+    }
+
+    let reversed_z = camera.far_depth == 0.0;
+
+    // Farthest depth of the tiles, the instance is hidden behind it
+    var far_depth = 1.0 - camera.far_depth;
+    for (var y = tile_min.y; y <= tile_max.y; y++) {
+        for (var x = tile_min.x; x <= tile_max.x; x++) {
+            let tile_depth = textureLoad(t_hierarchical_depth, vec2<i32>(x, y), 0).rg;
+            if reversed_z {
+                far_depth = min(far_depth, tile_depth.r);
+            } else {
+                far_depth = max(far_depth, tile_depth.g);
+            }
+        }
+    }
+
+    if reversed_z {
+        return ndc_max.z < far_depth;
+    }
+    return ndc_min.z > far_depth;
+}
+
 @compute @workgroup_size(32)
 fn cull(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let instance_index = global_id.x;
@@ -151,12 +262,34 @@ fn cull(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let instance = &instances.instances[instance_index];
     let transform = &(*instance).transform;
     let mesh_id = (*instance).mesh_id;
+
+    // Hidden instances don't cast shadows either
+    if mesh_id == REMOVED_MESH || ((*instance).flags & INSTANCE_HIDDEN) != 0u {
+        return;
+    }
+
     let mesh_info = &meshes_info[mesh_id];
 
     if !sphere_visible((*mesh_info).bounding_sphere, (*transform)) {
         return;
     }
 
+    if directional_light.occlusion_culling != 0u {
+        let sphere = (*mesh_info).bounding_sphere;
+        let center = ((*transform) * vec4<f32>(sphere.center, 1.0)).xyz;
+
+        let scale = vec3<f32>(
+            length((*transform)[0].xyz),
+            length((*transform)[1].xyz),
+            length((*transform)[2].xyz),
+        );
+        let radius = sphere.radius * max(max(scale.x, scale.y), scale.z);
+
+        if shadow_occluded(center, radius) {
+            return;
+        }
+    }
+
     let draw = &draw_indirects.draws[mesh_id];
     let draw_instance_index = (*draw).base_instance + atomicAdd(&(*draw).instance_count, 1u);
 
@@ -164,7 +297,22 @@ fn cull(@builtin(global_invocation_id) global_id: vec3<u32>) {
     (*draw_instance).transform = *transform;
     (*draw_instance).material_id = (*instance).material_id;
     (*draw_instance).skin_offset = (*mesh_info).skin_offset;
-    (*draw_instance).animation = (*instance).animation;
+
+    let animation = &(*instance).animation;
+    (*draw_instance).animations_id = array<u32, 3>(
+        (*animation).tracks[0].animation_id,
+        (*animation).tracks[1].animation_id,
+        (*animation).additive.animation_id,
+    );
+    (*draw_instance).animations_time = array<f32, 3>(
+        (*animation).tracks[0].time,
+        (*animation).tracks[1].time,
+        (*animation).additive.time,
+    );
+    (*draw_instance).animations_blend = vec2<f32>(
+        (*animation).blend,
+        (*animation).additive_weight,
+    );
 }
 
 @compute @workgroup_size(32)
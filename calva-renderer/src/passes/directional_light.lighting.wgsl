@@ -13,6 +13,10 @@ struct DirectionalLight {
     direction_world: vec4<f32>,
     direction_view: vec4<f32>,
     view_proj: mat4x4<f32>,
+    occlusion_culling: u32,
+    shadow_length: f32,
+    shadow_softness: f32,
+    shadows: u32,
 }
 @group(1) @binding(0) var<uniform> directional_light: DirectionalLight;
 
@@ -44,6 +48,32 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
 @group(2) @binding(3) var t_shadows: texture_depth_2d;
 @group(2) @binding(4) var t_sampler: sampler;
 
+// Exponential shadow mapping, averaged over a square kernel of the shadow
+// softness radius
+fn shadow_visibility(uv: vec2<f32>, depth: f32) -> f32 {
+    if (directional_light.shadows == 0u) {
+        return 1.0;
+    }
+
+    let ratio = 60.0;
+
+    let radius = directional_light.shadow_softness;
+    let taps = i32(ceil(radius));
+    let step = radius / max(f32(taps), 1.0) / vec2<f32>(textureDimensions(t_shadows));
+
+    var visibility = 0.0;
+    for (var y = -taps; y <= taps; y++) {
+        for (var x = -taps; x <= taps; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * step;
+            let light_depth = textureSample(t_shadows, t_sampler, uv + offset);
+            visibility += clamp(exp(ratio * 10.0 * (light_depth - depth)), 0.0, 1.0);
+        }
+    }
+
+    let width = f32(taps * 2 + 1);
+    return visibility / (width * width);
+}
+
 fn fresnel_schlick(cos_theta: f32, F0: vec3<f32>) -> vec3<f32> {
     return F0 + (1.0 - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
@@ -98,11 +128,7 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let frag_proj = frag_proj4.xyz / frag_proj4.w;
     let frag_proj_uv = frag_proj.xy * vec2<f32>(0.5, -0.5) + 0.5;
 
-    let light_depth = textureSample(t_shadows, t_sampler, frag_proj_uv);
-
-    // Exponential shadow mapping
-    let ratio = 60.0;
-    let visibility = clamp(exp(ratio * 10.0 * (light_depth - frag_proj.z)), 0.0, 1.0);
+    let visibility = shadow_visibility(frag_proj_uv, frag_proj.z);
 
     let N = normal;
     let V = normalize(-frag_pos_view.xyz);
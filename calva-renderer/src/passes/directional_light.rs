@@ -1,7 +1,7 @@
 use crate::{
-    AnimationState, AnimationsManager, Camera, CameraManager, DirectionalLight, MaterialId,
-    MeshesManager, RenderContext, RessourceRef, RessourcesManager, SkinsManager, UniformBuffer,
-    UniformData,
+    draw_indexed_indirects, AnimationId, AnimationsManager, Camera, CameraManager,
+    DirectionalLight, MaterialId, MeshesManager, RenderContext, ResourceRef, ResourcesManager,
+    SkinsManager, UniformBuffer, UniformData,
 };
 
 #[repr(C)]
@@ -10,7 +10,10 @@ struct DrawInstance {
     _model_matrix: [f32; 16],
     _material: MaterialId,
     _skin_offset: i32,
-    _animation: AnimationState,
+    _animations: [AnimationId; 3],
+    _animations_time: [f32; 3],
+    _animations_blend: [f32; 2],
+    _padding: [u32; 2],
 }
 
 impl DrawInstance {
@@ -30,8 +33,9 @@ impl DrawInstance {
 
             // Skinning
             5 => Sint32, // Skin offset
-            6 => Uint32, // Animation ID
-            7 => Float32, // Animation time
+            6 => Uint32x3, // Animations ID (blended tracks and additive layer)
+            7 => Float32x3, // Animations time
+            8 => Float32x2, // Animations blend and additive weight
         ],
     };
 }
@@ -40,26 +44,27 @@ pub struct DirectionalLightPassInputs<'a> {
     pub albedo_metallic: &'a wgpu::Texture,
     pub normal_roughness: &'a wgpu::Texture,
     pub depth: &'a wgpu::Texture,
+    pub hierarchical_depth: &'a wgpu::Texture,
     pub output: &'a wgpu::Texture,
 }
 
 pub struct DirectionalLightPass {
     pub uniform: UniformBuffer<DirectionalLightUniform>,
 
-    camera: RessourceRef<CameraManager>,
-    meshes: RessourceRef<MeshesManager>,
-    skins: RessourceRef<SkinsManager>,
-    animations: RessourceRef<AnimationsManager>,
+    camera: ResourceRef<CameraManager>,
+    meshes: ResourceRef<MeshesManager>,
+    skins: ResourceRef<SkinsManager>,
+    animations: ResourceRef<AnimationsManager>,
 
     output_view: wgpu::TextureView,
-    cull: DirectionalLightCull,
+    cull: Option<DirectionalLightCull>,
 
     sampler: wgpu::Sampler,
 
     light_depth_view: wgpu::TextureView,
     light_depth_pipeline: wgpu::RenderPipeline,
 
-    blur_pass: DirectionalLightBlur,
+    blur_pass: Option<DirectionalLightBlur>,
 
     lighting_bind_group_layout: wgpu::BindGroupLayout,
     lighting_bind_group: wgpu::BindGroup,
@@ -67,26 +72,64 @@ pub struct DirectionalLightPass {
 }
 
 impl DirectionalLightPass {
-    const SIZE: u32 = 2048;
-    const TEXTURE_SIZE: wgpu::Extent3d = wgpu::Extent3d {
-        width: Self::SIZE,
-        height: Self::SIZE,
-        depth_or_array_layers: 1,
-    };
+    pub const DEFAULT_SHADOW_SIZE: u32 = 2048;
 
     pub fn new(
         device: &wgpu::Device,
-        ressources: &RessourcesManager,
+        resources: &ResourcesManager,
         inputs: DirectionalLightPassInputs,
     ) -> Self {
-        let uniform = UniformBuffer::new(device, DirectionalLightUniform::default());
+        Self::with_shadow_size(device, resources, inputs, Self::DEFAULT_SHADOW_SIZE)
+    }
 
-        let camera = ressources.get::<CameraManager>();
-        let meshes = ressources.get::<MeshesManager>();
-        let skins = ressources.get::<SkinsManager>();
-        let animations = ressources.get::<AnimationsManager>();
+    /// Shadow map resolution can't be changed afterward, the pass has to be
+    /// created again
+    pub fn with_shadow_size(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DirectionalLightPassInputs,
+        shadow_size: u32,
+    ) -> Self {
+        Self::with_shadow_map(device, resources, inputs, Some(shadow_size))
+    }
 
-        let cull = DirectionalLightCull::new(device, ressources, &uniform);
+    /// The sun is always shaded unshadowed, the shadow map is not allocated
+    /// and shadow casters are not culled nor rendered.
+    pub fn without_shadows(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DirectionalLightPassInputs,
+    ) -> Self {
+        Self::with_shadow_map(device, resources, inputs, None)
+    }
+
+    fn with_shadow_map(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: DirectionalLightPassInputs,
+        shadow_size: Option<u32>,
+    ) -> Self {
+        let shadows = shadow_size.is_some();
+        // Still bound by the lighting pipeline, but never rendered nor sampled
+        let shadow_size = shadow_size.unwrap_or(1);
+
+        let uniform = UniformBuffer::new(
+            device,
+            DirectionalLightUniform {
+                shadow_size,
+                shadows,
+                ..Default::default()
+            },
+        );
+
+        let camera = resources.get::<CameraManager>();
+        let meshes = resources.get::<MeshesManager>();
+        let skins = resources.get::<SkinsManager>();
+        let animations = resources.get::<AnimationsManager>();
+
+        let cull = shadows.then(|| {
+            DirectionalLightCull::new(device, resources, &uniform, inputs.hierarchical_depth)
+        });
 
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("DirectionalLight sampler"),
@@ -97,7 +140,8 @@ impl DirectionalLightPass {
 
         let output_view = inputs.output.create_view(&Default::default());
 
-        let light_depth = Self::make_depth_texture(device, Some("DirectionalLight depth texture"));
+        let light_depth =
+            Self::make_depth_texture(device, Some("DirectionalLight depth texture"), shadow_size);
         let light_depth_view = light_depth.create_view(&Default::default());
 
         let light_depth_pipeline = {
@@ -147,7 +191,7 @@ impl DirectionalLightPass {
             })
         };
 
-        let blur_pass = blur::DirectionalLightBlur::new(device, &light_depth);
+        let blur_pass = shadows.then(|| blur::DirectionalLightBlur::new(device, &light_depth));
 
         let (lighting_bind_group_layout, lighting_bind_group, lighting_pipeline) = {
             let shader = device
@@ -285,7 +329,21 @@ impl DirectionalLightPass {
         }
     }
 
+    /// Width and height of the shadow map
+    pub fn shadow_size(&self) -> u32 {
+        self.uniform.shadow_size
+    }
+
+    /// `false` when created with [`DirectionalLightPass::without_shadows`]
+    pub fn has_shadow_map(&self) -> bool {
+        self.uniform.shadows
+    }
+
     pub fn rebind(&mut self, device: &wgpu::Device, inputs: DirectionalLightPassInputs) {
+        if let Some(cull) = &mut self.cull {
+            cull.rebind(device, inputs.hierarchical_depth);
+        }
+
         self.lighting_bind_group = Self::make_lighting_bind_group(
             device,
             &self.lighting_bind_group_layout,
@@ -297,6 +355,12 @@ impl DirectionalLightPass {
         self.output_view = inputs.output.create_view(&Default::default());
     }
 
+    pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+        if let Some(cull) = &mut self.cull {
+            cull.rebind_instances(device);
+        }
+    }
+
     pub fn update(&mut self, queue: &wgpu::Queue) {
         self.uniform.camera = ***self.camera.get();
         self.uniform.update(queue);
@@ -306,12 +370,49 @@ impl DirectionalLightPass {
     pub fn render(&self, ctx: &mut RenderContext) {
         ctx.encoder.profile_start("DirectionalLight");
 
+        if let (Some(cull), Some(blur_pass)) = (&self.cull, &self.blur_pass) {
+            self.render_shadows(ctx, cull, blur_pass);
+        }
+
         let camera = self.camera.get();
+
+        let mut lighting_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("DirectionalLight[lighting]"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        lighting_pass.set_pipeline(&self.lighting_pipeline);
+
+        lighting_pass.set_bind_group(0, &camera.bind_group, &[]);
+        lighting_pass.set_bind_group(1, &self.uniform.bind_group, &[]);
+        lighting_pass.set_bind_group(2, &self.lighting_bind_group, &[]);
+
+        lighting_pass.draw(0..3, 0..1);
+
+        drop(lighting_pass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn render_shadows(
+        &self,
+        ctx: &mut RenderContext,
+        cull: &DirectionalLightCull,
+        blur_pass: &DirectionalLightBlur,
+    ) {
         let meshes = self.meshes.get();
         let skins = self.skins.get();
         let animations = self.animations.get();
 
-        self.cull.cull(ctx, &self.uniform);
+        cull.cull(ctx, &self.uniform);
 
         let mut depth_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("DirectionalLight[depth]"),
@@ -332,47 +433,21 @@ impl DirectionalLightPass {
         depth_pass.set_bind_group(1, &skins.bind_group, &[]);
         depth_pass.set_bind_group(2, &animations.bind_group, &[]);
 
-        depth_pass.set_vertex_buffer(0, self.cull.draw_instances.slice(..));
+        depth_pass.set_vertex_buffer(0, cull.draw_instances.slice(..));
         depth_pass.set_vertex_buffer(1, meshes.vertices.slice(..));
 
         depth_pass.set_index_buffer(meshes.indices.slice(..), wgpu::IndexFormat::Uint32);
 
-        depth_pass.multi_draw_indexed_indirect_count(
-            &self.cull.draw_indirects,
-            std::mem::size_of::<u32>() as _,
-            &self.cull.draw_indirects,
-            0,
-            MeshesManager::MAX_MESHES as _,
+        draw_indexed_indirects(
+            &mut depth_pass,
+            ctx.device.features(),
+            &cull.draw_indirects,
+            meshes.count(),
         );
 
         drop(depth_pass);
 
-        self.blur_pass.render(ctx);
-
-        let mut lighting_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("DirectionalLight[lighting]"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.output_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
-        });
-
-        lighting_pass.set_pipeline(&self.lighting_pipeline);
-
-        lighting_pass.set_bind_group(0, &camera.bind_group, &[]);
-        lighting_pass.set_bind_group(1, &self.uniform.bind_group, &[]);
-        lighting_pass.set_bind_group(2, &self.lighting_bind_group, &[]);
-
-        lighting_pass.draw(0..3, 0..1);
-
-        drop(lighting_pass);
-
-        ctx.encoder.profile_end();
+        blur_pass.render(ctx);
     }
 
     fn make_lighting_bind_group(
@@ -419,10 +494,18 @@ impl DirectionalLightPass {
         })
     }
 
-    fn make_depth_texture(device: &wgpu::Device, label: wgpu::Label<'static>) -> wgpu::Texture {
+    fn make_depth_texture(
+        device: &wgpu::Device,
+        label: wgpu::Label<'static>,
+        size: u32,
+    ) -> wgpu::Texture {
         device.create_texture(&wgpu::TextureDescriptor {
             label,
-            size: Self::TEXTURE_SIZE,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
@@ -440,12 +523,41 @@ pub struct GpuDirectionalLightUniform {
     direction_world: glam::Vec4,
     direction_view: glam::Vec4,
     view_proj: glam::Mat4,
+    occlusion_culling: u32,
+    shadow_length: f32,
+    shadow_softness: f32,
+    shadows: u32,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Default)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct DirectionalLightUniform {
     pub light: DirectionalLight,
+    /// Skip shadow casters whose shadow can't be seen from the camera, turn
+    /// off to rule it out when debugging missing shadows
+    pub occlusion_culling: bool,
+    /// Radius of the shadows filter in shadow map texels, defaults to 0 (a
+    /// single tap, hard shadows). Clamped to [`Self::MAX_SHADOW_SOFTNESS`]
+    pub shadow_softness: f32,
     camera: Camera,
+    shadow_size: u32,
+    shadows: bool,
+}
+
+impl DirectionalLightUniform {
+    pub const MAX_SHADOW_SOFTNESS: f32 = 3.0;
+}
+
+impl Default for DirectionalLightUniform {
+    fn default() -> Self {
+        Self {
+            light: Default::default(),
+            occlusion_culling: true,
+            shadow_softness: 0.0,
+            camera: Default::default(),
+            shadow_size: DirectionalLightPass::DEFAULT_SHADOW_SIZE,
+            shadows: true,
+        }
+    }
 }
 
 impl UniformData for DirectionalLightUniform {
@@ -455,29 +567,7 @@ impl UniformData for DirectionalLightUniform {
         let light_dir = self.light.direction.normalize();
         let light_view = glam::Mat4::look_at_rh(glam::Vec3::ZERO, light_dir, glam::Vec3::Y);
 
-        // Frustum bounding sphere in view space
-        // https://lxjk.github.io/2017/04/15/Calculate-Minimal-Bounding-Sphere-of-Frustum.html
-        // https://stackoverflow.com/questions/2194812/finding-a-minimum-bounding-sphere-for-a-frustum
-        // https://stackoverflow.com/questions/56428880/how-to-extract-camera-parameters-from-projection-matrix
-        let proj = self.camera.proj;
-        let znear = proj.w_axis.z / (proj.z_axis.z - 1.0);
-        let zfar = proj.w_axis.z / (proj.z_axis.z + 1.0);
-
-        let k = f32::sqrt(1.0 + (proj.x_axis.x / proj.y_axis.y).powi(2)) * proj.x_axis.x.recip();
-        let k2 = k.powi(2);
-
-        let (mut center, mut radius) = if k2 >= (zfar - znear) / (zfar + znear) {
-            (glam::vec3(0.0, 0.0, -zfar), zfar * k)
-        } else {
-            (
-                glam::vec3(0.0, 0.0, -0.5 * (zfar + znear) * (1.0 + k2)),
-                0.5 * f32::sqrt(
-                    f32::powi(zfar - znear, 2)
-                        + 2.0 * (zfar.powi(2) + znear.powi(2)) * k2
-                        + f32::powi(zfar + znear, 2) * k.powi(4),
-                ),
-            )
-        };
+        let (mut center, mut radius) = frustum_bounding_sphere(self.camera.proj);
 
         // Move sphere to light view space
         center = (light_view * self.camera.view.inverse() * center.extend(1.0)).truncate();
@@ -486,7 +576,7 @@ impl UniformData for DirectionalLightUniform {
         // 1. prevent small radius changes due to float precision
         radius = (radius * 16.0).ceil() / 16.0;
         // 2. shadow texel size in light view space
-        let texel_size = radius * 2.0 / DirectionalLightPass::SIZE as f32;
+        let texel_size = radius * 2.0 / self.shadow_size as f32;
         // 3. allow center changes only in texel size increments
         center = (center / texel_size).ceil() * texel_size;
 
@@ -507,28 +597,75 @@ impl UniformData for DirectionalLightUniform {
             direction_world: light_dir.extend(0.0),
             direction_view: (glam::Quat::from_mat4(&self.camera.view) * light_dir).extend(0.0),
             view_proj: (light_proj * light_view),
+            occlusion_culling: self.occlusion_culling as _,
+            // Depth range of the light projection
+            shadow_length: radius * 2.0,
+            shadow_softness: self
+                .shadow_softness
+                .clamp(0.0, DirectionalLightUniform::MAX_SHADOW_SOFTNESS),
+            shadows: self.shadows as _,
         }
     }
 }
 
+/// Bounding sphere of the camera frustum, in view space
+fn frustum_bounding_sphere(proj: glam::Mat4) -> (glam::Vec3, f32) {
+    // Orthographic projections have no perspective divide, the frustum is a
+    // box and its depth range can't be extracted like below
+    if proj.z_axis.w == 0.0 {
+        let inv_proj = proj.inverse();
+
+        let min = inv_proj.project_point3(glam::vec3(-1.0, -1.0, 0.0));
+        let max = inv_proj.project_point3(glam::vec3(1.0, 1.0, 1.0));
+
+        return ((min + max) * 0.5, (max - min).length() * 0.5);
+    }
+
+    // https://lxjk.github.io/2017/04/15/Calculate-Minimal-Bounding-Sphere-of-Frustum.html
+    // https://stackoverflow.com/questions/2194812/finding-a-minimum-bounding-sphere-for-a-frustum
+    // https://stackoverflow.com/questions/56428880/how-to-extract-camera-parameters-from-projection-matrix
+    let znear = proj.w_axis.z / (proj.z_axis.z - 1.0);
+    let zfar = proj.w_axis.z / (proj.z_axis.z + 1.0);
+
+    let k = f32::sqrt(1.0 + (proj.x_axis.x / proj.y_axis.y).powi(2)) * proj.x_axis.x.recip();
+    let k2 = k.powi(2);
+
+    if k2 >= (zfar - znear) / (zfar + znear) {
+        (glam::vec3(0.0, 0.0, -zfar), zfar * k)
+    } else {
+        (
+            glam::vec3(0.0, 0.0, -0.5 * (zfar + znear) * (1.0 + k2)),
+            0.5 * f32::sqrt(
+                f32::powi(zfar - znear, 2)
+                    + 2.0 * (zfar.powi(2) + znear.powi(2)) * k2
+                    + f32::powi(zfar + znear, 2) * k.powi(4),
+            ),
+        )
+    }
+}
+
 use cull::*;
 mod cull {
     use crate::{
         CameraManager, Instance, InstancesManager, MeshInfo, MeshesManager, RenderContext,
-        RessourceRef, RessourcesManager, UniformBuffer,
+        ResourceRef, ResourcesManager, UniformBuffer,
     };
 
     use super::{DirectionalLightUniform, DrawInstance};
 
     pub struct DirectionalLightCull {
-        camera: RessourceRef<CameraManager>,
-        meshes: RessourceRef<MeshesManager>,
-        instances: RessourceRef<InstancesManager>,
+        camera: ResourceRef<CameraManager>,
+        meshes: ResourceRef<MeshesManager>,
+        instances: ResourceRef<InstancesManager>,
 
         pub(crate) draw_instances: wgpu::Buffer,
         pub(crate) draw_indirects: wgpu::Buffer,
 
+        instances_capacity: usize,
+        bind_group_layout: wgpu::BindGroupLayout,
         bind_group: wgpu::BindGroup,
+        hierarchical_depth_bind_group_layout: wgpu::BindGroupLayout,
+        hierarchical_depth_bind_group: wgpu::BindGroup,
         pipelines: (
             wgpu::ComputePipeline, // reset
             wgpu::ComputePipeline, // cull
@@ -539,21 +676,16 @@ mod cull {
     impl DirectionalLightCull {
         pub fn new(
             device: &wgpu::Device,
-            ressources: &RessourcesManager,
+            resources: &ResourcesManager,
             uniform: &UniformBuffer<DirectionalLightUniform>,
+            hierarchical_depth: &wgpu::Texture,
         ) -> Self {
-            let camera = ressources.get::<CameraManager>();
-            let meshes = ressources.get::<MeshesManager>();
-            let instances = ressources.get::<InstancesManager>();
+            let camera = resources.get::<CameraManager>();
+            let meshes = resources.get::<MeshesManager>();
+            let instances = resources.get::<InstancesManager>();
 
-            let draw_instances = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("DirectionalLight[cull] draw instances"),
-                size: (std::mem::size_of::<[DrawInstance; InstancesManager::MAX_INSTANCES]>()) as _,
-                usage: wgpu::BufferUsages::STORAGE
-                    | wgpu::BufferUsages::COPY_DST
-                    | wgpu::BufferUsages::VERTEX,
-                mapped_at_creation: false,
-            });
+            let instances_capacity = instances.get().capacity();
+            let draw_instances = Self::make_draw_instances(device, instances_capacity);
 
             let draw_indirects = device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("DirectionalLight[cull] draw indirects"),
@@ -642,32 +774,35 @@ mod cull {
                     ],
                 });
 
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("DirectionalLight[cull] bind group"),
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
+            let bind_group = Self::make_bind_group(
+                device,
+                &bind_group_layout,
+                &meshes.get(),
+                &instances.get(),
+                &draw_instances,
+                &draw_indirects,
+            );
+
+            let hierarchical_depth_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("DirectionalLight[cull] hierarchical depth bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        resource: meshes.get().meshes_info.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: instances.get().base_instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: instances.get().instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: draw_instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 4,
-                        resource: draw_indirects.as_entire_binding(),
-                    },
-                ],
-            });
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    }],
+                });
+
+            let hierarchical_depth_bind_group = Self::make_hierarchical_depth_bind_group(
+                device,
+                &hierarchical_depth_bind_group_layout,
+                hierarchical_depth,
+            );
 
             let shader =
                 device.create_shader_module(wgpu::include_wgsl!("directional_light.cull.wgsl"));
@@ -678,6 +813,7 @@ mod cull {
                     &camera.get().bind_group_layout,
                     &uniform.bind_group_layout,
                     &bind_group_layout,
+                    &hierarchical_depth_bind_group_layout,
                 ],
                 push_constant_ranges: &[],
             });
@@ -711,11 +847,44 @@ mod cull {
                 draw_instances,
                 draw_indirects,
 
+                instances_capacity,
+                bind_group_layout,
                 bind_group,
+                hierarchical_depth_bind_group_layout,
+                hierarchical_depth_bind_group,
                 pipelines,
             }
         }
 
+        pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+            let instances = self.instances.get();
+            if instances.capacity() == self.instances_capacity {
+                return;
+            }
+
+            let draw_instances = Self::make_draw_instances(device, instances.capacity());
+
+            self.bind_group = Self::make_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.meshes.get(),
+                &instances,
+                &draw_instances,
+                &self.draw_indirects,
+            );
+
+            self.instances_capacity = instances.capacity();
+            self.draw_instances = draw_instances;
+        }
+
+        pub fn rebind(&mut self, device: &wgpu::Device, hierarchical_depth: &wgpu::Texture) {
+            self.hierarchical_depth_bind_group = Self::make_hierarchical_depth_bind_group(
+                device,
+                &self.hierarchical_depth_bind_group_layout,
+                hierarchical_depth,
+            );
+        }
+
         pub fn cull(
             &self,
             ctx: &mut RenderContext,
@@ -735,7 +904,7 @@ mod cull {
             let meshes_workgroups_count =
                 (meshes_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
 
-            let instances_count: u32 = self.instances.get().count();
+            let instances_count: u32 = self.instances.get().high_water_mark();
             let instances_workgroups_count =
                 (instances_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
 
@@ -743,19 +912,93 @@ mod cull {
             cpass.set_bind_group(0, &camera.bind_group, &[]);
             cpass.set_bind_group(1, &uniform.bind_group, &[]);
             cpass.set_bind_group(2, &self.bind_group, &[]);
+            cpass.set_bind_group(3, &self.hierarchical_depth_bind_group, &[]);
             cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
 
             cpass.set_pipeline(&self.pipelines.1);
             cpass.set_bind_group(0, &camera.bind_group, &[]);
             cpass.set_bind_group(1, &uniform.bind_group, &[]);
             cpass.set_bind_group(2, &self.bind_group, &[]);
+            cpass.set_bind_group(3, &self.hierarchical_depth_bind_group, &[]);
             cpass.dispatch_workgroups(instances_workgroups_count, 1, 1);
 
-            cpass.set_pipeline(&self.pipelines.2);
-            cpass.set_bind_group(0, &camera.bind_group, &[]);
-            cpass.set_bind_group(1, &uniform.bind_group, &[]);
-            cpass.set_bind_group(2, &self.bind_group, &[]);
-            cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+            // Draws are only compacted when their count can be read back
+            if ctx
+                .device
+                .features()
+                .contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT)
+            {
+                cpass.set_pipeline(&self.pipelines.2);
+                cpass.set_bind_group(0, &camera.bind_group, &[]);
+                cpass.set_bind_group(1, &uniform.bind_group, &[]);
+                cpass.set_bind_group(2, &self.bind_group, &[]);
+                cpass.set_bind_group(3, &self.hierarchical_depth_bind_group, &[]);
+                cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+            }
+        }
+
+        fn make_draw_instances(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("DirectionalLight[cull] draw instances"),
+                size: capacity as wgpu::BufferAddress * DrawInstance::SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            })
+        }
+
+        fn make_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            meshes: &MeshesManager,
+            instances: &InstancesManager,
+            draw_instances: &wgpu::Buffer,
+            draw_indirects: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("DirectionalLight[cull] bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: meshes.meshes_info.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: instances.base_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: instances.instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: draw_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: draw_indirects.as_entire_binding(),
+                    },
+                ],
+            })
+        }
+
+        fn make_hierarchical_depth_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            hierarchical_depth: &wgpu::Texture,
+        ) -> wgpu::BindGroup {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("DirectionalLight[cull] hierarchical depth bind group"),
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &hierarchical_depth.create_view(&Default::default()),
+                    ),
+                }],
+            })
         }
     }
 }
@@ -802,6 +1045,7 @@ mod blur {
             let temp = DirectionalLightPass::make_depth_texture(
                 device,
                 Some("DirectionalLightBlur temp texture"),
+                output.width(),
             );
             let temp_view = temp.create_view(&Default::default());
             let output_view = output.create_view(&Default::default());
@@ -954,3 +1198,25 @@ mod blur {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shadow_softness_clamp() {
+        let softness = |shadow_softness| {
+            DirectionalLightUniform {
+                shadow_softness,
+                ..Default::default()
+            }
+            .as_gpu_type()
+            .shadow_softness
+        };
+
+        assert_eq!(softness(-1.0), 0.0);
+        assert_eq!(softness(0.0), 0.0);
+        assert_eq!(softness(1.5), 1.5);
+        assert_eq!(softness(10.0), DirectionalLightUniform::MAX_SHADOW_SOFTNESS);
+    }
+}
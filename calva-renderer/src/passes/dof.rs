@@ -0,0 +1,311 @@
+use crate::{
+    CameraManager, RenderContext, ResourceRef, ResourcesManager, UniformBuffer, UniformData,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DofConfig {
+    /// Distance to the focus plane, in world units
+    pub focus_distance: f32,
+    /// Aperture as an f-number (f/2.8, f/16, ...)
+    pub aperture: f32,
+    /// Lens focal length, in world units
+    pub focal_length: f32,
+    /// When set, `focus_distance` is ignored and read from the depth buffer
+    /// at the center of the screen
+    pub auto_focus: bool,
+}
+
+impl Default for DofConfig {
+    fn default() -> Self {
+        Self {
+            focus_distance: 10.0,
+            aperture: 2.8,
+            focal_length: 0.05,
+            auto_focus: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuDofConfig {
+    focus_distance: f32,
+    aperture: f32,
+    focal_length: f32,
+    auto_focus: u32,
+}
+
+impl UniformData for DofConfig {
+    type GpuType = GpuDofConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuDofConfig {
+            focus_distance: self.focus_distance,
+            aperture: self.aperture,
+            focal_length: self.focal_length,
+            auto_focus: self.auto_focus as _,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut DofConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Depth of field")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.auto_focus, "Auto focus");
+
+                ui.add_enabled(
+                    !self.auto_focus,
+                    egui::Slider::new(&mut self.focus_distance, 0.1..=100.0).text("Focus distance"),
+                );
+                ui.add(egui::Slider::new(&mut self.aperture, 1.0..=22.0).text("Aperture"));
+                ui.add(egui::Slider::new(&mut self.focal_length, 0.01..=0.3).text("Focal length"));
+            })
+            .header_response
+    }
+}
+
+pub struct DofPassInputs<'a> {
+    pub depth: &'a wgpu::Texture,
+    pub output: &'a wgpu::Texture,
+}
+
+/// Depth of field, runs in HDR on the lit scene, before tone mapping.
+///
+/// The circle of confusion is computed from the linearized depth with a thin
+/// lens model, out of focus pixels are blurred with a separable gather.
+pub struct DofPass {
+    pub config: UniformBuffer<DofConfig>,
+
+    camera: ResourceRef<CameraManager>,
+
+    temp_view: wgpu::TextureView,
+    output_view: wgpu::TextureView,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    horizontal_bind_group: wgpu::BindGroup,
+    vertical_bind_group: wgpu::BindGroup,
+
+    horizontal_pipeline: wgpu::RenderPipeline,
+    vertical_pipeline: wgpu::RenderPipeline,
+}
+
+impl DofPass {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager, inputs: DofPassInputs) -> Self {
+        let config = UniformBuffer::new(device, DofConfig::default());
+
+        let camera = resources.get::<CameraManager>();
+
+        let temp_view = Self::make_texture(device, &inputs).create_view(&Default::default());
+        let output_view = inputs.output.create_view(&Default::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Dof bind group layout"),
+            entries: &[
+                // Depth
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                // Color
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let horizontal_bind_group =
+            Self::make_bind_group(device, &bind_group_layout, inputs.depth, &output_view);
+        let vertical_bind_group =
+            Self::make_bind_group(device, &bind_group_layout, inputs.depth, &temp_view);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("dof.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Dof pipeline layout"),
+            bind_group_layouts: &[
+                &camera.get().bind_group_layout,
+                &config.bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let make_pipeline = |label: &str, entry_point: &str, format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+            })
+        };
+
+        let horizontal_pipeline =
+            make_pipeline("Dof[horizontal] pipeline", "fs_horizontal", Self::FORMAT);
+        let vertical_pipeline = make_pipeline(
+            "Dof[vertical] pipeline",
+            "fs_vertical",
+            inputs.output.format(),
+        );
+
+        Self {
+            config,
+
+            camera,
+
+            temp_view,
+            output_view,
+
+            bind_group_layout,
+            horizontal_bind_group,
+            vertical_bind_group,
+
+            horizontal_pipeline,
+            vertical_pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: DofPassInputs) {
+        self.temp_view = Self::make_texture(device, &inputs).create_view(&Default::default());
+        self.output_view = inputs.output.create_view(&Default::default());
+
+        self.horizontal_bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            inputs.depth,
+            &self.output_view,
+        );
+        self.vertical_bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            inputs.depth,
+            &self.temp_view,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        ctx.encoder.profile_start("Dof");
+
+        let camera = self.camera.get();
+
+        let mut draw = |label: &str,
+                        pipeline: &wgpu::RenderPipeline,
+                        bind_group: &wgpu::BindGroup,
+                        view: &wgpu::TextureView| {
+            let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &camera.bind_group, &[]);
+            rpass.set_bind_group(1, &self.config.bind_group, &[]);
+            rpass.set_bind_group(2, bind_group, &[]);
+
+            rpass.draw(0..3, 0..1);
+        };
+
+        draw(
+            "Dof[horizontal]",
+            &self.horizontal_pipeline,
+            &self.horizontal_bind_group,
+            &self.temp_view,
+        );
+        draw(
+            "Dof[vertical]",
+            &self.vertical_pipeline,
+            &self.vertical_bind_group,
+            &self.output_view,
+        );
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_texture(device: &wgpu::Device, inputs: &DofPassInputs) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Dof temp"),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..inputs.output.size()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[Self::FORMAT],
+        })
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        depth: &wgpu::Texture,
+        color_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Dof bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..Default::default()
+                        },
+                    )),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(color_view),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,129 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    focus_distance: f32,
+    aperture: f32,
+    focal_length: f32,
+    auto_focus: u32,
+}
+@group(1) @binding(0) var<uniform> config: Config;
+
+@group(2) @binding(0) var t_depth: texture_depth_2d;
+@group(2) @binding(1) var t_color: texture_2d<f32>;
+
+const SAMPLES: i32 = 8;
+const MAX_RADIUS: f32 = 16.0;
+const SENSOR_HEIGHT: f32 = 0.024; // 35mm film
+
+fn linear_depth(coord: vec2<i32>) -> f32 {
+    let size = vec2<f32>(textureDimensions(t_depth));
+    let uv = (vec2<f32>(coord) + 0.5) / size;
+    let ndc = (uv - 0.5) * vec2<f32>(2.0, -2.0);
+
+    let depth = textureLoad(t_depth, coord, 0);
+    let p = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+
+    return -p.z / p.w;
+}
+
+// Distance to the surface under the center of the screen
+fn focus_distance() -> f32 {
+    if config.auto_focus == 0u {
+        return config.focus_distance;
+    }
+
+    let center = vec2<i32>(textureDimensions(t_depth)) / 2;
+    return linear_depth(center);
+}
+
+// Circle of confusion radius in pixels, thin lens model
+fn circle_of_confusion(coord: vec2<i32>, focus: f32) -> f32 {
+    let z = linear_depth(coord);
+    let f = config.focal_length;
+
+    let diameter = abs(f * f * (z - focus) / (config.aperture * z * max(focus - f, 0.0001)));
+    let pixels = diameter / SENSOR_HEIGHT * f32(textureDimensions(t_depth).y) * 0.5;
+
+    return min(pixels, MAX_RADIUS);
+}
+
+fn clamp_coord(coord: vec2<i32>) -> vec2<i32> {
+    return clamp(coord, vec2<i32>(0), vec2<i32>(textureDimensions(t_color)) - 1);
+}
+
+@fragment
+fn fs_horizontal(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(in.position.xy);
+    let focus = focus_distance();
+    let coc = circle_of_confusion(coord, focus);
+
+    var color = vec3<f32>(0.0);
+    var weights = 0.0;
+    for (var i = -SAMPLES; i <= SAMPLES; i++) {
+        let offset = f32(i) / f32(SAMPLES) * coc;
+        let sample_coord = clamp_coord(coord + vec2<i32>(i32(round(offset)), 0));
+
+        // Only gather samples whose own blur reaches this pixel, avoids sharp
+        // foreground bleeding over the blurred background
+        let sample_coc = circle_of_confusion(sample_coord, focus);
+        let weight = select(0.0, 1.0, i == 0 || sample_coc >= abs(offset));
+
+        color += textureLoad(t_color, sample_coord, 0).rgb * weight;
+        weights += weight;
+    }
+
+    return vec4<f32>(color / weights, coc);
+}
+
+@fragment
+fn fs_vertical(in: VertexOutput) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(in.position.xy);
+    let coc = textureLoad(t_color, coord, 0).a;
+
+    var color = vec3<f32>(0.0);
+    var weights = 0.0;
+    for (var i = -SAMPLES; i <= SAMPLES; i++) {
+        let offset = f32(i) / f32(SAMPLES) * coc;
+        let sample = textureLoad(t_color, clamp_coord(coord + vec2<i32>(0, i32(round(offset)))), 0);
+
+        let weight = select(0.0, 1.0, i == 0 || sample.a >= abs(offset));
+
+        color += sample.rgb * weight;
+        weights += weight;
+    }
+
+    return vec4<f32>(color / weights, 1.0);
+}
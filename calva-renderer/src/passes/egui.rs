@@ -11,8 +11,15 @@ pub struct EguiPass {
 }
 
 impl EguiPass {
-    pub fn new(device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) -> Self {
-        let egui_renderer = egui_wgpu::Renderer::new(device, surface_config.format, None, 1);
+    /// `sample_count` is the [`Renderer::sample_count`] the UI is
+    /// anti-aliased with, see [`RenderContext::overlay`]
+    pub fn new(
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Self {
+        let egui_renderer =
+            egui_wgpu::Renderer::new(device, surface_config.format, None, sample_count);
 
         let screen_descriptor = egui_wgpu::renderer::ScreenDescriptor {
             size_in_pixels: [surface_config.width, surface_config.height],
@@ -71,22 +78,24 @@ impl EguiPass {
     }
 
     pub fn render(&self, ctx: &mut RenderContext) {
-        self.egui_renderer.render(
-            &mut ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Egui"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: ctx.frame,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Load,
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            }),
-            &self.paint_jobs,
-            &self.screen_descriptor,
-        );
+        ctx.overlay(None, |ctx| {
+            self.egui_renderer.render(
+                &mut ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Egui"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: ctx.frame,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                }),
+                &self.paint_jobs,
+                &self.screen_descriptor,
+            );
+        });
     }
 }
 
@@ -108,10 +117,11 @@ mod winit {
         pub fn new(
             device: &wgpu::Device,
             surface_config: &wgpu::SurfaceConfiguration,
+            sample_count: u32,
             event_loop: &EventLoop<()>,
         ) -> Self {
             Self {
-                pass: EguiPass::new(device, surface_config),
+                pass: EguiPass::new(device, surface_config, sample_count),
                 state: egui_winit::State::new(event_loop),
             }
         }
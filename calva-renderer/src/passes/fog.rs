@@ -0,0 +1,208 @@
+use crate::{
+    CameraManager, RenderContext, ResourceRef, ResourcesManager, SkyboxManager, UniformBuffer,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FogConfig {
+    pub color: [f32; 3],
+    /// Extinction per world unit at `height`
+    pub density: f32,
+    /// World height of the fog base
+    pub height: f32,
+    /// How fast the density decreases above `height`, 0 gives a uniform fog
+    pub falloff: f32,
+    /// Amount of the sky irradiance in the view direction blended into the
+    /// fog color, for aerial perspective
+    pub sky_blend: f32,
+    _padding: u32,
+}
+
+impl Default for FogConfig {
+    fn default() -> Self {
+        Self {
+            color: [0.5, 0.6, 0.7],
+            density: 0.02,
+            height: 0.0,
+            falloff: 0.2,
+            sky_blend: 0.0,
+            _padding: 0,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut FogConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Fog")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    egui::color_picker::color_edit_button_rgb(ui, &mut self.color);
+                    ui.add(egui::Label::new(egui::WidgetText::from("Color")).wrap(false));
+                });
+
+                ui.add(egui::Slider::new(&mut self.density, 0.0..=0.5).text("Density"));
+                ui.add(egui::Slider::new(&mut self.height, -50.0..=50.0).text("Height"));
+                ui.add(egui::Slider::new(&mut self.falloff, 0.0..=2.0).text("Height falloff"));
+                ui.add(egui::Slider::new(&mut self.sky_blend, 0.0..=1.0).text("Sky blend"));
+            })
+            .header_response
+    }
+}
+
+pub struct FogPassInputs<'a> {
+    pub depth: &'a wgpu::Texture,
+    pub output: &'a wgpu::Texture,
+}
+
+/// Exponential height fog, composited over the lit HDR color.
+///
+/// The fog is integrated along the view ray, so that looking down into a
+/// fog bank gets denser than looking across it. The sky is left untouched.
+pub struct FogPass {
+    pub config: UniformBuffer<FogConfig>,
+
+    camera: ResourceRef<CameraManager>,
+    skybox: ResourceRef<SkyboxManager>,
+
+    output_view: wgpu::TextureView,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl FogPass {
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager, inputs: FogPassInputs) -> Self {
+        let config = UniformBuffer::new(device, FogConfig::default());
+
+        let camera = resources.get::<CameraManager>();
+        let skybox = resources.get::<SkyboxManager>();
+
+        let output_view = inputs.output.create_view(&Default::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Fog bind group layout"),
+            entries: &[
+                // depth
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = Self::make_bind_group(device, &bind_group_layout, &inputs);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("fog.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fog pipeline layout"),
+            bind_group_layouts: &[
+                &config.bind_group_layout,
+                &bind_group_layout,
+                &camera.get().bind_group_layout,
+                &skybox.get().ibl_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Fog pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: inputs.output.format(),
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::COLOR,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        });
+
+        Self {
+            config,
+
+            camera,
+            skybox,
+
+            output_view,
+
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: FogPassInputs) {
+        self.output_view = inputs.output.create_view(&Default::default());
+        self.bind_group = Self::make_bind_group(device, &self.bind_group_layout, &inputs);
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        let camera = self.camera.get();
+        let skybox = self.skybox.get();
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fog"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.config.bind_group, &[]);
+        rpass.set_bind_group(1, &self.bind_group, &[]);
+        rpass.set_bind_group(2, &camera.bind_group, &[]);
+        rpass.set_bind_group(3, &skybox.ibl_bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        inputs: &FogPassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Fog bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&inputs.depth.create_view(
+                    &wgpu::TextureViewDescriptor {
+                        aspect: wgpu::TextureAspect::DepthOnly,
+                        ..Default::default()
+                    },
+                )),
+            }],
+        })
+    }
+}
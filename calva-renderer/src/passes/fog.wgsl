@@ -0,0 +1,86 @@
+//
+// Vertex shader
+//
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    return vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    color: vec3<f32>,
+    density: f32,
+    height: f32,
+    falloff: f32,
+    sky_blend: f32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var t_depth: texture_depth_2d;
+
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
+}
+@group(2) @binding(0) var<uniform> camera: Camera;
+
+@group(3) @binding(0) var t_irradiance: texture_cube<f32>;
+@group(3) @binding(1) var t_prefiltered: texture_cube<f32>;
+@group(3) @binding(2) var t_brdf_lut: texture_2d<f32>;
+@group(3) @binding(3) var t_ibl_sampler: sampler;
+
+// https://iquilezles.org/articles/fog/
+// Density integrated from the camera to the fragment, `ray` is not normalized
+fn fog_amount(origin: vec3<f32>, ray: vec3<f32>) -> f32 {
+    let base = config.density * exp(-config.falloff * (origin.y - config.height));
+
+    // (1 - e^-x) / x, tends to 1 for horizontal rays or a uniform fog
+    let x = config.falloff * ray.y;
+    var height_factor = 1.0;
+    if abs(x) > 1e-4 {
+        height_factor = (1.0 - exp(-x)) / x;
+    }
+
+    return 1.0 - exp(-base * length(ray) * height_factor);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(floor(position.xy));
+    let depth = textureLoad(t_depth, coord, 0);
+
+    // Sky
+    if depth == camera.far_depth { discard; }
+
+    let size = vec2<f32>(textureDimensions(t_depth));
+    let uv = position.xy / size;
+    let ndc = (uv - 0.5) * vec2<f32>(2.0, -2.0);
+
+    let frag_pos_view4 = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+    let frag_pos = camera.inv_view * vec4<f32>(frag_pos_view4.xyz / frag_pos_view4.w, 1.0);
+
+    let origin = camera.inv_view[3].xyz;
+    let ray = frag_pos.xyz - origin;
+
+    let sky = textureSampleLevel(t_irradiance, t_ibl_sampler, normalize(ray), 0.0).rgb;
+    let color = mix(config.color, sky, config.sky_blend);
+
+    return vec4<f32>(color, saturate(fog_amount(origin, ray)));
+}
@@ -1,4 +1,102 @@
-use crate::RenderContext;
+use crate::{RenderContext, UniformBuffer};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FxaaPreset {
+    Quality,
+    Balanced,
+    Fast,
+}
+
+impl FxaaPreset {
+    pub const ALL: [Self; 3] = [Self::Quality, Self::Balanced, Self::Fast];
+}
+
+impl std::fmt::Display for FxaaPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FxaaConfig {
+    /// Minimum local contrast to filter, relative to the brightest neighbour
+    pub edge_threshold: f32,
+    /// Minimum local contrast to filter, skips dark areas
+    pub edge_threshold_min: f32,
+    /// Amount of the filtered color blended in, lower keeps more detail
+    pub subpixel_quality: f32,
+}
+
+/// Filters every pixel with no threshold
+impl Default for FxaaConfig {
+    fn default() -> Self {
+        Self {
+            edge_threshold: 0.0,
+            edge_threshold_min: 0.0,
+            subpixel_quality: 1.0,
+        }
+    }
+}
+
+/// Values from the FXAA 3.11 reference
+impl From<FxaaPreset> for FxaaConfig {
+    fn from(preset: FxaaPreset) -> Self {
+        match preset {
+            FxaaPreset::Quality => Self {
+                edge_threshold: 0.125,
+                edge_threshold_min: 0.0312,
+                subpixel_quality: 0.75,
+            },
+            FxaaPreset::Balanced => Self {
+                edge_threshold: 0.166,
+                edge_threshold_min: 0.0625,
+                subpixel_quality: 0.75,
+            },
+            FxaaPreset::Fast => Self {
+                edge_threshold: 0.25,
+                edge_threshold_min: 0.0833,
+                subpixel_quality: 0.5,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut FxaaConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("FXAA")
+            .default_open(true)
+            .show(ui, |ui| {
+                let preset = FxaaPreset::ALL
+                    .into_iter()
+                    .find(|preset| FxaaConfig::from(*preset) == *self);
+
+                egui::ComboBox::from_label("Preset")
+                    .selected_text(preset.map_or("Custom".to_string(), |p| p.to_string()))
+                    .show_ui(ui, |ui| {
+                        for preset in FxaaPreset::ALL {
+                            if ui.selectable_label(false, preset.to_string()).clicked() {
+                                *self = preset.into();
+                            }
+                        }
+                    });
+
+                ui.add(
+                    egui::Slider::new(&mut self.edge_threshold, 0.0..=0.5).text("Edge threshold"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.edge_threshold_min, 0.0..=0.1)
+                        .text("Edge threshold min"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.subpixel_quality, 0.0..=1.0)
+                        .text("Subpixel quality"),
+                );
+            })
+            .header_response
+    }
+}
 
 pub struct FxaaPassInputs<'a> {
     pub input: &'a wgpu::Texture,
@@ -9,6 +107,8 @@ pub struct FxaaPassOutputs {
 }
 
 pub struct FxaaPass {
+    pub config: UniformBuffer<FxaaConfig>,
+
     pub outputs: FxaaPassOutputs,
     output_view: wgpu::TextureView,
 
@@ -20,6 +120,8 @@ pub struct FxaaPass {
 
 impl FxaaPass {
     pub fn new(device: &wgpu::Device, inputs: FxaaPassInputs) -> Self {
+        let config = UniformBuffer::new(device, FxaaConfig::default());
+
         let outputs = FxaaPassOutputs {
             output: Self::make_texture(device, &inputs),
         };
@@ -61,7 +163,7 @@ impl FxaaPass {
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Fxaa pipeline layout"),
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&config.bind_group_layout, &bind_group_layout],
             push_constant_ranges: &[],
         });
 
@@ -91,6 +193,8 @@ impl FxaaPass {
         });
 
         Self {
+            config,
+
             outputs,
             output_view,
 
@@ -111,6 +215,10 @@ impl FxaaPass {
             Self::make_bind_group(device, &self.bind_group_layout, &self.sampler, &inputs);
     }
 
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
     pub fn render(&self, ctx: &mut RenderContext) {
         let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Fxaa"),
@@ -126,7 +234,8 @@ impl FxaaPass {
         });
 
         rpass.set_pipeline(&self.pipeline);
-        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(0, &self.config.bind_group, &[]);
+        rpass.set_bind_group(1, &self.bind_group, &[]);
 
         rpass.draw(0..3, 0..1);
     }
@@ -142,7 +251,10 @@ impl FxaaPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: inputs.input.format(),
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            // Filled with a copy of the input when anti-aliasing is off
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
             view_formats: &[inputs.input.format()],
         })
     }
@@ -16,8 +16,15 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<
 // Fragment shader
 //
 
-@group(0) @binding(0) var t_sampler: sampler;
-@group(0) @binding(1) var t_input: texture_2d<f32>;
+struct Config {
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    subpixel_quality: f32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var t_sampler: sampler;
+@group(1) @binding(1) var t_input: texture_2d<f32>;
 
 const LUMA: vec3<f32> = vec3<f32>(0.299, 0.587, 0.114);
 const SPAN_MIN: vec2<f32> = vec2<f32>(-8.0, -8.0);
@@ -32,7 +39,8 @@ fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
     let luma_tr = dot(LUMA, textureLoad(t_input, coord + vec2<i32>( 1, -1), 0).rgb);
     let luma_bl = dot(LUMA, textureLoad(t_input, coord + vec2<i32>(-1,  1), 0).rgb);
     let luma_br = dot(LUMA, textureLoad(t_input, coord + vec2<i32>( 1,  1), 0).rgb);
-    let luma_c  = dot(LUMA, textureLoad(t_input, coord, 0).rgb);
+    let color_c = textureLoad(t_input, coord, 0).rgb;
+    let luma_c  = dot(LUMA, color_c);
 
     let luma_min = min(luma_c, min(
         min(luma_tl, luma_tr),
@@ -43,6 +51,11 @@ fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
         max(luma_bl, luma_br),
     ));
 
+    // Not enough contrast to be an edge
+    if luma_max - luma_min < max(config.edge_threshold_min, luma_max * config.edge_threshold) {
+        return vec4<f32>(color_c, 1.0);
+    }
+
     var dir = vec2<f32>(
         -((luma_tl + luma_tr) - (luma_bl + luma_br)),
          ((luma_tl + luma_bl) - (luma_tr + luma_br)),
@@ -56,12 +69,12 @@ fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
 
     let uv = position.xy * texel_size;
     let r1 = 0.5 * (
-        textureSample(t_input, t_sampler, uv + dir * vec2<f32>(1.0 / 3.0 - 0.5)).rgb +
-        textureSample(t_input, t_sampler, uv + dir * vec2<f32>(2.0 / 3.0 - 0.5)).rgb
+        textureSampleLevel(t_input, t_sampler, uv + dir * vec2<f32>(1.0 / 3.0 - 0.5), 0.0).rgb +
+        textureSampleLevel(t_input, t_sampler, uv + dir * vec2<f32>(2.0 / 3.0 - 0.5), 0.0).rgb
     );
     let r2 = 0.5 * (
-        textureSample(t_input, t_sampler, uv + dir * vec2<f32>(-0.5)).rgb +
-        textureSample(t_input, t_sampler, uv + dir * vec2<f32>( 0.5)).rgb
+        textureSampleLevel(t_input, t_sampler, uv + dir * vec2<f32>(-0.5), 0.0).rgb +
+        textureSampleLevel(t_input, t_sampler, uv + dir * vec2<f32>( 0.5), 0.0).rgb
     );
     let r_avg = (r1 + r2) * 0.5;
 
@@ -74,5 +87,5 @@ fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
         color = r1;
     }
 
-    return vec4<f32>(color, 1.0);
+    return vec4<f32>(mix(color_c, color, config.subpixel_quality), 1.0);
 }
\ No newline at end of file
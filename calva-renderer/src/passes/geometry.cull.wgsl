@@ -8,22 +8,51 @@ struct Camera {
 }
 @group(0) @binding(0) var<uniform> camera: Camera;
 
+struct Config {
+    lod_thresholds: vec3<f32>,
+    viewport_height: f32,
+}
+@group(2) @binding(0) var<uniform> config: Config;
+
+const MAX_LODS: u32 = 4u;
+
 struct MeshBoundingSphere {
     center: vec3<f32>,
     radius: f32,
 }
 
+struct MeshLod {
+    vertex_count: u32,
+    base_index: u32,
+}
+
+struct MeshLods {
+    count: u32,
+    levels: array<MeshLod, 3>,
+}
+
 struct MeshInfo {
     vertex_count: u32,
     base_index: u32,
     vertex_offset: i32,
     skin_offset: i32,
     bounding_sphere: MeshBoundingSphere,
+    lods: MeshLods,
 }
 
-struct AnimationState {
+struct AnimationTrack {
     animation_id: u32,
     time: f32,
+    speed: f32,
+    looping: u32,
+}
+
+struct AnimationState {
+    tracks: array<AnimationTrack, 2>,
+    blend: f32,
+    blend_speed: f32,
+    additive: AnimationTrack,
+    additive_weight: f32,
 }
 
 struct Instance {
@@ -31,7 +60,14 @@ struct Instance {
     mesh_id: u32,
     material_id: u32,
     animation: AnimationState,
+    color_tint: vec4<f32>,
+    // See InstanceFlags
+    flags: u32,
 }
+// Mesh of the freed instance slots, see InstancesManager::remove
+const REMOVED_MESH: u32 = 0xFFFFFFFFu;
+const INSTANCE_HIDDEN: u32 = 1u;
+
 struct Instances {
     count: u32,
     instances: array<Instance>
@@ -39,10 +75,18 @@ struct Instances {
 
 struct DrawInstance {
     transform: mat4x4<f32>,
-    normal_quat: vec4<f32>,
     material_id: u32,
     skin_offset: i32,
-    animation: AnimationState,
+    // Blended tracks and additive layer
+    animations_id: array<u32, 3>,
+    animations_time: array<f32, 3>,
+    // Tracks blend and additive weight
+    animations_blend: vec2<f32>,
+    // Instance slot plus one, zero being the background in the picking target
+    pick_id: u32,
+    _padding: u32,
+    color_tint: vec4<f32>,
+    prev_transform: mat4x4<f32>,
 }
 
 struct DrawIndexedIndirect {
@@ -72,17 +116,32 @@ var<storage, read_write> draw_instances: array<DrawInstance>;
 @group(1) @binding(4)
 var<storage, read_write> draw_indirects: DrawIndirects;
 
+@group(1) @binding(5)
+var<storage, read_write> prev_transforms: array<mat4x4<f32>>;
+
 @compute @workgroup_size(32)
 fn reset(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let mesh_id = global_id.x;
+    let draw_index = global_id.x;
+    let mesh_id = draw_index / MAX_LODS;
+    let lod = draw_index % MAX_LODS;
+
     let mesh_info = &meshes_info[mesh_id];
-    let draw = &draw_indirects.draws[mesh_id];
+    let draw = &draw_indirects.draws[draw_index];
 
-    (*draw).vertex_count = (*mesh_info).vertex_count;
+    if lod == 0u {
+        (*draw).vertex_count = (*mesh_info).vertex_count;
+        (*draw).base_index = (*mesh_info).base_index;
+    } else {
+        let level = (*mesh_info).lods.levels[lod - 1u];
+        (*draw).vertex_count = level.vertex_count;
+        (*draw).base_index = level.base_index;
+    }
     (*draw).instance_count = 0u;
-    (*draw).base_index = (*mesh_info).base_index;
     (*draw).vertex_offset = (*mesh_info).vertex_offset;
-    (*draw).base_instance = base_instances[mesh_id];
+
+    // Each LOD has its own copy of the instances layout
+    let instances_capacity = arrayLength(&prev_transforms);
+    (*draw).base_instance = lod * instances_capacity + base_instances[mesh_id];
 
     draw_indirects.count = 0u;
 }
@@ -108,59 +167,20 @@ fn sphere_visible(sphere: MeshBoundingSphere, transform: mat4x4<f32>, scale: vec
     );
 }
 
-fn axis_quat(x_axis: vec3<f32>, y_axis: vec3<f32>, z_axis: vec3<f32>) -> vec4<f32> {
-    // Based on https://github.com/microsoft/DirectXMath `XM$quaternionRotationMatrix`
-    if z_axis.z <= 0.0 {
-        // x^2 + y^2 >= z^2 + w^2
-        let dif10 = y_axis.y - x_axis.x;
-        let omm22 = 1.0 - z_axis.z;
-        if dif10 <= 0.0 {
-            // x^2 >= y^2
-            let four_xsq = omm22 - dif10;
-            let inv4x = 0.5 / sqrt(four_xsq);
-            return vec4<f32>(
-                four_xsq * inv4x,
-                (x_axis.y + y_axis.x) * inv4x,
-                (x_axis.z + z_axis.x) * inv4x,
-                (y_axis.z - z_axis.y) * inv4x,
-            );
-        } else {
-            // y^2 >= x^2
-            let four_ysq = omm22 + dif10;
-            let inv4y = 0.5 / sqrt(four_ysq);
-            return vec4<f32>(
-                (x_axis.y + y_axis.x) * inv4y,
-                four_ysq * inv4y,
-                (y_axis.z + z_axis.y) * inv4y,
-                (z_axis.x - x_axis.z) * inv4y,
-            );
-        }
-    } else {
-        // z^2 + w^2 >= x^2 + y^2
-        let sum10 = y_axis.y + x_axis.x;
-        let opm22 = 1.0 + z_axis.z;
-        if sum10 <= 0.0 {
-            // z^2 >= w^2
-            let four_zsq = opm22 - sum10;
-            let inv4z = 0.5 / sqrt(four_zsq);
-            return vec4<f32>(
-                (x_axis.z + z_axis.x) * inv4z,
-                (y_axis.z + z_axis.y) * inv4z,
-                four_zsq * inv4z,
-                (x_axis.y - y_axis.x) * inv4z,
-            );
-        } else {
-            // w^2 >= z^2
-            let four_wsq = opm22 + sum10;
-            let inv4w = 0.5 / sqrt(four_wsq);
-            return vec4<f32>(
-                (y_axis.z - z_axis.y) * inv4w,
-                (z_axis.x - x_axis.z) * inv4w,
-                (x_axis.y - y_axis.x) * inv4w,
-                four_wsq * inv4w,
-            );
+// Counts the LOD thresholds above the sphere radius projected on screen.
+// `lods_count` excludes the mesh itself, same as GeometryConfig::lod
+fn select_lod(lods_count: u32, center: vec3<f32>, radius: f32) -> u32 {
+    let distance = (camera.view_proj * vec4<f32>(center, 1.0)).w;
+    let radius_px = radius * camera.proj[1][1] / max(distance, 1e-4) * config.viewport_height * 0.5;
+
+    var lod = 0u;
+    for (var i = 0u; i < lods_count; i++) {
+        if radius_px < config.lod_thresholds[i] {
+            lod += 1u;
         }
     }
+
+    return lod;
 }
 
 @compute @workgroup_size(32)
@@ -174,42 +194,72 @@ fn cull(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let instance = &instances.instances[instance_index];
     let transform = &(*instance).transform;
     let mesh_id = (*instance).mesh_id;
+
+    if mesh_id == REMOVED_MESH || ((*instance).flags & INSTANCE_HIDDEN) != 0u {
+        return;
+    }
+
     let mesh_info = &meshes_info[mesh_id];
 
+    // Zeroed by InstancesManager for freshly added or shown instances, which
+    // have no previous transform yet
+    var prev_transform = prev_transforms[instance_index];
+    if prev_transform[3].w == 0.0 {
+        prev_transform = *transform;
+    }
+    prev_transforms[instance_index] = *transform;
+
     // /!\ negative scaling not supported
     let scale = vec3<f32>(
-        length(transpose(*transform)[0].xyz),
-        length(transpose(*transform)[1].xyz),
-        length(transpose(*transform)[2].xyz),
+        length((*transform)[0].xyz),
+        length((*transform)[1].xyz),
+        length((*transform)[2].xyz),
     );
 
-    if !sphere_visible((*mesh_info).bounding_sphere, (*transform), scale) {
+    let bounding_sphere = (*mesh_info).bounding_sphere;
+    if !sphere_visible(bounding_sphere, (*transform), scale) {
         return;
     }
 
-    let draw = &draw_indirects.draws[mesh_id];
+    let center = (*transform) * vec4<f32>(bounding_sphere.center, 1.0);
+    let radius = bounding_sphere.radius * max(max(scale.x, scale.y), scale.z);
+    let lod = select_lod((*mesh_info).lods.count, center.xyz / center.w, radius);
+
+    let draw = &draw_indirects.draws[mesh_id * MAX_LODS + lod];
     let draw_instance_index = (*draw).base_instance + atomicAdd(&(*draw).instance_count, 1u);
 
     let draw_instance = &draw_instances[draw_instance_index];
     (*draw_instance).transform = *transform;
-
-    let inv_scale = 1.0 / scale;
-    (*draw_instance).normal_quat = axis_quat(
-        (*transform)[0].xyz * inv_scale.x,
-        (*transform)[1].xyz * inv_scale.y,
-        (*transform)[2].xyz * inv_scale.z,
-    );
+    (*draw_instance).prev_transform = prev_transform;
 
     (*draw_instance).material_id = (*instance).material_id;
+    (*draw_instance).pick_id = instance_index + 1u;
     (*draw_instance).skin_offset = (*mesh_info).skin_offset;
-    (*draw_instance).animation = (*instance).animation;
+
+    let animation = &(*instance).animation;
+    (*draw_instance).animations_id = array<u32, 3>(
+        (*animation).tracks[0].animation_id,
+        (*animation).tracks[1].animation_id,
+        (*animation).additive.animation_id,
+    );
+    (*draw_instance).animations_time = array<f32, 3>(
+        (*animation).tracks[0].time,
+        (*animation).tracks[1].time,
+        (*animation).additive.time,
+    );
+    (*draw_instance).animations_blend = vec2<f32>(
+        (*animation).blend,
+        (*animation).additive_weight,
+    );
+
+    (*draw_instance).color_tint = (*instance).color_tint;
 }
 
 @compute @workgroup_size(32)
 fn count(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let mesh_id = global_id.x;
+    let draw_index = global_id.x;
 
-    let draw = &draw_indirects.draws[mesh_id];
+    let draw = &draw_indirects.draws[draw_index];
     let copy = *draw;
 
     if (*draw).instance_count > 0u {
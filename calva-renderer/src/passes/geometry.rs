@@ -1,16 +1,77 @@
+use std::sync::{mpsc, Arc};
+
 use crate::{
-    AnimationState, AnimationsManager, CameraManager, MaterialId, MaterialsManager, MeshesManager,
-    RenderContext, RessourceRef, RessourcesManager, SkinsManager, TexturesManager,
+    draw_indexed_indirects, AnimationId, AnimationsManager, Camera, CameraManager, FramePipelines,
+    InstanceId, InstancesManager, MaterialId, MaterialsManager, MeshesManager, RenderContext,
+    ResourceRef, ResourcesManager, SkinsManager, TexturesManager, UniformBuffer,
 };
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GeometryConfig {
+    /// Bounding sphere radius on screen, in pixels, under which each LOD past
+    /// the first one is used
+    pub lod_thresholds: [f32; MeshesManager::MAX_LODS - 1],
+    /// Set from the surface size on resize
+    viewport_height: f32,
+}
+
+impl Default for GeometryConfig {
+    fn default() -> Self {
+        Self {
+            lod_thresholds: [96.0, 48.0, 16.0],
+            viewport_height: 1.0,
+        }
+    }
+}
+
+impl GeometryConfig {
+    /// LOD selected for a bounding sphere of a mesh, same as the geometry cull
+    /// shader. `lods_count` is the number of lower detail levels registered
+    /// with [`MeshesManager::add_lod`], the mesh itself excluded.
+    pub fn lod(&self, camera: &Camera, center: glam::Vec3, radius: f32, lods_count: u32) -> u32 {
+        let distance = (camera.proj * camera.view * center.extend(1.0)).w;
+        let radius_px =
+            radius * camera.proj.y_axis.y / distance.max(1e-4) * self.viewport_height * 0.5;
+
+        self.lod_thresholds
+            .iter()
+            .take(lods_count as usize)
+            .filter(|threshold| radius_px < **threshold)
+            .count() as _
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut GeometryConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Geometry")
+            .default_open(true)
+            .show(ui, |ui| {
+                for (i, threshold) in self.lod_thresholds.iter_mut().enumerate() {
+                    ui.add(
+                        egui::Slider::new(threshold, 1.0..=512.0)
+                            .text(format!("LOD {} radius (px)", i + 1)),
+                    );
+                }
+            })
+            .header_response
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
 struct DrawInstance {
     _model_matrix: [f32; 16],
-    _normal_quat: [f32; 4],
     _material: MaterialId,
     _skin_offset: i32,
-    _animation: AnimationState,
+    _animations: [AnimationId; 3],
+    _animations_time: [f32; 3],
+    _animations_blend: [f32; 2],
+    _pick_id: u32,
+    _padding: u32,
+    _color_tint: [f32; 4],
+    _prev_model_matrix: [f32; 16],
 }
 
 impl DrawInstance {
@@ -25,75 +86,133 @@ impl DrawInstance {
             1 => Float32x4,
             2 => Float32x4,
             3 => Float32x4,
-            // Normal quat
-            4 => Float32x4,
             // Material
             5 => Uint32,
 
             // Skinning
             6 => Sint32, // Skin offset
-            7 => Uint32, // Animation ID
-            8 => Float32, // Animation time
+            7 => Uint32x3, // Animations ID (blended tracks and additive layer)
+            8 => Float32x3, // Animations time
+            9 => Float32x2, // Animations blend and additive weight
+
+            19 => Uint32x2, // Picking id and padding
+
+            18 => Float32x4, // Color tint
+
+            // Previous frame model matrix
+            14 => Float32x4,
+            15 => Float32x4,
+            16 => Float32x4,
+            17 => Float32x4,
         ],
     };
 }
 
+/// Texture formats of the gbuffer, written by [`GeometryPass`] and read by the
+/// lighting and post-processing passes
+pub struct GBufferFormats;
+
+impl GBufferFormats {
+    /// Base color (rgb) and metallic factor (a)
+    pub const ALBEDO_METALLIC: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8Unorm;
+    /// View space normal (xyz) and roughness factor (w)
+    pub const NORMAL_ROUGHNESS: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// Emitted radiance (rgb), not clamped for emissive strengths above one,
+    /// and material ambient occlusion (a)
+    pub const EMISSIVE: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    /// Screen space motion since last frame, in uv units
+    pub const VELOCITY: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+    /// Minimum (red) and maximum (green) depth of each 16x16 pixels tile,
+    /// written by [`HierarchicalDepthPass`](crate::HierarchicalDepthPass).
+    /// The closest depth is the maximum with reversed-Z.
+    pub const HIZ: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Float;
+    /// Instance slot plus one, zero on the background. Only written while
+    /// picking is enabled, see [`GeometryPass::set_picking_enabled`]
+    pub const PICKING: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+}
+
 pub struct GeometryPassOutputs {
     pub albedo_metallic: wgpu::Texture,
     pub normal_roughness: wgpu::Texture,
     pub emissive: wgpu::Texture,
+    pub velocity: wgpu::Texture,
     pub depth: wgpu::Texture,
 }
 
 pub struct GeometryPass {
+    pub config: UniformBuffer<GeometryConfig>,
+    /// Linear HDR color of the pixels not covered by any geometry, shown
+    /// where the skybox isn't drawn. The emissive target is cleared to it so
+    /// that the ambient light pass carries it to the lighting output.
+    pub clear_color: [f32; 3],
+
     pub outputs: GeometryPassOutputs,
 
-    camera: RessourceRef<CameraManager>,
-    textures: RessourceRef<TexturesManager>,
-    materials: RessourceRef<MaterialsManager>,
-    meshes: RessourceRef<MeshesManager>,
-    skins: RessourceRef<SkinsManager>,
-    animations: RessourceRef<AnimationsManager>,
+    camera: ResourceRef<CameraManager>,
+    textures: ResourceRef<TexturesManager>,
+    materials: ResourceRef<MaterialsManager>,
+    meshes: ResourceRef<MeshesManager>,
+    skins: ResourceRef<SkinsManager>,
+    animations: ResourceRef<AnimationsManager>,
+    instances: ResourceRef<InstancesManager>,
 
     cull: GeometryCull,
 
     albedo_metallic_view: wgpu::TextureView,
     normal_roughness_view: wgpu::TextureView,
     emissive_view: wgpu::TextureView,
+    velocity_view: wgpu::TextureView,
+    depth_attachment_view: wgpu::TextureView,
     depth_view: wgpu::TextureView,
 
     pipeline: wgpu::RenderPipeline,
+    picking_pipeline: wgpu::RenderPipeline,
+    wireframe_pipelines: Option<FramePipelines>,
+
+    /// Picking target and its view, only allocated while picking is enabled
+    picking: Option<(wgpu::Texture, wgpu::TextureView)>,
 }
 
 impl GeometryPass {
     pub const FEATURES: &'static [wgpu::Features] = &[
         wgpu::Features::TEXTURE_BINDING_ARRAY,
         wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
-        wgpu::Features::PARTIALLY_BOUND_BINDING_ARRAY,
-        wgpu::Features::MULTI_DRAW_INDIRECT,
     ];
 
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
         surface_config: &wgpu::SurfaceConfiguration,
-        ressources: &RessourcesManager,
+        resources: &ResourcesManager,
     ) -> Self {
-        let outputs = Self::make_outputs(device, surface_config);
+        let camera = resources.get::<CameraManager>();
+        let depth = camera.get().depth();
 
-        let camera = ressources.get::<CameraManager>();
-        let textures = ressources.get::<TexturesManager>();
-        let materials = ressources.get::<MaterialsManager>();
-        let meshes = ressources.get::<MeshesManager>();
-        let skins = ressources.get::<SkinsManager>();
-        let animations = ressources.get::<AnimationsManager>();
+        let outputs = Self::make_outputs(device, surface_config, depth.format);
+
+        let textures = resources.get::<TexturesManager>();
+        let materials = resources.get::<MaterialsManager>();
+        let meshes = resources.get::<MeshesManager>();
+        let skins = resources.get::<SkinsManager>();
+        let animations = resources.get::<AnimationsManager>();
+        let instances = resources.get::<InstancesManager>();
 
         let albedo_metallic_view = outputs.albedo_metallic.create_view(&Default::default());
         let normal_roughness_view = outputs.normal_roughness.create_view(&Default::default());
         let emissive_view = outputs.emissive.create_view(&Default::default());
-        let depth_view = outputs.depth.create_view(&Default::default());
+        let velocity_view = outputs.velocity.create_view(&Default::default());
+        let depth_attachment_view = outputs.depth.create_view(&Default::default());
+        let depth_view = Self::make_depth_only_view(&outputs.depth);
+
+        let config = UniformBuffer::new(
+            device,
+            GeometryConfig {
+                viewport_height: surface_config.height as _,
+                ..Default::default()
+            },
+        );
 
-        let cull = GeometryCull::new(device, ressources);
+        let cull = GeometryCull::new(device, resources, &config);
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("geometry.wgsl"));
 
@@ -109,77 +228,137 @@ impl GeometryPass {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Geometry[render] render pipeline"),
-            layout: Some(&pipeline_layout),
-            multiview: None,
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    DrawInstance::LAYOUT,
-                    // Positions
-                    wgpu::VertexBufferLayout {
-                        array_stride: MeshesManager::VERTEX_SIZE as _,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![10 => Float32x3],
-                    },
-                    // Normals
-                    wgpu::VertexBufferLayout {
-                        array_stride: MeshesManager::NORMAL_SIZE as _,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![11 => Float32x3],
-                    },
-                    // Tangents
-                    wgpu::VertexBufferLayout {
-                        array_stride: MeshesManager::TANGENT_SIZE as _,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![12 => Float32x4],
-                    },
-                    // UV
-                    wgpu::VertexBufferLayout {
-                        array_stride: MeshesManager::TEX_COORD_SIZE as _,
-                        step_mode: wgpu::VertexStepMode::Vertex,
-                        attributes: &wgpu::vertex_attr_array![13 => Float32x2],
-                    },
-                ],
+        let vertex_buffers = [
+            DrawInstance::LAYOUT,
+            // Positions
+            wgpu::VertexBufferLayout {
+                array_stride: MeshesManager::VERTEX_SIZE as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![10 => Float32x3],
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format: outputs.albedo_metallic.format(),
-                        blend: None,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: outputs.normal_roughness.format(),
-                        blend: None,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: outputs.emissive.format(),
-                        blend: None,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                ],
-            }),
-            primitive: wgpu::PrimitiveState {
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
+            // Normals
+            wgpu::VertexBufferLayout {
+                array_stride: MeshesManager::NORMAL_SIZE as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![11 => Float32x3],
             },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: outputs.depth.format(),
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: Default::default(),
-                bias: Default::default(),
-            }),
-            multisample: Default::default(),
+            // Tangents
+            wgpu::VertexBufferLayout {
+                array_stride: MeshesManager::TANGENT_SIZE as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![12 => Float32x4],
+            },
+            // UV
+            wgpu::VertexBufferLayout {
+                array_stride: MeshesManager::TEX_COORD_SIZE as _,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![13 => Float32x2],
+            },
+        ];
+
+        // The picking variant writes the instances ids to a fifth target
+        let targets = [
+            outputs.albedo_metallic.format(),
+            outputs.normal_roughness.format(),
+            outputs.emissive.format(),
+            outputs.velocity.format(),
+            GBufferFormats::PICKING,
+        ]
+        .map(|format| {
+            Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
         });
 
+        let make_pipeline = |label: &str, entry_point: &str, targets_count: usize| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                multiview: None,
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &vertex_buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &targets[..targets_count],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: outputs.depth.format(),
+                    depth_write_enabled: true,
+                    depth_compare: depth.compare(wgpu::CompareFunction::Less),
+                    stencil: Default::default(),
+                    bias: Default::default(),
+                }),
+                multisample: Default::default(),
+            })
+        };
+
+        let pipeline = make_pipeline("Geometry[render] render pipeline", "fs_main", 4);
+        let picking_pipeline =
+            make_pipeline("Geometry[picking] render pipeline", "fs_main_picking", 5);
+
+        // Line polygon mode is an optional device feature
+        let wireframe_pipelines = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| {
+                FramePipelines::new(move |device, format, sample_count| {
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Geometry[wireframe] render pipeline"),
+                        layout: Some(&pipeline_layout),
+                        multiview: None,
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &vertex_buffers,
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_wireframe",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format,
+                                blend: None,
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                            cull_mode: Some(wgpu::Face::Back),
+                            polygon_mode: wgpu::PolygonMode::Line,
+                            ..Default::default()
+                        },
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                            format: depth.format,
+                            depth_write_enabled: false,
+                            depth_compare: depth.compare(wgpu::CompareFunction::LessEqual),
+                            stencil: Default::default(),
+                            // Pull the edges in front of the faces they belong to
+                            bias: wgpu::DepthBiasState {
+                                constant: depth.near_bias(2),
+                                slope_scale: depth.near_bias(1) as f32,
+                                clamp: 0.0,
+                            },
+                        }),
+                        multisample: wgpu::MultisampleState {
+                            count: sample_count,
+                            ..Default::default()
+                        },
+                    })
+                })
+            });
+
         GeometryPass {
+            config,
+            clear_color: [0.0; 3],
+
             outputs,
 
             camera,
@@ -188,20 +367,28 @@ impl GeometryPass {
             meshes,
             skins,
             animations,
+            instances,
 
             cull,
 
             albedo_metallic_view,
             normal_roughness_view,
             emissive_view,
+            velocity_view,
+            depth_attachment_view,
             depth_view,
 
             pipeline,
+            picking_pipeline,
+            wireframe_pipelines,
+
+            picking: None,
         }
     }
 
     pub fn resize(&mut self, device: &wgpu::Device, surface_config: &wgpu::SurfaceConfiguration) {
-        self.outputs = Self::make_outputs(device, surface_config);
+        self.config.viewport_height = surface_config.height as _;
+        self.outputs = Self::make_outputs(device, surface_config, self.camera.get().depth().format);
 
         self.albedo_metallic_view = self
             .outputs
@@ -212,14 +399,147 @@ impl GeometryPass {
             .normal_roughness
             .create_view(&Default::default());
         self.emissive_view = self.outputs.emissive.create_view(&Default::default());
-        self.depth_view = self.outputs.depth.create_view(&Default::default());
+        self.velocity_view = self.outputs.velocity.create_view(&Default::default());
+        self.depth_attachment_view = self.outputs.depth.create_view(&Default::default());
+        self.depth_view = Self::make_depth_only_view(&self.outputs.depth);
+
+        if self.picking.is_some() {
+            self.picking = Some(Self::make_picking(device, surface_config));
+        }
+    }
+
+    /// See [`GBufferFormats::ALBEDO_METALLIC`]
+    pub fn albedo_metallic_view(&self) -> &wgpu::TextureView {
+        &self.albedo_metallic_view
+    }
+
+    /// See [`GBufferFormats::NORMAL_ROUGHNESS`]
+    pub fn normal_roughness_view(&self) -> &wgpu::TextureView {
+        &self.normal_roughness_view
+    }
+
+    /// See [`GBufferFormats::EMISSIVE`]
+    pub fn emissive_view(&self) -> &wgpu::TextureView {
+        &self.emissive_view
+    }
+
+    /// See [`GBufferFormats::VELOCITY`]
+    pub fn velocity_view(&self) -> &wgpu::TextureView {
+        &self.velocity_view
+    }
+
+    /// Non-linear depth aspect only, bindable as a `texture_depth_2d`.
+    /// See [`DepthConfig`](crate::DepthConfig) for its format
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        &self.depth_view
+    }
+
+    pub fn picking_enabled(&self) -> bool {
+        self.picking.is_some()
+    }
+
+    /// Allocates the picking target written along the gbuffer, or frees it.
+    /// Picking is disabled by default, it costs an extra R32Uint target.
+    pub fn set_picking_enabled(
+        &mut self,
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+        enabled: bool,
+    ) {
+        if enabled == self.picking.is_some() {
+            return;
+        }
+
+        self.picking = enabled.then(|| Self::make_picking(device, surface_config));
+    }
+
+    /// Instance covering the pixel at `x`, `y` in the last rendered frame,
+    /// in physical pixels from the top left corner of the surface.
+    ///
+    /// The pixel is read back asynchronously: the id is received once the
+    /// device has been polled. `None` is received for the background, for
+    /// pixels out of the surface, for instances removed in the meantime, or
+    /// when picking is disabled.
+    pub fn pick(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        x: u32,
+        y: u32,
+    ) -> mpsc::Receiver<Option<InstanceId>> {
+        let (sender, receiver) = mpsc::channel();
+
+        let Some((texture, _)) = self
+            .picking
+            .as_ref()
+            .filter(|(texture, _)| x < texture.width() && y < texture.height())
+        else {
+            sender.send(None).ok();
+            return receiver;
+        };
+
+        let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Geometry[picking] readback buffer"),
+            size: std::mem::size_of::<u32>() as _,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Geometry[picking] readback encoder"),
+        });
+        // A single row needs no padded bytes per row
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: Default::default(),
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let instances = self.instances.clone();
+        buffer
+            .clone()
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |res| {
+                let id = res.ok().and_then(|_| {
+                    let pick_id: u32 =
+                        bytemuck::pod_read_unaligned(&buffer.slice(..).get_mapped_range());
+                    buffer.unmap();
+
+                    instances.get().id_at(pick_id.checked_sub(1)?)
+                });
+
+                sender.send(id).ok();
+            });
+
+        receiver
+    }
+
+    pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+        self.cull.rebind_instances(device);
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
     }
 
     #[allow(clippy::too_many_arguments)]
     pub fn render(&self, ctx: &mut RenderContext) {
         ctx.encoder.profile_start("Geometry");
 
-        self.cull.cull(ctx);
+        self.cull.cull(ctx, &self.config);
 
         let camera = self.camera.get();
         let textures = self.textures.get();
@@ -228,40 +548,140 @@ impl GeometryPass {
         let animations = self.animations.get();
         let meshes = self.meshes.get();
 
+        let [r, g, b] = self.clear_color.map(f64::from);
+        let clear_color = wgpu::Color { r, g, b, a: 0.0 };
+
+        let color_attachments = [
+            (&self.albedo_metallic_view, wgpu::Color::BLACK),
+            (&self.normal_roughness_view, wgpu::Color::BLACK),
+            // No occlusion in alpha, so that ambient light leaves it untouched
+            (&self.emissive_view, clear_color),
+            (&self.velocity_view, wgpu::Color::BLACK),
+        ]
+        .into_iter()
+        .chain(
+            self.picking
+                .as_ref()
+                .map(|(_, view)| (view, wgpu::Color::TRANSPARENT)),
+        )
+        .map(|(view, color)| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: true,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
         let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Geometry[render]"),
-            color_attachments: &[
-                &self.albedo_metallic_view,
-                &self.normal_roughness_view,
-                &self.emissive_view,
-            ]
-            .map(|view| {
-                Some(wgpu::RenderPassColorAttachment {
-                    view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: true,
-                    },
-                })
-            }),
+            color_attachments: &color_attachments,
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_view,
+                view: &self.depth_attachment_view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Clear(self.camera.get().depth().far()),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        rpass.set_pipeline(match self.picking {
+            Some(_) => &self.picking_pipeline,
+            None => &self.pipeline,
+        });
+        self.draw(
+            &mut rpass,
+            ctx.device.features(),
+            [
+                &camera.bind_group,
+                &textures.bind_group,
+                &materials.bind_group,
+                &skins.bind_group,
+                &animations.bind_group,
+            ],
+            &meshes,
+        );
+
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    /// Wireframe pipeline is only available with the `POLYGON_MODE_LINE` feature
+    pub fn wireframe_supported(&self) -> bool {
+        self.wireframe_pipelines.is_some()
+    }
+
+    /// Draws the edges of the geometry culled for this frame on top of the
+    /// output, does nothing when wireframes are not supported
+    pub fn render_wireframe(&self, ctx: &mut RenderContext) {
+        let Some(wireframe_pipelines) = &self.wireframe_pipelines else {
+            return;
+        };
+
+        ctx.encoder.profile_start("Geometry[wireframe]");
+
+        let camera = self.camera.get();
+        let textures = self.textures.get();
+        let materials = self.materials.get();
+        let skins = self.skins.get();
+        let animations = self.animations.get();
+        let meshes = self.meshes.get();
+        let wireframe_pipeline = wireframe_pipelines.get(ctx);
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Geometry[wireframe]"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
                     store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.depth.unwrap_or(&self.depth_attachment_view),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: false,
                 }),
                 stencil_ops: None,
             }),
         });
 
-        rpass.set_pipeline(&self.pipeline);
+        rpass.set_pipeline(&wireframe_pipeline);
+        self.draw(
+            &mut rpass,
+            ctx.device.features(),
+            [
+                &camera.bind_group,
+                &textures.bind_group,
+                &materials.bind_group,
+                &skins.bind_group,
+                &animations.bind_group,
+            ],
+            &meshes,
+        );
 
-        rpass.set_bind_group(0, &camera.bind_group, &[]);
-        rpass.set_bind_group(1, &textures.bind_group, &[]);
-        rpass.set_bind_group(2, &materials.bind_group, &[]);
-        rpass.set_bind_group(3, &skins.bind_group, &[]);
-        rpass.set_bind_group(4, &animations.bind_group, &[]);
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        features: wgpu::Features,
+        bind_groups: [&'a wgpu::BindGroup; 5],
+        meshes: &'a MeshesManager,
+    ) {
+        for (index, bind_group) in bind_groups.into_iter().enumerate() {
+            rpass.set_bind_group(index as _, bind_group, &[]);
+        }
 
         rpass.set_vertex_buffer(0, self.cull.draw_instances.slice(..));
         rpass.set_vertex_buffer(1, meshes.vertices.slice(..));
@@ -271,22 +691,48 @@ impl GeometryPass {
 
         rpass.set_index_buffer(meshes.indices.slice(..), wgpu::IndexFormat::Uint32);
 
-        rpass.multi_draw_indexed_indirect_count(
+        draw_indexed_indirects(
+            rpass,
+            features,
             &self.cull.draw_indirects,
-            std::mem::size_of::<u32>() as _,
-            &self.cull.draw_indirects,
-            0,
-            MeshesManager::MAX_MESHES as _,
+            meshes.count() * MeshesManager::MAX_LODS as u32,
         );
+    }
 
-        drop(rpass);
+    fn make_depth_only_view(depth: &wgpu::Texture) -> wgpu::TextureView {
+        depth.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        })
+    }
 
-        ctx.encoder.profile_end();
+    fn make_picking(
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Geometry picking texture"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: GBufferFormats::PICKING,
+            view_formats: &[GBufferFormats::PICKING],
+        });
+        let view = texture.create_view(&Default::default());
+
+        (texture, view)
     }
 
     fn make_outputs(
         device: &wgpu::Device,
         surface_config: &wgpu::SurfaceConfiguration,
+        depth_format: wgpu::TextureFormat,
     ) -> GeometryPassOutputs {
         let size = wgpu::Extent3d {
             width: surface_config.width,
@@ -301,8 +747,8 @@ impl GeometryPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            format: wgpu::TextureFormat::Bgra8Unorm,
-            view_formats: &[wgpu::TextureFormat::Bgra8Unorm],
+            format: GBufferFormats::ALBEDO_METALLIC,
+            view_formats: &[GBufferFormats::ALBEDO_METALLIC],
         });
 
         let normal_roughness = device.create_texture(&wgpu::TextureDescriptor {
@@ -312,8 +758,8 @@ impl GeometryPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            format: wgpu::TextureFormat::Rgba16Float,
-            view_formats: &[wgpu::TextureFormat::Rgba16Float],
+            format: GBufferFormats::NORMAL_ROUGHNESS,
+            view_formats: &[GBufferFormats::NORMAL_ROUGHNESS],
         });
 
         let emissive = device.create_texture(&wgpu::TextureDescriptor {
@@ -323,8 +769,19 @@ impl GeometryPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            format: wgpu::TextureFormat::Bgra8Unorm,
-            view_formats: &[wgpu::TextureFormat::Bgra8Unorm],
+            format: GBufferFormats::EMISSIVE,
+            view_formats: &[GBufferFormats::EMISSIVE],
+        });
+
+        let velocity = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("GBuffer velocity texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            format: GBufferFormats::VELOCITY,
+            view_formats: &[GBufferFormats::VELOCITY],
         });
 
         let depth = device.create_texture(&wgpu::TextureDescriptor {
@@ -334,14 +791,15 @@ impl GeometryPass {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            format: wgpu::TextureFormat::Depth24PlusStencil8,
-            view_formats: &[wgpu::TextureFormat::Depth24PlusStencil8],
+            format: depth_format,
+            view_formats: &[depth_format],
         });
 
         GeometryPassOutputs {
             albedo_metallic,
             normal_roughness,
             emissive,
+            velocity,
             depth,
         }
     }
@@ -351,19 +809,21 @@ use cull::*;
 mod cull {
     use crate::{
         CameraManager, Instance, InstancesManager, MeshInfo, MeshesManager, RenderContext,
-        RessourceRef, RessourcesManager,
+        ResourceRef, ResourcesManager, UniformBuffer,
     };
 
-    use super::DrawInstance;
+    use super::{DrawInstance, GeometryConfig};
 
     pub struct GeometryCull {
-        camera: RessourceRef<CameraManager>,
-        meshes: RessourceRef<MeshesManager>,
-        instances: RessourceRef<InstancesManager>,
+        camera: ResourceRef<CameraManager>,
+        meshes: ResourceRef<MeshesManager>,
+        instances: ResourceRef<InstancesManager>,
 
         pub(crate) draw_instances: wgpu::Buffer,
         pub(crate) draw_indirects: wgpu::Buffer,
 
+        instances_capacity: usize,
+        bind_group_layout: wgpu::BindGroupLayout,
         bind_group: wgpu::BindGroup,
         pipelines: (
             wgpu::ComputePipeline, // reset
@@ -373,26 +833,26 @@ mod cull {
     }
 
     impl GeometryCull {
-        pub fn new(device: &wgpu::Device, ressources: &RessourcesManager) -> Self {
-            let camera = ressources.get::<CameraManager>();
-            let meshes = ressources.get::<MeshesManager>();
-            let instances = ressources.get::<InstancesManager>();
-
-            let draw_instances = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("Geometry[cull] draw instances"),
-                size: (std::mem::size_of::<[DrawInstance; InstancesManager::MAX_INSTANCES]>()) as _,
-                usage: wgpu::BufferUsages::STORAGE
-                    | wgpu::BufferUsages::COPY_DST
-                    | wgpu::BufferUsages::VERTEX,
-                mapped_at_creation: false,
-            });
+        pub fn new(
+            device: &wgpu::Device,
+            resources: &ResourcesManager,
+            config: &UniformBuffer<GeometryConfig>,
+        ) -> Self {
+            let camera = resources.get::<CameraManager>();
+            let meshes = resources.get::<MeshesManager>();
+            let instances = resources.get::<InstancesManager>();
+
+            let instances_capacity = instances.get().capacity();
+            let draw_instances = Self::make_draw_instances(device, instances_capacity);
 
             let draw_indirects = device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("Geometry[cull] draw indirects"),
                 size: {
                     let count_size = std::mem::size_of::<u32>();
+                    // One draw per mesh LOD
                     let indirects_size = std::mem::size_of::<
-                        [wgpu::util::DrawIndexedIndirect; MeshesManager::MAX_MESHES],
+                        [wgpu::util::DrawIndexedIndirect;
+                            MeshesManager::MAX_MESHES * MeshesManager::MAX_LODS],
                     >();
 
                     count_size + indirects_size
@@ -471,41 +931,42 @@ mod cull {
                             },
                             count: None,
                         },
+                        // Previous transforms
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 5,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                    glam::Mat4,
+                                >(
+                                )
+                                    as _),
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Geometry[cull] bind group"),
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: meshes.get().meshes_info.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: instances.get().base_instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: instances.get().instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 3,
-                        resource: draw_instances.as_entire_binding(),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 4,
-                        resource: draw_indirects.as_entire_binding(),
-                    },
-                ],
-            });
+            let bind_group = Self::make_bind_group(
+                device,
+                &bind_group_layout,
+                &meshes.get(),
+                &instances.get(),
+                &draw_instances,
+                &draw_indirects,
+            );
 
             let shader = device.create_shader_module(wgpu::include_wgsl!("geometry.cull.wgsl"));
 
             let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Geometry[cull] pipeline layout"),
-                bind_group_layouts: &[&camera.get().bind_group_layout, &bind_group_layout],
+                bind_group_layouts: &[
+                    &camera.get().bind_group_layout,
+                    &bind_group_layout,
+                    &config.bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
@@ -538,12 +999,35 @@ mod cull {
                 draw_instances,
                 draw_indirects,
 
+                instances_capacity,
+                bind_group_layout,
                 bind_group,
                 pipelines,
             }
         }
 
-        pub fn cull(&self, ctx: &mut RenderContext) {
+        pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+            let instances = self.instances.get();
+            if instances.capacity() == self.instances_capacity {
+                return;
+            }
+
+            let draw_instances = Self::make_draw_instances(device, instances.capacity());
+
+            self.bind_group = Self::make_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.meshes.get(),
+                &instances,
+                &draw_instances,
+                &self.draw_indirects,
+            );
+
+            self.instances_capacity = instances.capacity();
+            self.draw_instances = draw_instances;
+        }
+
+        pub fn cull(&self, ctx: &mut RenderContext, config: &UniformBuffer<GeometryConfig>) {
             let camera = self.camera.get();
 
             let mut cpass = ctx
@@ -554,28 +1038,207 @@ mod cull {
 
             const WORKGROUP_SIZE: u32 = 32;
 
-            let meshes_count: u32 = self.meshes.get().count();
-            let meshes_workgroups_count =
-                (meshes_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
+            let draws_count = self.meshes.get().count() * MeshesManager::MAX_LODS as u32;
+            let draws_workgroups_count = (draws_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
 
-            let instances_count: u32 = self.instances.get().count();
+            let instances_count: u32 = self.instances.get().high_water_mark();
             let instances_workgroups_count =
                 (instances_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
 
             cpass.set_pipeline(&self.pipelines.0);
             cpass.set_bind_group(0, &camera.bind_group, &[]);
             cpass.set_bind_group(1, &self.bind_group, &[]);
-            cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+            cpass.set_bind_group(2, &config.bind_group, &[]);
+            cpass.dispatch_workgroups(draws_workgroups_count, 1, 1);
 
             cpass.set_pipeline(&self.pipelines.1);
             cpass.set_bind_group(0, &camera.bind_group, &[]);
             cpass.set_bind_group(1, &self.bind_group, &[]);
+            cpass.set_bind_group(2, &config.bind_group, &[]);
             cpass.dispatch_workgroups(instances_workgroups_count, 1, 1);
 
-            cpass.set_pipeline(&self.pipelines.2);
-            cpass.set_bind_group(0, &camera.bind_group, &[]);
-            cpass.set_bind_group(1, &self.bind_group, &[]);
-            cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+            // Draws are only compacted when their count can be read back
+            if ctx
+                .device
+                .features()
+                .contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT)
+            {
+                cpass.set_pipeline(&self.pipelines.2);
+                cpass.set_bind_group(0, &camera.bind_group, &[]);
+                cpass.set_bind_group(1, &self.bind_group, &[]);
+                cpass.set_bind_group(2, &config.bind_group, &[]);
+                cpass.dispatch_workgroups(draws_workgroups_count, 1, 1);
+            }
+        }
+
+        /// Each LOD has its own copy of the instances layout, all instances
+        /// of a mesh may use the same LOD: the buffer holds
+        /// `capacity * MAX_LODS` draw instances whatever the number of LODs
+        /// registered, 768 bytes per instance slot (48MB at the initial
+        /// capacity).
+        fn make_draw_instances(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Geometry[cull] draw instances"),
+                size: (capacity * MeshesManager::MAX_LODS) as wgpu::BufferAddress
+                    * DrawInstance::SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            })
+        }
+
+        fn make_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            meshes: &MeshesManager,
+            instances: &InstancesManager,
+            draw_instances: &wgpu::Buffer,
+            draw_indirects: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Geometry[cull] bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: meshes.meshes_info.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: instances.base_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: instances.instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: draw_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: draw_indirects.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: instances.prev_transforms.as_entire_binding(),
+                    },
+                ],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils, Engine, Instance};
+
+    #[test]
+    fn lod_follows_screen_size() {
+        let config = GeometryConfig {
+            viewport_height: 1080.0,
+            ..Default::default()
+        };
+
+        let camera = Camera {
+            view: glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Z, glam::Vec3::Y),
+            proj: glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_4, 16.0 / 9.0, 0.1, 1000.0),
+        };
+        let lods = MeshesManager::MAX_LODS as u32 - 1;
+
+        // Fills a good part of the screen
+        let near = glam::vec3(0.0, 0.0, -5.0);
+        assert_eq!(config.lod(&camera, near, 1.0, lods), 0);
+
+        // A few pixels wide
+        let far = glam::vec3(0.0, 0.0, -500.0);
+        assert_eq!(config.lod(&camera, far, 1.0, lods), lods);
+
+        // Only the registered LODs are used
+        assert_eq!(config.lod(&camera, far, 1.0, 1), 1);
+        // Meshes without LODs are never switched
+        assert_eq!(config.lod(&camera, far, 1.0, 0), 0);
+    }
+
+    #[test]
+    fn cull_selects_lods() {
+        let Some(renderer) = test_utils::renderer((256, 256)) else {
+            return;
+        };
+
+        let mut engine = Engine::new(&renderer);
+
+        let vertices = [
+            [-1.0f32, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let normals = [[0.0f32, 0.0, 1.0]; 4];
+        let tangents = [[1.0f32, 0.0, 0.0, 1.0]; 4];
+        let tex_coords = [[0.0f32; 2]; 4];
+        let indices = [0u32, 1, 2, 0, 2, 3];
+        let meshes = engine.resources.get::<MeshesManager>();
+        let mesh = meshes.get().add(
+            &renderer.queue,
+            (glam::Vec3::ZERO, 1.0),
+            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(&normals),
+            bytemuck::cast_slice(&tangents),
+            bytemuck::cast_slice(&tex_coords),
+            bytemuck::cast_slice(&indices),
+            None,
+        );
+        meshes.get().add_lod(
+            &renderer.queue,
+            mesh,
+            &[&indices, &indices[..3], &indices[3..]],
+        );
+
+        // Over a hundred pixels wide, then a couple of pixels
+        let instances = engine.resources.get::<InstancesManager>();
+        instances.get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            [-1.0, -99.0].map(|z| {
+                Instance::new(glam::Mat4::from_translation(glam::vec3(0.0, 0.0, z))).with_mesh(mesh)
+            }),
+        );
+
+        {
+            let camera = engine.resources.get::<CameraManager>();
+            let mut camera = camera.get_mut();
+            camera.view = glam::Mat4::look_at_rh(glam::Vec3::Z, glam::Vec3::ZERO, glam::Vec3::Y);
+            camera.proj = glam::Mat4::perspective_rh(1.0, 1.0, 0.1, 1000.0);
         }
+
+        engine.update(&renderer);
+        renderer.render(|ctx| engine.render(ctx)).unwrap();
+
+        let data = test_utils::read_buffer(
+            &renderer.device,
+            &renderer.queue,
+            &engine.geometry.cull.draw_indirects,
+        );
+        // Draws follow the count header, they may have been compacted: the
+        // LOD is told by the instances layout copy each draw points to
+        let draws: &[[u32; 5]] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<u32>()..][..std::mem::size_of::<[u32; 5]>()
+                * MeshesManager::MAX_LODS
+                * meshes.get().count() as usize],
+        );
+        let capacity = instances.get().capacity() as u32;
+        let mut lods = draws
+            .iter()
+            .filter(|draw| draw[1] > 0)
+            .map(|draw| (draw[4] / capacity, draw[1]))
+            .collect::<Vec<_>>();
+        lods.sort_unstable();
+        lods.dedup();
+
+        let last_lod = MeshesManager::MAX_LODS as u32 - 1;
+        assert_eq!(lods, [(0, 1), (last_lod, 1)]);
     }
 }
@@ -5,37 +5,68 @@ struct Camera {
     inv_view: mat4x4<f32>,
     inv_proj: mat4x4<f32>,
     frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
 }
 @group(0) @binding(0) var<uniform> camera: Camera;
 
 @group(1) @binding(0) var textures: binding_array<texture_2d<f32>>;
-@group(1) @binding(1) var textures_sampler: sampler;
+@group(1) @binding(1) var textures_samplers: binding_array<sampler>;
+
+// Texture ids hold the view index in the low 24 bits and the sampler index
+// in the high 8 bits
+fn sample_texture(id: u32, uv: vec2<f32>) -> vec4<f32> {
+    return textureSample(textures[id & 0xffffffu], textures_samplers[id >> 24u], uv);
+}
 
 struct Material {
     albedo: u32,
     normal: u32,
     metallic_roughness: u32,
     emissive: u32,
+    emissive_factor: vec3<f32>,
+    emissive_strength: f32,
+    base_color_factor: vec4<f32>,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    alpha_mode: u32,
+    alpha_cutoff: f32,
+    // Albedo, normal, metallic roughness, emissive and occlusion
+    uv_transforms: array<mat3x2<f32>, 5>,
+    occlusion: u32,
+    occlusion_strength: f32,
 }
+
+const ALPHA_MODE_OPAQUE: u32 = 0u;
 @group(2) @binding(0) var<storage, read> materials: array<Material>;
 
 @group(3) @binding(0) var<storage, read> skinning_joints: array<u32>;
 @group(3) @binding(1) var<storage, read> skinning_weights: array<vec4<f32>>;
 
 @group(4) @binding(0) var animations: binding_array<texture_2d_array<f32>>;
-@group(4) @binding(1) var animations_sampler: sampler;
+@group(4) @binding(2) var<storage, read> animations_samples_per_sec: array<f32>;
 
 struct MeshInstance {
     @location(0) model_matrix_0: vec4<f32>,
     @location(1) model_matrix_1: vec4<f32>,
     @location(2) model_matrix_2: vec4<f32>,
     @location(3) model_matrix_3: vec4<f32>,
-    @location(4) normal_quat: vec4<f32>,
     @location(5) material_id: u32,
 
     @location(6) skin_offset: i32,
-    @location(7) animation_id: u32,
-    @location(8) animation_time: f32,
+    @location(7) animations_id: vec3<u32>,
+    @location(8) animations_time: vec3<f32>,
+    @location(9) animations_blend: vec2<f32>,
+
+    // Picking id (x) and padding
+    @location(19) pick_id: vec2<u32>,
+
+    @location(18) color_tint: vec4<f32>,
+
+    @location(14) prev_model_matrix_0: vec4<f32>,
+    @location(15) prev_model_matrix_1: vec4<f32>,
+    @location(16) prev_model_matrix_2: vec4<f32>,
+    @location(17) prev_model_matrix_3: vec4<f32>,
 }
 
 struct VertexInput {
@@ -53,31 +84,135 @@ struct VertexOutput {
     @location(3) bitangent: vec3<f32>,
     @location(4) uv: vec2<f32>,
     @location(5) @interpolate(flat) material_id: u32,
+    @location(6) current_position: vec4<f32>,
+    @location(7) prev_position: vec4<f32>,
+    @location(8) @interpolate(flat) color_tint: vec4<f32>,
+    @location(9) @interpolate(flat) pick_id: u32,
 }
 
-fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
-    return v + 2.0 * cross(q.xyz, cross(q.xyz, v) + q.w * v);
+// Inverse transpose scaled by the determinant (cofactor matrix), normals are
+// normalized afterward so only the determinant sign is kept. Unlike a rotation
+// this keeps normals perpendicular to non-uniformly scaled surfaces.
+fn normal_matrix(m: mat3x3<f32>) -> mat3x3<f32> {
+    let cofactor = mat3x3<f32>(
+        cross(m[1], m[2]),
+        cross(m[2], m[0]),
+        cross(m[0], m[1]),
+    );
+    return cofactor * sign(determinant(m));
 }
 
 fn mat4_to_mat3(m: mat4x4<f32>) -> mat3x3<f32> {
     return mat3x3<f32>(m[0].xyz, m[1].xyz, m[2].xyz);
 }
 
-const ANIMATIONS_SAMPLES_PER_SEC: f32 = 15.0;
-fn get_joint_matrix(animation_id: u32, time: f32, joint_index: u32) -> mat4x4<f32> {
-    let texture = animations[animation_id];
-    let dim = textureDimensions(texture);
+fn load_joint_matrix(animation_id: u32, joint_index: u32, frame_index: u32) -> mat4x4<f32> {
+    let coord = vec2<i32>(i32(joint_index), i32(frame_index));
+
+    return mat4x4<f32>(
+        textureLoad(animations[animation_id], coord, 0, 0),
+        textureLoad(animations[animation_id], coord, 1, 0),
+        textureLoad(animations[animation_id], coord, 2, 0),
+        textureLoad(animations[animation_id], coord, 3, 0),
+    );
+}
+
+// Rotation of an orthonormal basis, as a (x, y, z, w) quaternion
+fn quat_from_basis(m: mat3x3<f32>) -> vec4<f32> {
+    let trace = m[0].x + m[1].y + m[2].z;
+
+    if trace > 0.0 {
+        let s = 2.0 * sqrt(1.0 + trace);
+        return vec4<f32>(m[1].z - m[2].y, m[2].x - m[0].z, m[0].y - m[1].x, s * s * 0.25) / s;
+    }
+    if m[0].x > m[1].y && m[0].x > m[2].z {
+        let s = 2.0 * sqrt(1.0 + m[0].x - m[1].y - m[2].z);
+        return vec4<f32>(s * s * 0.25, m[1].x + m[0].y, m[2].x + m[0].z, m[1].z - m[2].y) / s;
+    }
+    if m[1].y > m[2].z {
+        let s = 2.0 * sqrt(1.0 + m[1].y - m[0].x - m[2].z);
+        return vec4<f32>(m[1].x + m[0].y, s * s * 0.25, m[2].y + m[1].z, m[2].x - m[0].z) / s;
+    }
+    let s = 2.0 * sqrt(1.0 + m[2].z - m[0].x - m[1].y);
+    return vec4<f32>(m[2].x + m[0].z, m[2].y + m[1].z, s * s * 0.25, m[0].y - m[1].x) / s;
+}
+
+fn quat_to_basis(q: vec4<f32>) -> mat3x3<f32> {
+    let x2 = q.x + q.x;
+    let y2 = q.y + q.y;
+    let z2 = q.z + q.z;
+    let xx = q.x * x2;
+    let xy = q.x * y2;
+    let xz = q.x * z2;
+    let yy = q.y * y2;
+    let yz = q.y * z2;
+    let zz = q.z * z2;
+    let wx = q.w * x2;
+    let wy = q.w * y2;
+    let wz = q.w * z2;
 
-    let pixel_size = 1.0 / vec2<f32>(f32(dim.x), f32(dim.y));
+    return mat3x3<f32>(
+        vec3<f32>(1.0 - (yy + zz), xy + wz, xz - wy),
+        vec3<f32>(xy - wz, 1.0 - (xx + zz), yz + wx),
+        vec3<f32>(xz + wy, yz - wx, 1.0 - (xx + yy)),
+    );
+}
 
-    let frame = time * ANIMATIONS_SAMPLES_PER_SEC;
-    let uv = (vec2<f32>(f32(joint_index), frame) + 0.5) * pixel_size;
+fn quat_slerp(a: vec4<f32>, b: vec4<f32>, t: f32) -> vec4<f32> {
+    // Shortest path
+    let d = dot(a, b);
+    let end = select(b, -b, d < 0.0);
+    let cos_theta = abs(d);
+
+    // Nearly parallel, falls back to a normalized lerp
+    if cos_theta > 0.9995 {
+        return normalize(mix(a, end, t));
+    }
+
+    let theta = acos(cos_theta);
+    return (a * sin((1.0 - t) * theta) + end * sin(t * theta)) / sin(theta);
+}
+
+// Joint matrices are decomposed so that rotations are slerped, a plain
+// matrix lerp would shrink the mesh between frames
+fn interpolate_joint(a: mat4x4<f32>, b: mat4x4<f32>, t: f32) -> mat4x4<f32> {
+    let scale_a = vec3<f32>(length(a[0].xyz), length(a[1].xyz), length(a[2].xyz));
+    let scale_b = vec3<f32>(length(b[0].xyz), length(b[1].xyz), length(b[2].xyz));
+
+    let rotation_a = quat_from_basis(mat3x3<f32>(
+        a[0].xyz / scale_a.x,
+        a[1].xyz / scale_a.y,
+        a[2].xyz / scale_a.z,
+    ));
+    let rotation_b = quat_from_basis(mat3x3<f32>(
+        b[0].xyz / scale_b.x,
+        b[1].xyz / scale_b.y,
+        b[2].xyz / scale_b.z,
+    ));
+
+    let basis = quat_to_basis(quat_slerp(rotation_a, rotation_b, t));
+    let scale = mix(scale_a, scale_b, t);
 
     return mat4x4<f32>(
-        textureSampleLevel(texture, animations_sampler, uv, 0, 0.0),
-        textureSampleLevel(texture, animations_sampler, uv, 1, 0.0),
-        textureSampleLevel(texture, animations_sampler, uv, 2, 0.0),
-        textureSampleLevel(texture, animations_sampler, uv, 3, 0.0),
+        vec4<f32>(basis[0] * scale.x, 0.0),
+        vec4<f32>(basis[1] * scale.y, 0.0),
+        vec4<f32>(basis[2] * scale.z, 0.0),
+        vec4<f32>(mix(a[3].xyz, b[3].xyz, t), 1.0),
+    );
+}
+
+fn get_joint_matrix(animation_id: u32, time: f32, joint_index: u32) -> mat4x4<f32> {
+    let frames_count = textureDimensions(animations[animation_id]).y;
+
+    // Looping animations blend their last frame back into the first one
+    let frame = time * animations_samples_per_sec[animation_id];
+    let frame_index = u32(frame) % frames_count;
+    let next_frame_index = (frame_index + 1u) % frames_count;
+
+    return interpolate_joint(
+        load_joint_matrix(animation_id, joint_index, frame_index),
+        load_joint_matrix(animation_id, joint_index, next_frame_index),
+        fract(frame),
     );
 }
 
@@ -126,25 +261,43 @@ fn vs_main(
         instance.model_matrix_2,
         instance.model_matrix_3,
     );
-
-    var normal = in.normal;
-    var tangent = in.tangent.xyz;
+    var prev_model_matrix = mat4x4<f32>(
+        instance.prev_model_matrix_0,
+        instance.prev_model_matrix_1,
+        instance.prev_model_matrix_2,
+        instance.prev_model_matrix_3,
+    );
 
     let skin_index = u32(i32(vertex_index) + instance.skin_offset);
     if skin_index > 0u {
-        let skinning_matrix = get_skinning_matrix(
-            instance.animation_id,
-            instance.animation_time,
+        var skinning_matrix = get_skinning_matrix(
+            instance.animations_id.x,
+            instance.animations_time.x,
             skin_index
         );
 
-        model_matrix *= skinning_matrix;
+        if instance.animations_blend.x > 0.0 {
+            skinning_matrix = skinning_matrix * (1.0 - instance.animations_blend.x)
+                + get_skinning_matrix(
+                    instance.animations_id.y,
+                    instance.animations_time.y,
+                    skin_index
+                ) * instance.animations_blend.x;
+        }
+
+        // Additive layer, relative to its first frame
+        if instance.animations_blend.y > 0.0 {
+            let additive = get_skinning_matrix(
+                instance.animations_id.z,
+                instance.animations_time.z,
+                skin_index
+            );
+            let reference = get_skinning_matrix(instance.animations_id.z, 0.0, skin_index);
+            skinning_matrix += (additive - reference) * instance.animations_blend.y;
+        }
 
-        // non-uniform scaling bones will cause normal mapping issues,
-        // it does not worth computing the inverse scale tho.
-        let skinnig_normal = mat4_to_mat3(skinning_matrix);
-        normal = skinnig_normal * normal;
-        tangent = skinnig_normal * tangent;
+        model_matrix *= skinning_matrix;
+        prev_model_matrix *= skinning_matrix;
     }
 
     let world_pos = model_matrix * vec4<f32>(in.position, 1.0);
@@ -155,13 +308,20 @@ fn vs_main(
     out.clip_position = camera.proj * view_pos;
     out.position = view_pos.xyz / view_pos.w;
 
-    let normal_matrix = mat4_to_mat3(camera.view);
-    out.normal = normal_matrix * rotate(instance.normal_quat, normal);
-    out.tangent = normal_matrix * rotate(instance.normal_quat, tangent.xyz);
+    // Tangents follow the surface, normals stay perpendicular to it
+    let view_matrix = mat4_to_mat3(camera.view);
+    let model = mat4_to_mat3(model_matrix);
+    out.normal = normalize(view_matrix * normal_matrix(model) * in.normal);
+    out.tangent = normalize(view_matrix * model * in.tangent.xyz);
     out.bitangent = cross(out.normal, out.tangent) * in.tangent.w;
 
     out.uv = in.uv;
     out.material_id = instance.material_id;
+    out.color_tint = instance.color_tint;
+    out.pick_id = instance.pick_id.x;
+
+    out.current_position = out.clip_position;
+    out.prev_position = camera.prev_view_proj * prev_model_matrix * vec4<f32>(in.position, 1.0);
 
     return out;
 }
@@ -176,6 +336,16 @@ struct FragmentOutput {
     @location(0) albedo_metallic: vec4<f32>,
     @location(1) normal_roughness: vec4<f32>,
     @location(2) emissive: vec4<f32>,
+    @location(3) velocity: vec2<f32>,
+}
+
+struct PickingFragmentOutput {
+    @location(0) albedo_metallic: vec4<f32>,
+    @location(1) normal_roughness: vec4<f32>,
+    @location(2) emissive: vec4<f32>,
+    @location(3) velocity: vec2<f32>,
+    // Instance slot plus one, zero being the background
+    @location(4) pick_id: u32,
 }
 
 fn get_vert_normal(in: VertexOutput) -> vec3<f32> {
@@ -213,9 +383,12 @@ fn get_tbn(in: VertexOutput) -> mat3x3<f32> {
     );
 }
 
+fn transform_uv(in: VertexOutput, transform: mat3x2<f32>) -> vec2<f32> {
+    return transform * vec3<f32>(in.uv, 1.0);
+}
+
 fn normal_map(in: VertexOutput, material: Material) -> vec3<f32> {
-    let texture = textures[material.normal];
-    return textureSample(texture, textures_sampler, in.uv).rgb;
+    return sample_texture(material.normal, transform_uv(in, material.uv_transforms[1])).rgb;
 }
 
 fn get_normal(in: VertexOutput, material: Material) -> vec3<f32> {
@@ -228,13 +401,23 @@ fn get_normal(in: VertexOutput, material: Material) -> vec3<f32> {
     return normalize(tbn * n);
 }
 
-@fragment
-fn fs_main(in: VertexOutput) -> FragmentOutput {
+fn gbuffer(in: VertexOutput) -> FragmentOutput {
     let material = materials[in.material_id];
 
-    let albedo = textureSample(textures[material.albedo], textures_sampler, in.uv);
-    let emissive = textureSample(textures[material.emissive], textures_sampler, in.uv);
-    let metallic_roughness = textureSample(textures[material.metallic_roughness], textures_sampler, in.uv).bg;
+    // Factors are used directly when no texture is bound
+    let albedo_texture = sample_texture(material.albedo, transform_uv(in, material.uv_transforms[0]));
+    let albedo = material.base_color_factor
+        * select(vec4<f32>(1.0), albedo_texture, material.albedo != 0u)
+        * vec4<f32>(in.color_tint.rgb, 1.0);
+    let emissive_texture = sample_texture(material.emissive, transform_uv(in, material.uv_transforms[3])).rgb;
+    let emissive = material.emissive_factor * material.emissive_strength
+        * select(vec3<f32>(1.0), emissive_texture, material.emissive != 0u)
+        * in.color_tint.rgb * in.color_tint.a;
+    let metallic_roughness_texture = sample_texture(material.metallic_roughness, transform_uv(in, material.uv_transforms[2])).bg;
+    let metallic_roughness = vec2<f32>(material.metallic_factor, material.roughness_factor)
+        * select(vec2<f32>(1.0), metallic_roughness_texture, material.metallic_roughness != 0u);
+    let occlusion_texture = sample_texture(material.occlusion, transform_uv(in, material.uv_transforms[4])).r;
+    let occlusion = select(1.0, mix(1.0, occlusion_texture, material.occlusion_strength), material.occlusion != 0u);
 
     // let material_data = vec3<u32>(
     //     pack2x16float(in.uv),
@@ -242,11 +425,41 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     //     in.material_id,
     // );
 
-    if albedo.a < 0.5 { discard; }
+    // Blended materials are cut out too, until a forward transparent pass exists
+    if material.alpha_mode != ALPHA_MODE_OPAQUE && albedo.a < material.alpha_cutoff { discard; }
+
+    // Screen space motion in uv units, jitter removed so still pixels have no velocity
+    let current_position = in.current_position.xy / in.current_position.w - camera.jitter;
+    let prev_position = in.prev_position.xy / in.prev_position.w;
+    let velocity = (current_position - prev_position) * vec2<f32>(0.5, -0.5);
 
     return FragmentOutput(
         vec4<f32>(albedo.rgb, metallic_roughness.x),
         vec4<f32>(get_normal(in, material), metallic_roughness.y),
-        vec4<f32>(emissive.rgb, 1.0),
+        vec4<f32>(emissive, occlusion),
+        velocity,
+    );
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    return gbuffer(in);
+}
+
+@fragment
+fn fs_main_picking(in: VertexOutput) -> PickingFragmentOutput {
+    let out = gbuffer(in);
+
+    return PickingFragmentOutput(
+        out.albedo_metallic,
+        out.normal_roughness,
+        out.emissive,
+        out.velocity,
+        in.pick_id,
     );
 }
+
+@fragment
+fn fs_wireframe() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 1.0, 0.5, 1.0);
+}
@@ -1,10 +1,11 @@
-use crate::RenderContext;
+use crate::{GBufferFormats, RenderContext};
 
 pub struct HierarchicalDepthPassInputs<'a> {
     pub depth: &'a wgpu::Texture,
 }
 
 pub struct HierarchicalDepthPassOutputs {
+    /// Closest (red) and farthest (green) depth of each 16x16 pixels tile
     pub output: wgpu::Texture,
 }
 
@@ -61,7 +62,7 @@ impl HierarchicalDepthPass {
                     visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::StorageTexture {
                         access: wgpu::StorageTextureAccess::WriteOnly,
-                        format: wgpu::TextureFormat::R32Float,
+                        format: GBufferFormats::HIZ,
                         view_dimension: wgpu::TextureViewDimension::D2,
                     },
                     count: None,
@@ -115,6 +116,11 @@ impl HierarchicalDepthPass {
         )
     }
 
+    /// See [`GBufferFormats::HIZ`]
+    pub fn hiz_view(&self) -> &wgpu::TextureView {
+        &self.output_view
+    }
+
     pub fn render(&self, ctx: &mut RenderContext) {
         let mut cpass = ctx
             .encoder
@@ -141,9 +147,9 @@ impl HierarchicalDepthPass {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::R32Float,
+            format: GBufferFormats::HIZ,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
-            view_formats: &[wgpu::TextureFormat::R32Float],
+            view_formats: &[GBufferFormats::HIZ],
         });
 
         HierarchicalDepthPassOutputs { output }
@@ -1,8 +1,9 @@
 @group(0) @binding(0) var t_sampler: sampler;
 @group(0) @binding(1) var t_depth: texture_depth_2d;
-@group(0) @binding(2) var t_output: texture_storage_2d<r32float, write>;
+@group(0) @binding(2) var t_output: texture_storage_2d<rg32float, write>;
 
 var<workgroup> workgroup_min: atomic<u32>;
+var<workgroup> workgroup_max: atomic<u32>;
 
 @compute @workgroup_size(4, 4, 1)
 fn main(
@@ -13,6 +14,7 @@ fn main(
 ) {
     if (local_index == 0u) {
         workgroup_min = bitcast<u32>(1.0);
+        workgroup_max = bitcast<u32>(0.0);
     }
 
     workgroupBarrier();
@@ -40,12 +42,25 @@ fn main(
         min(gather_min.z, gather_min.w),
     );
 
+    let gather_max = max(
+        max(gather1, gather2),
+        max(gather3, gather4),
+    );
+
+    let final_max = max(
+        max(gather_max.x, gather_max.y),
+        max(gather_max.z, gather_max.w),
+    );
+
+    // Positive floats keep their ordering when compared as integers
     atomicMin(&workgroup_min, bitcast<u32>(final_min));
+    atomicMax(&workgroup_max, bitcast<u32>(final_max));
 
     workgroupBarrier();
 
     if (local_index == 0u) {
         let min_depth = bitcast<f32>(workgroup_min);
-        textureStore(t_output, wg_id.xy, vec4<f32>(min_depth));
+        let max_depth = bitcast<f32>(workgroup_max);
+        textureStore(t_output, wg_id.xy, vec4<f32>(min_depth, max_depth, 0.0, 0.0));
 	}
 }
\ No newline at end of file
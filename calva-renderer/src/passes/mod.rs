@@ -1,25 +1,49 @@
 mod ambient_light;
 mod animate;
+mod auto_exposure;
+mod bloom;
+mod debug_blit;
+mod debug_shapes;
+mod decals;
 mod directional_light;
+mod dof;
 #[cfg(feature = "egui")]
 mod egui;
+mod fog;
 mod fxaa;
 mod geometry;
 mod hierarchical_depth;
+mod normals_debug;
 mod point_lights;
+mod screen_effects;
+mod skeleton_debug;
 mod skybox;
 mod ssao;
+mod ssr;
+mod taa;
 mod tone_mapping;
 
 #[cfg(feature = "egui")]
 pub use self::egui::*;
 pub use ambient_light::*;
 pub use animate::*;
+pub use auto_exposure::*;
+pub use bloom::*;
+pub use debug_blit::*;
+pub use debug_shapes::*;
+pub use decals::*;
 pub use directional_light::*;
+pub use dof::*;
+pub use fog::*;
 pub use fxaa::*;
 pub use geometry::*;
 pub use hierarchical_depth::*;
+pub use normals_debug::*;
 pub use point_lights::*;
+pub use screen_effects::*;
+pub use skeleton_debug::*;
 pub use skybox::*;
 pub use ssao::*;
+pub use ssr::*;
+pub use taa::*;
 pub use tone_mapping::*;
@@ -0,0 +1,206 @@
+use crate::{
+    CameraManager, FramePipelines, MeshId, MeshVertices, MeshesManager, RenderContext, ResourceRef,
+    ResourcesManager, UniformBuffer,
+};
+
+/// Mesh drawn by [`NormalsDebugPass`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NormalsDebug {
+    pub mesh: MeshId,
+    /// Instance transform
+    pub transform: glam::Mat4,
+    /// Length of the segments, in world units
+    pub length: f32,
+    /// Also draws the tangents (red) and bitangents (green), next to the
+    /// normals (blue)
+    pub tangents: bool,
+}
+
+impl Default for NormalsDebug {
+    fn default() -> Self {
+        Self {
+            mesh: Default::default(),
+            transform: glam::Mat4::IDENTITY,
+            length: 0.05,
+            tangents: true,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut NormalsDebug {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Normals")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.length, 0.001..=1.0)
+                        .logarithmic(true)
+                        .text("Length"),
+                );
+                ui.checkbox(&mut self.tangents, "Tangents");
+            })
+            .header_response
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct NormalsDebugUniform {
+    transform: glam::Mat4,
+    normal_matrix: glam::Mat4,
+    length: f32,
+    _padding: [u32; 3],
+}
+
+/// Vertex normals of a mesh drawn as lines over the final frame, read straight
+/// from the [`MeshesManager`] buffers. Useful to diagnose normal mapping
+/// issues, tangents with a broken handedness are drawn in magenta, see
+/// [`MeshVertices::invalid_tangents`].
+///
+/// Skinned meshes are drawn in their bind pose.
+pub struct NormalsDebugPass {
+    camera: ResourceRef<CameraManager>,
+    meshes: ResourceRef<MeshesManager>,
+
+    uniform: UniformBuffer<NormalsDebugUniform>,
+    vertices: Option<MeshVertices>,
+    tangents: bool,
+
+    pipelines: FramePipelines,
+}
+
+impl NormalsDebugPass {
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager) -> Self {
+        let camera = resources.get::<CameraManager>();
+        let meshes = resources.get::<MeshesManager>();
+
+        let uniform = UniformBuffer::new(device, NormalsDebugUniform::default());
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("normals_debug.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("NormalsDebug pipeline layout"),
+            bind_group_layouts: &[&camera.get().bind_group_layout, &uniform.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("NormalsDebug pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    // Mesh vertices are stepped per instance, each instance
+                    // drawing the segments of a single vertex
+                    buffers: &[
+                        wgpu::VertexBufferLayout {
+                            array_stride: MeshesManager::VERTEX_SIZE,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                        },
+                        wgpu::VertexBufferLayout {
+                            array_stride: MeshesManager::NORMAL_SIZE,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &wgpu::vertex_attr_array![1 => Float32x3],
+                        },
+                        wgpu::VertexBufferLayout {
+                            array_stride: MeshesManager::TANGENT_SIZE,
+                            step_mode: wgpu::VertexStepMode::Instance,
+                            attributes: &wgpu::vertex_attr_array![2 => Float32x4],
+                        },
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                // Drawn over the mesh
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        });
+
+        Self {
+            camera,
+            meshes,
+
+            uniform,
+            vertices: None,
+            tangents: false,
+
+            pipelines,
+        }
+    }
+
+    /// Sets the drawn mesh, `None` to hide it. The transform has to be updated
+    /// every frame to follow a moving instance.
+    pub fn update(&mut self, queue: &wgpu::Queue, normals: Option<NormalsDebug>) {
+        let Some(normals) = normals else {
+            self.vertices = None;
+            return;
+        };
+
+        *self.uniform = NormalsDebugUniform {
+            transform: normals.transform,
+            normal_matrix: normals.transform.inverse().transpose(),
+            length: normals.length,
+            _padding: Default::default(),
+        };
+        self.uniform.update(queue);
+
+        self.vertices = Some(self.meshes.get().vertices(normals.mesh));
+        self.tangents = normals.tangents;
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        let Some(vertices) = self.vertices.filter(|vertices| vertices.count > 0) else {
+            return;
+        };
+
+        let camera = self.camera.get();
+        let meshes = self.meshes.get();
+        let pipeline = self.pipelines.get(ctx);
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("NormalsDebug"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_bind_group(1, &self.uniform.bind_group, &[]);
+        rpass.set_vertex_buffer(0, meshes.vertices.slice(..));
+        rpass.set_vertex_buffer(1, meshes.normals.slice(..));
+        rpass.set_vertex_buffer(2, meshes.tangents.slice(..));
+
+        // Normal, then tangent and bitangent segments
+        let segments = if self.tangents { 3 } else { 1 };
+        rpass.draw(
+            0..2 * segments,
+            vertices.offset..vertices.offset + vertices.count,
+        );
+    }
+}
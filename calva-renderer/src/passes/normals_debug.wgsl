@@ -0,0 +1,85 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct NormalsDebug {
+    transform: mat4x4<f32>,
+    // Inverse transpose of the transform
+    normal_matrix: mat4x4<f32>,
+    length: f32,
+}
+@group(1) @binding(0) var<uniform> normals_debug: NormalsDebug;
+
+const NORMAL_COLOR: vec3<f32> = vec3<f32>(0.0, 0.0, 1.0);
+const TANGENT_COLOR: vec3<f32> = vec3<f32>(1.0, 0.0, 0.0);
+const BITANGENT_COLOR: vec3<f32> = vec3<f32>(0.0, 1.0, 0.0);
+const INVALID_COLOR: vec3<f32> = vec3<f32>(1.0, 0.0, 1.0);
+
+//
+// Vertex shader
+//
+
+// One instance per mesh vertex, each drawing its normal, tangent and
+// bitangent segments
+struct VertexInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+}
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    let normal = normalize((normals_debug.normal_matrix * vec4<f32>(in.normal, 0.0)).xyz);
+    let tangent = normalize((normals_debug.transform * vec4<f32>(in.tangent.xyz, 0.0)).xyz);
+    let bitangent = cross(normal, tangent) * in.tangent.w;
+
+    // The handedness has to be a unit sign, see MeshVertices::invalid_tangents
+    let valid = abs(in.tangent.w) == 1.0;
+
+    var direction: vec3<f32>;
+    var color: vec3<f32>;
+    switch in.vertex_index / 2u {
+        case 0u: {
+            direction = normal;
+            color = NORMAL_COLOR;
+        }
+        case 1u: {
+            direction = tangent;
+            color = select(INVALID_COLOR, TANGENT_COLOR, valid);
+        }
+        default: {
+            direction = bitangent;
+            color = select(INVALID_COLOR, BITANGENT_COLOR, valid);
+        }
+    }
+
+    let start = (normals_debug.transform * vec4<f32>(in.position, 1.0)).xyz;
+    let position = start + direction * normals_debug.length * f32(in.vertex_index % 2u);
+
+    var out: VertexOutput;
+    out.position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.color = color;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
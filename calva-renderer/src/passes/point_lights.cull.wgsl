@@ -0,0 +1,196 @@
+struct PointLightShadow {
+    view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    radius: f32,
+}
+@group(0) @binding(0) var<uniform> shadow: PointLightShadow;
+
+struct MeshBoundingSphere {
+    center: vec3<f32>,
+    radius: f32,
+}
+
+struct MeshLod {
+    vertex_count: u32,
+    base_index: u32,
+}
+
+struct MeshLods {
+    count: u32,
+    levels: array<MeshLod, 3>,
+}
+
+struct MeshInfo {
+    vertex_count: u32,
+    base_index: u32,
+    vertex_offset: i32,
+    skin_offset: i32,
+    bounding_sphere: MeshBoundingSphere,
+    lods: MeshLods,
+}
+
+struct AnimationTrack {
+    animation_id: u32,
+    time: f32,
+    speed: f32,
+    looping: u32,
+}
+
+struct AnimationState {
+    tracks: array<AnimationTrack, 2>,
+    blend: f32,
+    blend_speed: f32,
+    additive: AnimationTrack,
+    additive_weight: f32,
+}
+
+struct Instance {
+    transform: mat4x4<f32>,
+    mesh_id: u32,
+    material_id: u32,
+    animation: AnimationState,
+    color_tint: vec4<f32>,
+    // See InstanceFlags
+    flags: u32,
+}
+// Mesh of the freed instance slots, see InstancesManager::remove
+const REMOVED_MESH: u32 = 0xFFFFFFFFu;
+const INSTANCE_HIDDEN: u32 = 1u;
+
+struct Instances {
+    count: u32,
+    instances: array<Instance>
+}
+
+struct CullInfo {
+    view_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+
+struct DrawInstance {
+    transform: mat4x4<f32>,
+    material_id: u32,
+    skin_offset: i32,
+    // Blended tracks and additive layer
+    animations_id: array<u32, 3>,
+    animations_time: array<f32, 3>,
+    // Tracks blend and additive weight
+    animations_blend: vec2<f32>,
+    _padding: vec2<u32>,
+}
+
+struct DrawIndexedIndirect {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    base_index: u32,
+    vertex_offset: i32,
+    base_instance: u32,
+}
+struct DrawIndirects {
+    count: atomic<u32>,
+    draws: array<DrawIndexedIndirect>,
+}
+
+@group(1) @binding(0)
+var<storage, read> meshes_info: array<MeshInfo>;
+
+@group(1) @binding(1)
+var<storage, read> base_instances: array<u32>;
+
+@group(1) @binding(2)
+var<storage, read> instances: Instances;
+
+@group(1) @binding(3)
+var<storage, read_write> draw_instances: array<DrawInstance>;
+
+@group(1) @binding(4)
+var<storage, read_write> draw_indirects: DrawIndirects;
+
+@compute @workgroup_size(32)
+fn reset(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let mesh_id = global_id.x;
+    let mesh_info = &meshes_info[mesh_id];
+    let draw = &draw_indirects.draws[mesh_id];
+
+    (*draw).vertex_count = (*mesh_info).vertex_count;
+    (*draw).instance_count = 0u;
+    (*draw).base_index = (*mesh_info).base_index;
+    (*draw).vertex_offset = (*mesh_info).vertex_offset;
+    (*draw).base_instance = base_instances[mesh_id];
+
+    draw_indirects.count = 0u;
+}
+
+fn sphere_visible(sphere: MeshBoundingSphere, transform: mat4x4<f32>) -> bool {
+    let center_world = (transform * vec4<f32>(sphere.center, 1.0)).xyz;
+
+    let scale = vec3<f32>(
+        length(transform[0].xyz),
+        length(transform[1].xyz),
+        length(transform[2].xyz),
+    );
+    let radius_world = sphere.radius * max(max(scale.x, scale.y), scale.z);
+
+    // Anything outside of the light range can't cast shadows in its cube map
+    return distance(center_world, shadow.position) < shadow.radius + radius_world;
+}
+
+@compute @workgroup_size(32)
+fn cull(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let instance_index = global_id.x;
+
+    if instance_index >= instances.count {
+        return;
+    }
+
+    let instance = &instances.instances[instance_index];
+    let transform = &(*instance).transform;
+    let mesh_id = (*instance).mesh_id;
+
+    // Hidden instances don't cast shadows either
+    if mesh_id == REMOVED_MESH || ((*instance).flags & INSTANCE_HIDDEN) != 0u {
+        return;
+    }
+
+    let mesh_info = &meshes_info[mesh_id];
+
+    if !sphere_visible((*mesh_info).bounding_sphere, (*transform)) {
+        return;
+    }
+
+    let draw = &draw_indirects.draws[mesh_id];
+    let draw_instance_index = (*draw).base_instance + atomicAdd(&(*draw).instance_count, 1u);
+
+    let draw_instance = &draw_instances[draw_instance_index];
+    (*draw_instance).transform = *transform;
+    (*draw_instance).material_id = (*instance).material_id;
+    (*draw_instance).skin_offset = (*mesh_info).skin_offset;
+
+    let animation = &(*instance).animation;
+    (*draw_instance).animations_id = array<u32, 3>(
+        (*animation).tracks[0].animation_id,
+        (*animation).tracks[1].animation_id,
+        (*animation).additive.animation_id,
+    );
+    (*draw_instance).animations_time = array<f32, 3>(
+        (*animation).tracks[0].time,
+        (*animation).tracks[1].time,
+        (*animation).additive.time,
+    );
+    (*draw_instance).animations_blend = vec2<f32>(
+        (*animation).blend,
+        (*animation).additive_weight,
+    );
+}
+
+@compute @workgroup_size(32)
+fn count(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let mesh_id = global_id.x;
+
+    let draw = &draw_indirects.draws[mesh_id];
+    let copy = *draw;
+
+    if (*draw).instance_count > 0u {
+        draw_indirects.draws[atomicAdd(&draw_indirects.count, 1u)] = copy;
+    }
+}
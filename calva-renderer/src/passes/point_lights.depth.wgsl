@@ -0,0 +1,221 @@
+struct PointLightShadow {
+    view_proj: mat4x4<f32>,
+    position: vec3<f32>,
+    radius: f32,
+}
+@group(0) @binding(0) var<uniform> shadow: PointLightShadow;
+
+@group(1) @binding(0) var<storage, read> skinning_joints: array<u32>;
+@group(1) @binding(1) var<storage, read> skinning_weights: array<vec4<f32>>;
+
+// TODO: should it be a texture_storage_2d_array?
+@group(2) @binding(0) var animations: binding_array<texture_2d_array<f32>>;
+@group(2) @binding(2) var<storage, read> animations_samples_per_sec: array<f32>;
+
+struct MeshInstance {
+    @location(0) model_matrix_0: vec4<f32>,
+    @location(1) model_matrix_1: vec4<f32>,
+    @location(2) model_matrix_2: vec4<f32>,
+    @location(3) model_matrix_3: vec4<f32>,
+
+    @location(4) material: u32,
+
+    @location(5) skin_offset: i32,
+    @location(6) animations_id: vec3<u32>,
+    @location(7) animations_time: vec3<f32>,
+    @location(8) animations_blend: vec2<f32>,
+}
+
+struct VertexInput {
+    @location(10) position: vec3<f32>,
+}
+
+fn load_joint_matrix(animation_id: u32, joint_index: u32, frame_index: u32) -> mat4x4<f32> {
+    let coord = vec2<i32>(i32(joint_index), i32(frame_index));
+
+    return mat4x4<f32>(
+        textureLoad(animations[animation_id], coord, 0, 0),
+        textureLoad(animations[animation_id], coord, 1, 0),
+        textureLoad(animations[animation_id], coord, 2, 0),
+        textureLoad(animations[animation_id], coord, 3, 0),
+    );
+}
+
+// Rotation of an orthonormal basis, as a (x, y, z, w) quaternion
+fn quat_from_basis(m: mat3x3<f32>) -> vec4<f32> {
+    let trace = m[0].x + m[1].y + m[2].z;
+
+    if trace > 0.0 {
+        let s = 2.0 * sqrt(1.0 + trace);
+        return vec4<f32>(m[1].z - m[2].y, m[2].x - m[0].z, m[0].y - m[1].x, s * s * 0.25) / s;
+    }
+    if m[0].x > m[1].y && m[0].x > m[2].z {
+        let s = 2.0 * sqrt(1.0 + m[0].x - m[1].y - m[2].z);
+        return vec4<f32>(s * s * 0.25, m[1].x + m[0].y, m[2].x + m[0].z, m[1].z - m[2].y) / s;
+    }
+    if m[1].y > m[2].z {
+        let s = 2.0 * sqrt(1.0 + m[1].y - m[0].x - m[2].z);
+        return vec4<f32>(m[1].x + m[0].y, s * s * 0.25, m[2].y + m[1].z, m[2].x - m[0].z) / s;
+    }
+    let s = 2.0 * sqrt(1.0 + m[2].z - m[0].x - m[1].y);
+    return vec4<f32>(m[2].x + m[0].z, m[2].y + m[1].z, s * s * 0.25, m[0].y - m[1].x) / s;
+}
+
+fn quat_to_basis(q: vec4<f32>) -> mat3x3<f32> {
+    let x2 = q.x + q.x;
+    let y2 = q.y + q.y;
+    let z2 = q.z + q.z;
+    let xx = q.x * x2;
+    let xy = q.x * y2;
+    let xz = q.x * z2;
+    let yy = q.y * y2;
+    let yz = q.y * z2;
+    let zz = q.z * z2;
+    let wx = q.w * x2;
+    let wy = q.w * y2;
+    let wz = q.w * z2;
+
+    return mat3x3<f32>(
+        vec3<f32>(1.0 - (yy + zz), xy + wz, xz - wy),
+        vec3<f32>(xy - wz, 1.0 - (xx + zz), yz + wx),
+        vec3<f32>(xz + wy, yz - wx, 1.0 - (xx + yy)),
+    );
+}
+
+fn quat_slerp(a: vec4<f32>, b: vec4<f32>, t: f32) -> vec4<f32> {
+    // Shortest path
+    let d = dot(a, b);
+    let end = select(b, -b, d < 0.0);
+    let cos_theta = abs(d);
+
+    // Nearly parallel, falls back to a normalized lerp
+    if cos_theta > 0.9995 {
+        return normalize(mix(a, end, t));
+    }
+
+    let theta = acos(cos_theta);
+    return (a * sin((1.0 - t) * theta) + end * sin(t * theta)) / sin(theta);
+}
+
+// Joint matrices are decomposed so that rotations are slerped, a plain
+// matrix lerp would shrink the mesh between frames
+fn interpolate_joint(a: mat4x4<f32>, b: mat4x4<f32>, t: f32) -> mat4x4<f32> {
+    let scale_a = vec3<f32>(length(a[0].xyz), length(a[1].xyz), length(a[2].xyz));
+    let scale_b = vec3<f32>(length(b[0].xyz), length(b[1].xyz), length(b[2].xyz));
+
+    let rotation_a = quat_from_basis(mat3x3<f32>(
+        a[0].xyz / scale_a.x,
+        a[1].xyz / scale_a.y,
+        a[2].xyz / scale_a.z,
+    ));
+    let rotation_b = quat_from_basis(mat3x3<f32>(
+        b[0].xyz / scale_b.x,
+        b[1].xyz / scale_b.y,
+        b[2].xyz / scale_b.z,
+    ));
+
+    let basis = quat_to_basis(quat_slerp(rotation_a, rotation_b, t));
+    let scale = mix(scale_a, scale_b, t);
+
+    return mat4x4<f32>(
+        vec4<f32>(basis[0] * scale.x, 0.0),
+        vec4<f32>(basis[1] * scale.y, 0.0),
+        vec4<f32>(basis[2] * scale.z, 0.0),
+        vec4<f32>(mix(a[3].xyz, b[3].xyz, t), 1.0),
+    );
+}
+
+fn get_joint_matrix(animation_id: u32, time: f32, joint_index: u32) -> mat4x4<f32> {
+    let frames_count = textureDimensions(animations[animation_id]).y;
+
+    // Looping animations blend their last frame back into the first one
+    let frame = time * animations_samples_per_sec[animation_id];
+    let frame_index = u32(frame) % frames_count;
+    let next_frame_index = (frame_index + 1u) % frames_count;
+
+    return interpolate_joint(
+        load_joint_matrix(animation_id, joint_index, frame_index),
+        load_joint_matrix(animation_id, joint_index, next_frame_index),
+        fract(frame),
+    );
+}
+
+fn get_skinning_matrix(animation_id: u32, time: f32, skin_index: u32) -> mat4x4<f32> {
+    if animation_id == 0u {
+        return mat4x4<f32>(
+            vec4<f32>(1.0, 0.0, 0.0, 0.0),
+            vec4<f32>(0.0, 1.0, 0.0, 0.0),
+            vec4<f32>(0.0, 0.0, 1.0, 0.0),
+            vec4<f32>(0.0, 0.0, 0.0, 1.0),
+        );
+    }
+
+    let packed_joints = skinning_joints[skin_index];
+    let weights = skinning_weights[skin_index];
+
+    let joints = vec4<u32>(
+        (packed_joints >> 0u) & 0xFFu,
+        (packed_joints >> 8u) & 0xFFu,
+        (packed_joints >> 16u) & 0xFFu,
+        (packed_joints >> 24u) & 0xFFu,
+    );
+
+    let m1 = get_joint_matrix(animation_id, time, joints.x) * weights.x;
+    let m2 = get_joint_matrix(animation_id, time, joints.y) * weights.y;
+    let m3 = get_joint_matrix(animation_id, time, joints.z) * weights.z;
+    let m4 = get_joint_matrix(animation_id, time, joints.w) * weights.w;
+
+    return mat4x4<f32>(
+        m1[0] + m2[0] + m3[0] + m4[0],
+        m1[1] + m2[1] + m3[1] + m4[1],
+        m1[2] + m2[2] + m3[2] + m4[2],
+        m1[3] + m2[3] + m3[3] + m4[3],
+    );
+}
+
+@vertex
+fn vs_main(
+    instance: MeshInstance,
+    in: VertexInput,
+    @builtin(vertex_index) vertex_index: u32
+) -> @builtin(position) vec4<f32> {
+    var model_matrix = mat4x4<f32>(
+        instance.model_matrix_0,
+        instance.model_matrix_1,
+        instance.model_matrix_2,
+        instance.model_matrix_3,
+    );
+
+    let skin_index = u32(i32(vertex_index) + instance.skin_offset);
+    if skin_index > 0u {
+        var skinning_matrix = get_skinning_matrix(
+            instance.animations_id.x,
+            instance.animations_time.x,
+            skin_index
+        );
+
+        if instance.animations_blend.x > 0.0 {
+            skinning_matrix = skinning_matrix * (1.0 - instance.animations_blend.x)
+                + get_skinning_matrix(
+                    instance.animations_id.y,
+                    instance.animations_time.y,
+                    skin_index
+                ) * instance.animations_blend.x;
+        }
+
+        // Additive layer, relative to its first frame
+        if instance.animations_blend.y > 0.0 {
+            let additive = get_skinning_matrix(
+                instance.animations_id.z,
+                instance.animations_time.z,
+                skin_index
+            );
+            let reference = get_skinning_matrix(instance.animations_id.z, 0.0, skin_index);
+            skinning_matrix += (additive - reference) * instance.animations_blend.y;
+        }
+
+        model_matrix *= skinning_matrix;
+    }
+
+    return shadow.view_proj * model_matrix * vec4<f32>(in.position, 1.0);
+}
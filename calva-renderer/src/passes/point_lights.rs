@@ -1,10 +1,64 @@
 use wgpu::util::DeviceExt;
 
 use crate::{
-    util::icosphere::Icosphere, CameraManager, LightsManager, PointLight, RenderContext,
-    RessourceRef, RessourcesManager,
+    util::icosphere::Icosphere, CameraManager, GpuPointLight, LightsManager, RenderContext,
+    ResourceRef, ResourcesManager, UniformBuffer, UniformData,
 };
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLightsConfig {
+    /// Render the shadows of the point lights casting shadows, up to
+    /// [`LightsManager::MAX_SHADOWED_POINT_LIGHTS`]
+    pub shadows: bool,
+    /// Depth offset applied before the shadow test, in world units
+    pub shadow_bias: f32,
+}
+
+impl Default for PointLightsConfig {
+    fn default() -> Self {
+        Self {
+            shadows: true,
+            shadow_bias: 0.05,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuPointLightsConfig {
+    /// Shadow slots sampled by the lighting
+    shadows: u32,
+    shadow_bias: f32,
+}
+
+impl UniformData for PointLightsConfig {
+    type GpuType = GpuPointLightsConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuPointLightsConfig {
+            shadows: if self.shadows {
+                LightsManager::MAX_SHADOWED_POINT_LIGHTS as _
+            } else {
+                0
+            },
+            shadow_bias: self.shadow_bias,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut PointLightsConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Point lights")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.checkbox(&mut self.shadows, "Shadows");
+                ui.add(egui::Slider::new(&mut self.shadow_bias, 0.0..=0.5).text("Shadow bias"));
+            })
+            .header_response
+    }
+}
+
 pub struct PointLightsPassInputs<'a> {
     pub albedo_metallic: &'a wgpu::Texture,
     pub normal_roughness: &'a wgpu::Texture,
@@ -13,8 +67,10 @@ pub struct PointLightsPassInputs<'a> {
 }
 
 pub struct PointLightsPass {
-    camera: RessourceRef<CameraManager>,
-    lights: RessourceRef<LightsManager>,
+    pub config: UniformBuffer<PointLightsConfig>,
+
+    camera: ResourceRef<CameraManager>,
+    lights: ResourceRef<LightsManager>,
 
     vertex_count: u32,
     vertices: wgpu::Buffer,
@@ -26,6 +82,10 @@ pub struct PointLightsPass {
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
 
+    shadows: PointLightShadows,
+    shadows_enabled: bool,
+    shadow_maps: bool,
+
     stencil_pipeline: wgpu::RenderPipeline,
     lighting_pipeline: wgpu::RenderPipeline,
 }
@@ -33,11 +93,40 @@ pub struct PointLightsPass {
 impl PointLightsPass {
     pub fn new(
         device: &wgpu::Device,
-        ressources: &RessourcesManager,
+        resources: &ResourcesManager,
+        inputs: PointLightsPassInputs,
+    ) -> Self {
+        Self::with_shadow_maps(device, resources, inputs, true)
+    }
+
+    /// Lights are always shaded unshadowed, the shadow maps are not allocated.
+    /// E.g. for top-down games where point light shadows are barely visible.
+    pub fn without_shadows(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
         inputs: PointLightsPassInputs,
     ) -> Self {
-        let camera = ressources.get::<CameraManager>();
-        let lights = ressources.get::<LightsManager>();
+        Self::with_shadow_maps(device, resources, inputs, false)
+    }
+
+    fn with_shadow_maps(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        inputs: PointLightsPassInputs,
+        shadow_maps: bool,
+    ) -> Self {
+        let config = UniformBuffer::new(device, PointLightsConfig::default());
+
+        let camera = resources.get::<CameraManager>();
+        let lights = resources.get::<LightsManager>();
+
+        // Still bound by the lighting pipeline, but never rendered nor sampled
+        let shadow_size = if shadow_maps {
+            PointLightShadows::SIZE
+        } else {
+            1
+        };
+        let shadows = PointLightShadows::new(device, resources, shadow_size);
 
         let icosphere = Icosphere::new(1);
 
@@ -56,19 +145,20 @@ impl PointLightsPass {
         let vertex_buffers_layout = [
             // PointLights instances
             wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<PointLight>() as _,
+                array_stride: GpuPointLight::SIZE,
                 step_mode: wgpu::VertexStepMode::Instance,
                 attributes: &wgpu::vertex_attr_array![
                     0 => Float32x3, // Position
                     1 => Float32,   // Radius
                     2 => Float32x3, // Color
+                    3 => Sint32,    // Shadow slot
                 ],
             },
             // Icosphere vertices
             wgpu::VertexBufferLayout {
                 array_stride: std::mem::size_of::<[f32; 3]>() as _,
                 step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &wgpu::vertex_attr_array![3 => Float32x3],
+                attributes: &wgpu::vertex_attr_array![4 => Float32x3],
             },
         ];
 
@@ -96,9 +186,9 @@ impl PointLightsPass {
                     ..Default::default()
                 },
                 depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth24PlusStencil8,
+                    format: inputs.depth.format(),
                     depth_write_enabled: false,
-                    depth_compare: wgpu::CompareFunction::Less,
+                    depth_compare: camera.get().depth().compare(wgpu::CompareFunction::Less),
                     stencil: wgpu::StencilState {
                         front: wgpu::StencilFaceState {
                             compare: wgpu::CompareFunction::Always,
@@ -182,7 +272,12 @@ impl PointLightsPass {
         let lighting_pipeline = {
             let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("PointLights[lighting] pipeline layout"),
-                bind_group_layouts: &[&camera.get().bind_group_layout, &bind_group_layout],
+                bind_group_layouts: &[
+                    &camera.get().bind_group_layout,
+                    &bind_group_layout,
+                    &config.bind_group_layout,
+                    &shadows.bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
@@ -216,7 +311,7 @@ impl PointLightsPass {
                     ..Default::default()
                 },
                 depth_stencil: Some(wgpu::DepthStencilState {
-                    format: wgpu::TextureFormat::Depth24PlusStencil8,
+                    format: inputs.depth.format(),
                     depth_write_enabled: false,
                     depth_compare: wgpu::CompareFunction::Always,
                     stencil: wgpu::StencilState {
@@ -243,6 +338,8 @@ impl PointLightsPass {
         };
 
         Self {
+            config,
+
             camera,
             lights,
 
@@ -256,6 +353,10 @@ impl PointLightsPass {
             bind_group_layout,
             bind_group,
 
+            shadows,
+            shadows_enabled: shadow_maps,
+            shadow_maps,
+
             stencil_pipeline,
             lighting_pipeline,
         }
@@ -269,12 +370,44 @@ impl PointLightsPass {
         self.depth_view = inputs.depth.create_view(&Default::default());
     }
 
+    pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+        self.shadows.rebind_instances(device);
+    }
+
+    /// `false` when created with [`PointLightsPass::without_shadows`]
+    pub fn has_shadow_maps(&self) -> bool {
+        self.shadow_maps
+    }
+
+    /// Lighting skips the shadow slots above the uploaded shadows count, so
+    /// disabled shadows are uploaded as a zero count and stale shadow maps are
+    /// never sampled
+    pub fn update(&mut self, queue: &wgpu::Queue, shadows_enabled: bool) {
+        let shadows_enabled = shadows_enabled && self.shadow_maps;
+
+        let shadows = self.config.shadows;
+        self.config.shadows &= shadows_enabled;
+        self.config.update(queue);
+        self.config.shadows = shadows;
+
+        self.shadows_enabled = shadows_enabled && shadows;
+        self.shadows
+            .update(queue, self.lights.get().point_light_shadows());
+    }
+
     pub fn render(&self, ctx: &mut RenderContext) {
         ctx.encoder.profile_start("PointLights");
 
         let camera = self.camera.get();
         let lights = self.lights.get();
 
+        let shadows_count = if self.shadows_enabled {
+            lights.point_light_shadows().len()
+        } else {
+            0
+        };
+        self.shadows.render(ctx, shadows_count);
+
         let mut stencil_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("PointLights[stencil]"),
             color_attachments: &[],
@@ -295,7 +428,11 @@ impl PointLightsPass {
         stencil_pass.set_vertex_buffer(1, self.vertices.slice(..));
         stencil_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
 
-        stencil_pass.draw_indexed(0..self.vertex_count, 0, 0..lights.count_point_lights());
+        stencil_pass.draw_indexed(
+            0..self.vertex_count,
+            0,
+            0..lights.point_lights_high_water_mark(),
+        );
 
         drop(stencil_pass);
 
@@ -319,12 +456,18 @@ impl PointLightsPass {
         lighting_pass.set_pipeline(&self.lighting_pipeline);
         lighting_pass.set_bind_group(0, &camera.bind_group, &[]);
         lighting_pass.set_bind_group(1, &self.bind_group, &[]);
+        lighting_pass.set_bind_group(2, &self.config.bind_group, &[]);
+        lighting_pass.set_bind_group(3, &self.shadows.bind_group, &[]);
 
         lighting_pass.set_vertex_buffer(0, lights.point_lights.slice(..));
         lighting_pass.set_vertex_buffer(1, self.vertices.slice(..));
         lighting_pass.set_index_buffer(self.indices.slice(..), wgpu::IndexFormat::Uint16);
 
-        lighting_pass.draw_indexed(0..self.vertex_count, 0, 0..lights.count_point_lights());
+        lighting_pass.draw_indexed(
+            0..self.vertex_count,
+            0,
+            0..lights.point_lights_high_water_mark(),
+        );
 
         drop(lighting_pass);
 
@@ -370,3 +513,635 @@ impl PointLightsPass {
         })
     }
 }
+
+use shadows::*;
+mod shadows {
+    use crate::{
+        draw_indexed_indirects, AnimationId, AnimationsManager, Instance, InstancesManager,
+        LightsManager, MaterialId, MeshInfo, MeshesManager, PointLight, RenderContext, ResourceRef,
+        ResourcesManager, SkinsManager,
+    };
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+    struct DrawInstance {
+        _model_matrix: [f32; 16],
+        _material: MaterialId,
+        _skin_offset: i32,
+        _animations: [AnimationId; 3],
+        _animations_time: [f32; 3],
+        _animations_blend: [f32; 2],
+        _padding: [u32; 2],
+    }
+
+    impl DrawInstance {
+        const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+
+        const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+            array_stride: Self::SIZE,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![
+                // Model matrix
+                0 => Float32x4,
+                1 => Float32x4,
+                2 => Float32x4,
+                3 => Float32x4,
+
+                4 => Uint32, // Material
+
+                // Skinning
+                5 => Sint32, // Skin offset
+                6 => Uint32x3, // Animations ID (blended tracks and additive layer)
+                7 => Float32x3, // Animations time
+                8 => Float32x2, // Animations blend and additive weight
+            ],
+        };
+    }
+
+    /// One cube face of a shadowed point light
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+    struct GpuPointLightShadow {
+        view_proj: glam::Mat4,
+        position: glam::Vec3,
+        radius: f32,
+    }
+
+    impl GpuPointLightShadow {
+        const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+    }
+
+    /// Cube depth maps of the shadowed point lights, stored in a cube array
+    /// indexed by the light shadow slot.
+    ///
+    /// Casters are culled against the light sphere once per light, then drawn
+    /// in each of the six faces with the same indirect draws.
+    pub struct PointLightShadows {
+        meshes: ResourceRef<MeshesManager>,
+        instances: ResourceRef<InstancesManager>,
+        skins: ResourceRef<SkinsManager>,
+        animations: ResourceRef<AnimationsManager>,
+
+        uniform_stride: wgpu::BufferAddress,
+        uniform: wgpu::Buffer,
+        uniform_bind_group: wgpu::BindGroup,
+
+        draw_instances: wgpu::Buffer,
+        draw_indirects: wgpu::Buffer,
+        instances_capacity: usize,
+        cull_bind_group_layout: wgpu::BindGroupLayout,
+        cull_bind_group: wgpu::BindGroup,
+        cull_pipelines: (
+            wgpu::ComputePipeline, // reset
+            wgpu::ComputePipeline, // cull
+            wgpu::ComputePipeline, // count
+        ),
+
+        faces_views: Vec<wgpu::TextureView>,
+        depth_pipeline: wgpu::RenderPipeline,
+
+        pub bind_group_layout: wgpu::BindGroupLayout,
+        pub bind_group: wgpu::BindGroup,
+    }
+
+    impl PointLightShadows {
+        pub const SIZE: u32 = 512;
+        const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+        const LAYERS: usize = LightsManager::MAX_SHADOWED_POINT_LIGHTS * 6;
+
+        /// Near plane as a fraction of the light radius, which is the far plane
+        pub const NEAR_RATIO: f32 = 0.01;
+
+        // Cube faces direction and up vector, in the +X, -X, +Y, -Y, +Z, -Z
+        // layer order expected by cube textures
+        const FACES: [(glam::Vec3, glam::Vec3); 6] = [
+            (glam::Vec3::X, glam::Vec3::Y),
+            (glam::Vec3::NEG_X, glam::Vec3::Y),
+            (glam::Vec3::Y, glam::Vec3::NEG_Z),
+            (glam::Vec3::NEG_Y, glam::Vec3::Z),
+            (glam::Vec3::Z, glam::Vec3::Y),
+            (glam::Vec3::NEG_Z, glam::Vec3::Y),
+        ];
+
+        pub fn new(device: &wgpu::Device, resources: &ResourcesManager, size: u32) -> Self {
+            let meshes = resources.get::<MeshesManager>();
+            let instances = resources.get::<InstancesManager>();
+            let skins = resources.get::<SkinsManager>();
+            let animations = resources.get::<AnimationsManager>();
+
+            let uniform_stride = wgpu::util::align_to(
+                GpuPointLightShadow::SIZE,
+                device.limits().min_uniform_buffer_offset_alignment as _,
+            );
+
+            let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("PointLightShadows uniform"),
+                size: uniform_stride * Self::LAYERS as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let uniform_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("PointLightShadows uniform bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: wgpu::BufferSize::new(GpuPointLightShadow::SIZE),
+                        },
+                        count: None,
+                    }],
+                });
+
+            let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("PointLightShadows uniform bind group"),
+                layout: &uniform_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &uniform,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(GpuPointLightShadow::SIZE),
+                    }),
+                }],
+            });
+
+            let instances_capacity = instances.get().capacity();
+            let draw_instances = Self::make_draw_instances(device, instances_capacity);
+
+            let draw_indirects = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("PointLightShadows[cull] draw indirects"),
+                size: {
+                    let count_size = std::mem::size_of::<u32>();
+                    let indirects_size = std::mem::size_of::<
+                        [wgpu::util::DrawIndexedIndirect; MeshesManager::MAX_MESHES],
+                    >();
+
+                    count_size + indirects_size
+                } as _,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::INDIRECT,
+                mapped_at_creation: false,
+            });
+
+            let cull_bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("PointLightShadows[cull] bind group layout"),
+                    entries: &[
+                        // Mesh data
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(MeshInfo::SIZE),
+                            },
+                            count: None,
+                        },
+                        // Base instances
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(
+                                    std::mem::size_of::<u32>() as _
+                                ),
+                            },
+                            count: None,
+                        },
+                        // Instances
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(
+                                    std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                                        + Instance::SIZE,
+                                ),
+                            },
+                            count: None,
+                        },
+                        // Draw instances
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 3,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(DrawInstance::SIZE),
+                            },
+                            count: None,
+                        },
+                        // Draw indirects
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 4,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: wgpu::BufferSize::new(
+                                    std::mem::size_of::<u32>() as u64
+                                        + std::mem::size_of::<wgpu::util::DrawIndexedIndirect>()
+                                            as u64,
+                                ),
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+            let cull_bind_group = Self::make_cull_bind_group(
+                device,
+                &cull_bind_group_layout,
+                &meshes.get(),
+                &instances.get(),
+                &draw_instances,
+                &draw_indirects,
+            );
+
+            let cull_pipelines = {
+                let shader =
+                    device.create_shader_module(wgpu::include_wgsl!("point_lights.cull.wgsl"));
+
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("PointLightShadows[cull] pipeline layout"),
+                        bind_group_layouts: &[&uniform_bind_group_layout, &cull_bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+
+                let make_pipeline = |label: &str, entry_point: &str| {
+                    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: Some(label),
+                        layout: Some(&pipeline_layout),
+                        module: &shader,
+                        entry_point,
+                    })
+                };
+
+                (
+                    make_pipeline("PointLightShadows[cull] reset pipeline", "reset"),
+                    make_pipeline("PointLightShadows[cull] cull pipeline", "cull"),
+                    make_pipeline("PointLightShadows[cull] count pipeline", "count"),
+                )
+            };
+
+            let depth = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("PointLightShadows depth texture"),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: Self::LAYERS as _,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[Self::FORMAT],
+            });
+
+            let faces_views = (0..Self::LAYERS as u32)
+                .map(|layer| {
+                    depth.create_view(&wgpu::TextureViewDescriptor {
+                        dimension: Some(wgpu::TextureViewDimension::D2),
+                        base_array_layer: layer,
+                        array_layer_count: Some(1),
+                        ..Default::default()
+                    })
+                })
+                .collect();
+
+            let depth_pipeline = {
+                let shader =
+                    device.create_shader_module(wgpu::include_wgsl!("point_lights.depth.wgsl"));
+
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("PointLightShadows[depth] pipeline layout"),
+                        bind_group_layouts: &[
+                            &uniform_bind_group_layout,
+                            &skins.get().bind_group_layout,
+                            &animations.get().bind_group_layout,
+                        ],
+                        push_constant_ranges: &[],
+                    });
+
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some("PointLightShadows[depth] pipeline"),
+                    layout: Some(&pipeline_layout),
+                    multiview: None,
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[
+                            DrawInstance::LAYOUT,
+                            // Positions
+                            wgpu::VertexBufferLayout {
+                                array_stride: MeshesManager::VERTEX_SIZE as _,
+                                step_mode: wgpu::VertexStepMode::Vertex,
+                                attributes: &wgpu::vertex_attr_array![10 => Float32x3],
+                            },
+                        ],
+                    },
+                    fragment: None,
+                    primitive: Default::default(),
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: Self::FORMAT,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: Default::default(),
+                        bias: Default::default(),
+                    }),
+                    multisample: Default::default(),
+                })
+            };
+
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("PointLightShadows bind group layout"),
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::CubeArray,
+                                sample_type: wgpu::TextureSampleType::Depth,
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                            count: None,
+                        },
+                    ],
+                });
+
+            let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("PointLightShadows sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                ..Default::default()
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("PointLightShadows bind group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&depth.create_view(
+                            &wgpu::TextureViewDescriptor {
+                                dimension: Some(wgpu::TextureViewDimension::CubeArray),
+                                ..Default::default()
+                            },
+                        )),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            });
+
+            Self {
+                meshes,
+                instances,
+                skins,
+                animations,
+
+                uniform_stride,
+                uniform,
+                uniform_bind_group,
+
+                draw_instances,
+                draw_indirects,
+                instances_capacity,
+                cull_bind_group_layout,
+                cull_bind_group,
+                cull_pipelines,
+
+                faces_views,
+                depth_pipeline,
+
+                bind_group_layout,
+                bind_group,
+            }
+        }
+
+        pub fn rebind_instances(&mut self, device: &wgpu::Device) {
+            let instances = self.instances.get();
+            if instances.capacity() == self.instances_capacity {
+                return;
+            }
+
+            let draw_instances = Self::make_draw_instances(device, instances.capacity());
+
+            self.cull_bind_group = Self::make_cull_bind_group(
+                device,
+                &self.cull_bind_group_layout,
+                &self.meshes.get(),
+                &instances,
+                &draw_instances,
+                &self.draw_indirects,
+            );
+
+            self.instances_capacity = instances.capacity();
+            self.draw_instances = draw_instances;
+        }
+
+        pub fn update(&self, queue: &wgpu::Queue, point_lights: &[PointLight]) {
+            let mut data = vec![0_u8; self.uniform.size() as usize];
+
+            for (slot, point_light) in point_lights.iter().enumerate() {
+                let proj = glam::Mat4::perspective_lh(
+                    std::f32::consts::FRAC_PI_2,
+                    1.0,
+                    point_light.radius * Self::NEAR_RATIO,
+                    point_light.radius,
+                );
+
+                for (face, (direction, up)) in Self::FACES.iter().enumerate() {
+                    let view = glam::Mat4::look_at_lh(
+                        point_light.position,
+                        point_light.position + *direction,
+                        *up,
+                    );
+
+                    let shadow = GpuPointLightShadow {
+                        view_proj: proj * view,
+                        position: point_light.position,
+                        radius: point_light.radius,
+                    };
+
+                    let offset = self.uniform_offset(slot * 6 + face) as usize;
+                    data[offset..offset + GpuPointLightShadow::SIZE as usize]
+                        .copy_from_slice(bytemuck::bytes_of(&shadow));
+                }
+            }
+
+            queue.write_buffer(&self.uniform, 0, &data);
+        }
+
+        pub fn render(&self, ctx: &mut RenderContext, count: usize) {
+            let meshes = self.meshes.get();
+            let skins = self.skins.get();
+            let animations = self.animations.get();
+
+            for slot in 0..count {
+                self.cull(ctx, slot);
+
+                for face in 0..6 {
+                    let layer = slot * 6 + face;
+
+                    let mut depth_pass =
+                        ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                            label: Some("PointLightShadows[depth]"),
+                            color_attachments: &[],
+                            depth_stencil_attachment: Some(
+                                wgpu::RenderPassDepthStencilAttachment {
+                                    view: &self.faces_views[layer],
+                                    depth_ops: Some(wgpu::Operations {
+                                        load: wgpu::LoadOp::Clear(1.0),
+                                        store: true,
+                                    }),
+                                    stencil_ops: None,
+                                },
+                            ),
+                        });
+
+                    depth_pass.set_pipeline(&self.depth_pipeline);
+
+                    depth_pass.set_bind_group(
+                        0,
+                        &self.uniform_bind_group,
+                        &[self.uniform_offset(layer)],
+                    );
+                    depth_pass.set_bind_group(1, &skins.bind_group, &[]);
+                    depth_pass.set_bind_group(2, &animations.bind_group, &[]);
+
+                    depth_pass.set_vertex_buffer(0, self.draw_instances.slice(..));
+                    depth_pass.set_vertex_buffer(1, meshes.vertices.slice(..));
+
+                    depth_pass
+                        .set_index_buffer(meshes.indices.slice(..), wgpu::IndexFormat::Uint32);
+
+                    draw_indexed_indirects(
+                        &mut depth_pass,
+                        ctx.device.features(),
+                        &self.draw_indirects,
+                        meshes.count(),
+                    );
+                }
+            }
+        }
+
+        fn cull(&self, ctx: &mut RenderContext, slot: usize) {
+            let mut cpass = ctx
+                .encoder
+                .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("PointLightShadows[cull]"),
+                });
+
+            const WORKGROUP_SIZE: u32 = 32;
+
+            let meshes_count: u32 = self.meshes.get().count();
+            let meshes_workgroups_count =
+                (meshes_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
+
+            let instances_count: u32 = self.instances.get().high_water_mark();
+            let instances_workgroups_count =
+                (instances_count as f32 / WORKGROUP_SIZE as f32).ceil() as u32;
+
+            // Any face holds the light position and radius
+            let offsets = [self.uniform_offset(slot * 6)];
+
+            cpass.set_pipeline(&self.cull_pipelines.0);
+            cpass.set_bind_group(0, &self.uniform_bind_group, &offsets);
+            cpass.set_bind_group(1, &self.cull_bind_group, &[]);
+            cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+
+            cpass.set_pipeline(&self.cull_pipelines.1);
+            cpass.set_bind_group(0, &self.uniform_bind_group, &offsets);
+            cpass.set_bind_group(1, &self.cull_bind_group, &[]);
+            cpass.dispatch_workgroups(instances_workgroups_count, 1, 1);
+
+            // Draws are only compacted when their count can be read back
+            if ctx
+                .device
+                .features()
+                .contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT)
+            {
+                cpass.set_pipeline(&self.cull_pipelines.2);
+                cpass.set_bind_group(0, &self.uniform_bind_group, &offsets);
+                cpass.set_bind_group(1, &self.cull_bind_group, &[]);
+                cpass.dispatch_workgroups(meshes_workgroups_count, 1, 1);
+            }
+        }
+
+        fn uniform_offset(&self, layer: usize) -> wgpu::DynamicOffset {
+            (layer as wgpu::BufferAddress * self.uniform_stride) as _
+        }
+
+        fn make_draw_instances(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("PointLightShadows[cull] draw instances"),
+                size: capacity as wgpu::BufferAddress * DrawInstance::SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_DST
+                    | wgpu::BufferUsages::VERTEX,
+                mapped_at_creation: false,
+            })
+        }
+
+        fn make_cull_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            meshes: &MeshesManager,
+            instances: &InstancesManager,
+            draw_instances: &wgpu::Buffer,
+            draw_indirects: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("PointLightShadows[cull] bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: meshes.meshes_info.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: instances.base_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: instances.instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: draw_instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: draw_indirects.as_entire_binding(),
+                    },
+                ],
+            })
+        }
+    }
+}
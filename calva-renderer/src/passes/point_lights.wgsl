@@ -12,10 +12,11 @@ struct LightInstance {
     @location(0) position: vec3<f32>,
     @location(1) radius: f32,
     @location(2) color: vec3<f32>,
+    @location(3) shadow_slot: i32,
 }
 
 struct VertexInput {
-    @location(3) position: vec3<f32>,
+    @location(4) position: vec3<f32>,
 }
 
 fn get_clip_pos(
@@ -50,6 +51,9 @@ struct VertexOutput {
     @location(2) l_position: vec3<f32>,
     @location(3) l_inv_square_radius: f32,
     @location(4) l_color: vec3<f32>,
+    @location(5) l_position_world: vec3<f32>,
+    @location(6) l_radius: f32,
+    @location(7) @interpolate(flat) l_shadow_slot: i32,
 }
 
 @vertex
@@ -66,6 +70,9 @@ fn vs_main_lighting(
     out.l_position = (camera.view * vec4<f32>(instance.position, 1.0)).xyz;
     out.l_inv_square_radius = 1.0 / (instance.radius * instance.radius);
     out.l_color = instance.color;
+    out.l_position_world = instance.position;
+    out.l_radius = instance.radius;
+    out.l_shadow_slot = instance.shadow_slot;
 
     return out;
 }
@@ -79,6 +86,58 @@ fn vs_main_lighting(
 @group(1) @binding(2) var t_normal_roughness: texture_2d<f32>;
 @group(1) @binding(3) var t_depth: texture_depth_2d;
 
+struct Config {
+    shadows: u32,
+    shadow_bias: f32,
+}
+@group(2) @binding(0) var<uniform> config: Config;
+
+@group(3) @binding(0) var t_shadows: texture_depth_cube_array;
+@group(3) @binding(1) var t_shadows_sampler: sampler_comparison;
+
+// Must match PointLightShadows::NEAR_RATIO
+const SHADOW_NEAR_RATIO: f32 = 0.01;
+const SHADOW_PCF_RADIUS: f32 = 0.01;
+
+// Depth of a point in the cube face it projects to, the light radius is the far plane
+fn shadow_depth(light_to_frag: vec3<f32>, radius: f32) -> f32 {
+    let near = radius * SHADOW_NEAR_RATIO;
+    let far = radius;
+
+    let abs_dir = abs(light_to_frag);
+    let z = max(max(abs_dir.x, abs_dir.y), abs_dir.z) - config.shadow_bias;
+
+    return far * (z - near) / ((far - near) * max(z, near));
+}
+
+fn shadow_factor(light_to_frag: vec3<f32>, radius: f32, slot: i32) -> f32 {
+    if slot < 0 || u32(slot) >= config.shadows {
+        return 1.0;
+    }
+
+    let depth = shadow_depth(light_to_frag, radius);
+    let offset = length(light_to_frag) * SHADOW_PCF_RADIUS;
+
+    var shadow = textureSampleCompareLevel(t_shadows, t_shadows_sampler, light_to_frag, slot, depth);
+    for (var i = 0u; i < 8u; i++) {
+        let corner = vec3<f32>(
+            f32(i & 1u) * 2.0 - 1.0,
+            f32((i >> 1u) & 1u) * 2.0 - 1.0,
+            f32((i >> 2u) & 1u) * 2.0 - 1.0,
+        );
+
+        shadow += textureSampleCompareLevel(
+            t_shadows,
+            t_shadows_sampler,
+            light_to_frag + corner * offset,
+            slot,
+            depth,
+        );
+    }
+
+    return shadow / 9.0;
+}
+
 fn fresnel_schlick(cos_theta: f32, F0: vec3<f32>) -> vec3<f32> {
     return F0 + (1.0 - F0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
@@ -143,7 +202,10 @@ fn fs_main_lighting(in: VertexOutput) -> @location(0) vec4<f32> {
     let smooth_factor = saturate(1.0 - factor * factor);
     let attenuation = smooth_factor * smooth_factor / max(dist_square, 0.0001);
 
-    let radiance = in.l_color * attenuation;
+    let frag_pos_world = (camera.inv_view * vec4<f32>(frag_pos_view, 1.0)).xyz;
+    let shadow = shadow_factor(frag_pos_world - in.l_position_world, in.l_radius, in.l_shadow_slot);
+
+    let radiance = in.l_color * attenuation * shadow;
 
     let F0 = mix(vec3<f32>(0.04), albedo, metallic);
     let F = fresnel_schlick(max(dot(H, V), 0.0), F0);
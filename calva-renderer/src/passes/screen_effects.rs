@@ -0,0 +1,252 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::{FramePipelines, RenderContext, UniformBuffer};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ScreenEffectsConfig {
+    /// Darkening at the corners, 0 disables the vignette
+    pub vignette_intensity: f32,
+    /// Distance from the center, relative to the half diagonal, where the
+    /// vignette starts
+    pub vignette_radius: f32,
+    /// Channels offset at the corners, relative to the screen size
+    pub aberration_strength: f32,
+}
+
+impl Default for ScreenEffectsConfig {
+    fn default() -> Self {
+        Self {
+            vignette_intensity: 0.5,
+            vignette_radius: 0.5,
+            aberration_strength: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut ScreenEffectsConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Screen effects")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.vignette_intensity, 0.0..=1.0)
+                        .text("Vignette intensity"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.vignette_radius, 0.0..=1.0).text("Vignette radius"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.aberration_strength, 0.0..=0.02)
+                        .text("Chromatic aberration"),
+                );
+            })
+            .header_response
+    }
+}
+
+/// Intermediate target of the size of the frame
+struct ScreenEffectsInput {
+    size: (u32, u32),
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Vignette and chromatic aberration, applied to the tone mapped image.
+///
+/// The final passes render to an intermediate target instead of the frame, see
+/// [`ScreenEffectsPass::render`].
+pub struct ScreenEffectsPass {
+    pub config: UniformBuffer<ScreenEffectsConfig>,
+
+    /// Recreated when the frame size changes
+    input: Mutex<Option<Arc<ScreenEffectsInput>>>,
+
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: FramePipelines,
+}
+
+impl ScreenEffectsPass {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let config = UniformBuffer::new(device, ScreenEffectsConfig::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ScreenEffects sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ScreenEffects bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("screen_effects.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ScreenEffects pipeline layout"),
+            bind_group_layouts: &[&config.bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ScreenEffects pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        });
+
+        Self {
+            config,
+
+            input: Default::default(),
+
+            sampler,
+            bind_group_layout,
+            pipelines,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    /// Renders `f` to an intermediate target of the frame size and format
+    /// [`Self::FORMAT`], then applies the effects to the frame
+    pub fn render(&self, ctx: &mut RenderContext, f: impl FnOnce(&mut RenderContext)) {
+        let size = ctx.size;
+        let input = self.input(ctx.device, size);
+        f(&mut ctx.with_frame(&input.view, Self::FORMAT, size));
+
+        ctx.encoder.profile_start("ScreenEffects");
+
+        let pipeline = self.pipelines.get(ctx);
+        let viewport = ctx.viewport;
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("ScreenEffects"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        if let Some(viewport) = viewport {
+            viewport.apply(&mut rpass);
+        }
+
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &self.config.bind_group, &[]);
+        rpass.set_bind_group(1, &input.bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn input(&self, device: &wgpu::Device, size: (u32, u32)) -> Arc<ScreenEffectsInput> {
+        let mut input = self.input.lock();
+
+        match &*input {
+            Some(input) if input.size == size => input.clone(),
+            _ => input
+                .insert(Arc::new(self.make_input(device, size)))
+                .clone(),
+        }
+    }
+
+    fn make_input(&self, device: &wgpu::Device, size: (u32, u32)) -> ScreenEffectsInput {
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("ScreenEffects input"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&Default::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ScreenEffects bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+        });
+
+        ScreenEffectsInput {
+            size,
+            view,
+            bind_group,
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = out.position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    aberration_strength: f32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var t_sampler: sampler;
+@group(1) @binding(1) var t_input: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let dims = vec2<f32>(textureDimensions(t_input));
+
+    // Input covers the whole frame, uv is relative to the viewport
+    let uv = in.position.xy / dims;
+    let from_center = in.uv - 0.5;
+
+    // Red and blue pushed outward and inward along the radius
+    let offset = from_center * config.aberration_strength;
+    let color = vec3<f32>(
+        textureSample(t_input, t_sampler, uv + offset).r,
+        textureSample(t_input, t_sampler, uv).g,
+        textureSample(t_input, t_sampler, uv - offset).b,
+    );
+
+    // Distance relative to the half diagonal, 1 in the corners
+    let distance = length(from_center) / length(vec2<f32>(0.5));
+    let vignette = 1.0 - config.vignette_intensity
+        * smoothstep(config.vignette_radius, 1.0, distance);
+
+    return vec4<f32>(color * vignette, 1.0);
+}
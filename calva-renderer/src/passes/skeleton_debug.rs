@@ -0,0 +1,215 @@
+use crate::{
+    AnimationId, AnimationsManager, CameraManager, FramePipelines, RenderContext, ResourceRef,
+    ResourcesManager, SkeletonId, SkinsManager, UniformBuffer,
+};
+
+/// Skeleton drawn by [`SkeletonDebugPass`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SkeletonDebug {
+    pub skeleton: SkeletonId,
+    /// Instance transform
+    pub transform: glam::Mat4,
+    /// Null to draw the bind pose
+    pub animation: AnimationId,
+    /// Animation time, in seconds
+    pub time: f32,
+    pub color: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkeletonDebugUniform {
+    transform: glam::Mat4,
+    color: [f32; 3],
+    animation: AnimationId,
+    time: f32,
+    _padding: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BoneVertex {
+    bind_position: glam::Vec3,
+    joint: u32,
+}
+
+/// Joints hierarchy of a skinned instance drawn as lines over the final frame,
+/// posed with the baked matrices of [`AnimationsManager`]. Useful to tell
+/// broken skin weights from broken animations.
+///
+/// The skeleton is set with [`SkeletonDebugPass::update`], the animation time
+/// has to be updated every frame to follow the instance.
+pub struct SkeletonDebugPass {
+    camera: ResourceRef<CameraManager>,
+    animations: ResourceRef<AnimationsManager>,
+    skins: ResourceRef<SkinsManager>,
+
+    uniform: UniformBuffer<SkeletonDebugUniform>,
+    skeleton: Option<SkeletonId>,
+    vertices: wgpu::Buffer,
+    vertex_count: u32,
+
+    pipelines: FramePipelines,
+}
+
+impl SkeletonDebugPass {
+    pub const MAX_JOINTS: usize = 256;
+
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager) -> Self {
+        let camera = resources.get::<CameraManager>();
+        let animations = resources.get::<AnimationsManager>();
+        let skins = resources.get::<SkinsManager>();
+
+        let uniform = UniformBuffer::new(device, SkeletonDebugUniform::default());
+
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SkeletonDebug vertices"),
+            size: (2 * Self::MAX_JOINTS * std::mem::size_of::<BoneVertex>()) as _,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("skeleton_debug.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SkeletonDebug pipeline layout"),
+            bind_group_layouts: &[
+                &camera.get().bind_group_layout,
+                &animations.get().bind_group_layout,
+                &uniform.bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("SkeletonDebug pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<BoneVertex>() as _,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![
+                            0 => Float32x3,
+                            1 => Uint32,
+                        ],
+                    }],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    ..Default::default()
+                },
+                // Drawn over the skinned mesh
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        });
+
+        Self {
+            camera,
+            animations,
+            skins,
+
+            uniform,
+            skeleton: None,
+            vertices,
+            vertex_count: 0,
+
+            pipelines,
+        }
+    }
+
+    /// Sets the drawn skeleton, `None` to hide it. Joints above
+    /// [`Self::MAX_JOINTS`] are dropped.
+    pub fn update(&mut self, queue: &wgpu::Queue, skeleton: Option<SkeletonDebug>) {
+        let Some(skeleton) = skeleton else {
+            self.skeleton = None;
+            self.vertex_count = 0;
+            return;
+        };
+
+        *self.uniform = SkeletonDebugUniform {
+            transform: skeleton.transform,
+            color: skeleton.color,
+            animation: skeleton.animation,
+            time: skeleton.time,
+            _padding: Default::default(),
+        };
+        self.uniform.update(queue);
+
+        if self.skeleton == Some(skeleton.skeleton) {
+            return;
+        }
+        self.skeleton = Some(skeleton.skeleton);
+
+        let skins = self.skins.get();
+        let Some(joints) = skins.skeleton(skeleton.skeleton) else {
+            self.vertex_count = 0;
+            return;
+        };
+
+        let vertex = |joint: u32| BoneVertex {
+            bind_position: joints.bind_positions[joint as usize],
+            joint,
+        };
+
+        // One line from each joint to its parent
+        let vertices = joints
+            .parents
+            .iter()
+            .take(Self::MAX_JOINTS)
+            .enumerate()
+            .filter_map(|(joint, parent)| Some([vertex(joint as _), vertex((*parent)?)]))
+            .flatten()
+            .collect::<Vec<_>>();
+
+        self.vertex_count = vertices.len() as _;
+        queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(&vertices));
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        let camera = self.camera.get();
+        let animations = self.animations.get();
+        let pipeline = self.pipelines.get(ctx);
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("SkeletonDebug"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.frame,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_bind_group(1, &animations.bind_group, &[]);
+        rpass.set_bind_group(2, &self.uniform.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+
+        rpass.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -5,6 +5,10 @@ struct Camera {
     inv_view: mat4x4<f32>,
     inv_proj: mat4x4<f32>,
     frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
 }
 @group(0) @binding(0) var<uniform> camera: Camera;
 
@@ -24,9 +28,11 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
         f32(vertex_index & 1u),
     ) * 2.0;
 
-    let clip = vec4<f32>(tc * 2.0 - 1.0, 1.0, 1.0);
+    // On the far plane, behind everything
+    let clip = vec4<f32>(tc * 2.0 - 1.0, camera.far_depth, 1.0);
 
-    var view_ray4 = camera.inv_proj * clip;
+    // Unprojected in between the planes, the far plane may be at infinity
+    var view_ray4 = camera.inv_proj * vec4<f32>(clip.xy, 0.5, 1.0);
     var view_ray = view_ray4.xyz / view_ray4.w;
 
     // Use rotation only
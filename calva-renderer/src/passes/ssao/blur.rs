@@ -1,4 +1,4 @@
-use crate::RenderContext;
+use crate::{CameraManager, RenderContext, ResourceRef, ResourcesManager};
 
 use super::SsaoPass;
 
@@ -17,56 +17,80 @@ impl std::fmt::Display for Direction {
     }
 }
 
+/// Separable gaussian blur of the ambient occlusion. When bilateral, samples
+/// are weighted by their depth similarity so that occlusion doesn't bleed
+/// across silhouettes.
 pub struct SsaoBlurPass<const WIDTH: u32, const HEIGHT: u32> {
+    camera: ResourceRef<CameraManager>,
+
     temp_view: wgpu::TextureView,
     output_view: wgpu::TextureView,
 
-    h_pass: wgpu::RenderBundle,
-    v_pass: wgpu::RenderBundle,
+    bind_group_layout: wgpu::BindGroupLayout,
+    /// Indexed by direction, then bilateral
+    pipelines: [[wgpu::RenderPipeline; 2]; 2],
+
+    h_pass: [wgpu::RenderBundle; 2],
+    v_pass: [wgpu::RenderBundle; 2],
 }
 
 impl<const WIDTH: u32, const HEIGHT: u32> SsaoBlurPass<WIDTH, HEIGHT> {
-    pub fn new(device: &wgpu::Device, output: &wgpu::Texture) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        resources: &ResourcesManager,
+        output: &wgpu::Texture,
+        depth: &wgpu::Texture,
+    ) -> Self {
+        let camera = resources.get::<CameraManager>();
+
         let temp = SsaoPass::<WIDTH, HEIGHT>::make_texture(device, Some("SsaoBlur temp texture"));
         let temp_view = temp.create_view(&Default::default());
         let output_view = output.create_view(&Default::default());
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("SsaoBlur bind group layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Texture {
-                    multisampled: false,
-                    view_dimension: wgpu::TextureViewDimension::D2,
-                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            entries: &[
+                // input
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                // depth
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+            ],
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("SsaoBlur pipeline layout"),
-            bind_group_layouts: &[&bind_group_layout],
+            bind_group_layouts: &[&bind_group_layout, &camera.get().bind_group_layout],
             push_constant_ranges: &[],
         });
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("blur.wgsl"));
 
-        let make_render_bundle = |direction: Direction| {
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some(format!("SsaoBlur[{direction}] bind group").as_str()),
-                layout: &bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(match direction {
-                        Direction::Horizontal => &output_view,
-                        Direction::Vertical => &temp_view,
-                    }),
-                }],
-            });
+        let make_pipeline = |direction: Direction, bilateral: bool| {
+            let entry_point = if bilateral {
+                format!("fs_main_bilateral_{direction}")
+            } else {
+                format!("fs_main_{direction}")
+            };
 
-            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some(format!("SsaoBlur[{direction}] pipeline").as_str()),
                 layout: Some(&pipeline_layout),
                 vertex: wgpu::VertexState {
@@ -76,7 +100,7 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoBlurPass<WIDTH, HEIGHT> {
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
-                    entry_point: format!("fs_main_{direction}").as_str(),
+                    entry_point: entry_point.as_str(),
                     targets: &[Some(wgpu::ColorTargetState {
                         format: output.format(),
                         blend: None,
@@ -87,38 +111,49 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoBlurPass<WIDTH, HEIGHT> {
                 depth_stencil: None,
                 multisample: Default::default(),
                 multiview: None,
-            });
-
-            let mut encoder =
-                device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
-                    label: Some(format!("SsaoBlur[{direction}] render bundle").as_str()),
-                    color_formats: &[Some(output.format())],
-                    depth_stencil: None,
-                    sample_count: 1,
-                    multiview: None,
-                });
-
-            encoder.set_pipeline(&pipeline);
-            encoder.set_bind_group(0, &bind_group, &[]);
-
-            encoder.draw(0..3, 0..1);
-
-            encoder.finish(&Default::default())
+            })
         };
 
-        let h_pass = make_render_bundle(Direction::Horizontal);
-        let v_pass = make_render_bundle(Direction::Vertical);
+        let pipelines = [Direction::Horizontal, Direction::Vertical]
+            .map(|direction| [false, true].map(|bilateral| make_pipeline(direction, bilateral)));
+
+        let [h_pass, v_pass] = Self::make_render_bundles(
+            device,
+            &camera,
+            &bind_group_layout,
+            &pipelines,
+            &temp_view,
+            &output_view,
+            depth,
+        );
 
         Self {
+            camera,
+
             temp_view,
             output_view,
 
+            bind_group_layout,
+            pipelines,
+
             h_pass,
             v_pass,
         }
     }
 
-    pub fn render(&self, ctx: &mut RenderContext) {
+    pub fn rebind(&mut self, device: &wgpu::Device, depth: &wgpu::Texture) {
+        [self.h_pass, self.v_pass] = Self::make_render_bundles(
+            device,
+            &self.camera,
+            &self.bind_group_layout,
+            &self.pipelines,
+            &self.temp_view,
+            &self.output_view,
+            depth,
+        );
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext, bilateral: bool) {
         ctx.encoder.profile_start("Ssao[blur]");
 
         ctx.encoder
@@ -134,7 +169,7 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoBlurPass<WIDTH, HEIGHT> {
                 })],
                 depth_stencil_attachment: None,
             })
-            .execute_bundles(std::iter::once(&self.h_pass));
+            .execute_bundles(std::iter::once(&self.h_pass[bilateral as usize]));
 
         ctx.encoder
             .begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -149,8 +184,63 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoBlurPass<WIDTH, HEIGHT> {
                 })],
                 depth_stencil_attachment: None,
             })
-            .execute_bundles(std::iter::once(&self.v_pass));
+            .execute_bundles(std::iter::once(&self.v_pass[bilateral as usize]));
 
         ctx.encoder.profile_end();
     }
+
+    fn make_render_bundles(
+        device: &wgpu::Device,
+        camera: &ResourceRef<CameraManager>,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        pipelines: &[[wgpu::RenderPipeline; 2]; 2],
+        temp_view: &wgpu::TextureView,
+        output_view: &wgpu::TextureView,
+        depth: &wgpu::Texture,
+    ) -> [[wgpu::RenderBundle; 2]; 2] {
+        let camera = camera.get();
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
+            ..Default::default()
+        });
+
+        [Direction::Horizontal, Direction::Vertical].map(|direction| {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(format!("SsaoBlur[{direction}] bind group").as_str()),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(match direction {
+                            Direction::Horizontal => output_view,
+                            Direction::Vertical => temp_view,
+                        }),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&depth_view),
+                    },
+                ],
+            });
+
+            pipelines[direction as usize].each_ref().map(|pipeline| {
+                let mut encoder =
+                    device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                        label: Some(format!("SsaoBlur[{direction}] render bundle").as_str()),
+                        color_formats: &[Some(wgpu::TextureFormat::R8Unorm)],
+                        depth_stencil: None,
+                        sample_count: 1,
+                        multiview: None,
+                    });
+
+                encoder.set_pipeline(pipeline);
+                encoder.set_bind_group(0, &bind_group, &[]);
+                encoder.set_bind_group(1, &camera.bind_group, &[]);
+
+                encoder.draw(0..3, 0..1);
+
+                encoder.finish(&Default::default())
+            })
+        })
+    }
 }
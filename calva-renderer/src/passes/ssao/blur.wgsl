@@ -17,6 +17,20 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<
 //
 
 @group(0) @binding(0) var t_input: texture_2d<f32>;
+@group(0) @binding(1) var t_depth: texture_depth_2d;
+
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+@group(1) @binding(0) var<uniform> camera: Camera;
+
+// Relative depth difference at which a sample weight drops to 1/e
+const DEPTH_SHARPNESS: f32 = 0.05;
 
 fn blur(position: vec4<f32>, direction: vec2<i32>) -> f32 {
     let c = vec2<i32>(floor(position.xy));
@@ -43,3 +57,46 @@ fn fs_main_horizontal(@builtin(position) position: vec4<f32>) -> @location(0) f3
 fn fs_main_vertical(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
     return blur(position, vec2<i32>(0, 1));
 }
+
+// Linear depth of the full resolution depth buffer texel under the input one
+fn linear_depth(c: vec2<i32>) -> f32 {
+    let scale = vec2<f32>(textureDimensions(t_depth)) / vec2<f32>(textureDimensions(t_input));
+    let depth_coord = vec2<i32>((vec2<f32>(c) + 0.5) * scale);
+
+    let depth = textureLoad(t_depth, depth_coord, 0);
+    let position = camera.inv_proj * vec4<f32>(0.0, 0.0, depth, 1.0);
+
+    return -position.z / position.w;
+}
+
+fn bilateral_blur(position: vec4<f32>, direction: vec2<i32>) -> f32 {
+    let c = vec2<i32>(floor(position.xy));
+    let center_depth = linear_depth(c);
+
+    var kernel = array<f32, 7>(1.0, 6.0, 15.0, 20.0, 15.0, 6.0, 1.0);
+
+    var result: f32 = 0.0;
+    var total_weight: f32 = 0.0;
+
+    for (var i: i32 = -3; i <= 3; i++) {
+        let coord = c + vec2<i32>(i) * direction;
+
+        let dz = (linear_depth(coord) - center_depth) / (center_depth * DEPTH_SHARPNESS);
+        let weight = kernel[i + 3] * exp(-dz * dz);
+
+        result += textureLoad(t_input, coord, 0).r * weight;
+        total_weight += weight;
+    }
+
+    return result / total_weight;
+}
+
+@fragment
+fn fs_main_bilateral_horizontal(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    return bilateral_blur(position, vec2<i32>(1, 0));
+}
+
+@fragment
+fn fs_main_bilateral_vertical(@builtin(position) position: vec4<f32>) -> @location(0) f32 {
+    return bilateral_blur(position, vec2<i32>(0, 1));
+}
@@ -0,0 +1,155 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.ndc = out.position.xy;
+    out.uv = out.ndc * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    radius: f32,
+    bias: f32,
+    power: f32,
+    samples_count: u32,
+}
+@group(1) @binding(0) var<uniform> config: Config;
+
+// Replaced by the samples count of each pipeline variant
+const SAMPLES_COUNT: u32 = 32u;
+const MAX_SAMPLES_COUNT: u32 = 64u;
+struct RandomData {
+    samples: array<vec4<f32>, MAX_SAMPLES_COUNT>,
+    noise: array<array<vec4<f32>, 4>, 4>,
+}
+@group(2) @binding(0) var<uniform> random_data: RandomData;
+
+@group(3) @binding(0) var t_sampler: sampler;
+@group(3) @binding(1) var t_normal: texture_2d<f32>;
+@group(3) @binding(2) var t_depth: texture_depth_2d;
+
+const PI: f32 = 3.14159265359;
+const HALF_PI: f32 = 1.57079632679;
+
+// Each slice marches the depth buffer on both sides of the fragment
+const SLICES_COUNT: u32 = 4u;
+
+fn view_position(uv: vec2<f32>) -> vec3<f32> {
+    let t_depth_size = vec2<f32>(textureDimensions(t_depth));
+    let coord = clamp(vec2<i32>(uv * t_depth_size), vec2<i32>(0), vec2<i32>(t_depth_size) - 1);
+
+    let depth = textureLoad(t_depth, coord, 0);
+    let ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let position = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+
+    return position.xyz / position.w;
+}
+
+// Cosine of the highest horizon found along `direction`, in uv space
+fn horizon_cos(
+    in: VertexOutput,
+    frag_position: vec3<f32>,
+    view_dir: vec3<f32>,
+    direction: vec2<f32>,
+    jitter: f32,
+) -> f32 {
+    let steps_count = SAMPLES_COUNT / (2u * SLICES_COUNT);
+    var result = -1.0;
+
+    for (var i: u32 = 0u; i < steps_count; i++) {
+        let s = (f32(i) + jitter) / f32(steps_count);
+        let sample_pos = view_position(in.uv + direction * s);
+
+        let delta = sample_pos - frag_position;
+        let len = length(delta);
+        if len < config.bias {
+            continue;
+        }
+
+        // Samples fade out of the horizon past the radius, avoiding halos
+        // around foreground objects
+        let falloff = saturate(len / config.radius - 1.0);
+        result = max(result, mix(dot(delta / len, view_dir), -1.0, falloff));
+    }
+
+    return result;
+}
+
+// Cosine weighted visible arc of the slice, between the normal and `h`
+fn integrate_arc(h: f32, n: f32, cos_n: f32) -> f32 {
+    return 0.25 * (-cos(2.0 * h - n) + cos_n + 2.0 * h * sin(n));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) f32 {
+    let frag_position = view_position(in.uv);
+    let frag_normal = normalize(textureSample(t_normal, t_sampler, in.uv).xyz);
+    let view_dir = normalize(-frag_position);
+
+    // Radius projected on screen, in uv units
+    let radius_uv = config.radius * 0.5 * vec2<f32>(camera.proj[0][0], camera.proj[1][1]) / -frag_position.z;
+
+    let c = vec2<i32>(floor(in.position.xy));
+    let random = random_data.noise[c.x & 3][c.y & 3].xy;
+    let rotation = atan2(random.y, random.x);
+    let jitter = random.x * 0.5 + 0.5;
+
+    var visibility: f32 = 0.0;
+    for (var i: u32 = 0u; i < SLICES_COUNT; i++) {
+        let phi = rotation + f32(i) * PI / f32(SLICES_COUNT);
+        let direction = vec3<f32>(cos(phi), sin(phi), 0.0);
+
+        // Normal projected on the slice plane
+        let ortho_dir = direction - dot(direction, view_dir) * view_dir;
+        let axis = normalize(cross(direction, view_dir));
+        let proj_normal = frag_normal - axis * dot(frag_normal, axis);
+        let proj_len = length(proj_normal);
+
+        let cos_n = clamp(dot(proj_normal, view_dir) / proj_len, -1.0, 1.0);
+        let n = sign(dot(ortho_dir, proj_normal)) * acos(cos_n);
+
+        // View space y is up, uv y is down
+        let direction_uv = direction.xy * vec2<f32>(1.0, -1.0) * radius_uv;
+
+        let h0 = -acos(horizon_cos(in, frag_position, view_dir, -direction_uv, jitter));
+        let h1 = acos(horizon_cos(in, frag_position, view_dir, direction_uv, jitter));
+
+        let h0_clamped = n + max(h0 - n, -HALF_PI);
+        let h1_clamped = n + min(h1 - n, HALF_PI);
+
+        visibility += proj_len * (integrate_arc(h0_clamped, n, cos_n) + integrate_arc(h1_clamped, n, cos_n));
+    }
+
+    visibility = saturate(visibility / f32(SLICES_COUNT));
+    return pow(visibility, config.power);
+}
@@ -1,22 +1,107 @@
-use crate::{CameraManager, RenderContext, RessourceRef, RessourcesManager, UniformBuffer};
+use crate::{
+    CameraManager, RenderContext, ResourceRef, ResourcesManager, UniformBuffer, UniformData,
+};
 
 mod blit;
 mod blur;
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+/// Algorithm computing the ambient occlusion factor
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum SsaoMethod {
+    /// Random samples in the normal oriented hemisphere, compared against the
+    /// depth buffer
+    #[default]
+    Hemisphere,
+    /// Ground truth ambient occlusion: horizon angles are searched in the
+    /// depth buffer along a few screen space slices and the visible arc is
+    /// integrated analytically. Less noisy and without the hemisphere halos,
+    /// at the cost of more ALU per sample.
+    Gtao,
+}
+
+impl SsaoMethod {
+    pub const ALL: [Self; 2] = [Self::Hemisphere, Self::Gtao];
+
+    fn shader(&self) -> &'static str {
+        match self {
+            Self::Hemisphere => include_str!("ssao.wgsl"),
+            Self::Gtao => include_str!("gtao.wgsl"),
+        }
+    }
+}
+
+impl std::fmt::Display for SsaoMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Hemisphere => "Hemisphere",
+            Self::Gtao => "GTAO",
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct SsaoConfig {
+    pub method: SsaoMethod,
     pub radius: f32,
     pub bias: f32,
     pub power: f32,
+    /// Kernel samples taken per pixel, one of [`SsaoConfig::SAMPLES_COUNTS`].
+    ///
+    /// Cost grows linearly with the count, each sample being a depth fetch.
+    /// 16 samples show banding on large radii, 64 are mostly noise free
+    /// before the blur. [`SsaoMethod::Gtao`] splits them in horizon steps
+    /// over 4 slices.
+    pub samples_count: u32,
+    /// Blur weighted by depth similarity, keeping occlusion from bleeding
+    /// across silhouettes. Costs an extra depth fetch per blur tap.
+    pub bilateral: bool,
+}
+
+impl SsaoConfig {
+    /// Sample counts with a precompiled pipeline, other values fall back to
+    /// the largest one below
+    pub const SAMPLES_COUNTS: [u32; 3] = [16, 32, 64];
+
+    /// Index of the pipeline variant used for [`SsaoConfig::samples_count`]
+    fn variant(&self) -> usize {
+        Self::SAMPLES_COUNTS
+            .iter()
+            .rposition(|count| *count <= self.samples_count)
+            .unwrap_or(0)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuSsaoConfig {
+    radius: f32,
+    bias: f32,
+    power: f32,
+    samples_count: u32,
+}
+
+impl UniformData for SsaoConfig {
+    type GpuType = GpuSsaoConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuSsaoConfig {
+            radius: self.radius,
+            bias: self.bias,
+            power: self.power,
+            samples_count: self.samples_count,
+        }
+    }
 }
 
 impl Default for SsaoConfig {
     fn default() -> Self {
         Self {
+            method: SsaoMethod::default(),
             radius: 0.3,
             bias: 0.025,
             power: 1.0,
+            samples_count: 32,
+            bilateral: true,
         }
     }
 }
@@ -27,9 +112,26 @@ impl egui::Widget for &mut SsaoConfig {
         egui::CollapsingHeader::new("SSAO")
             .default_open(true)
             .show(ui, |ui| {
+                egui::ComboBox::from_label("Method")
+                    .selected_text(self.method.to_string())
+                    .show_ui(ui, |ui| {
+                        for method in SsaoMethod::ALL {
+                            ui.selectable_value(&mut self.method, method, method.to_string());
+                        }
+                    });
                 ui.add(egui::Slider::new(&mut self.radius, 0.0..=4.0).text("Radius"));
                 ui.add(egui::Slider::new(&mut self.bias, 0.0..=0.1).text("Bias"));
                 ui.add(egui::Slider::new(&mut self.power, 0.0..=8.0).text("Power"));
+
+                egui::ComboBox::from_label("Samples")
+                    .selected_text(self.samples_count.to_string())
+                    .show_ui(ui, |ui| {
+                        for count in SsaoConfig::SAMPLES_COUNTS {
+                            ui.selectable_value(&mut self.samples_count, count, count.to_string());
+                        }
+                    });
+
+                ui.checkbox(&mut self.bilateral, "Bilateral blur");
             })
             .header_response
     }
@@ -38,16 +140,24 @@ impl egui::Widget for &mut SsaoConfig {
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 struct SsaoRandom {
-    samples: [glam::Vec4; SsaoRandom::SAMPLES_COUNT],
+    samples: [glam::Vec4; SsaoRandom::MAX_SAMPLES_COUNT],
     noise: [glam::Vec4; 16],
 }
 
 impl SsaoRandom {
-    const SAMPLES_COUNT: usize = 32;
+    const MAX_SAMPLES_COUNT: usize = 64;
+
+    /// Kernel of `samples_count` samples, scaled so that they cluster near the
+    /// origin. Unused samples are zeroed.
+    fn new(samples_count: u32) -> Self {
+        let samples_count = (samples_count as usize).min(Self::MAX_SAMPLES_COUNT);
 
-    fn new() -> Self {
-        let samples = (0..Self::SAMPLES_COUNT)
+        let samples = (0..Self::MAX_SAMPLES_COUNT)
             .map(|i| {
+                if i >= samples_count {
+                    return glam::Vec4::ZERO;
+                }
+
                 let sample = glam::vec4(
                     rand::random::<f32>() * 2.0 - 1.0,
                     rand::random::<f32>() * 2.0 - 1.0,
@@ -56,7 +166,7 @@ impl SsaoRandom {
                 )
                 .normalize();
 
-                let scale = i as f32 / Self::SAMPLES_COUNT as f32;
+                let scale = i as f32 / samples_count as f32;
                 sample
                     * glam::Vec4::lerp(
                         glam::Vec4::splat(0.1),
@@ -91,18 +201,27 @@ pub struct SsaoPassInputs<'a> {
     pub output: &'a wgpu::Texture,
 }
 
+pub struct SsaoPassOutputs {
+    /// Blurred ambient occlusion factor, at the pass resolution
+    pub output: wgpu::Texture,
+}
+
 pub struct SsaoPass<const WIDTH: u32, const HEIGHT: u32> {
     pub config: UniformBuffer<SsaoConfig>,
+    pub outputs: SsaoPassOutputs,
     random: UniformBuffer<SsaoRandom>,
+    /// Pipeline variant of the current kernel
+    variant: usize,
 
-    camera: RessourceRef<CameraManager>,
+    camera: ResourceRef<CameraManager>,
 
     output_view: wgpu::TextureView,
 
     sampler: wgpu::Sampler,
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
-    pipeline: wgpu::RenderPipeline,
+    /// One pipeline per [`SsaoMethod`] and [`SsaoConfig::SAMPLES_COUNTS`]
+    pipelines: Vec<[wgpu::RenderPipeline; SsaoMethod::ALL.len()]>,
 
     blur: blur::SsaoBlurPass<WIDTH, HEIGHT>,
     blit: blit::SsaoBlitPass,
@@ -111,13 +230,15 @@ pub struct SsaoPass<const WIDTH: u32, const HEIGHT: u32> {
 impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
     pub fn new(
         device: &wgpu::Device,
-        ressources: &RessourcesManager,
+        resources: &ResourcesManager,
         inputs: SsaoPassInputs,
     ) -> Self {
         let config = UniformBuffer::new(device, SsaoConfig::default());
-        let random = UniformBuffer::new(device, SsaoRandom::new());
+        let variant = config.variant();
+        let random =
+            UniformBuffer::new(device, SsaoRandom::new(SsaoConfig::SAMPLES_COUNTS[variant]));
 
-        let camera = ressources.get::<CameraManager>();
+        let camera = resources.get::<CameraManager>();
 
         let output = Self::make_texture(device, Some("Ssao output"));
         let output_view = output.create_view(&Default::default());
@@ -179,36 +300,59 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
             push_constant_ranges: &[],
         });
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("ssao.wgsl"));
+        let make_pipeline = |method: SsaoMethod, samples_count: u32| {
+            // Loop bound is a constant so that drivers can unroll it
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(format!("Ssao[{method}] shader").as_str()),
+                source: wgpu::ShaderSource::Wgsl(
+                    method
+                        .shader()
+                        .replace(
+                            "const SAMPLES_COUNT: u32 = 32u;",
+                            &format!("const SAMPLES_COUNT: u32 = {samples_count}u;"),
+                        )
+                        .into(),
+                ),
+            });
+
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(format!("Ssao[{method}] pipeline").as_str()),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: output.format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multiview: None,
+                multisample: Default::default(),
+            })
+        };
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Ssao pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: output.format(),
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: Default::default(),
-            depth_stencil: None,
-            multiview: None,
-            multisample: Default::default(),
-        });
+        let pipelines = SsaoConfig::SAMPLES_COUNTS
+            .iter()
+            .map(|samples_count| {
+                SsaoMethod::ALL.map(|method| make_pipeline(method, *samples_count))
+            })
+            .collect();
 
-        let blur = blur::SsaoBlurPass::new(device, &output);
+        let blur = blur::SsaoBlurPass::new(device, resources, &output, inputs.depth);
         let blit = blit::SsaoBlitPass::new(device, &output, inputs.output);
 
         Self {
             config,
+            outputs: SsaoPassOutputs { output },
+            variant,
             random,
 
             camera,
@@ -217,7 +361,7 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
 
             bind_group_layout,
             bind_group,
-            pipeline,
+            pipelines,
 
             output_view,
             blur,
@@ -229,11 +373,25 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
         self.bind_group =
             Self::make_bind_group(device, &self.bind_group_layout, &self.sampler, &inputs);
 
+        self.blur.rebind(device, inputs.depth);
         self.blit.rebind(inputs.output);
     }
 
     pub fn update(&mut self, queue: &wgpu::Queue) {
         self.config.update(queue);
+
+        if self.config.variant() != self.variant {
+            self.regenerate_kernel(queue);
+        }
+    }
+
+    /// Draws a new random kernel and noise, for the configured samples count.
+    /// Called on samples count changes, or to reseed the noise pattern.
+    pub fn regenerate_kernel(&mut self, queue: &wgpu::Queue) {
+        self.variant = self.config.variant();
+
+        *self.random = SsaoRandom::new(SsaoConfig::SAMPLES_COUNTS[self.variant]);
+        self.random.update(queue);
     }
 
     pub fn render(&self, ctx: &mut RenderContext) {
@@ -254,7 +412,7 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
             depth_stencil_attachment: None,
         });
 
-        rpass.set_pipeline(&self.pipeline);
+        rpass.set_pipeline(&self.pipelines[self.variant][self.config.method as usize]);
         rpass.set_bind_group(0, &camera.bind_group, &[]);
         rpass.set_bind_group(1, &self.config.bind_group, &[]);
         rpass.set_bind_group(2, &self.random.bind_group, &[]);
@@ -264,12 +422,29 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
 
         drop(rpass);
 
-        self.blur.render(ctx);
+        self.blur.render(ctx, self.config.bilateral);
         self.blit.render(ctx);
 
         ctx.encoder.profile_end();
     }
 
+    /// Clears the output to white, i.e. no occlusion, without running the
+    /// pass
+    pub fn clear(&self, ctx: &mut RenderContext) {
+        ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ssao[clear]"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+    }
+
     fn make_texture(device: &wgpu::Device, label: wgpu::Label<'static>) -> wgpu::Texture {
         device.create_texture(&wgpu::TextureDescriptor {
             label,
@@ -320,3 +495,23 @@ impl<const WIDTH: u32, const HEIGHT: u32> SsaoPass<WIDTH, HEIGHT> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernel_samples_count() {
+        for samples_count in SsaoConfig::SAMPLES_COUNTS {
+            let random = SsaoRandom::new(samples_count);
+            let (used, unused) = random.samples.split_at(samples_count as usize);
+
+            for sample in used {
+                assert!(sample.z >= 0.0);
+                assert!(sample.length() <= 1.0 + f32::EPSILON);
+                assert!(sample.length() > 0.0);
+            }
+            assert!(unused.iter().all(|sample| *sample == glam::Vec4::ZERO));
+        }
+    }
+}
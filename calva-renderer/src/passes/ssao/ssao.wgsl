@@ -41,12 +41,15 @@ struct Config {
     radius: f32,
     bias: f32,
     power: f32,
+    samples_count: u32,
 }
 @group(1) @binding(0) var<uniform> config: Config;
 
+// Replaced by the samples count of each pipeline variant
 const SAMPLES_COUNT: u32 = 32u;
+const MAX_SAMPLES_COUNT: u32 = 64u;
 struct RandomData {
-    samples: array<vec4<f32>, SAMPLES_COUNT>,
+    samples: array<vec4<f32>, MAX_SAMPLES_COUNT>,
     noise: array<array<vec4<f32>, 4>, 4>,
 }
 @group(2) @binding(0) var<uniform> random_data: RandomData;
@@ -0,0 +1,405 @@
+use crate::{CameraManager, RenderContext, ResourceRef, ResourcesManager, UniformBuffer};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsrConfig {
+    pub max_steps: u32,
+    pub thickness: f32,
+    pub max_distance: f32,
+}
+
+impl Default for SsrConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 64,
+            thickness: 0.2,
+            max_distance: 16.0,
+        }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut SsrConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("SSR")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add(egui::Slider::new(&mut self.max_steps, 0..=256).text("Max steps"));
+                ui.add(egui::Slider::new(&mut self.thickness, 0.0..=2.0).text("Thickness"));
+                ui.add(egui::Slider::new(&mut self.max_distance, 0.0..=64.0).text("Max distance"));
+            })
+            .header_response
+    }
+}
+
+pub struct SsrPassInputs<'a> {
+    pub normal_roughness: &'a wgpu::Texture,
+    pub depth: &'a wgpu::Texture,
+    pub hierarchical_depth: &'a wgpu::Texture,
+    pub output: &'a wgpu::Texture,
+}
+
+pub struct SsrPass {
+    pub config: UniformBuffer<SsrConfig>,
+
+    camera: ResourceRef<CameraManager>,
+
+    reflections_view: wgpu::TextureView,
+    output_view: wgpu::TextureView,
+
+    sampler: wgpu::Sampler,
+
+    march_bind_group_layout: wgpu::BindGroupLayout,
+    march_bind_group: wgpu::BindGroup,
+    march_pipeline: wgpu::RenderPipeline,
+
+    blit_bind_group_layout: wgpu::BindGroupLayout,
+    blit_bind_group: wgpu::BindGroup,
+    blit_pipeline: wgpu::RenderPipeline,
+}
+
+impl SsrPass {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(device: &wgpu::Device, resources: &ResourcesManager, inputs: SsrPassInputs) -> Self {
+        let config = UniformBuffer::new(device, SsrConfig::default());
+
+        let camera = resources.get::<CameraManager>();
+
+        let reflections_view = Self::make_texture(device, &inputs).create_view(&Default::default());
+        let output_view = inputs.output.create_view(&Default::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Ssr sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let march_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Ssr[march] bind group layout"),
+                entries: &[
+                    // Sampler
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    // Normal roughness
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    // Depth
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Depth,
+                        },
+                        count: None,
+                    },
+                    // Hierarchical depth
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                    // Color
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let blit_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Ssr[blit] bind group layout"),
+                entries: &[
+                    // Reflections
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let march_bind_group =
+            Self::make_march_bind_group(device, &march_bind_group_layout, &sampler, &inputs);
+        let blit_bind_group =
+            Self::make_blit_bind_group(device, &blit_bind_group_layout, &reflections_view);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("ssr.wgsl"));
+
+        let march_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Ssr[march] pipeline layout"),
+                bind_group_layouts: &[
+                    &camera.get().bind_group_layout,
+                    &config.bind_group_layout,
+                    &march_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let march_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Ssr[march] pipeline"),
+            layout: Some(&march_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_march",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: Self::FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        });
+
+        let blit_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Ssr[blit] pipeline layout"),
+            bind_group_layouts: &[&blit_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let blit_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Ssr[blit] pipeline"),
+            layout: Some(&blit_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_blit",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: inputs.output.format(),
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        });
+
+        Self {
+            config,
+
+            camera,
+
+            reflections_view,
+            output_view,
+
+            sampler,
+
+            march_bind_group_layout,
+            march_bind_group,
+            march_pipeline,
+
+            blit_bind_group_layout,
+            blit_bind_group,
+            blit_pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: SsrPassInputs) {
+        self.reflections_view =
+            Self::make_texture(device, &inputs).create_view(&Default::default());
+        self.output_view = inputs.output.create_view(&Default::default());
+
+        self.march_bind_group = Self::make_march_bind_group(
+            device,
+            &self.march_bind_group_layout,
+            &self.sampler,
+            &inputs,
+        );
+        self.blit_bind_group = Self::make_blit_bind_group(
+            device,
+            &self.blit_bind_group_layout,
+            &self.reflections_view,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        ctx.encoder.profile_start("Ssr");
+
+        let camera = self.camera.get();
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ssr[march]"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.reflections_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.march_pipeline);
+        rpass.set_bind_group(0, &camera.bind_group, &[]);
+        rpass.set_bind_group(1, &self.config.bind_group, &[]);
+        rpass.set_bind_group(2, &self.march_bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+
+        drop(rpass);
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ssr[blit]"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.output_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.blit_pipeline);
+        rpass.set_bind_group(0, &self.blit_bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+
+        drop(rpass);
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_texture(device: &wgpu::Device, inputs: &SsrPassInputs) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Ssr reflections"),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..inputs.output.size()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[Self::FORMAT],
+        })
+    }
+
+    fn make_march_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        inputs: &SsrPassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ssr[march] bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.normal_roughness.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&inputs.depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..Default::default()
+                        },
+                    )),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.hierarchical_depth.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.output.create_view(&Default::default()),
+                    ),
+                },
+            ],
+        })
+    }
+
+    fn make_blit_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        reflections_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Ssr[blit] bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(reflections_view),
+            }],
+        })
+    }
+}
@@ -0,0 +1,138 @@
+struct Camera {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    view_proj: mat4x4<f32>,
+    inv_view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    frustum: array<vec4<f32>, 6>,
+    prev_view_proj: mat4x4<f32>,
+    jitter: vec2<f32>,
+    // 0.0 with reversed-Z, 1.0 otherwise
+    far_depth: f32,
+}
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.ndc = out.position.xy;
+    out.uv = out.ndc * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    max_steps: u32,
+    thickness: f32,
+    max_distance: f32,
+}
+@group(1) @binding(0) var<uniform> config: Config;
+
+@group(2) @binding(0) var t_sampler: sampler;
+@group(2) @binding(1) var t_normal_roughness: texture_2d<f32>;
+@group(2) @binding(2) var t_depth: texture_depth_2d;
+@group(2) @binding(3) var t_hierarchical_depth: texture_2d<f32>;
+@group(2) @binding(4) var t_color: texture_2d<f32>;
+
+fn view_position(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let p = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+    return p.xyz / p.w;
+}
+
+fn uv_to_ndc(uv: vec2<f32>) -> vec2<f32> {
+    return (uv - 0.5) * vec2<f32>(2.0, -2.0);
+}
+
+@fragment
+fn fs_march(in: VertexOutput) -> @location(0) vec4<f32> {
+    let depth_size = vec2<f32>(textureDimensions(t_depth));
+    let hierarchical_depth_size = vec2<f32>(textureDimensions(t_hierarchical_depth));
+
+    let coord = vec2<i32>(in.position.xy);
+    let depth = textureLoad(t_depth, coord, 0);
+
+    // Sky
+    if depth == camera.far_depth { return vec4<f32>(0.0); }
+    let reversed_z = camera.far_depth == 0.0;
+
+    let normal_roughness = textureLoad(t_normal_roughness, coord, 0);
+    let normal = normalize(normal_roughness.xyz);
+    let roughness = normal_roughness.w;
+
+    if roughness >= 1.0 { return vec4<f32>(0.0); }
+
+    let origin = view_position(in.ndc, depth);
+    let direction = reflect(normalize(origin), normal);
+
+    let step = config.max_distance / f32(max(config.max_steps, 1u));
+
+    for (var i = 1u; i <= config.max_steps; i++) {
+        let ray = origin + direction * step * f32(i);
+
+        let clip = camera.proj * vec4<f32>(ray, 1.0);
+        if clip.w <= 0.0 { break; }
+
+        let ray_ndc = clip.xyz / clip.w;
+        let uv = ray_ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+        // Off-screen, nothing to reflect
+        if any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) { break; }
+
+        // Ray is in front of every surface of the tile, no need for the full resolution depth
+        let tile_depth = textureLoad(
+            t_hierarchical_depth,
+            vec2<i32>(uv * hierarchical_depth_size),
+            0,
+        ).rg;
+        if reversed_z {
+            if ray_ndc.z > tile_depth.g { continue; }
+        } else {
+            if ray_ndc.z < tile_depth.r { continue; }
+        }
+
+        let scene_depth = textureLoad(t_depth, vec2<i32>(uv * depth_size), 0);
+        let scene = view_position(uv_to_ndc(uv), scene_depth);
+
+        let delta = scene.z - ray.z;
+        if delta > 0.0 && delta < config.thickness {
+            let color = textureSampleLevel(t_color, t_sampler, uv, 0.0).rgb;
+
+            let edges = 1.0 - pow(max(abs(uv.x - 0.5), abs(uv.y - 0.5)) * 2.0, 8.0);
+            let distance = 1.0 - f32(i) / f32(config.max_steps);
+            let fresnel = 1.0 - max(dot(-normalize(origin), normal), 0.0);
+
+            let strength = (1.0 - roughness) * edges * distance * fresnel;
+
+            return vec4<f32>(color, clamp(strength, 0.0, 1.0));
+        }
+    }
+
+    return vec4<f32>(0.0);
+}
+
+@group(0) @binding(0) var t_reflections: texture_2d<f32>;
+
+@fragment
+fn fs_blit(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(t_reflections, vec2<i32>(in.position.xy), 0);
+}
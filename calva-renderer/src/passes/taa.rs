@@ -0,0 +1,280 @@
+use crate::{RenderContext, UniformBuffer};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TaaConfig {
+    pub feedback: f32,
+}
+
+impl Default for TaaConfig {
+    fn default() -> Self {
+        Self { feedback: 0.9 }
+    }
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut TaaConfig {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("TAA")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.add(egui::Slider::new(&mut self.feedback, 0.0..=0.99).text("Feedback"));
+            })
+            .header_response
+    }
+}
+
+pub struct TaaPassInputs<'a> {
+    pub input: &'a wgpu::Texture,
+    pub velocity: &'a wgpu::Texture,
+    pub output: &'a wgpu::Texture,
+}
+
+pub struct TaaPass {
+    pub config: UniformBuffer<TaaConfig>,
+
+    resolved: wgpu::Texture,
+    history: wgpu::Texture,
+
+    resolved_view: wgpu::TextureView,
+    output_view: wgpu::TextureView,
+
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl TaaPass {
+    pub fn new(device: &wgpu::Device, inputs: TaaPassInputs) -> Self {
+        let config = UniformBuffer::new(device, TaaConfig::default());
+
+        let resolved = Self::make_texture(device, &inputs, "Taa resolved");
+        let history = Self::make_texture(device, &inputs, "Taa history");
+
+        let resolved_view = resolved.create_view(&Default::default());
+        let output_view = inputs.output.create_view(&Default::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Taa sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Taa bind group layout"),
+            entries: &[
+                // Sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                // Input
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                // History
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                // Velocity
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group =
+            Self::make_bind_group(device, &bind_group_layout, &sampler, &history, &inputs);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Taa pipeline layout"),
+            bind_group_layouts: &[&config.bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("taa.wgsl"));
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Taa pipeline"),
+            layout: Some(&pipeline_layout),
+            multiview: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: inputs.output.format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: resolved.format(),
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+        });
+
+        Self {
+            config,
+
+            resolved,
+            history,
+
+            resolved_view,
+            output_view,
+
+            sampler,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn rebind(&mut self, device: &wgpu::Device, inputs: TaaPassInputs) {
+        self.resolved = Self::make_texture(device, &inputs, "Taa resolved");
+        self.history = Self::make_texture(device, &inputs, "Taa history");
+
+        self.resolved_view = self.resolved.create_view(&Default::default());
+        self.output_view = inputs.output.create_view(&Default::default());
+
+        self.bind_group = Self::make_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.history,
+            &inputs,
+        );
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        self.config.update(queue);
+    }
+
+    pub fn render(&self, ctx: &mut RenderContext) {
+        ctx.encoder.profile_start("Taa");
+
+        let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Taa"),
+            color_attachments: &[&self.output_view, &self.resolved_view].map(|view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })
+            }),
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.config.bind_group, &[]);
+        rpass.set_bind_group(1, &self.bind_group, &[]);
+
+        rpass.draw(0..3, 0..1);
+
+        drop(rpass);
+
+        ctx.encoder.copy_texture_to_texture(
+            self.resolved.as_image_copy(),
+            self.history.as_image_copy(),
+            self.history.size(),
+        );
+
+        ctx.encoder.profile_end();
+    }
+
+    fn make_texture(device: &wgpu::Device, inputs: &TaaPassInputs, label: &str) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                depth_or_array_layers: 1,
+                ..inputs.output.size()
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: inputs.output.format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[inputs.output.format()],
+        })
+    }
+
+    fn make_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        history: &wgpu::Texture,
+        inputs: &TaaPassInputs,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Taa bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.input.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &history.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.velocity.create_view(&Default::default()),
+                    ),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,76 @@
+//
+// Vertex shader
+//
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let tc = vec2<f32>(
+        f32(vertex_index >> 1u),
+        f32(vertex_index & 1u),
+    ) * 2.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = out.position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
+}
+
+//
+// Fragment shader
+//
+
+struct Config {
+    feedback: f32,
+}
+@group(0) @binding(0) var<uniform> config: Config;
+
+@group(1) @binding(0) var t_sampler: sampler;
+@group(1) @binding(1) var t_input: texture_2d<f32>;
+@group(1) @binding(2) var t_history: texture_2d<f32>;
+@group(1) @binding(3) var t_velocity: texture_2d<f32>;
+
+struct FragmentOutput {
+    @location(0) output: vec4<f32>,
+    @location(1) resolved: vec4<f32>,
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let size = vec2<i32>(textureDimensions(t_input));
+    let coord = vec2<i32>(in.position.xy);
+
+    let current = textureLoad(t_input, coord, 0).rgb;
+
+    // Neighborhood clamping, history colors outside of the 3x3 box are ghosts
+    var color_min = current;
+    var color_max = current;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbor_coord = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let neighbor = textureLoad(t_input, neighbor_coord, 0).rgb;
+
+            color_min = min(color_min, neighbor);
+            color_max = max(color_max, neighbor);
+        }
+    }
+
+    let velocity = textureLoad(t_velocity, coord, 0).xy;
+    let history_uv = in.uv - velocity;
+
+    var color = current;
+    if all(history_uv >= vec2<f32>(0.0)) && all(history_uv <= vec2<f32>(1.0)) {
+        let history = textureSampleLevel(t_history, t_sampler, history_uv, 0.0).rgb;
+        color = mix(current, clamp(history, color_min, color_max), config.feedback);
+    }
+
+    return FragmentOutput(
+        vec4<f32>(color, 1.0),
+        vec4<f32>(color, 1.0),
+    );
+}
@@ -1,10 +1,67 @@
-use crate::{RenderContext, UniformBuffer};
+use crate::{FramePipelines, RenderContext, UniformBuffer, UniformData};
+
+/// Curve mapping the linear HDR input to the displayable 0..1 range
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ToneMappingOperator {
+    /// Exposure and gamma only, values above 1 are clipped
+    #[default]
+    None,
+    Reinhard,
+    /// Reinhard with a white point, see [`ToneMappingConfig::white_point`]
+    ReinhardExtended,
+    /// Stephen Hill's fit of the ACES RRT + ODT
+    Aces,
+    AgX,
+}
 
-#[repr(C)]
-#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+impl ToneMappingOperator {
+    pub const ALL: [Self; 5] = [
+        Self::None,
+        Self::Reinhard,
+        Self::ReinhardExtended,
+        Self::Aces,
+        Self::AgX,
+    ];
+}
+
+impl std::fmt::Display for ToneMappingOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The input is expected to be linear HDR radiance, exposure is applied
+/// before the operator and gamma after it.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct ToneMappingConfig {
     pub exposure: f32,
     pub gamma: f32,
+    pub operator: ToneMappingOperator,
+    /// Smallest exposed value mapped to pure white by
+    /// [`ToneMappingOperator::ReinhardExtended`]
+    pub white_point: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuToneMappingConfig {
+    exposure: f32,
+    gamma: f32,
+    operator: u32,
+    white_point: f32,
+}
+
+impl UniformData for ToneMappingConfig {
+    type GpuType = GpuToneMappingConfig;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuToneMappingConfig {
+            exposure: self.exposure,
+            gamma: self.gamma,
+            operator: self.operator as _,
+            white_point: self.white_point,
+        }
+    }
 }
 
 #[cfg(feature = "egui")]
@@ -13,8 +70,20 @@ impl egui::Widget for &mut ToneMappingConfig {
         egui::CollapsingHeader::new("Tone mapping")
             .default_open(true)
             .show(ui, |ui| {
+                egui::ComboBox::from_label("Operator")
+                    .selected_text(self.operator.to_string())
+                    .show_ui(ui, |ui| {
+                        for operator in ToneMappingOperator::ALL {
+                            ui.selectable_value(&mut self.operator, operator, operator.to_string());
+                        }
+                    });
                 ui.add(egui::Slider::new(&mut self.exposure, -10.0..=10.0).text("Exposure"));
                 ui.add(egui::Slider::new(&mut self.gamma, 0.0..=5.0).text("Gamma"));
+                if self.operator == ToneMappingOperator::ReinhardExtended {
+                    ui.add(
+                        egui::Slider::new(&mut self.white_point, 1.0..=20.0).text("White point"),
+                    );
+                }
             })
             .header_response
     }
@@ -25,13 +94,16 @@ impl Default for ToneMappingConfig {
         Self {
             exposure: 0.0,
             gamma: 1.0,
+            operator: ToneMappingOperator::default(),
+            white_point: 4.0,
         }
     }
 }
 
 pub struct ToneMappingPassInputs<'a> {
-    pub format: wgpu::TextureFormat,
     pub input: &'a wgpu::Texture,
+    /// See [`crate::AutoExposurePassOutputs::exposure`]
+    pub exposure: &'a wgpu::Buffer,
 }
 
 pub struct ToneMappingPass {
@@ -39,7 +111,7 @@ pub struct ToneMappingPass {
 
     bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
-    pipeline: wgpu::RenderPipeline,
+    pipelines: FramePipelines,
 }
 
 impl ToneMappingPass {
@@ -60,6 +132,17 @@ impl ToneMappingPass {
                     },
                     count: None,
                 },
+                // Auto exposure
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -73,27 +156,32 @@ impl ToneMappingPass {
             push_constant_ranges: &[],
         });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("ToneMapping pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: inputs.format,
-                    blend: None,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: Default::default(),
-            depth_stencil: None,
-            multisample: Default::default(),
-            multiview: None,
+        let pipelines = FramePipelines::new(move |device, format, sample_count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("ToneMapping pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
         });
 
         Self {
@@ -101,7 +189,7 @@ impl ToneMappingPass {
 
             bind_group_layout,
             bind_group,
-            pipeline,
+            pipelines,
         }
     }
 
@@ -114,6 +202,9 @@ impl ToneMappingPass {
     }
 
     pub fn render(&self, ctx: &mut RenderContext) {
+        let pipeline = self.pipelines.get(ctx);
+        let viewport = ctx.viewport;
+
         let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("ToneMapping"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
@@ -127,7 +218,11 @@ impl ToneMappingPass {
             depth_stencil_attachment: None,
         });
 
-        rpass.set_pipeline(&self.pipeline);
+        if let Some(viewport) = viewport {
+            viewport.apply(&mut rpass);
+        }
+
+        rpass.set_pipeline(&pipeline);
         rpass.set_bind_group(0, &self.config.bind_group, &[]);
         rpass.set_bind_group(1, &self.bind_group, &[]);
 
@@ -142,12 +237,18 @@ impl ToneMappingPass {
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("ToneMapping bind group"),
             layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::TextureView(
-                    &inputs.input.create_view(&Default::default()),
-                ),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &inputs.input.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: inputs.exposure.as_entire_binding(),
+                },
+            ],
         })
     }
 }
@@ -2,35 +2,135 @@
 // Vertex shader
 //
 
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
 @vertex
-fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     let tc = vec2<f32>(
         f32(vertex_index >> 1u),
         f32(vertex_index & 1u),
     ) * 2.0;
 
-    return vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    var out: VertexOutput;
+    out.position = vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = out.position.xy * vec2<f32>(0.5, -0.5) + 0.5;
+
+    return out;
 }
 
 //
 // Fragment shader
 //
 
-
 struct Config {
     exposure: f32,
     gamma: f32,
+    tone_operator: u32,
+    white_point: f32,
 }
 @group(0) @binding(0) var<uniform> config: Config;
 
+struct AutoExposure {
+    adapted: f32,
+    exposure: f32,
+}
+
 @group(1) @binding(0) var t_hdr: texture_2d<f32>;
+@group(1) @binding(1) var<uniform> auto_exposure: AutoExposure;
+
+//
+// Operators, all take exposed linear HDR and return linear 0..1
+//
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+fn reinhard_extended(color: vec3<f32>, white_point: f32) -> vec3<f32> {
+    let numerator = color * (1.0 + color / (white_point * white_point));
+    return numerator / (1.0 + color);
+}
+
+// https://github.com/TheRealMJP/BakingLab/blob/master/BakingLab/ACES.hlsl
+fn aces_fitted(color: vec3<f32>) -> vec3<f32> {
+    // sRGB => XYZ => D65_2_D60 => AP1 => RRT_SAT
+    let input_mat = mat3x3<f32>(
+        vec3<f32>(0.59719, 0.07600, 0.02840),
+        vec3<f32>(0.35458, 0.90834, 0.13383),
+        vec3<f32>(0.04823, 0.01566, 0.83777),
+    );
+    // ODT_SAT => XYZ => D60_2_D65 => sRGB
+    let output_mat = mat3x3<f32>(
+        vec3<f32>(1.60475, -0.10208, -0.00327),
+        vec3<f32>(-0.53108, 1.10813, -0.07276),
+        vec3<f32>(-0.07367, -0.00605, 1.07602),
+    );
+
+    let v = input_mat * color;
+
+    // RRT and ODT fit
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+
+    return saturate(output_mat * (a / b));
+}
+
+// https://iolite-engine.com/blog_posts/minimal_agx_implementation
+fn agx(color: vec3<f32>) -> vec3<f32> {
+    let agx_mat = mat3x3<f32>(
+        vec3<f32>(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3<f32>(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3<f32>(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+    let agx_mat_inv = mat3x3<f32>(
+        vec3<f32>(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3<f32>(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3<f32>(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    // Log2 encoding, clamped so black doesn't end up as -inf
+    var v = agx_mat * color;
+    v = clamp(log2(max(v, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+
+    // Default contrast, 6th order polynomial approximation
+    let x2 = v * v;
+    let x4 = x2 * x2;
+    v = 15.5 * x4 * x2
+        - 40.14 * x4 * v
+        + 31.96 * x4
+        - 6.868 * x2 * v
+        + 0.4298 * x2
+        + 0.1191 * v
+        - 0.00232;
+
+    // Back to linear
+    v = agx_mat_inv * v;
+    return pow(saturate(v), vec3<f32>(2.2));
+}
 
 @fragment
-fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
-    let hdr = textureLoad(t_hdr, vec2<i32>(position.xy), 0).rgb;
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Relative to the viewport, which covers the whole input
+    let coord = vec2<i32>(in.uv * vec2<f32>(textureDimensions(t_hdr)));
+    let hdr = textureLoad(t_hdr, coord, 0).rgb;
 
     // https://docs.blender.org/manual/en/3.4/render/color_management.html?highlight=exposure
-    let color = hdr * exp2(config.exposure);
+    // Manual exposure acts as a compensation when auto exposure is enabled
+    var color = hdr * exp2(config.exposure + auto_exposure.exposure);
+
+    switch config.tone_operator {
+        case 1u: { color = reinhard(color); }
+        case 2u: { color = reinhard_extended(color, config.white_point); }
+        case 3u: { color = aces_fitted(color); }
+        case 4u: { color = agx(color); }
+        default: {}
+    }
 
     // Gamma correction
     return vec4<f32>(
@@ -1,12 +1,140 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::{anyhow, Result};
+use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard};
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 #[cfg(feature = "profiler")]
 use wgpu_profiler::{GpuProfiler, GpuTimerScopeResult};
 
+pub enum RenderTarget {
+    Surface(wgpu::Surface),
+    /// Offscreen rendering, see [`Renderer::new_headless`]
+    Texture(wgpu::Texture),
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// The frame could not be acquired, nothing was rendered. The surface
+    /// needs a [`Renderer::reconfigure`] when `Lost` or `Outdated`.
+    Surface(wgpu::SurfaceError),
+    /// The frame was acquired but could not be recorded or submitted
+    Submit(anyhow::Error),
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(error) => write!(f, "Cannot acquire frame: {error}"),
+            Self::Submit(error) => write!(f, "Cannot submit frame: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Surface(error) => Some(error),
+            Self::Submit(error) => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<wgpu::SurfaceError> for RenderError {
+    fn from(error: wgpu::SurfaceError) -> Self {
+        Self::Surface(error)
+    }
+}
+
+/// Returned by the [`Renderer`] constructors when the adapter can't run the
+/// engine, downcast the `anyhow::Error` to get it back
+#[derive(Debug)]
+pub struct UnsupportedAdapterError {
+    pub adapter_info: wgpu::AdapterInfo,
+    pub missing_features: wgpu::Features,
+    /// Name, required and supported values of the limits out of range
+    pub missing_limits: Vec<(&'static str, u64, u64)>,
+}
+
+impl std::fmt::Display for UnsupportedAdapterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let wgpu::AdapterInfo { name, backend, .. } = &self.adapter_info;
+        write!(f, "Adapter {name} ({backend:?}) is not supported")?;
+
+        if !self.missing_features.is_empty() {
+            write!(f, ", missing features: {:?}", self.missing_features)?;
+        }
+
+        for (i, (limit, required, supported)) in self.missing_limits.iter().enumerate() {
+            let separator = if i == 0 {
+                ", limits out of range: "
+            } else {
+                ", "
+            };
+            write!(
+                f,
+                "{separator}{limit} (required {required}, supported {supported})"
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnsupportedAdapterError {}
+
+/// What the device was created with, to pick pass implementations at
+/// runtime, see [`Renderer::capabilities`]
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub adapter_info: wgpu::AdapterInfo,
+    /// Required features and the supported optional ones
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+    pub downlevel: wgpu::DownlevelCapabilities,
+}
+
+impl Capabilities {
+    /// Culled draws are batched in a single call, otherwise they are issued
+    /// one by one
+    pub fn multi_draw_indirect(&self) -> bool {
+        self.features.contains(wgpu::Features::MULTI_DRAW_INDIRECT)
+    }
+
+    /// The draw count is read by the GPU, otherwise culled draws are still
+    /// issued with no instance
+    pub fn multi_draw_indirect_count(&self) -> bool {
+        self.features
+            .contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT)
+    }
+
+    /// See [`crate::Engine::wireframe_enabled`]
+    pub fn wireframe(&self) -> bool {
+        self.features.contains(wgpu::Features::POLYGON_MODE_LINE)
+    }
+
+    /// See [`crate::DepthConfig::reversed_z`]
+    pub fn depth32float_stencil8(&self) -> bool {
+        self.features
+            .contains(wgpu::Features::DEPTH32FLOAT_STENCIL8)
+    }
+
+    /// Profiler scopes are timed on the GPU, otherwise on the CPU
+    pub fn gpu_timestamps(&self) -> bool {
+        self.features.contains(wgpu::Features::TIMESTAMP_QUERY)
+    }
+
+    /// See [`Renderer::anisotropy_supported`]
+    pub fn anisotropic_filtering(&self) -> bool {
+        self.downlevel
+            .flags
+            .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING)
+    }
+}
+
 pub struct Renderer {
-    pub surface: wgpu::Surface,
+    pub target: RenderTarget,
+    /// Size and format of the render target, headless renderers are never
+    /// presented
     pub surface_config: wgpu::SurfaceConfiguration,
 
     pub adapter: wgpu::Adapter,
@@ -15,21 +143,33 @@ pub struct Renderer {
     pub device: Arc<wgpu::Device>,
     pub queue: wgpu::Queue,
 
+    screenshots: Screenshots,
+    msaa: Msaa,
+
     #[cfg(feature = "profiler")]
     pub profiler: std::cell::RefCell<RendererProfiler>,
 }
 
 impl Renderer {
+    /// Browsers' WebGPU exposes no binding arrays, which keeps the renderer
+    /// off the web backend
     const FEATURES: wgpu::Features = wgpu::Features::empty()
         .union(wgpu::Features::DEPTH_CLIP_CONTROL) // all platforms
-        .union(wgpu::Features::MULTI_DRAW_INDIRECT) // Vulkan, DX12, Metal
-        .union(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) // Vulkan, DX12
-        .union(wgpu::Features::INDIRECT_FIRST_INSTANCE) // Vulkan, DX12, Metal
+        .union(wgpu::Features::INDIRECT_FIRST_INSTANCE) // Vulkan, DX12, Metal, WebGPU
         .union(wgpu::Features::TEXTURE_BINDING_ARRAY) // Vulkan, DX12, Metal
         .union(wgpu::Features::STORAGE_RESOURCE_BINDING_ARRAY) // Vulkan, Metal
         .union(wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING) // Vulkan, DX12, Metal
-        .union(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) // All except WebGL
+        .union(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES); // All except WebGL
+
+    /// Requested only when supported by the adapter
+    const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::empty()
+        .union(wgpu::Features::MULTI_DRAW_INDIRECT) // Vulkan, DX12, Metal
+        .union(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) // Vulkan, DX12
         .union(wgpu::Features::POLYGON_MODE_LINE) // Vulkan, DX12, Metal
+        .union(wgpu::Features::DEPTH32FLOAT_STENCIL8) // Most, see DepthConfig::reversed_z
+        .union(wgpu::Features::TEXTURE_COMPRESSION_BC) // Desktop
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2) // Mobile
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC) // Mobile
         .union(
             #[cfg(feature = "profiler")]
             GpuProfiler::ALL_WGPU_TIMER_FEATURES, // Vulkan, DX12
@@ -41,37 +181,10 @@ impl Renderer {
     where
         W: HasRawWindowHandle + HasRawDisplayHandle,
     {
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN,
-            ..Default::default()
-        });
+        let instance = Self::make_instance();
         let surface = unsafe { instance.create_surface(window) }?;
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .ok_or_else(|| anyhow!("Cannot request WebGPU adapter"))?;
-
-        let adapter_info = adapter.get_info();
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: Some("Renderer device"),
-                    features: Self::FEATURES,
-                    limits: wgpu::Limits {
-                        max_sampled_textures_per_shader_stage: 512,
-                        max_push_constant_size: 128,
-                        max_bind_groups: 6,
-                        ..Default::default()
-                    },
-                },
-                None,
-            )
-            .await?;
+        let adapter = Self::request_adapter(&instance, Some(&surface)).await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
 
         let mut surface_config = surface
             .get_default_config(&adapter, size.0, size.1)
@@ -82,27 +195,41 @@ impl Renderer {
 
         surface.configure(&device, &surface_config);
 
-        #[cfg(feature = "profiler")]
-        let profiler = {
-            let mut profiler = GpuProfiler::new(4, queue.get_timestamp_period(), device.features());
-            profiler.enable_debug_marker = false;
-            std::cell::RefCell::new(RendererProfiler {
-                inner: profiler,
-                results: vec![],
-            })
+        Ok(Self::from_parts(
+            RenderTarget::Surface(surface),
+            surface_config,
+            adapter,
+            device,
+            queue,
+        ))
+    }
+
+    /// Renderer without a window, frames are rendered to an offscreen texture
+    /// which can be read back with [`Renderer::capture`]
+    pub async fn new_headless(size: (u32, u32)) -> Result<Self> {
+        let instance = Self::make_instance();
+        let adapter = Self::request_adapter(&instance, None).await?;
+        let (device, queue) = Self::request_device(&adapter).await?;
+
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: size.0,
+            height: size.1,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
         };
 
-        Ok(Self {
+        let texture = Self::make_headless_texture(&device, &surface_config);
+
+        Ok(Self::from_parts(
+            RenderTarget::Texture(texture),
+            surface_config,
             adapter,
-            adapter_info,
-            device: Arc::new(device),
+            device,
             queue,
-            surface,
-            surface_config,
-
-            #[cfg(feature = "profiler")]
-            profiler,
-        })
+        ))
     }
 
     // pub fn size(&self) -> (u32, u32) {
@@ -116,24 +243,100 @@ impl Renderer {
 
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        self.msaa.resize((width, height));
+
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.surface_config),
+            RenderTarget::Texture(texture) => {
+                *texture = Self::make_headless_texture(&self.device, &self.surface_config)
+            }
+        }
+    }
+
+    /// Samples per pixel of the overlays, see [`RenderContext::overlay`]
+    pub fn sample_count(&self) -> u32 {
+        self.msaa.sample_count
+    }
+
+    /// Anti-aliases the overlays with `sample_count` samples per pixel, 1 to
+    /// draw them directly to the frame. Fails when the frame or the `depth`
+    /// formats don't support that many samples, `depth` being the gbuffer
+    /// depth the overlays are tested against.
+    ///
+    /// Passes drawing overlays build their pipelines for the sample count of
+    /// [`RenderContext::sample_count`], egui has to be recreated for it.
+    pub fn set_sample_count(&mut self, sample_count: u32, depth: crate::DepthConfig) -> Result<()> {
+        let supported = [self.surface_config.format, depth.format]
+            .into_iter()
+            .all(|format| {
+                self.adapter
+                    .get_texture_format_features(format)
+                    .flags
+                    .sample_count_supported(sample_count)
+            });
+
+        if !supported {
+            return Err(anyhow!(
+                "{sample_count} samples per pixel are not supported"
+            ));
+        }
+
+        self.msaa.set_sample_count(sample_count);
+        Ok(())
+    }
+
+    /// Recreates the surface swapchain with the current configuration, e.g.
+    /// after a [`wgpu::SurfaceError::Lost`]. Headless renderers are unaffected.
+    pub fn reconfigure(&self) {
+        if let RenderTarget::Surface(surface) = &self.target {
+            surface.configure(&self.device, &self.surface_config);
+        }
     }
 
-    pub fn render(&self, cb: impl FnOnce(&mut RenderContext)) -> Result<()> {
+    pub fn render(
+        &self,
+        cb: impl FnOnce(&mut RenderContext),
+    ) -> std::result::Result<(), RenderError> {
         let mut encoder = self.device.create_command_encoder(&Default::default());
 
-        let frame = self.surface.get_current_texture()?;
-        let frame_view = frame.texture.create_view(&Default::default());
+        let (frame, frame_view) = match &self.target {
+            RenderTarget::Surface(surface) => {
+                let frame = surface.get_current_texture()?;
+                let frame_view = frame.texture.create_view(&Default::default());
+                (Some(frame), frame_view)
+            }
+            RenderTarget::Texture(texture) => (None, texture.create_view(&Default::default())),
+        };
+
+        let screenshot_requests = self.screenshots.take_requests();
+
+        // Swapchain images can't be copied from, frames are rendered offscreen
+        // and then blitted to the swapchain when a screenshot is requested
+        let offscreen = match (&self.target, screenshot_requests.is_empty()) {
+            (RenderTarget::Surface(_), false) => Some(Screenshots::make_offscreen(
+                &self.device,
+                &self.surface_config,
+            )),
+            _ => None,
+        };
+        let offscreen_view = offscreen
+            .as_ref()
+            .map(|texture| texture.create_view(&Default::default()));
 
         #[cfg(feature = "profiler")]
-        let mut renderer_profiler = self.profiler.try_borrow_mut()?;
+        let mut renderer_profiler = self
+            .profiler
+            .try_borrow_mut()
+            .map_err(|error| RenderError::Submit(error.into()))?;
         #[cfg(feature = "profiler")]
-        let profiler = &mut renderer_profiler.inner;
+        let profiler = &mut *renderer_profiler;
 
         #[cfg(feature = "profiler")]
         profiler.begin_scope("RenderFrame", &mut encoder, &self.device);
 
         let mut context = RenderContext {
+            device: &self.device,
+            queue: &self.queue,
             encoder: ProfilerCommandEncoder {
                 encoder: &mut encoder,
 
@@ -142,11 +345,29 @@ impl Renderer {
                 #[cfg(feature = "profiler")]
                 profiler,
             },
-            frame: &frame_view,
+            frame: offscreen_view.as_ref().unwrap_or(&frame_view),
+            format: self.surface_config.format,
+            size: (self.surface_config.width, self.surface_config.height),
+            sample_count: 1,
+            depth: None,
+            viewport: None,
+            msaa: Some(&self.msaa),
         };
 
         cb(&mut context);
 
+        let readback = match (&self.target, &offscreen) {
+            _ if screenshot_requests.is_empty() => None,
+            (RenderTarget::Texture(texture), _) | (_, Some(texture)) => {
+                Some(Readback::new(&self.device, &mut encoder, texture))
+            }
+            _ => None,
+        };
+        if let Some(offscreen_view) = &offscreen_view {
+            self.screenshots
+                .blit(&self.device, &mut encoder, offscreen_view, &frame_view);
+        }
+
         #[cfg(feature = "profiler")]
         {
             profiler.end_scope(&mut encoder);
@@ -154,18 +375,804 @@ impl Renderer {
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
+        if let Some(frame) = frame {
+            frame.present();
+        }
+
+        if let Some(readback) = readback {
+            readback.send(screenshot_requests);
+        }
 
         #[cfg(feature = "profiler")]
-        {
-            profiler.end_frame().unwrap();
+        renderer_profiler.end_frame();
 
-            if let Some(results) = profiler.process_finished_frame() {
-                renderer_profiler.results = results
+        Ok(())
+    }
+
+    /// Captures the next rendered frame, the image is sent once the GPU is
+    /// done with it, without blocking the render loop.
+    ///
+    /// The frame is captured as presented, after everything drawn by the
+    /// [`Renderer::render`] callback, including UI overlays like egui.
+    pub fn request_screenshot(&self) -> std::sync::mpsc::Receiver<image::RgbaImage> {
+        self.screenshots.request()
+    }
+
+    /// Reads back the last frame rendered by a headless renderer
+    pub fn capture(&self) -> Result<image::RgbaImage> {
+        let RenderTarget::Texture(texture) = &self.target else {
+            return Err(anyhow!("Only headless renderers can be captured"));
+        };
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        let readback = Readback::new(&self.device, &mut encoder, texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        readback.send(vec![sender]);
+        self.device.poll(wgpu::Maintain::Wait);
+
+        Ok(receiver.recv()?)
+    }
+
+    /// Owned copy of the GPU timings, see [`RendererProfiler::results`]
+    #[cfg(feature = "profiler")]
+    pub fn profiler_results(&self) -> Vec<ProfilerResult> {
+        self.profiler.borrow().results.clone()
+    }
+
+    /// Features and limits of the device, the optional features are only
+    /// enabled when the adapter supports them
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            adapter_info: self.adapter_info.clone(),
+            features: self.device.features(),
+            limits: self.device.limits(),
+            downlevel: self.adapter.get_downlevel_capabilities(),
+        }
+    }
+
+    /// Whether samplers honor [`wgpu::SamplerDescriptor::anisotropy_clamp`],
+    /// see [`crate::TexturesManager::set_anisotropy`]
+    pub fn anisotropy_supported(&self) -> bool {
+        self.adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING)
+    }
+
+    /// Runs `f` with validation errors captured instead of panicking, for
+    /// setups which may fail like shader compilation or pipeline creation.
+    /// Returns the first error raised by `f`, the objects it created are
+    /// invalid then.
+    pub async fn with_error_capture<R>(&self, f: impl FnOnce() -> R) -> Result<R, wgpu::Error> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = f();
+
+        match self.device.pop_error_scope().await {
+            Some(error) => Err(error),
+            None => Ok(result),
+        }
+    }
+
+    fn make_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::VULKAN,
+            ..Default::default()
+        })
+    }
+
+    async fn request_adapter(
+        instance: &wgpu::Instance,
+        compatible_surface: Option<&wgpu::Surface>,
+    ) -> Result<wgpu::Adapter> {
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface,
+            })
+            .await
+            .ok_or_else(|| anyhow!("Cannot request WebGPU adapter"))
+    }
+
+    fn limits() -> wgpu::Limits {
+        wgpu::Limits {
+            max_sampled_textures_per_shader_stage: 512,
+            max_push_constant_size: 128,
+            max_bind_groups: 6,
+            max_vertex_attributes: 20,
+            max_storage_buffer_binding_size: 256 << 20,
+            ..Default::default()
+        }
+    }
+
+    /// Errors with everything the adapter lacks at once, instead of the first
+    /// device creation failure
+    fn check_adapter(adapter: &wgpu::Adapter) -> Result<()> {
+        let missing_features = Self::FEATURES - adapter.features();
+
+        let mut missing_limits = vec![];
+        Self::limits().check_limits_with_fail_fn(
+            &adapter.limits(),
+            false,
+            |limit, required, supported| missing_limits.push((limit, required, supported)),
+        );
+
+        if missing_features.is_empty() && missing_limits.is_empty() {
+            return Ok(());
+        }
+
+        Err(UnsupportedAdapterError {
+            adapter_info: adapter.get_info(),
+            missing_features,
+            missing_limits,
+        }
+        .into())
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue)> {
+        Self::check_adapter(adapter)?;
+
+        Ok(adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("Renderer device"),
+                    features: Self::FEATURES | (Self::OPTIONAL_FEATURES & adapter.features()),
+                    limits: Self::limits(),
+                },
+                None,
+            )
+            .await?)
+    }
+
+    fn from_parts(
+        target: RenderTarget,
+        surface_config: wgpu::SurfaceConfiguration,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+    ) -> Self {
+        #[cfg(feature = "profiler")]
+        let profiler = {
+            let mut profiler = GpuProfiler::new(4, queue.get_timestamp_period(), device.features());
+            profiler.enable_debug_marker = false;
+
+            let timestamps = device.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+
+            std::cell::RefCell::new(RendererProfiler {
+                inner: profiler,
+                cpu: (!timestamps).then(CpuProfiler::default),
+                results: vec![],
+            })
+        };
+
+        let screenshots = Screenshots::new(&device, surface_config.format);
+        let msaa = Msaa::new(&device, (surface_config.width, surface_config.height));
+
+        Self {
+            target,
+            surface_config,
+
+            adapter_info: adapter.get_info(),
+            adapter,
+            device: Arc::new(device),
+            queue,
+
+            screenshots,
+            msaa,
+
+            #[cfg(feature = "profiler")]
+            profiler,
+        }
+    }
+
+    fn make_headless_texture(
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Renderer headless target"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: surface_config.usage,
+            view_formats: &[],
+        })
+    }
+}
+
+use screenshot::*;
+mod screenshot {
+    use std::{
+        cell::RefCell,
+        sync::{mpsc, Arc},
+    };
+
+    pub struct Screenshots {
+        requests: RefCell<Vec<mpsc::Sender<image::RgbaImage>>>,
+
+        bind_group_layout: wgpu::BindGroupLayout,
+        pipeline: wgpu::RenderPipeline,
+    }
+
+    impl Screenshots {
+        pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+            let bind_group_layout =
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Screenshot bind group layout"),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    }],
+                });
+
+            let shader = device.create_shader_module(wgpu::include_wgsl!("screenshot.wgsl"));
+
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Screenshot pipeline layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Screenshot blit pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None,
+            });
+
+            Self {
+                requests: Default::default(),
+
+                bind_group_layout,
+                pipeline,
             }
         }
 
-        Ok(())
+        pub fn request(&self) -> mpsc::Receiver<image::RgbaImage> {
+            let (sender, receiver) = mpsc::channel();
+            self.requests.borrow_mut().push(sender);
+            receiver
+        }
+
+        pub fn take_requests(&self) -> Vec<mpsc::Sender<image::RgbaImage>> {
+            std::mem::take(&mut self.requests.borrow_mut())
+        }
+
+        pub fn make_offscreen(
+            device: &wgpu::Device,
+            surface_config: &wgpu::SurfaceConfiguration,
+        ) -> wgpu::Texture {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Screenshot offscreen frame"),
+                size: wgpu::Extent3d {
+                    width: surface_config.width,
+                    height: surface_config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: surface_config.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        }
+
+        pub fn blit(
+            &self,
+            device: &wgpu::Device,
+            encoder: &mut wgpu::CommandEncoder,
+            input: &wgpu::TextureView,
+            output: &wgpu::TextureView,
+        ) {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Screenshot bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input),
+                }],
+            });
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Screenshot blit"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+
+    /// Copy of a frame to a mappable buffer
+    pub struct Readback {
+        buffer: Arc<wgpu::Buffer>,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        padded_bytes_per_row: u32,
+    }
+
+    impl Readback {
+        const BYTES_PER_PIXEL: u32 = std::mem::size_of::<[u8; 4]>() as _;
+
+        pub fn new(
+            device: &wgpu::Device,
+            encoder: &mut wgpu::CommandEncoder,
+            texture: &wgpu::Texture,
+        ) -> Self {
+            let (width, height) = (texture.width(), texture.height());
+
+            // Rows of texture copies must be aligned to 256 bytes
+            let padded_bytes_per_row = wgpu::util::align_to(
+                width * Self::BYTES_PER_PIXEL,
+                wgpu::COPY_BYTES_PER_ROW_ALIGNMENT,
+            );
+
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Screenshot readback"),
+                size: (padded_bytes_per_row * height) as _,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            encoder.copy_texture_to_buffer(
+                texture.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(padded_bytes_per_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                texture.size(),
+            );
+
+            Self {
+                buffer: Arc::new(buffer),
+                format: texture.format(),
+                width,
+                height,
+                padded_bytes_per_row,
+            }
+        }
+
+        /// Maps the buffer once the copy is done and sends the image, senders
+        /// are dropped without an image if the frame format is not supported
+        pub fn send(self, senders: Vec<mpsc::Sender<image::RgbaImage>>) {
+            let buffer = self.buffer.clone();
+            buffer.slice(..).map_async(wgpu::MapMode::Read, move |res| {
+                if res.is_err() {
+                    return;
+                }
+
+                let Some(image) = self.to_image() else {
+                    return;
+                };
+
+                for sender in senders {
+                    sender.send(image.clone()).ok();
+                }
+            });
+        }
+
+        fn to_image(&self) -> Option<image::RgbaImage> {
+            let bgra = match self.format {
+                wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+                _ => return None,
+            };
+
+            let bytes_per_row = (self.width * Self::BYTES_PER_PIXEL) as usize;
+
+            let mut pixels = self
+                .buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks(self.padded_bytes_per_row as _)
+                .flat_map(|row| &row[..bytes_per_row])
+                .copied()
+                .collect::<Vec<_>>();
+            self.buffer.unmap();
+
+            if bgra {
+                for pixel in pixels.chunks_exact_mut(Self::BYTES_PER_PIXEL as _) {
+                    pixel.swap(0, 2);
+                }
+            }
+
+            image::RgbaImage::from_raw(self.width, self.height, pixels)
+        }
+    }
+}
+
+use msaa::*;
+mod msaa {
+    use std::{collections::HashMap, sync::Arc};
+
+    use parking_lot::Mutex;
+
+    use crate::{FramePipelines, RenderContext};
+
+    /// Multisampled color and depth targets overlays are drawn to
+    pub struct MsaaTargets {
+        key: (u32, u32, wgpu::TextureFormat, Option<wgpu::TextureFormat>),
+
+        pub color_view: wgpu::TextureView,
+        pub resolve_view: wgpu::TextureView,
+        pub depth_view: Option<wgpu::TextureView>,
+    }
+
+    /// See [`RenderContext::overlay`]
+    pub struct Msaa {
+        pub sample_count: u32,
+        pub size: (u32, u32),
+
+        targets: Mutex<Option<Arc<MsaaTargets>>>,
+
+        shader: Arc<wgpu::ShaderModule>,
+        color_bind_group_layout: Arc<wgpu::BindGroupLayout>,
+        depth_bind_group_layout: wgpu::BindGroupLayout,
+
+        composite_pipelines: FramePipelines,
+        /// Indexed by depth format
+        depth_pipelines: Mutex<HashMap<wgpu::TextureFormat, Arc<wgpu::RenderPipeline>>>,
+    }
+
+    impl Msaa {
+        pub fn new(device: &wgpu::Device, size: (u32, u32)) -> Self {
+            let shader = Arc::new(device.create_shader_module(wgpu::include_wgsl!("msaa.wgsl")));
+
+            let make_bind_group_layout = |label, sample_type| {
+                device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some(label),
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type,
+                        },
+                        count: None,
+                    }],
+                })
+            };
+
+            let color_bind_group_layout = Arc::new(make_bind_group_layout(
+                "Msaa composite bind group layout",
+                wgpu::TextureSampleType::Float { filterable: false },
+            ));
+            let depth_bind_group_layout = make_bind_group_layout(
+                "Msaa depth bind group layout",
+                wgpu::TextureSampleType::Depth,
+            );
+
+            let composite_pipelines = {
+                let shader = shader.clone();
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Msaa composite pipeline layout"),
+                        bind_group_layouts: &[&color_bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+
+                FramePipelines::new(move |device, format, sample_count| {
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Msaa composite pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_composite",
+                            targets: &[Some(wgpu::ColorTargetState {
+                                format,
+                                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                                write_mask: wgpu::ColorWrites::ALL,
+                            })],
+                        }),
+                        primitive: Default::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState {
+                            count: sample_count,
+                            ..Default::default()
+                        },
+                        multiview: None,
+                    })
+                })
+            };
+
+            Self {
+                sample_count: 1,
+                size,
+
+                targets: Default::default(),
+
+                shader,
+                color_bind_group_layout,
+                depth_bind_group_layout,
+
+                composite_pipelines,
+                depth_pipelines: Default::default(),
+            }
+        }
+
+        pub fn set_sample_count(&mut self, sample_count: u32) {
+            self.sample_count = sample_count;
+            *self.targets.get_mut() = None;
+            self.depth_pipelines.get_mut().clear();
+        }
+
+        pub fn resize(&mut self, size: (u32, u32)) {
+            self.size = size;
+            *self.targets.get_mut() = None;
+        }
+
+        /// Targets for a frame of `format`, recreated when the format or the
+        /// depth format changes
+        pub fn targets(
+            &self,
+            device: &wgpu::Device,
+            format: wgpu::TextureFormat,
+            depth_format: Option<wgpu::TextureFormat>,
+        ) -> Arc<MsaaTargets> {
+            let key = (self.size.0, self.size.1, format, depth_format);
+            let mut targets = self.targets.lock();
+
+            match &*targets {
+                Some(targets) if targets.key == key => targets.clone(),
+                _ => targets
+                    .insert(Arc::new(self.make_targets(device, key)))
+                    .clone(),
+            }
+        }
+
+        /// Clears the color target and fills the depth target with `depth`
+        pub fn begin(
+            &self,
+            device: &wgpu::Device,
+            encoder: &mut wgpu::CommandEncoder,
+            targets: &MsaaTargets,
+            depth: Option<&wgpu::Texture>,
+        ) {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Msaa clear"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            let (Some(depth), Some(depth_view)) = (depth, &targets.depth_view) else {
+                return;
+            };
+
+            let pipeline = self.depth_pipeline(device, depth.format());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Msaa depth bind group"),
+                layout: &self.depth_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&depth.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            aspect: wgpu::TextureAspect::DepthOnly,
+                            ..Default::default()
+                        },
+                    )),
+                }],
+            });
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Msaa depth"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        /// Resolves the color target and blends it over the frame of `ctx`
+        pub fn end(&self, ctx: &mut RenderContext, targets: &MsaaTargets) {
+            ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Msaa resolve"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.color_view,
+                    resolve_target: Some(&targets.resolve_view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: false,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            let pipeline = self.composite_pipelines.get(ctx);
+            let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Msaa composite bind group"),
+                layout: &self.color_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&targets.resolve_view),
+                }],
+            });
+
+            let mut rpass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Msaa composite"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: ctx.frame,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        fn depth_pipeline(
+            &self,
+            device: &wgpu::Device,
+            format: wgpu::TextureFormat,
+        ) -> Arc<wgpu::RenderPipeline> {
+            let mut pipelines = self.depth_pipelines.lock();
+            let pipeline = pipelines.entry(format).or_insert_with(|| {
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some("Msaa depth pipeline layout"),
+                        bind_group_layouts: &[&self.depth_bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+
+                Arc::new(
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("Msaa depth pipeline"),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &self.shader,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &self.shader,
+                            entry_point: "fs_depth",
+                            targets: &[],
+                        }),
+                        primitive: Default::default(),
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                            format,
+                            depth_write_enabled: true,
+                            depth_compare: wgpu::CompareFunction::Always,
+                            stencil: Default::default(),
+                            bias: Default::default(),
+                        }),
+                        multisample: wgpu::MultisampleState {
+                            count: self.sample_count,
+                            ..Default::default()
+                        },
+                        multiview: None,
+                    }),
+                )
+            });
+
+            pipeline.clone()
+        }
+
+        fn make_targets(
+            &self,
+            device: &wgpu::Device,
+            key: (u32, u32, wgpu::TextureFormat, Option<wgpu::TextureFormat>),
+        ) -> MsaaTargets {
+            let (width, height, format, depth_format) = key;
+
+            let make_view = |label, format, sample_count, usage| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some(label),
+                        size: wgpu::Extent3d {
+                            width,
+                            height,
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count,
+                        dimension: wgpu::TextureDimension::D2,
+                        format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | usage,
+                        view_formats: &[],
+                    })
+                    .create_view(&Default::default())
+            };
+
+            MsaaTargets {
+                key,
+
+                color_view: make_view(
+                    "Msaa color",
+                    format,
+                    self.sample_count,
+                    wgpu::TextureUsages::empty(),
+                ),
+                resolve_view: make_view(
+                    "Msaa resolve",
+                    format,
+                    1,
+                    wgpu::TextureUsages::TEXTURE_BINDING,
+                ),
+                depth_view: depth_format.map(|depth_format| {
+                    make_view(
+                        "Msaa depth",
+                        depth_format,
+                        self.sample_count,
+                        wgpu::TextureUsages::empty(),
+                    )
+                }),
+            }
+        }
     }
 }
 
@@ -175,12 +1182,16 @@ impl egui::Widget for &Renderer {
         egui::CollapsingHeader::new("Adapter")
             .default_open(true)
             .show(ui, |ui| {
+                let capabilities = self.capabilities();
                 let wgpu::AdapterInfo {
                     name,
+                    backend,
                     driver,
                     driver_info,
                     ..
-                } = &self.adapter_info;
+                } = &capabilities.adapter_info;
+
+                let yes_no = |supported: bool| if supported { "Yes" } else { "No" };
 
                 egui::Grid::new("EguiPass::AdapterInfo")
                     .num_columns(2)
@@ -193,27 +1204,417 @@ impl egui::Widget for &Renderer {
 
                         ui.label("Driver");
                         ui.label(format!("{driver} ({driver_info})"));
+
+                        ui.end_row();
+
+                        ui.label("Backend");
+                        ui.label(format!("{backend:?}"));
+
+                        ui.end_row();
+
+                        ui.label("Multi draw indirect");
+                        ui.label(yes_no(capabilities.multi_draw_indirect()));
+
+                        ui.end_row();
+
+                        ui.label("GPU timestamps");
+                        ui.label(yes_no(capabilities.gpu_timestamps()));
                     });
             })
             .header_response
     }
 }
 
+/// Region of the frame, in pixels
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    pub fn apply(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.set_viewport(
+            self.x as _,
+            self.y as _,
+            self.width as _,
+            self.height as _,
+            0.0,
+            1.0,
+        );
+        rpass.set_scissor_rect(self.x, self.y, self.width, self.height);
+    }
+}
+
 pub struct RenderContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
     pub encoder: ProfilerCommandEncoder<'a>,
     pub frame: &'a wgpu::TextureView,
+    /// Format of `frame`, passes drawing to it build their pipelines for it
+    pub format: wgpu::TextureFormat,
+    /// Size of `frame`
+    pub size: (u32, u32),
+    /// Samples per pixel of `frame`, only above 1 within
+    /// [`RenderContext::overlay`]
+    pub sample_count: u32,
+    /// Multisampled copy of the gbuffer depth within
+    /// [`RenderContext::overlay`], depth tested overlays draw with it in place
+    /// of the gbuffer depth when set
+    pub depth: Option<&'a wgpu::TextureView>,
+    /// Region of `frame` passes drawing to it are constrained to, the whole
+    /// frame when `None`
+    pub viewport: Option<Viewport>,
+
+    msaa: Option<&'a Msaa>,
+}
+
+impl<'a> RenderContext<'a> {
+    pub fn set_viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.viewport = Some(Viewport {
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+
+    /// Submits the commands recorded so far, buffers written to the queue
+    /// after this call are only seen by the commands recorded afterward
+    pub fn submit(&mut self) {
+        let encoder = std::mem::replace(
+            self.encoder.encoder,
+            self.device.create_command_encoder(&Default::default()),
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Same context drawing to another color target of `size`
+    pub fn with_frame<'b>(
+        &'b mut self,
+        frame: &'b wgpu::TextureView,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> RenderContext<'b> {
+        RenderContext {
+            device: self.device,
+            queue: self.queue,
+            encoder: ProfilerCommandEncoder {
+                encoder: &mut *self.encoder.encoder,
+
+                #[cfg(feature = "profiler")]
+                device: self.encoder.device,
+                #[cfg(feature = "profiler")]
+                profiler: &mut *self.encoder.profiler,
+            },
+            frame,
+            format,
+            size,
+            sample_count: 1,
+            depth: None,
+            viewport: self.viewport,
+            msaa: self.msaa,
+        }
+    }
+
+    /// Draws the overlays of `f` anti-aliased with the
+    /// [`Renderer::sample_count`], e.g. debug lines or egui.
+    ///
+    /// Overlays are drawn to a multisampled target cleared to transparent,
+    /// which is then resolved and blended over the frame. The gbuffer itself
+    /// stays single sampled, only the overlays edges are smoothed: `depth` is
+    /// the gbuffer depth copied to each sample for depth tested overlays,
+    /// exposed as [`RenderContext::depth`].
+    pub fn overlay(&mut self, depth: Option<&wgpu::Texture>, f: impl FnOnce(&mut RenderContext)) {
+        let Some(msaa) = self.msaa.filter(|msaa| msaa.sample_count > 1) else {
+            return f(self);
+        };
+
+        self.encoder.profile_start("Overlay");
+
+        let targets = msaa.targets(self.device, self.format, depth.map(|depth| depth.format()));
+        msaa.begin(self.device, self.encoder.encoder, &targets, depth);
+
+        f(&mut RenderContext {
+            device: self.device,
+            queue: self.queue,
+            encoder: ProfilerCommandEncoder {
+                encoder: &mut *self.encoder.encoder,
+
+                #[cfg(feature = "profiler")]
+                device: self.encoder.device,
+                #[cfg(feature = "profiler")]
+                profiler: &mut *self.encoder.profiler,
+            },
+            frame: &targets.color_view,
+            format: self.format,
+            size: msaa.size,
+            sample_count: msaa.sample_count,
+            depth: targets.depth_view.as_ref(),
+            viewport: self.viewport,
+            // Already drawing overlays
+            msaa: None,
+        });
+
+        msaa.end(self, &targets);
+
+        self.encoder.profile_end();
+    }
+}
+
+type MakeFramePipeline =
+    dyn Fn(&wgpu::Device, wgpu::TextureFormat, u32) -> wgpu::RenderPipeline + Send + Sync;
+
+/// Render pipelines drawing to [`RenderContext::frame`], built on first use for
+/// each frame format and sample count
+pub(crate) struct FramePipelines {
+    make: Box<MakeFramePipeline>,
+    pipelines: RwLock<HashMap<(wgpu::TextureFormat, u32), wgpu::RenderPipeline>>,
+}
+
+impl FramePipelines {
+    pub fn new(
+        make: impl Fn(&wgpu::Device, wgpu::TextureFormat, u32) -> wgpu::RenderPipeline
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            make: Box::new(make),
+            pipelines: Default::default(),
+        }
+    }
+
+    pub fn get<'a>(
+        &'a self,
+        ctx: &RenderContext,
+    ) -> MappedRwLockReadGuard<'a, wgpu::RenderPipeline> {
+        let key = (ctx.format, ctx.sample_count);
+
+        if !self.pipelines.read().contains_key(&key) {
+            let pipeline = (self.make)(ctx.device, key.0, key.1);
+            self.pipelines.write().insert(key, pipeline);
+        }
+
+        RwLockReadGuard::map(self.pipelines.read(), |pipelines| &pipelines[&key])
+    }
 }
 
+/// Draws the commands of an indirect buffer made of a `u32` draw count followed
+/// by [`wgpu::util::DrawIndexedIndirect`] commands, as filled by the cull passes.
+///
+/// The count is only read with `MULTI_DRAW_INDIRECT_COUNT`. Otherwise the cull
+/// passes leave the commands in place and all `max_count` are issued, culled
+/// ones having no instance.
+pub(crate) fn draw_indexed_indirects<'a>(
+    rpass: &mut wgpu::RenderPass<'a>,
+    features: wgpu::Features,
+    buffer: &'a wgpu::Buffer,
+    max_count: u32,
+) {
+    const COUNT_SIZE: wgpu::BufferAddress = std::mem::size_of::<u32>() as _;
+    const STRIDE: wgpu::BufferAddress = std::mem::size_of::<wgpu::util::DrawIndexedIndirect>() as _;
+
+    if features.contains(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) {
+        rpass.multi_draw_indexed_indirect_count(buffer, COUNT_SIZE, buffer, 0, max_count);
+    } else if features.contains(wgpu::Features::MULTI_DRAW_INDIRECT) {
+        rpass.multi_draw_indexed_indirect(buffer, COUNT_SIZE, max_count);
+    } else {
+        for index in 0..max_count as wgpu::BufferAddress {
+            rpass.draw_indexed_indirect(buffer, COUNT_SIZE + index * STRIDE);
+        }
+    }
+}
+
+/// GPU timing of a profiled scope, see [`Renderer::profiler_results`]
+#[cfg(feature = "profiler")]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProfilerResult {
+    pub label: String,
+    /// Start and end timestamps, in seconds
+    pub time: std::ops::Range<f64>,
+    pub nested_scopes: Vec<ProfilerResult>,
+}
+
+#[cfg(feature = "profiler")]
+impl ProfilerResult {
+    pub fn duration(&self) -> std::time::Duration {
+        std::time::Duration::from_secs_f64((self.time.end - self.time.start).max(0.0))
+    }
+
+    /// This scope and all its nested scopes, depth first, along with their
+    /// `/` separated labels path
+    pub fn flatten(&self) -> Vec<(String, std::time::Duration)> {
+        fn walk(
+            result: &ProfilerResult,
+            parent: Option<&str>,
+            out: &mut Vec<(String, std::time::Duration)>,
+        ) {
+            let path = match parent {
+                Some(parent) => format!("{parent}/{}", result.label),
+                None => result.label.clone(),
+            };
+
+            out.push((path.clone(), result.duration()));
+            for nested in &result.nested_scopes {
+                walk(nested, Some(&path), out);
+            }
+        }
+
+        let mut out = vec![];
+        walk(self, None, &mut out);
+        out
+    }
+}
+
+#[cfg(feature = "profiler")]
+impl From<GpuTimerScopeResult> for ProfilerResult {
+    fn from(result: GpuTimerScopeResult) -> Self {
+        Self {
+            label: result.label,
+            time: result.time,
+            nested_scopes: result.nested_scopes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Records scopes with CPU timestamps when the device doesn't support
+/// timestamp queries.
+///
+/// The frame scope spans from the previous frame submission to this one,
+/// nested scopes only measure the time spent recording their commands.
+#[cfg(feature = "profiler")]
+#[derive(Default)]
+struct CpuProfiler {
+    epoch: Option<std::time::Instant>,
+    last_submit: Option<f64>,
+    open_scopes: Vec<ProfilerResult>,
+    frame: Option<ProfilerResult>,
+}
+
+#[cfg(feature = "profiler")]
+impl CpuProfiler {
+    fn now(&mut self) -> f64 {
+        self.epoch
+            .get_or_insert_with(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+    }
+
+    fn begin_scope(&mut self, label: &str) {
+        let now = self.now();
+        let start = if self.open_scopes.is_empty() {
+            self.last_submit.unwrap_or(now)
+        } else {
+            now
+        };
+
+        self.open_scopes.push(ProfilerResult {
+            label: label.to_owned(),
+            time: start..start,
+            nested_scopes: vec![],
+        });
+    }
+
+    fn end_scope(&mut self) {
+        let now = self.now();
+        let Some(mut scope) = self.open_scopes.pop() else {
+            return;
+        };
+
+        scope.time.end = now;
+        match self.open_scopes.last_mut() {
+            Some(parent) => parent.nested_scopes.push(scope),
+            // Ended on submit, see `end_frame`
+            None => self.frame = Some(scope),
+        }
+    }
+
+    fn end_frame(&mut self) -> Vec<ProfilerResult> {
+        let now = self.now();
+        self.last_submit = Some(now);
+        self.open_scopes.clear();
+
+        self.frame
+            .take()
+            .map(|mut frame| {
+                frame.time.end = now;
+                frame
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+/// GPU timings of the renderer scopes, falls back to CPU timings when the
+/// device doesn't support timestamp queries
 #[cfg(feature = "profiler")]
 pub struct RendererProfiler {
     inner: GpuProfiler,
-    results: Vec<GpuTimerScopeResult>,
+    cpu: Option<CpuProfiler>,
+    results: Vec<ProfilerResult>,
+}
+
+#[cfg(feature = "profiler")]
+impl RendererProfiler {
+    /// Whether timings are measured on the CPU instead of the GPU
+    pub fn is_cpu_fallback(&self) -> bool {
+        self.cpu.is_some()
+    }
+
+    fn begin_scope(
+        &mut self,
+        label: &str,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+    ) {
+        if let Some(cpu) = &mut self.cpu {
+            cpu.begin_scope(label);
+        }
+        self.inner.begin_scope(label, encoder, device);
+    }
+
+    fn end_scope(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(cpu) = &mut self.cpu {
+            cpu.end_scope();
+        }
+        self.inner.end_scope(encoder);
+    }
+
+    fn resolve_queries(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.inner.resolve_queries(encoder);
+    }
+
+    /// To be called once the frame is submitted
+    fn end_frame(&mut self) {
+        // Also keeps the GPU profiler bookkeeping going without timestamps
+        self.inner.end_frame().unwrap();
+        let gpu_results = self.inner.process_finished_frame();
+
+        match &mut self.cpu {
+            Some(cpu) => self.results = cpu.end_frame(),
+            None => {
+                if let Some(results) = gpu_results {
+                    self.results = results.into_iter().map(Into::into).collect();
+                }
+            }
+        }
+    }
+
+    /// Scopes of the most recent frame whose timings are available, usually a
+    /// few frames behind the last rendered one
+    pub fn results(&self) -> &[ProfilerResult] {
+        &self.results
+    }
 }
 
 #[cfg(all(feature = "profiler", feature = "egui"))]
 impl egui::Widget for &RendererProfiler {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        fn profiler_ui(results: &[GpuTimerScopeResult]) -> impl FnOnce(&mut egui::Ui) + '_ {
+        fn profiler_ui(results: &[ProfilerResult]) -> impl FnOnce(&mut egui::Ui) + '_ {
             move |ui| {
                 let frame = egui::Frame {
                     inner_margin: egui::style::Margin {
@@ -230,8 +1631,7 @@ impl egui::Widget for &RendererProfiler {
                             columns[1].with_layout(
                                 egui::Layout::right_to_left(egui::Align::TOP),
                                 |ui| {
-                                    let time =
-                                        (result.time.end - result.time.start) * 1000.0 * 1000.0;
+                                    let time = result.duration().as_secs_f64() * 1000.0 * 1000.0;
                                     let time_str = format!("{time:.3}");
                                     ui.monospace(format!("{time_str} µs"));
                                 },
@@ -244,7 +1644,13 @@ impl egui::Widget for &RendererProfiler {
             }
         }
 
-        egui::CollapsingHeader::new("Profiler")
+        let title = if self.is_cpu_fallback() {
+            "Profiler (CPU)"
+        } else {
+            "Profiler"
+        };
+
+        egui::CollapsingHeader::new(title)
             .default_open(true)
             .show(ui, profiler_ui(&self.results))
             .header_response
@@ -257,7 +1663,7 @@ pub struct ProfilerCommandEncoder<'a> {
     #[cfg(feature = "profiler")]
     device: &'a wgpu::Device,
     #[cfg(feature = "profiler")]
-    profiler: &'a mut GpuProfiler,
+    profiler: &'a mut RendererProfiler,
 }
 
 impl<'a> ProfilerCommandEncoder<'a> {
@@ -282,7 +1688,7 @@ impl<'a> ProfilerCommandEncoder<'a> {
     ) -> wgpu_profiler::scope::OwningScope<wgpu::ComputePass> {
         wgpu_profiler::scope::OwningScope::start(
             desc.label.unwrap_or("???"),
-            self.profiler,
+            &mut self.profiler.inner,
             self.encoder.begin_compute_pass(desc),
             self.device,
         )
@@ -295,7 +1701,7 @@ impl<'a> ProfilerCommandEncoder<'a> {
     ) -> wgpu_profiler::scope::OwningScope<wgpu::RenderPass<'pass>> {
         wgpu_profiler::scope::OwningScope::start(
             desc.label.unwrap_or("???"),
-            self.profiler,
+            &mut self.profiler.inner,
             self.encoder.begin_render_pass(desc),
             self.device,
         )
@@ -314,3 +1720,224 @@ impl<'a> std::ops::DerefMut for ProfilerCommandEncoder<'a> {
         self.encoder
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_utils, DepthConfig};
+
+    fn clear(renderer: &Renderer, color: wgpu::Color) {
+        renderer
+            .render(|ctx| {
+                ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: ctx.frame,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(color),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn unsupported_adapter_message() {
+        let error = UnsupportedAdapterError {
+            adapter_info: wgpu::AdapterInfo {
+                name: "llvmpipe".to_owned(),
+                vendor: 0,
+                device: 0,
+                device_type: wgpu::DeviceType::Cpu,
+                driver: String::new(),
+                driver_info: String::new(),
+                backend: wgpu::Backend::Vulkan,
+            },
+            missing_features: wgpu::Features::TEXTURE_BINDING_ARRAY,
+            missing_limits: vec![("max_bind_groups", 6, 4)],
+        };
+
+        let message = error.to_string();
+        assert!(message.starts_with("Adapter llvmpipe (Vulkan) is not supported"));
+        assert!(message.contains("TEXTURE_BINDING_ARRAY"));
+        assert!(message.contains("max_bind_groups (required 6, supported 4)"));
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn profiler_result_flatten() {
+        let scope = |label: &str, time, nested_scopes| ProfilerResult {
+            label: label.to_owned(),
+            time,
+            nested_scopes,
+        };
+
+        let frame = scope(
+            "RenderFrame",
+            0.0..0.004,
+            vec![
+                scope(
+                    "Geometry",
+                    0.0..0.001,
+                    vec![scope("Cull", 0.0..0.0005, vec![])],
+                ),
+                scope("Bloom", 0.001..0.003, vec![]),
+            ],
+        );
+
+        let paths = frame
+            .flatten()
+            .into_iter()
+            .map(|(path, duration)| (path, duration.as_micros()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            paths,
+            [
+                ("RenderFrame".to_owned(), 4000),
+                ("RenderFrame/Geometry".to_owned(), 1000),
+                ("RenderFrame/Geometry/Cull".to_owned(), 500),
+                ("RenderFrame/Bloom".to_owned(), 2000),
+            ]
+        );
+    }
+
+    #[cfg(feature = "profiler")]
+    #[test]
+    fn cpu_profiler_nests_scopes() {
+        let mut profiler = CpuProfiler::default();
+
+        for _ in 0..2 {
+            profiler.begin_scope("RenderFrame");
+            profiler.begin_scope("Geometry");
+            profiler.begin_scope("Cull");
+            profiler.end_scope();
+            profiler.end_scope();
+            profiler.begin_scope("Bloom");
+            profiler.end_scope();
+            profiler.end_scope();
+
+            let results = profiler.end_frame();
+            assert_eq!(results.len(), 1);
+
+            let paths = results[0]
+                .flatten()
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            assert_eq!(
+                paths,
+                [
+                    "RenderFrame",
+                    "RenderFrame/Geometry",
+                    "RenderFrame/Geometry/Cull",
+                    "RenderFrame/Bloom"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn shader_errors_captured() {
+        let Some(renderer) = test_utils::renderer((70, 4)) else {
+            return;
+        };
+
+        let shader = |source: &'static str| {
+            pollster::block_on(renderer.with_error_capture(|| {
+                renderer
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: None,
+                        source: wgpu::ShaderSource::Wgsl(source.into()),
+                    })
+            }))
+        };
+
+        assert!(shader(
+            "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }"
+        )
+        .is_ok());
+        assert!(shader("@fragment fn fs_main() -> @location(0) vec4<f32> { return 1; }").is_err());
+    }
+
+    #[test]
+    fn headless_capture() {
+        let Some(renderer) = test_utils::renderer((70, 4)) else {
+            return;
+        };
+
+        clear(&renderer, wgpu::Color::RED);
+
+        // 70 pixels rows are padded to 256 bytes in the readback buffer
+        let image = renderer.capture().unwrap();
+        assert_eq!(image.dimensions(), (70, 4));
+        assert!(image.pixels().all(|pixel| pixel.0 == [255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn screenshot_of_next_frame() {
+        let Some(renderer) = test_utils::renderer((70, 4)) else {
+            return;
+        };
+
+        clear(&renderer, wgpu::Color::RED);
+        let screenshot = renderer.request_screenshot();
+        assert!(screenshot.try_recv().is_err());
+
+        clear(&renderer, wgpu::Color::BLUE);
+        renderer.device.poll(wgpu::Maintain::Wait);
+
+        let image = screenshot.try_recv().unwrap();
+        assert!(image.pixels().all(|pixel| pixel.0 == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn msaa_overlay() {
+        let Some(mut renderer) = test_utils::renderer((70, 4)) else {
+            return;
+        };
+
+        assert!(renderer.set_sample_count(3, Default::default()).is_err());
+        renderer
+            .set_sample_count(4, DepthConfig::reversed_z(&renderer.device))
+            .unwrap();
+        renderer.set_sample_count(4, Default::default()).unwrap();
+
+        clear(&renderer, wgpu::Color::RED);
+        renderer
+            .render(|ctx| {
+                // Untouched overlay pixels are transparent
+                ctx.overlay(None, |ctx| assert_eq!(ctx.sample_count, 4));
+                ctx.overlay(None, |ctx| {
+                    ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: None,
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: ctx.frame,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color {
+                                    r: 0.0,
+                                    g: 0.0,
+                                    b: 0.5,
+                                    a: 0.5,
+                                }),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+                });
+            })
+            .unwrap();
+
+        // Premultiplied half transparent blue over red
+        let image = renderer.capture().unwrap();
+        let [r, g, b, _] = image.get_pixel(0, 0).0;
+        assert!(r > 150 && g == 0 && b > 150);
+    }
+}
@@ -0,0 +1,475 @@
+use wgpu::util::DeviceExt;
+
+use crate::Resource;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AnimationId(u32);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationPlayback {
+    pub speed: f32,
+    pub looping: bool,
+    pub start_time: f32,
+}
+
+impl Default for AnimationPlayback {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            looping: true,
+            start_time: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AnimationTrack {
+    pub animation: AnimationId,
+    pub time: f32,
+    pub speed: f32,
+    looping: u32,
+}
+
+impl AnimationTrack {
+    pub fn new(animation: AnimationId, playback: AnimationPlayback) -> Self {
+        Self {
+            animation,
+            time: playback.start_time,
+            speed: playback.speed,
+            looping: playback.looping as _,
+        }
+    }
+
+    pub fn looping(&self) -> bool {
+        self.looping != 0
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping as _;
+    }
+}
+
+/// Two animation tracks blended together, `blend` being the weight of the
+/// second track. Both tracks time and the blend factor are advanced by the
+/// animate pass, `blend_speed` is signed to fade toward either track.
+///
+/// The CPU copy of `blend` is advanced by [`AnimationState::update`] with the
+/// same step as the animate pass, so a crossfade started mid-fade knows which
+/// track still carries most of the weight without reading the GPU back.
+///
+/// An additive track can be layered on top of the blended pose, e.g. an aim
+/// offset or breathing. Its reference pose is its own first frame: each joint
+/// becomes `base + additive_weight * (additive - reference)`, so additive
+/// animations should start with the neutral pose they were authored against
+/// (the bind pose is not used, baked frames don't carry it).
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AnimationState {
+    pub tracks: [AnimationTrack; 2],
+    pub blend: f32,
+    pub blend_speed: f32,
+    pub additive: AnimationTrack,
+    pub additive_weight: f32,
+    _padding: [u32; 3],
+}
+
+impl AnimationState {
+    pub fn new(animation: AnimationId, playback: AnimationPlayback) -> Self {
+        Self {
+            tracks: [AnimationTrack::new(animation, playback), Default::default()],
+            ..Default::default()
+        }
+    }
+
+    /// Index of the track carrying most of the weight, the one being faded
+    /// in on a tie
+    pub fn active_track(&self) -> usize {
+        if self.blend > 0.5 || (self.blend == 0.5 && self.blend_speed > 0.0) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Starts a new animation on the track with the lowest weight and fades
+    /// toward it over `duration` seconds, returns the index of the track which
+    /// was replaced. The blend factor is kept, a crossfade restarted mid-fade
+    /// continues from the current weights.
+    pub fn crossfade(
+        &mut self,
+        animation: AnimationId,
+        playback: AnimationPlayback,
+        duration: f32,
+    ) -> usize {
+        let track = 1 - self.active_track();
+
+        self.tracks[track] = AnimationTrack::new(animation, playback);
+
+        let blend_speed = 1.0 / duration.max(f32::EPSILON);
+        self.blend_speed = if track == 1 {
+            blend_speed
+        } else {
+            -blend_speed
+        };
+
+        track
+    }
+
+    /// Advances the blend factor by `dt` seconds, as the animate pass does
+    pub fn update(&mut self, dt: f32) {
+        self.blend = (self.blend + dt * self.blend_speed).clamp(0.0, 1.0);
+    }
+
+    /// Whether the blend factor still moves on the next update
+    pub(crate) fn is_fading(&self) -> bool {
+        (self.blend_speed > 0.0 && self.blend < 1.0) || (self.blend_speed < 0.0 && self.blend > 0.0)
+    }
+
+    /// Whether the animate pass leaves the state untouched: no track is
+    /// playing and the blend factor is settled
+    pub(crate) fn is_static(&self) -> bool {
+        let null = AnimationId::default();
+
+        self.tracks.iter().all(|track| track.animation == null)
+            && self.additive.animation == null
+            && self.blend_speed == 0.0
+    }
+
+    /// Layers `animation` on top of the blended tracks
+    pub fn set_additive(
+        &mut self,
+        animation: AnimationId,
+        playback: AnimationPlayback,
+        weight: f32,
+    ) {
+        self.additive = AnimationTrack::new(animation, playback);
+        self.additive_weight = weight;
+    }
+
+    /// Joint matrix of the base pose with an additive layer applied, as
+    /// computed by the skinning shaders
+    pub fn apply_additive(
+        base: glam::Mat4,
+        additive: glam::Mat4,
+        reference: glam::Mat4,
+        weight: f32,
+    ) -> glam::Mat4 {
+        if weight > 0.0 {
+            base + (additive - reference) * weight
+        } else {
+            base
+        }
+    }
+}
+
+impl From<AnimationId> for AnimationState {
+    fn from(animation: AnimationId) -> Self {
+        Self::new(animation, Default::default())
+    }
+}
+
+/// Skeletal animations baked as joints matrices, sampled by the skinning
+/// shaders.
+///
+/// Each animation is stored as a texture of `joints × frames × 64 bytes`
+/// (one `Mat4` per joint and per frame), the number of frames being the
+/// animation duration times its sample rate. Lower rates trade smoothness for
+/// VRAM, e.g. a 50 joints, 2 seconds animation takes 96KB at 15 samples per
+/// second and 384KB at 60.
+///
+/// Playback doesn't depend on the frame rate: shaders interpolate between the
+/// two baked frames around the animation time, see
+/// [`AnimationsManager::interpolate_joint`].
+pub struct AnimationsManager {
+    views: Vec<wgpu::TextureView>,
+    samples_per_sec: wgpu::Buffer,
+
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl AnimationsManager {
+    /// Sample rate used by loaders when none is specified
+    pub const DEFAULT_SAMPLES_PER_SEC: f32 = 15.0;
+
+    const MAX_ANIMATIONS: usize = 512;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let mut views = Vec::with_capacity(Self::MAX_ANIMATIONS);
+
+        views.push(
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("AnimationsManager null texture"),
+                    size: wgpu::Extent3d {
+                        width: 1,
+                        height: 1,
+                        depth_or_array_layers: 4,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba32Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[wgpu::TextureFormat::Rgba32Float],
+                })
+                .create_view(&Default::default()),
+        );
+
+        let samples_per_sec = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("AnimationsManager samples per sec"),
+            contents: bytemuck::cast_slice(&[Self::DEFAULT_SAMPLES_PER_SEC; Self::MAX_ANIMATIONS]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("AnimationsManager bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: core::num::NonZeroU32::new(Self::MAX_ANIMATIONS as _),
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group =
+            Self::create_bind_group(device, &bind_group_layout, &views, &samples_per_sec);
+
+        Self {
+            views,
+            samples_per_sec,
+
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// `animation` holds the joints matrices of each frame, frames being
+    /// sampled every `1 / samples_per_sec` seconds
+    pub fn add(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        animation: Vec<Vec<glam::Mat4>>,
+        samples_per_sec: f32,
+    ) -> AnimationId {
+        let pixels = (0..4)
+            .flat_map(|i| {
+                animation
+                    .iter()
+                    .flatten()
+                    .map(move |joint_transform| joint_transform.col(i))
+            })
+            .collect::<Vec<_>>();
+
+        let view = device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("Animations texture"),
+                    size: wgpu::Extent3d {
+                        width: animation[0].len() as _,
+                        height: animation.len() as _,
+                        depth_or_array_layers: 4,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba32Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[wgpu::TextureFormat::Rgba32Float],
+                },
+                bytemuck::cast_slice(&pixels),
+            )
+            .create_view(&Default::default());
+
+        let id = AnimationId(self.views.len() as _);
+        queue.write_buffer(
+            &self.samples_per_sec,
+            (id.0 as usize * std::mem::size_of::<f32>()) as _,
+            bytemuck::bytes_of(&samples_per_sec),
+        );
+
+        self.views.push(view);
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.views,
+            &self.samples_per_sec,
+        );
+        id
+    }
+
+    /// Joint matrix at `t` between the joint matrices `a` and `b` of two
+    /// consecutive baked frames, as computed by the skinning shaders.
+    /// Rotations are slerped, translations and scales lerped.
+    pub fn interpolate_joint(a: glam::Mat4, b: glam::Mat4, t: f32) -> glam::Mat4 {
+        let (scale_a, rotation_a, translation_a) = a.to_scale_rotation_translation();
+        let (scale_b, rotation_b, translation_b) = b.to_scale_rotation_translation();
+
+        glam::Mat4::from_scale_rotation_translation(
+            scale_a.lerp(scale_b, t),
+            rotation_a.slerp(rotation_b, t),
+            translation_a.lerp(translation_b, t),
+        )
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        views: &[wgpu::TextureView],
+        samples_per_sec: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let views = (0..Self::MAX_ANIMATIONS)
+            .map(|i| views.get(i).unwrap_or(&views[0]))
+            .collect::<Vec<_>>();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("AnimationsManager bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureViewArray(
+                        &views,
+                        // &views.iter().collect::<Vec<_>>(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: samples_per_sec.as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+impl Resource for AnimationsManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn additive_layer() {
+        let base = glam::Mat4::from_rotation_translation(
+            glam::Quat::from_rotation_y(0.3),
+            glam::vec3(1.0, 2.0, 3.0),
+        );
+        let reference = glam::Mat4::from_translation(glam::vec3(0.0, 1.0, 0.0));
+        let additive = glam::Mat4::from_translation(glam::vec3(0.5, 1.0, 0.0));
+
+        // A zero weight layer leaves the base pose untouched
+        assert_eq!(
+            AnimationState::apply_additive(base, additive, reference, 0.0),
+            base
+        );
+
+        // The reference pose itself adds nothing
+        assert_eq!(
+            AnimationState::apply_additive(base, reference, reference, 1.0),
+            base
+        );
+
+        let layered = AnimationState::apply_additive(base, additive, reference, 0.5);
+        assert_eq!(
+            layered.transform_point3(glam::Vec3::ZERO),
+            glam::vec3(1.25, 2.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn crossfade_replaces_lowest_weight_track() {
+        let playback = AnimationPlayback::default();
+        let mut state = AnimationState::new(AnimationId(1), playback);
+        assert_eq!(state.active_track(), 0);
+
+        assert_eq!(state.crossfade(AnimationId(2), playback, 0.5), 1);
+        assert_eq!(state.blend_speed, 2.0);
+        assert!(state.is_fading());
+
+        // Settled on the second track
+        state.update(0.3);
+        assert_eq!(state.active_track(), 1);
+        state.update(0.3);
+        assert_eq!(state.blend, 1.0);
+        assert!(!state.is_fading());
+
+        assert_eq!(state.crossfade(AnimationId(3), playback, 0.25), 0);
+        assert_eq!(state.blend_speed, -4.0);
+        assert_eq!(state.tracks[0].animation, AnimationId(3));
+        assert_eq!(state.tracks[1].animation, AnimationId(2));
+
+        // Restarted early in the fade: the second track still weighs the
+        // most, the freshly started first track is replaced again
+        state.update(0.05);
+        assert!((state.blend - 0.8).abs() < 1e-6);
+        assert_eq!(state.crossfade(AnimationId(4), playback, 0.25), 0);
+        assert_eq!(state.tracks[0].animation, AnimationId(4));
+        assert_eq!(state.tracks[1].animation, AnimationId(2));
+        assert_eq!(state.blend_speed, -4.0);
+
+        // Restarted late in the fade, the second track is the one to go
+        state.update(0.15);
+        assert!((state.blend - 0.2).abs() < 1e-6);
+        assert_eq!(state.crossfade(AnimationId(5), playback, 0.0), 1);
+        assert_eq!(state.tracks[0].animation, AnimationId(4));
+        assert_eq!(state.tracks[1].animation, AnimationId(5));
+        assert!(state.blend_speed > 0.0);
+        assert!(!state.is_static());
+
+        state.update(0.01);
+        assert_eq!(state.blend, 1.0);
+    }
+
+    #[test]
+    fn interpolate_slowly_rotating_joint() {
+        let frame = |angle: f32| {
+            glam::Mat4::from_rotation_translation(
+                glam::Quat::from_rotation_z(angle),
+                glam::vec3(0.0, angle, 0.0),
+            )
+        };
+        let (a, b) = (frame(0.0), frame(1.2));
+
+        assert_eq!(AnimationsManager::interpolate_joint(a, b, 0.0), a);
+
+        for t in [0.25, 0.5, 0.75] {
+            let joint = AnimationsManager::interpolate_joint(a, b, t);
+            assert!(joint.abs_diff_eq(frame(1.2 * t), 1e-5), "{t}: {joint}");
+
+            // Unlike a matrix lerp, the joint keeps its scale between frames
+            assert!((joint.determinant() - 1.0).abs() < 1e-5);
+            assert!(((a + (b - a) * t).determinant() - 1.0).abs() > 1e-2);
+        }
+    }
+
+    #[test]
+    fn instance_layout() {
+        // Mirrors the WGSL structs, instances are 16 bytes aligned
+        assert_eq!(std::mem::size_of::<AnimationState>(), 72);
+        assert_eq!(std::mem::size_of::<crate::Instance>() % 16, 0);
+    }
+}
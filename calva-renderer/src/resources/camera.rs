@@ -0,0 +1,461 @@
+use crate::{Resource, UniformBuffer, UniformData};
+
+/// Depth buffer of the gbuffer, set once for the whole engine with
+/// [`crate::Engine::with_depth`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DepthConfig {
+    /// Depth and stencil format, `Depth24PlusStencil8` by default. The depth
+    /// is non-linear and sampled through the depth aspect only.
+    pub format: wgpu::TextureFormat,
+    /// Near plane at depth 1 and far plane at 0. With a float format this
+    /// spreads the precision evenly over the distance instead of packing it
+    /// near the camera, avoiding z-fighting on distant geometry.
+    ///
+    /// Camera projections are still given with the standard convention, e.g.
+    /// from [`glam::Mat4::perspective_rh`], they are reversed on upload.
+    pub reversed_z: bool,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        Self {
+            format: wgpu::TextureFormat::Depth24PlusStencil8,
+            reversed_z: false,
+        }
+    }
+}
+
+impl DepthConfig {
+    /// Reversed-Z, with a float depth buffer when the device supports it
+    pub fn reversed_z(device: &wgpu::Device) -> Self {
+        let format = if device
+            .features()
+            .contains(wgpu::Features::DEPTH32FLOAT_STENCIL8)
+        {
+            wgpu::TextureFormat::Depth32FloatStencil8
+        } else {
+            Self::default().format
+        };
+
+        Self {
+            format,
+            reversed_z: true,
+        }
+    }
+
+    /// Depth of the far plane, the depth buffer is cleared to it
+    pub fn far(&self) -> f32 {
+        if self.reversed_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// `compare` as written for the standard convention, flipped with
+    /// reversed-Z
+    pub fn compare(&self, compare: wgpu::CompareFunction) -> wgpu::CompareFunction {
+        use wgpu::CompareFunction::*;
+
+        match (self.reversed_z, compare) {
+            (true, Less) => Greater,
+            (true, LessEqual) => GreaterEqual,
+            (true, Greater) => Less,
+            (true, GreaterEqual) => LessEqual,
+            _ => compare,
+        }
+    }
+
+    /// Constant depth bias moving fragments `amount` toward the camera
+    pub fn near_bias(&self, amount: i32) -> i32 {
+        if self.reversed_z {
+            amount
+        } else {
+            -amount
+        }
+    }
+
+    /// Standard projection remapped to the depth convention
+    pub fn projection(&self, proj: glam::Mat4) -> glam::Mat4 {
+        if !self.reversed_z {
+            return proj;
+        }
+
+        // z' = w - z
+        let flip = glam::Mat4::from_cols(
+            glam::Vec4::X,
+            glam::Vec4::Y,
+            glam::Vec4::NEG_Z,
+            glam::Vec4::Z + glam::Vec4::W,
+        );
+
+        flip * proj
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuCamera {
+    view: glam::Mat4,
+    proj: glam::Mat4,
+    view_proj: glam::Mat4,
+    inv_view: glam::Mat4,
+    inv_proj: glam::Mat4,
+    frustum: [glam::Vec4; 6],
+    prev_view_proj: glam::Mat4,
+    jitter: glam::Vec2,
+    /// See [`DepthConfig::far`]
+    far_depth: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct Camera {
+    pub view: glam::Mat4,
+    pub proj: glam::Mat4,
+}
+
+impl Camera {
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.proj * self.view)
+    }
+
+    /// World space ray through a point of the screen given in normalized
+    /// device coordinates (y up), returns its origin on the near plane and
+    /// its normalized direction.
+    ///
+    /// From pixels: `ndc_x = 2 * x / width - 1`, `ndc_y = 1 - 2 * y / height`.
+    pub fn screen_ray(&self, ndc_x: f32, ndc_y: f32) -> (glam::Vec3, glam::Vec3) {
+        let inv_view_proj = (self.proj * self.view).inverse();
+
+        // Depth 1 is at infinity with infinite projections
+        let near = inv_view_proj.project_point3(glam::vec3(ndc_x, ndc_y, 0.0));
+        let further = inv_view_proj.project_point3(glam::vec3(ndc_x, ndc_y, 0.5));
+
+        (near, (further - near).normalize())
+    }
+
+    /// Normalized device coordinates (y up) of a world space point, `None`
+    /// when it is behind the camera. Points out of the view are not clipped,
+    /// they land outside of the `[-1, 1]` range.
+    pub fn world_to_screen(&self, point: glam::Vec3) -> Option<glam::Vec2> {
+        let view_position = self.view.transform_point3(point);
+        if view_position.z >= 0.0 {
+            return None;
+        }
+
+        Some(self.proj.project_point3(view_position).truncate())
+    }
+}
+
+impl UniformData for Camera {
+    type GpuType = GpuCamera;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        let view_proj = self.proj * self.view;
+
+        GpuCamera {
+            view: self.view,
+            proj: self.proj,
+            view_proj,
+            inv_view: self.view.inverse(),
+            inv_proj: self.proj.inverse(),
+            frustum: Frustum::from_view_proj(view_proj).planes,
+            prev_view_proj: view_proj,
+            jitter: glam::Vec2::ZERO,
+            far_depth: 1.0,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Planes of a view frustum, for culling on the CPU the same way the cull
+/// passes do on the GPU, e.g. to only simulate what's on screen
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes. Normals (xyz) are unit
+    /// length and point inward, `w` is the plane distance to the origin.
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Frustum of a projection with a [0, 1] depth range, in the space
+    /// `view_proj` transforms from
+    pub fn from_view_proj(view_proj: glam::Mat4) -> Self {
+        use glam::Vec4Swizzles;
+
+        let normalize = |plane: glam::Vec4| {
+            let length = plane.xyz().length();
+
+            // Far plane of an infinite projection, nothing is behind it
+            if length > 0.0 {
+                plane / length
+            } else {
+                glam::Vec4::W
+            }
+        };
+
+        Self {
+            planes: [
+                view_proj.row(3) + view_proj.row(0), // left
+                view_proj.row(3) - view_proj.row(0), // right
+                view_proj.row(3) + view_proj.row(1), // bottom
+                view_proj.row(3) - view_proj.row(1), // top
+                view_proj.row(2),                    // near
+                view_proj.row(3) - view_proj.row(2), // far
+            ]
+            .map(normalize),
+        }
+    }
+
+    pub fn contains_point(&self, point: glam::Vec3) -> bool {
+        self.contains_sphere(point, 0.0)
+    }
+
+    /// Whether the sphere is at least partly inside the frustum
+    pub fn contains_sphere(&self, center: glam::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.dot(center.extend(1.0)) >= -radius)
+    }
+
+    /// Whether the box is at least partly inside the frustum. Conservative:
+    /// large boxes near the frustum corners may intersect none of it.
+    pub fn intersects_aabb(&self, min: glam::Vec3, max: glam::Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner of the box the farthest along the plane normal
+            let corner = glam::Vec3::select(plane.truncate().cmpge(glam::Vec3::ZERO), max, min);
+            plane.dot(corner.extend(1.0)) >= 0.0
+        })
+    }
+}
+
+pub struct CameraManager {
+    uniform: UniformBuffer<Camera>,
+    depth: DepthConfig,
+
+    jitter: Option<(u32, u32)>,
+    frame: u32,
+    prev_view_proj: Option<glam::Mat4>,
+    gpu_camera: Option<GpuCamera>,
+}
+
+impl CameraManager {
+    const JITTER_SAMPLES: u32 = 8;
+
+    pub fn new(device: &wgpu::Device, depth: DepthConfig) -> Self {
+        Self {
+            uniform: UniformBuffer::new(device, Camera::default()),
+            depth,
+
+            jitter: None,
+            frame: 0,
+            prev_view_proj: None,
+            gpu_camera: None,
+        }
+    }
+
+    /// Depth buffer convention the projection is uploaded with, passes testing
+    /// against the gbuffer depth build their pipelines for it
+    pub fn depth(&self) -> DepthConfig {
+        self.depth
+    }
+
+    /// Enables sub-pixel jittering of the projection for the given viewport size,
+    /// following a Halton (2, 3) sequence. Used by temporal anti-aliasing.
+    pub fn set_jitter(&mut self, viewport: Option<(u32, u32)>) {
+        self.jitter = viewport;
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        let jitter = self.jitter.map_or(glam::Vec2::ZERO, |(width, height)| {
+            let index = self.frame % Self::JITTER_SAMPLES + 1;
+            let offset = glam::vec2(halton(index, 2), halton(index, 3)) - 0.5;
+
+            offset * 2.0 / glam::vec2(width as f32, height as f32)
+        });
+
+        let view_proj = self.depth.projection(self.proj) * self.view;
+
+        let jittered = Camera {
+            view: self.view,
+            proj: glam::Mat4::from_translation(jitter.extend(0.0)) * self.proj,
+        };
+
+        let gpu_camera = GpuCamera {
+            prev_view_proj: self.prev_view_proj.unwrap_or(view_proj),
+            jitter,
+            ..self.gpu_camera(&jittered)
+        };
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&gpu_camera));
+
+        self.prev_view_proj = Some(view_proj);
+        self.gpu_camera = Some(gpu_camera);
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Binds another camera until the next [`Self::update`] or [`Self::restore`],
+    /// without jitter and without motion since last frame
+    pub fn write_view(&self, queue: &wgpu::Queue, camera: &Camera) {
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&self.gpu_camera(camera)),
+        );
+    }
+
+    /// Binds back the camera written by the last [`Self::update`]
+    pub fn restore(&self, queue: &wgpu::Queue) {
+        if let Some(gpu_camera) = &self.gpu_camera {
+            queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(gpu_camera));
+        }
+    }
+
+    /// Frustum planes are extracted from the standard projection, the
+    /// matrices are then reversed for reversed-Z
+    fn gpu_camera(&self, camera: &Camera) -> GpuCamera {
+        let gpu_camera = camera.as_gpu_type();
+        if !self.depth.reversed_z {
+            return gpu_camera;
+        }
+
+        let proj = self.depth.projection(camera.proj);
+        let view_proj = proj * camera.view;
+
+        GpuCamera {
+            proj,
+            view_proj,
+            inv_proj: proj.inverse(),
+            prev_view_proj: view_proj,
+            far_depth: self.depth.far(),
+            ..gpu_camera
+        }
+    }
+}
+
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
+impl std::ops::Deref for CameraManager {
+    type Target = UniformBuffer<Camera>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.uniform
+    }
+}
+
+impl std::ops::DerefMut for CameraManager {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.uniform
+    }
+}
+
+impl Resource for CameraManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device, DepthConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frustum_culling() {
+        let frustum = Camera {
+            view: glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Z, glam::Vec3::Y),
+            proj: glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 100.0),
+        }
+        .frustum();
+
+        assert!(frustum.contains_point(glam::vec3(0.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(glam::vec3(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(glam::vec3(0.0, 0.0, -200.0)));
+        assert!(!frustum.contains_point(glam::vec3(-20.0, 0.0, -10.0)));
+
+        // Straddles the left plane, x = z at 90 degrees fov
+        assert!(frustum.contains_sphere(glam::vec3(-11.0, 0.0, -10.0), 2.0));
+        assert!(!frustum.contains_sphere(glam::vec3(-13.0, 0.0, -10.0), 2.0));
+        // Straddles the far plane
+        assert!(frustum.contains_sphere(glam::vec3(0.0, 0.0, -101.0), 2.0));
+
+        assert!(
+            frustum.intersects_aabb(glam::vec3(-12.0, -1.0, -11.0), glam::vec3(-9.0, 1.0, -9.0))
+        );
+        assert!(!frustum.intersects_aabb(glam::vec3(-1.0, -1.0, 1.0), glam::vec3(1.0, 1.0, 2.0)));
+
+        // Infinite projections have no far plane
+        let infinite = Frustum::from_view_proj(glam::Mat4::perspective_infinite_rh(1.0, 1.0, 0.1));
+        assert!(infinite.contains_point(glam::vec3(0.0, 0.0, -1e6)));
+    }
+
+    #[test]
+    fn screen_ray_round_trip() {
+        let eye = glam::vec3(2.0, 3.0, 5.0);
+        let target = glam::vec3(0.0, 1.0, 0.0);
+
+        for proj in [
+            glam::Mat4::perspective_rh(1.0, 1.5, 0.1, 100.0),
+            glam::Mat4::perspective_infinite_rh(1.0, 1.5, 0.1),
+        ] {
+            let camera = Camera {
+                view: glam::Mat4::look_at_rh(eye, target, glam::Vec3::Y),
+                proj,
+            };
+
+            let (origin, direction) = camera.screen_ray(0.0, 0.0);
+            assert!(origin.distance(eye) < 0.2, "{origin}");
+            assert!(
+                direction.abs_diff_eq((target - eye).normalize(), 1e-4),
+                "{direction}"
+            );
+
+            let ndc = glam::vec2(0.5, -0.25);
+            let (origin, direction) = camera.screen_ray(ndc.x, ndc.y);
+            let screen = camera.world_to_screen(origin + direction * 10.0).unwrap();
+            assert!(screen.abs_diff_eq(ndc, 1e-4), "{screen}");
+
+            assert_eq!(camera.world_to_screen(eye * 2.0 - target), None);
+        }
+    }
+
+    #[test]
+    fn reversed_z_projection() {
+        let depth = DepthConfig {
+            reversed_z: true,
+            ..Default::default()
+        };
+
+        let ndc_depth = |proj: glam::Mat4, z: f32| proj.project_point3(glam::vec3(0.0, 0.0, z)).z;
+
+        let proj = depth.projection(glam::Mat4::perspective_rh(1.0, 1.0, 0.1, 380.0));
+        assert!((ndc_depth(proj, -0.1) - 1.0).abs() < 1e-4);
+        assert!(ndc_depth(proj, -380.0).abs() < 1e-4);
+        assert!(ndc_depth(proj, -10.0) > ndc_depth(proj, -20.0));
+
+        let infinite = depth.projection(glam::Mat4::perspective_infinite_rh(1.0, 1.0, 0.1));
+        assert!((ndc_depth(infinite, -0.1) - 1.0).abs() < 1e-4);
+        assert!(ndc_depth(infinite, -1e6) < 1e-6);
+
+        assert_eq!(
+            depth.compare(wgpu::CompareFunction::LessEqual),
+            wgpu::CompareFunction::GreaterEqual
+        );
+        assert_eq!(
+            DepthConfig::default().projection(glam::Mat4::IDENTITY),
+            glam::Mat4::IDENTITY
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{MaterialId, Resource};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DecalId(u32);
+
+/// Texture projected on the gbuffer by [`DecalsPass`](crate::DecalsPass).
+///
+/// The decal volume is the unit cube (`-0.5..0.5` on each axis) placed by
+/// `transform`. Its material albedo is projected along the local -Z axis, with
+/// the texture u along X and v along -Y, the albedo alpha blending the decal
+/// over the surface. The material normal map, if any, replaces the surface
+/// normal the same way. Other material properties are ignored.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Decal {
+    pub transform: glam::Mat4,
+    pub material: MaterialId,
+    /// Cosine of the largest angle between the surface normal and the decal
+    /// local +Z axis, surfaces facing further away are left untouched
+    pub normal_threshold: f32,
+}
+
+impl Default for Decal {
+    fn default() -> Self {
+        Self {
+            transform: glam::Mat4::IDENTITY,
+            material: Default::default(),
+            normal_threshold: 0.5,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GpuDecal {
+    pub transform: glam::Mat4,
+    pub inv_transform: glam::Mat4,
+    pub material: MaterialId,
+    pub normal_threshold: f32,
+    pub _padding: [u32; 2],
+}
+
+impl GpuDecal {
+    pub(crate) const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+}
+
+impl From<&Decal> for GpuDecal {
+    fn from(decal: &Decal) -> Self {
+        Self {
+            transform: decal.transform,
+            inv_transform: decal.transform.inverse(),
+            material: decal.material,
+            normal_threshold: decal.normal_threshold,
+            _padding: Default::default(),
+        }
+    }
+}
+
+/// Decals storage, slots freed by [`DecalsManager::remove`] are reused by the
+/// next [`DecalsManager::add`], like [`LightsManager`](crate::LightsManager)
+/// point lights.
+///
+/// Removing decals never moves the others, their [`DecalId`] stay valid.
+pub struct DecalsManager {
+    /// `None` for freed slots
+    decals_data: Vec<Option<GpuDecal>>,
+    pub(crate) decals: wgpu::Buffer,
+    free_slots: Vec<u32>,
+}
+
+impl DecalsManager {
+    pub const MAX_DECALS: usize = 4096;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let decals = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("DecalsManager decals"),
+            size: GpuDecal::SIZE * Self::MAX_DECALS as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            decals_data: vec![],
+            decals,
+            free_slots: vec![],
+        }
+    }
+
+    /// Number of live decals
+    pub fn count(&self) -> u32 {
+        (self.decals_data.len() - self.free_slots.len()) as _
+    }
+
+    /// Number of slots used in the GPU buffer, including the freed ones
+    /// waiting for reuse
+    pub fn high_water_mark(&self) -> u32 {
+        self.decals_data.len() as _
+    }
+
+    /// Decals past [`DecalsManager::MAX_DECALS`] are dropped, their ids are
+    /// not returned
+    pub fn add(&mut self, queue: &wgpu::Queue, decals: &[Decal]) -> Vec<DecalId> {
+        let first_decal_index = self.decals_data.len();
+
+        let mut ids = Vec::with_capacity(decals.len());
+        let mut reused_slots = vec![];
+
+        for decal in decals {
+            let slot = match self.free_slots.pop() {
+                Some(slot) => {
+                    self.decals_data[slot as usize] = Some(decal.into());
+                    reused_slots.push(slot);
+                    slot
+                }
+                None if self.decals_data.len() < Self::MAX_DECALS => {
+                    self.decals_data.push(Some(decal.into()));
+                    (self.decals_data.len() - 1) as u32
+                }
+                None => break,
+            };
+            ids.push(DecalId(slot));
+        }
+
+        for slot in reused_slots {
+            self.write_decal(queue, slot);
+        }
+
+        let appended = self.decals_data[first_decal_index..]
+            .iter()
+            .map(|decal| decal.unwrap_or_default())
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.decals,
+            first_decal_index as wgpu::BufferAddress * GpuDecal::SIZE,
+            bytemuck::cast_slice(&appended),
+        );
+
+        ids
+    }
+
+    /// Frees the decals slots for reuse, unknown or already removed ids are
+    /// ignored.
+    ///
+    /// Removed ids must not be used afterward, their slot may be handed out
+    /// again by a later [`DecalsManager::add`].
+    pub fn remove(&mut self, queue: &wgpu::Queue, ids: &[DecalId]) {
+        for id in ids {
+            let Some(decal) = self.decals_data.get_mut(id.0 as usize) else {
+                continue;
+            };
+            if decal.take().is_none() {
+                continue;
+            }
+
+            self.free_slots.push(id.0);
+
+            // Zero transforms collapse the volume, freed slots are never drawn
+            self.write_decal(queue, id.0);
+        }
+    }
+
+    /// Moves or changes a live decal in place, unknown or removed ids are
+    /// ignored
+    pub fn update(&mut self, queue: &wgpu::Queue, id: DecalId, decal: Decal) {
+        let Some(Some(current)) = self.decals_data.get_mut(id.0 as usize) else {
+            return;
+        };
+
+        *current = (&decal).into();
+        self.write_decal(queue, id.0);
+    }
+
+    fn write_decal(&self, queue: &wgpu::Queue, slot: u32) {
+        queue.write_buffer(
+            &self.decals,
+            slot as wgpu::BufferAddress * GpuDecal::SIZE,
+            bytemuck::bytes_of(&self.decals_data[slot as usize].unwrap_or_default()),
+        );
+    }
+}
+
+impl Resource for DecalsManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn reuse_freed_slots() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = DecalsManager::new(&device);
+
+        let decal = |x: f32| Decal {
+            transform: glam::Mat4::from_translation(glam::vec3(x, 0.0, 0.0)),
+            ..Default::default()
+        };
+
+        let ids = manager.add(&queue, &[decal(0.0), decal(1.0), decal(2.0)]);
+        manager.remove(&queue, &ids[..2]);
+        // Removing twice is a no-op
+        manager.remove(&queue, &ids[..2]);
+        assert_eq!(manager.count(), 1);
+
+        // Removed ids are ignored
+        manager.update(&queue, ids[0], decal(5.0));
+        assert_eq!(manager.decals_data[0], None);
+
+        let reused = manager.add(&queue, &[decal(3.0), decal(4.0), decal(5.0)]);
+        assert_eq!(manager.count(), 4);
+        assert_eq!(manager.high_water_mark(), 4);
+        assert!(reused[..2].contains(&ids[0]) && reused[..2].contains(&ids[1]));
+
+        manager.update(&queue, ids[2], decal(6.0));
+        let moved = manager.decals_data[2].unwrap();
+        assert_eq!(moved.inv_transform.w_axis.x, -6.0);
+    }
+}
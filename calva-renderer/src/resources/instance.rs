@@ -0,0 +1,973 @@
+use crate::{
+    AnimationId, AnimationPlayback, AnimationState, AnimationTrack, MaterialId, MeshId,
+    MeshesManager, Resource,
+};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceId(u32);
+
+impl From<InstanceId> for u32 {
+    fn from(value: InstanceId) -> u32 {
+        value.0
+    }
+}
+
+/// A mesh placed in the world, as stored in the [`InstancesManager`] buffer.
+///
+/// The struct is uploaded as is, fields are packed in declaration order:
+///
+/// | Field        | Bytes | Notes                                    |
+/// |--------------|-------|------------------------------------------|
+/// | `transform`  | 64    | Column major model matrix                |
+/// | `mesh`       | 4     |                                          |
+/// | `material`   | 4     | Default is `Material::default()`         |
+/// | `animation`  | 72    | See [`AnimationState`]                   |
+/// | `color_tint` | 16    | Linear rgb multiplier and emissive scale |
+/// | `flags`      | 16    | See [`InstanceFlags`]                    |
+///
+/// Nothing else is stored per instance: the skin offset and LOD are derived
+/// from the transform and the mesh by the culling passes every frame, and the
+/// normals transform (see [`Instance::normal_matrix`]) by the geometry pass.
+///
+/// Instances can be built without a glTF model:
+///
+/// ```
+/// # use calva_renderer::{AnimationId, Instance, MaterialId, MeshId};
+/// # let (mesh, material, animation) =
+/// #     (MeshId::default(), MaterialId::default(), AnimationId::default());
+/// let instance = Instance::new(glam::Mat4::from_translation(glam::Vec3::Y))
+///     .with_mesh(mesh)
+///     .with_material(material)
+///     .with_animation(animation);
+/// ```
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    pub transform: glam::Mat4,
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub animation: AnimationState,
+    /// Multiplies the material albedo (rgb) and emissive (rgb scaled by a),
+    /// white by default
+    pub color_tint: [f32; 4],
+    pub flags: InstanceFlags,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            transform: Default::default(),
+            mesh: Default::default(),
+            material: Default::default(),
+            animation: Default::default(),
+            color_tint: [1.0; 4],
+            flags: Default::default(),
+        }
+    }
+}
+
+impl Instance {
+    pub const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+
+    const ANIMATION_OFFSET: wgpu::BufferAddress = (std::mem::size_of::<glam::Mat4>()
+        + std::mem::size_of::<MeshId>()
+        + std::mem::size_of::<MaterialId>()) as _;
+
+    const COLOR_TINT_OFFSET: wgpu::BufferAddress =
+        Self::ANIMATION_OFFSET + std::mem::size_of::<AnimationState>() as wgpu::BufferAddress;
+
+    const FLAGS_OFFSET: wgpu::BufferAddress =
+        Self::COLOR_TINT_OFFSET + std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
+
+    pub fn new(transform: glam::Mat4) -> Self {
+        Self {
+            transform,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_mesh(self, mesh: MeshId) -> Self {
+        Self { mesh, ..self }
+    }
+
+    pub fn with_material(self, material: MaterialId) -> Self {
+        Self { material, ..self }
+    }
+
+    pub fn with_animation(self, animation: AnimationId) -> Self {
+        Self {
+            animation: animation.into(),
+            ..self
+        }
+    }
+
+    pub fn with_color_tint(self, color_tint: [f32; 4]) -> Self {
+        Self { color_tint, ..self }
+    }
+
+    /// Transform applied to the mesh normals by the geometry pass, the
+    /// inverse transpose of the transform so normals stay perpendicular to
+    /// non-uniformly scaled surfaces. Transformed normals have to be
+    /// normalized.
+    pub fn normal_matrix(&self) -> glam::Mat3 {
+        glam::Mat3::from_mat4(self.transform).inverse().transpose()
+    }
+
+    pub fn transform(&mut self, transform: glam::Mat4) {
+        self.transform = transform * self.transform;
+    }
+
+    pub fn animate(&mut self, animation: AnimationId) {
+        self.animation = animation.into();
+    }
+}
+
+/// Per instance switches read by the culling passes
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceFlags {
+    bits: u32,
+    _padding: [u32; 3],
+}
+
+impl InstanceFlags {
+    const HIDDEN: u32 = 1;
+
+    /// Hidden instances keep their slot and their animation keeps being
+    /// advanced, but they are neither drawn nor casting shadows
+    pub fn set_visible(&mut self, visible: bool) {
+        if visible {
+            self.bits &= !Self::HIDDEN;
+        } else {
+            self.bits |= Self::HIDDEN;
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.bits & Self::HIDDEN == 0
+    }
+}
+
+/// Instances storage, slots freed by [`InstancesManager::remove`] are reused
+/// by the next [`InstancesManager::add`] so the buffer only grows up to the
+/// highest number of simultaneously live instances.
+///
+/// Removing instances never moves the others, their [`InstanceId`] stay valid.
+pub struct InstancesManager {
+    base_instances_data: Vec<u32>,
+    pub(crate) base_instances: wgpu::Buffer,
+
+    instances_data: Vec<Instance>,
+    pub(crate) instances: wgpu::Buffer,
+    /// Transform of each slot on the previous frame, kept by the geometry
+    /// culling for motion vectors. Zeroed when a slot is handed out again or
+    /// shown again, the instance then starts without motion.
+    pub(crate) prev_transforms: wgpu::Buffer,
+    capacity: usize,
+
+    free_slots: Vec<u32>,
+    /// Slots moved by [`InstancesManager::set_transform`] since the last
+    /// [`InstancesManager::flush`]
+    dirty_transforms: Vec<u32>,
+    /// Slots whose blend factor is still moving, mirrored on the CPU by
+    /// [`InstancesManager::update_animations`]
+    fading: Vec<u32>,
+}
+
+impl InstancesManager {
+    pub const INITIAL_CAPACITY: usize = 1 << 16;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let base_instances_data = vec![0; MeshesManager::MAX_MESHES];
+        let base_instances = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("InstancesManager base instances"),
+            size: std::mem::size_of::<[u32; MeshesManager::MAX_MESHES]>() as _,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let capacity = Self::INITIAL_CAPACITY;
+        let instances_data = Vec::with_capacity(capacity);
+        let instances = Self::make_instances_buffer(device, capacity);
+        let prev_transforms = Self::make_prev_transforms_buffer(device, capacity);
+
+        Self {
+            base_instances_data,
+            base_instances,
+
+            instances_data,
+            instances,
+            prev_transforms,
+            capacity,
+
+            free_slots: vec![],
+            dirty_transforms: vec![],
+            fading: vec![],
+        }
+    }
+
+    /// Number of instances the GPU buffer can hold before being reallocated
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Adds instances, growing the GPU buffer to the next power of two when
+    /// needed.
+    ///
+    /// Existing [`InstanceId`]s stay valid across a grow, the passes
+    /// referencing the instances buffer rebind it on the next engine update.
+    pub fn add(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: impl IntoIterator<Item = Instance>,
+    ) -> Vec<InstanceId> {
+        let first_instance_index = self.instances_data.len();
+
+        let mut ids = vec![];
+        let mut reused_slots = vec![];
+
+        let mut min_mesh_index: wgpu::BufferAddress = self.base_instances_data.len() as _;
+        for instance in instances.into_iter() {
+            let slot = match self.free_slots.pop() {
+                Some(slot) => {
+                    self.instances_data[slot as usize] = instance;
+                    reused_slots.push(slot);
+                    slot
+                }
+                None => {
+                    self.instances_data.push(instance);
+                    (self.instances_data.len() - 1) as u32
+                }
+            };
+            ids.push(InstanceId(slot));
+            if instance.animation.is_fading() {
+                self.fading.push(slot);
+            }
+
+            let mesh_index: usize = instance.mesh.into();
+
+            for base_instance in self.base_instances_data[(mesh_index + 1)..].iter_mut() {
+                *base_instance += 1;
+            }
+
+            min_mesh_index = min_mesh_index.min(mesh_index as _);
+        }
+
+        if self.instances_data.len() > self.capacity {
+            self.grow(device, queue, self.instances_data.len());
+        }
+
+        queue.write_buffer(
+            &self.instances,
+            0,
+            bytemuck::bytes_of(&(self.instances_data.len() as u32)),
+        );
+        // Slots past the previous high water mark were never written
+        for slot in reused_slots {
+            self.write_instance(queue, slot);
+            self.reset_prev_transform(queue, slot);
+        }
+        queue.write_buffer(
+            &self.instances,
+            std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + first_instance_index as wgpu::BufferAddress * Instance::SIZE,
+            bytemuck::cast_slice(&self.instances_data[first_instance_index..]),
+        );
+        self.write_base_instances(queue, min_mesh_index as _);
+
+        ids
+    }
+
+    /// Frees the instances slots for reuse, unknown or already removed ids
+    /// are ignored.
+    ///
+    /// Removed ids must not be used afterward, their slot may be handed out
+    /// again by a later [`InstancesManager::add`].
+    pub fn remove(&mut self, queue: &wgpu::Queue, ids: &[InstanceId]) {
+        let mut min_mesh_index = self.base_instances_data.len();
+
+        for id in ids {
+            let Some(instance) = self.instances_data.get_mut(id.0 as usize) else {
+                continue;
+            };
+
+            if instance.mesh == MeshId::REMOVED {
+                continue;
+            }
+
+            let mesh_index: usize = instance.mesh.into();
+            for base_instance in self.base_instances_data[(mesh_index + 1)..].iter_mut() {
+                *base_instance -= 1;
+            }
+            min_mesh_index = min_mesh_index.min(mesh_index);
+
+            // Freed slots are skipped by the GPU culling
+            *instance = Instance {
+                mesh: MeshId::REMOVED,
+                ..Default::default()
+            };
+            self.free_slots.push(id.0);
+            self.write_instance(queue, id.0);
+        }
+
+        if min_mesh_index < self.base_instances_data.len() {
+            self.write_base_instances(queue, min_mesh_index);
+        }
+    }
+
+    pub fn set_animation(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: InstanceId,
+        animation: AnimationId,
+        playback: AnimationPlayback,
+    ) {
+        let Some(instance) = self
+            .instances_data
+            .get_mut(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+        else {
+            return;
+        };
+
+        // The additive layer is kept, restarting with the new animation
+        let additive = instance.animation;
+        instance.animation = AnimationState::new(animation, playback);
+        instance.animation.additive = additive.additive;
+        instance.animation.additive_weight = additive.additive_weight;
+
+        queue.write_buffer(
+            &self.instances,
+            std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + id.0 as wgpu::BufferAddress * Instance::SIZE,
+            bytemuck::bytes_of(instance),
+        );
+    }
+
+    /// Fades the instance animation into a new one over `duration` seconds.
+    ///
+    /// Only the replaced track and the blend speed are written, the faded out
+    /// track and the blend factor keep being advanced on the GPU.
+    pub fn crossfade_animation(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: InstanceId,
+        animation: AnimationId,
+        playback: AnimationPlayback,
+        duration: f32,
+    ) {
+        let Some(instance) = self
+            .instances_data
+            .get_mut(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+        else {
+            return;
+        };
+
+        let track = instance.animation.crossfade(animation, playback, duration);
+        if !self.fading.contains(&id.0) {
+            self.fading.push(id.0);
+        }
+
+        let animation_offset = std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+            + id.0 as wgpu::BufferAddress * Instance::SIZE
+            + Instance::ANIMATION_OFFSET;
+
+        queue.write_buffer(
+            &self.instances,
+            animation_offset
+                + (track * std::mem::size_of::<AnimationTrack>()) as wgpu::BufferAddress,
+            bytemuck::bytes_of(&instance.animation.tracks[track]),
+        );
+        queue.write_buffer(
+            &self.instances,
+            animation_offset
+                + (std::mem::size_of::<[AnimationTrack; 2]>() + std::mem::size_of::<f32>())
+                    as wgpu::BufferAddress,
+            bytemuck::bytes_of(&instance.animation.blend_speed),
+        );
+    }
+
+    /// Layers an additive animation on top of the instance animation, see
+    /// [`AnimationState`] for the reference pose convention.
+    ///
+    /// Only the additive track and its weight are written, the base tracks
+    /// keep being advanced on the GPU.
+    pub fn set_additive_animation(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: InstanceId,
+        animation: AnimationId,
+        playback: AnimationPlayback,
+        weight: f32,
+    ) {
+        let Some(instance) = self
+            .instances_data
+            .get_mut(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+        else {
+            return;
+        };
+
+        instance.animation.set_additive(animation, playback, weight);
+
+        let additive_offset = std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+            + id.0 as wgpu::BufferAddress * Instance::SIZE
+            + Instance::ANIMATION_OFFSET
+            + (std::mem::size_of::<[AnimationTrack; 2]>() + std::mem::size_of::<[f32; 2]>())
+                as wgpu::BufferAddress;
+
+        queue.write_buffer(
+            &self.instances,
+            additive_offset,
+            bytemuck::bytes_of(&instance.animation.additive),
+        );
+        queue.write_buffer(
+            &self.instances,
+            additive_offset + std::mem::size_of::<AnimationTrack>() as wgpu::BufferAddress,
+            bytemuck::bytes_of(&instance.animation.additive_weight),
+        );
+    }
+
+    /// E.g. to flash a monster red when hit, see [`Instance::color_tint`]
+    pub fn set_color_tint(&mut self, queue: &wgpu::Queue, id: InstanceId, color_tint: [f32; 4]) {
+        let Some(instance) = self
+            .instances_data
+            .get_mut(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+        else {
+            return;
+        };
+
+        instance.color_tint = color_tint;
+
+        queue.write_buffer(
+            &self.instances,
+            std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + id.0 as wgpu::BufferAddress * Instance::SIZE
+                + Instance::COLOR_TINT_OFFSET,
+            bytemuck::bytes_of(&instance.color_tint),
+        );
+    }
+
+    /// Toggles the instance without freeing its slot, e.g. for doors or
+    /// destructible props, see [`InstanceFlags::set_visible`]
+    pub fn set_visible(&mut self, queue: &wgpu::Queue, id: InstanceId, visible: bool) {
+        let Some(was_visible) = self.is_visible(id) else {
+            return;
+        };
+
+        // Hidden instances are skipped by the culling, their previous
+        // transform is stale
+        if visible && !was_visible {
+            self.reset_prev_transform(queue, id.0);
+        }
+
+        let instance = &mut self.instances_data[id.0 as usize];
+        instance.flags.set_visible(visible);
+
+        queue.write_buffer(
+            &self.instances,
+            std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + id.0 as wgpu::BufferAddress * Instance::SIZE
+                + Instance::FLAGS_OFFSET,
+            bytemuck::bytes_of(&instance.flags.bits),
+        );
+    }
+
+    /// `None` for removed instances
+    pub fn is_visible(&self, id: InstanceId) -> Option<bool> {
+        self.instances_data
+            .get(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+            .map(|instance| instance.flags.is_visible())
+    }
+
+    /// Moves the instance, the GPU buffer is only written on the next
+    /// [`InstancesManager::flush`] so that many instances moved in the same
+    /// frame are uploaded together
+    pub fn set_transform(&mut self, id: InstanceId, transform: glam::Mat4) {
+        let Some(instance) = self
+            .instances_data
+            .get_mut(id.0 as usize)
+            .filter(|instance| instance.mesh != MeshId::REMOVED)
+        else {
+            return;
+        };
+
+        instance.transform = transform;
+        self.dirty_transforms.push(id.0);
+    }
+
+    /// Advances the CPU copy of the fading blend factors by `dt` seconds, the
+    /// same step the animate pass applies on the GPU. Called by
+    /// [`Engine::update`](crate::Engine::update) so that
+    /// [`InstancesManager::crossfade_animation`] picks the right track.
+    pub fn update_animations(&mut self, dt: f32) {
+        let instances_data = &mut self.instances_data;
+        self.fading.retain(|&slot| {
+            let instance = &mut instances_data[slot as usize];
+            if instance.mesh == MeshId::REMOVED || !instance.animation.is_fading() {
+                return false;
+            }
+
+            instance.animation.update(dt);
+            instance.animation.is_fading()
+        });
+    }
+
+    /// Uploads the transforms changed by [`InstancesManager::set_transform`],
+    /// called by [`Engine::update`](crate::Engine::update).
+    ///
+    /// Adjacent moved slots are coalesced into a single write of the whole
+    /// instances. Animations are advanced on the GPU though, so instances
+    /// with a playing animation break the run and only get their transform
+    /// written.
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        if self.dirty_transforms.is_empty() {
+            return;
+        }
+
+        let mut slots = std::mem::take(&mut self.dirty_transforms);
+        slots.sort_unstable();
+        slots.dedup();
+
+        let is_static = |slot: u32| self.instances_data[slot as usize].animation.is_static();
+
+        let mut slots = slots.into_iter().peekable();
+        while let Some(first) = slots.next() {
+            let offset = std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + first as wgpu::BufferAddress * Instance::SIZE;
+
+            if !is_static(first) {
+                queue.write_buffer(
+                    &self.instances,
+                    offset,
+                    bytemuck::bytes_of(&self.instances_data[first as usize].transform),
+                );
+                continue;
+            }
+
+            let mut last = first;
+            while let Some(slot) = slots.next_if(|&slot| slot == last + 1 && is_static(slot)) {
+                last = slot;
+            }
+
+            queue.write_buffer(
+                &self.instances,
+                offset,
+                bytemuck::cast_slice(&self.instances_data[first as usize..=last as usize]),
+            );
+        }
+    }
+
+    /// Id of the live instance stored in the GPU slot `index`, the index
+    /// written in the picking target of [`GeometryPass`](crate::GeometryPass).
+    /// `None` for freed or unused slots.
+    pub fn id_at(&self, index: u32) -> Option<InstanceId> {
+        let instance = self.instances_data.get(index as usize)?;
+        (instance.mesh != MeshId::REMOVED).then_some(InstanceId(index))
+    }
+
+    /// Number of live instances
+    pub fn count(&self) -> u32 {
+        (self.instances_data.len() - self.free_slots.len()) as _
+    }
+
+    /// Number of slots used in the GPU buffer, including the freed ones
+    /// waiting for reuse
+    pub fn high_water_mark(&self) -> u32 {
+        self.instances_data.len() as _
+    }
+
+    fn write_instance(&self, queue: &wgpu::Queue, slot: u32) {
+        queue.write_buffer(
+            &self.instances,
+            std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + slot as wgpu::BufferAddress * Instance::SIZE,
+            bytemuck::bytes_of(&self.instances_data[slot as usize]),
+        );
+    }
+
+    fn reset_prev_transform(&self, queue: &wgpu::Queue, slot: u32) {
+        queue.write_buffer(
+            &self.prev_transforms,
+            slot as wgpu::BufferAddress * std::mem::size_of::<glam::Mat4>() as wgpu::BufferAddress,
+            bytemuck::bytes_of(&glam::Mat4::ZERO),
+        );
+    }
+
+    fn write_base_instances(&self, queue: &wgpu::Queue, min_mesh_index: usize) {
+        queue.write_buffer(
+            &self.base_instances,
+            (min_mesh_index * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&self.base_instances_data[min_mesh_index..]),
+        );
+    }
+
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, min_capacity: usize) {
+        let capacity = min_capacity.next_power_of_two();
+        let instances = Self::make_instances_buffer(device, capacity);
+        let prev_transforms = Self::make_prev_transforms_buffer(device, capacity);
+
+        // Animations are advanced on the GPU, instances can't be written back
+        // from the CPU copy
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("InstancesManager grow encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.instances, 0, &instances, 0, self.instances.size());
+        encoder.copy_buffer_to_buffer(
+            &self.prev_transforms,
+            0,
+            &prev_transforms,
+            0,
+            self.prev_transforms.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.instances = instances;
+        self.prev_transforms = prev_transforms;
+        self.capacity = capacity;
+    }
+
+    fn make_instances_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("InstancesManager instances"),
+            size: std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+                + capacity as wgpu::BufferAddress * Instance::SIZE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn make_prev_transforms_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("InstancesManager previous transforms"),
+            size: (capacity * std::mem::size_of::<glam::Mat4>()) as _,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
+
+impl Resource for InstancesManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn builder() {
+        let transform = glam::Mat4::from_translation(glam::Vec3::X);
+        let instance = Instance::new(transform)
+            .with_mesh(bytemuck::cast(3u32))
+            .with_material(MaterialId::default())
+            .with_animation(AnimationId::default())
+            .with_color_tint([0.5; 4]);
+
+        assert_eq!(instance.transform, transform);
+        assert_eq!(u32::from(instance.mesh), 3);
+        assert_eq!(instance.color_tint, [0.5; 4]);
+        assert!(instance.flags.is_visible());
+
+        // Documented packing
+        assert_eq!(Instance::SIZE, 176);
+    }
+
+    #[test]
+    fn normal_matrix_non_uniform_scale() {
+        let transform = glam::Mat4::from_scale_rotation_translation(
+            glam::vec3(1.0, 2.0, 1.0),
+            glam::Quat::from_rotation_y(0.7),
+            glam::vec3(1.0, 2.0, 3.0),
+        );
+        let normal_matrix = Instance::new(transform).normal_matrix();
+
+        // Tangents of a (1, 2, 1) stretched cube, normals must stay perpendicular
+        for (normal, tangent) in [
+            (glam::Vec3::X, glam::Vec3::Y),
+            (glam::Vec3::Y, glam::Vec3::Z),
+            (
+                glam::vec3(0.0, 1.0, 1.0).normalize(),
+                glam::vec3(0.0, 1.0, -1.0),
+            ),
+            (
+                glam::vec3(1.0, 1.0, 0.0).normalize(),
+                glam::vec3(1.0, -1.0, 0.0),
+            ),
+        ] {
+            let normal = (normal_matrix * normal).normalize();
+            let tangent = transform.transform_vector3(tangent).normalize();
+            assert!(normal.dot(tangent).abs() < 1e-5, "{normal} · {tangent}");
+        }
+
+        // Rotations are kept as is
+        let rotation = glam::Mat4::from_rotation_x(1.2);
+        let normal_matrix = Instance::new(rotation).normal_matrix();
+        assert!(normal_matrix.abs_diff_eq(glam::Mat3::from_mat4(rotation), 1e-5));
+    }
+
+    #[test]
+    fn add_grows_past_initial_capacity() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+
+        let make_instance = |i: usize| Instance {
+            transform: glam::Mat4::from_translation(glam::vec3(i as f32, 0.0, 0.0)),
+            ..Default::default()
+        };
+
+        let first = manager.add(&device, &queue, (0..10).map(make_instance));
+
+        let count = InstancesManager::INITIAL_CAPACITY + 1;
+        let rest = manager.add(&device, &queue, (10..count).map(make_instance));
+
+        assert_eq!(manager.count(), count as u32);
+        assert_eq!(manager.capacity(), count.next_power_of_two());
+
+        // Handles given out before the grow still point to the same instances
+        assert_eq!(first[3], InstanceId(3));
+        assert_eq!(rest[0], InstanceId(10));
+
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let (header, instances) = data.split_at(std::mem::size_of::<[u32; 4]>());
+
+        assert_eq!(bytemuck::from_bytes::<u32>(&header[..4]), &(count as u32));
+
+        let instances: &[Instance] =
+            bytemuck::cast_slice(&instances[..count * Instance::SIZE as usize]);
+        for (i, instance) in instances.iter().enumerate() {
+            assert_eq!(instance.transform, make_instance(i).transform);
+        }
+    }
+
+    #[test]
+    fn remove_reuses_freed_slots() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+
+        let make_instance = |i: usize| Instance {
+            transform: glam::Mat4::from_translation(glam::vec3(i as f32, 0.0, 0.0)),
+            ..Default::default()
+        };
+
+        const LIVE: usize = 1000;
+
+        let mut live = manager
+            .add(&device, &queue, (0..LIVE).map(make_instance))
+            .into_iter()
+            .enumerate()
+            .collect::<Vec<_>>();
+
+        for round in 1..=20 {
+            // Drop every other instance, shifting the pattern each round
+            let (removed, kept): (Vec<_>, Vec<_>) = live
+                .into_iter()
+                .enumerate()
+                .partition(|(i, _)| (i + round) % 2 == 0);
+
+            let removed = removed
+                .into_iter()
+                .map(|(_, (_, id))| id)
+                .collect::<Vec<_>>();
+            manager.remove(&queue, &removed);
+            // Removing twice is a no-op
+            manager.remove(&queue, &removed);
+            assert!(removed.iter().all(|id| manager.id_at(id.0).is_none()));
+
+            live = kept.into_iter().map(|(_, entry)| entry).collect();
+            assert_eq!(manager.count() as usize, live.len());
+
+            let values = (0..removed.len()).map(|i| round * LIVE + i);
+            let ids = manager.add(&device, &queue, values.clone().map(make_instance));
+            live.extend(values.zip(ids));
+
+            assert_eq!(manager.count() as usize, LIVE);
+            assert_eq!(manager.high_water_mark() as usize, LIVE);
+        }
+
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let instances: &[Instance] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<[u32; 4]>()..][..LIVE * Instance::SIZE as usize],
+        );
+        for (value, id) in live {
+            assert_eq!(manager.id_at(id.0), Some(id));
+            assert_eq!(
+                instances[id.0 as usize].transform,
+                make_instance(value).transform
+            );
+        }
+
+        assert_eq!(manager.base_instances_data[0], 0);
+        assert_eq!(manager.base_instances_data[1], LIVE as u32);
+    }
+
+    #[test]
+    fn set_visible_keeps_slot() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+        let ids = manager.add(&device, &queue, [Instance::default(); 2]);
+
+        manager.set_visible(&queue, ids[0], false);
+        assert_eq!(manager.is_visible(ids[0]), Some(false));
+        assert_eq!(manager.is_visible(ids[1]), Some(true));
+        assert_eq!(manager.count(), 2);
+
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let instances: &[Instance] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<[u32; 4]>()..][..2 * Instance::SIZE as usize],
+        );
+        assert!(!instances[0].flags.is_visible());
+        assert!(instances[1].flags.is_visible());
+
+        manager.set_visible(&queue, ids[0], true);
+        assert_eq!(manager.is_visible(ids[0]), Some(true));
+
+        // Slots are reused visible
+        manager.set_visible(&queue, ids[1], false);
+        manager.remove(&queue, &ids[1..]);
+        assert_eq!(manager.is_visible(ids[1]), None);
+        let reused = manager.add(&device, &queue, [Instance::default()]);
+        assert_eq!(reused[0], ids[1]);
+        assert_eq!(manager.is_visible(reused[0]), Some(true));
+    }
+
+    #[test]
+    fn reset_prev_transforms() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+        let ids = manager.add(&device, &queue, [Instance::default(); 3]);
+
+        // As left by the geometry culling
+        queue.write_buffer(
+            &manager.prev_transforms,
+            0,
+            bytemuck::cast_slice(&[glam::Mat4::IDENTITY; 3]),
+        );
+
+        let prev_transforms = |manager: &InstancesManager| {
+            let data = test_utils::read_buffer(&device, &queue, &manager.prev_transforms);
+            bytemuck::cast_slice::<_, glam::Mat4>(&data)[..3].to_vec()
+        };
+
+        // Hiding keeps it, showing again resets it
+        manager.set_visible(&queue, ids[1], false);
+        assert_eq!(prev_transforms(&manager)[1], glam::Mat4::IDENTITY);
+        manager.set_visible(&queue, ids[1], true);
+
+        manager.remove(&queue, &ids[2..]);
+        let reused = manager.add(&device, &queue, [Instance::default()]);
+        assert_eq!(reused[0], ids[2]);
+
+        assert_eq!(
+            prev_transforms(&manager),
+            [glam::Mat4::IDENTITY, glam::Mat4::ZERO, glam::Mat4::ZERO]
+        );
+    }
+
+    #[test]
+    fn flush_moved_transforms() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+
+        let mut instances = [Instance::default(); 4];
+        instances[3].animation.blend_speed = 1.0;
+        let ids = manager.add(&device, &queue, instances);
+
+        // Stands for the animate pass advancing the blend factor
+        let blend_offset = std::mem::size_of::<[u32; 4]>() as wgpu::BufferAddress
+            + 3 * Instance::SIZE
+            + Instance::ANIMATION_OFFSET
+            + std::mem::size_of::<[AnimationTrack; 2]>() as wgpu::BufferAddress;
+        queue.write_buffer(
+            &manager.instances,
+            blend_offset,
+            bytemuck::bytes_of(&0.5f32),
+        );
+
+        let transform = |x: f32| glam::Mat4::from_translation(glam::vec3(x, 0.0, 0.0));
+        for (i, id) in ids.iter().enumerate().skip(1) {
+            manager.set_transform(*id, transform(i as f32));
+        }
+        // Moved twice in the same frame, the last one wins
+        manager.set_transform(ids[1], transform(5.0));
+
+        // Deferred until flushed
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let gpu_instances: &[Instance] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<[u32; 4]>()..][..4 * Instance::SIZE as usize],
+        );
+        assert_eq!(gpu_instances[1].transform, glam::Mat4::IDENTITY);
+
+        manager.flush(&queue);
+        assert!(manager.dirty_transforms.is_empty());
+
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let gpu_instances: &[Instance] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<[u32; 4]>()..][..4 * Instance::SIZE as usize],
+        );
+        assert_eq!(gpu_instances[0].transform, glam::Mat4::IDENTITY);
+        assert_eq!(gpu_instances[1].transform, transform(5.0));
+        assert_eq!(gpu_instances[2].transform, transform(2.0));
+        assert_eq!(gpu_instances[3].transform, transform(3.0));
+        // Animated instances only get their transform written
+        assert_eq!(gpu_instances[3].animation.blend, 0.5);
+    }
+
+    #[test]
+    fn color_tint() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = InstancesManager::new(&device);
+
+        let transform = glam::Mat4::from_translation(glam::vec3(1.0, 2.0, 3.0));
+        let ids = manager.add(
+            &device,
+            &queue,
+            [Instance {
+                transform,
+                ..Default::default()
+            }; 2],
+        );
+        manager.set_color_tint(&queue, ids[1], [1.0, 0.0, 0.0, 2.0]);
+
+        let data = test_utils::read_buffer(&device, &queue, &manager.instances);
+        let instances: &[Instance] = bytemuck::cast_slice(
+            &data[std::mem::size_of::<[u32; 4]>()..][..2 * Instance::SIZE as usize],
+        );
+
+        // Untinted instances are left white
+        assert_eq!(instances[0].color_tint, [1.0; 4]);
+        assert_eq!(instances[1].color_tint, [1.0, 0.0, 0.0, 2.0]);
+        assert_eq!(instances[1].transform, transform);
+    }
+}
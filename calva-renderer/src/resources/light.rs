@@ -0,0 +1,410 @@
+use crate::Resource;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLightId(u32);
+
+/// Conversions from photometric units to the radiometric ones the lighting
+/// passes work with.
+///
+/// Lights are shaded in watts: [`PointLight::color`] is a radiant intensity
+/// (W/sr) attenuated by the inverse square distance, and
+/// `DirectionalLight::color * DirectionalLight::intensity` an irradiance
+/// (W/m²). Photometric values are brought back to watts with the 683 lm/W
+/// efficacy the lumen is defined against, so that a scene authored in
+/// candela/lux keeps the same exposure as one authored in watts.
+pub struct PhotometricUnits;
+
+impl PhotometricUnits {
+    /// Luminous efficacy of the 555nm monochromatic light, in lm/W
+    pub const LUMENS_PER_WATT: f32 = 683.0;
+
+    /// Irradiance (W/m²) below which a point light contribution is cut, used
+    /// by [`PhotometricUnits::point_light_range`]
+    pub const RANGE_CUTOFF: f32 = 0.08;
+
+    /// Luminous intensity (cd) to radiant intensity (W/sr), e.g. a glTF
+    /// `KHR_lights_punctual` point light intensity to [`PointLight::color`]
+    pub fn candela_to_radiant(candela: f32) -> f32 {
+        candela / Self::LUMENS_PER_WATT
+    }
+
+    /// Illuminance (lx) to irradiance (W/m²), e.g. a glTF
+    /// `KHR_lights_punctual` directional light intensity to
+    /// [`DirectionalLight::intensity`]
+    pub fn lux_to_radiant(lux: f32) -> f32 {
+        lux / Self::LUMENS_PER_WATT
+    }
+
+    /// Radiant flux (W) of an isotropic point light to its radiant intensity
+    /// (W/sr), e.g. a Blender light power to [`PointLight::color`]
+    pub fn watts_to_radiant(watts: f32) -> f32 {
+        watts / (4.0 * std::f32::consts::PI)
+    }
+
+    /// Distance at which the irradiance of a point light of radiant intensity
+    /// `color` falls under [`PhotometricUnits::RANGE_CUTOFF`], for lights
+    /// without an explicit range
+    pub fn point_light_range(color: glam::Vec3) -> f32 {
+        (color.max_element() / Self::RANGE_CUTOFF).sqrt()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct PointLight {
+    pub position: glam::Vec3,
+    pub radius: f32,
+    /// Radiant intensity in W/sr, see [`PhotometricUnits`]
+    pub color: glam::Vec3,
+    /// Only the first [`LightsManager::MAX_SHADOWED_POINT_LIGHTS`] shadow
+    /// casters get a shadow map slot, others are lit without shadows
+    pub cast_shadows: bool,
+}
+
+impl PointLight {
+    pub fn transform(&mut self, transform: glam::Mat4) {
+        self.position = (transform * self.position.extend(1.0)).truncate();
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GpuPointLight {
+    pub position: glam::Vec3,
+    pub radius: f32,
+    pub color: glam::Vec3,
+    /// Index of the light in the shadow maps, -1 when it has none
+    pub shadow_slot: i32,
+}
+
+impl GpuPointLight {
+    pub(crate) const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: glam::Vec3,
+    pub color: [f32; 3],
+    /// Irradiance in W/m², see [`PhotometricUnits`]
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: glam::vec3(0.5, -1.0, 0.5),
+            color: [1.0; 3],
+            intensity: 5.0,
+        }
+    }
+}
+
+/// Point lights storage, slots freed by [`LightsManager::remove_point_lights`]
+/// are reused by the next [`LightsManager::add_point_lights`] so that
+/// streaming lights in and out only writes the touched slots.
+///
+/// Removing lights never moves the others, their [`PointLightId`] stay valid.
+pub struct LightsManager {
+    /// `None` for freed slots
+    point_lights_data: Vec<Option<GpuPointLight>>,
+    pub(crate) point_lights: wgpu::Buffer,
+    free_slots: Vec<u32>,
+
+    point_light_shadows: Vec<PointLight>,
+    free_shadow_slots: Vec<u32>,
+}
+
+impl LightsManager {
+    const MAX_POINT_LIGHTS: usize = 10_000;
+    pub const MAX_SHADOWED_POINT_LIGHTS: usize = 4;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let point_lights = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("LightsManager point lights"),
+            size: GpuPointLight::SIZE * Self::MAX_POINT_LIGHTS as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            point_lights_data: vec![],
+            point_lights,
+            free_slots: vec![],
+
+            point_light_shadows: Vec::with_capacity(Self::MAX_SHADOWED_POINT_LIGHTS),
+            free_shadow_slots: vec![],
+        }
+    }
+
+    /// Number of live point lights
+    pub fn count_point_lights(&self) -> u32 {
+        (self.point_lights_data.len() - self.free_slots.len()) as _
+    }
+
+    /// Number of slots used in the GPU buffer, including the freed ones
+    /// waiting for reuse
+    pub fn point_lights_high_water_mark(&self) -> u32 {
+        self.point_lights_data.len() as _
+    }
+
+    /// Point lights owning a shadow map, indexed by their shadow slot.
+    /// Slots freed by a removed light keep its last value until reused.
+    pub fn point_light_shadows(&self) -> &[PointLight] {
+        &self.point_light_shadows
+    }
+
+    pub fn add_point_lights(
+        &mut self,
+        queue: &wgpu::Queue,
+        point_lights: &[PointLight],
+    ) -> Vec<PointLightId> {
+        let first_point_light_index = self.point_lights_data.len();
+
+        let mut ids = Vec::with_capacity(point_lights.len());
+        let mut reused_slots = vec![];
+
+        for point_light in point_lights {
+            let gpu_point_light = GpuPointLight {
+                position: point_light.position,
+                radius: point_light.radius,
+                color: point_light.color,
+                shadow_slot: self.acquire_shadow_slot(point_light),
+            };
+
+            let slot = match self.free_slots.pop() {
+                Some(slot) => {
+                    self.point_lights_data[slot as usize] = Some(gpu_point_light);
+                    reused_slots.push(slot);
+                    slot
+                }
+                None => {
+                    self.point_lights_data.push(Some(gpu_point_light));
+                    (self.point_lights_data.len() - 1) as u32
+                }
+            };
+            ids.push(PointLightId(slot));
+        }
+
+        for slot in reused_slots {
+            self.write_point_light(queue, slot);
+        }
+
+        let appended = self.point_lights_data[first_point_light_index..]
+            .iter()
+            .map(|point_light| point_light.unwrap_or_default())
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.point_lights,
+            first_point_light_index as wgpu::BufferAddress * GpuPointLight::SIZE,
+            bytemuck::cast_slice(&appended),
+        );
+
+        ids
+    }
+
+    /// Frees the point lights slots for reuse, unknown or already removed ids
+    /// are ignored.
+    ///
+    /// Removed ids must not be used afterward, their slot may be handed out
+    /// again by a later [`LightsManager::add_point_lights`].
+    pub fn remove_point_lights(&mut self, queue: &wgpu::Queue, ids: &[PointLightId]) {
+        for id in ids {
+            let Some(Some(point_light)) = self
+                .point_lights_data
+                .get_mut(id.0 as usize)
+                .map(Option::take)
+            else {
+                continue;
+            };
+
+            self.release_shadow_slot(point_light.shadow_slot);
+            self.free_slots.push(id.0);
+
+            // Zero radius lights have no volume, freed slots are never shaded
+            self.write_point_light(queue, id.0);
+        }
+    }
+
+    /// Moves or recolors a live point light in place, unknown or removed ids
+    /// are ignored
+    pub fn update_point_light(
+        &mut self,
+        queue: &wgpu::Queue,
+        id: PointLightId,
+        point_light: PointLight,
+    ) {
+        let Some(Some(current)) = self.point_lights_data.get(id.0 as usize).copied() else {
+            return;
+        };
+
+        let shadow_slot = match (current.shadow_slot, point_light.cast_shadows) {
+            (-1, true) => self.acquire_shadow_slot(&point_light),
+            (-1, false) => -1,
+            (slot, true) => {
+                self.point_light_shadows[slot as usize] = point_light;
+                slot
+            }
+            (slot, false) => {
+                self.release_shadow_slot(slot);
+                -1
+            }
+        };
+
+        self.point_lights_data[id.0 as usize] = Some(GpuPointLight {
+            position: point_light.position,
+            radius: point_light.radius,
+            color: point_light.color,
+            shadow_slot,
+        });
+        self.write_point_light(queue, id.0);
+    }
+
+    /// Shadow map slot for a new shadow caster, -1 when all are taken
+    fn acquire_shadow_slot(&mut self, point_light: &PointLight) -> i32 {
+        if !point_light.cast_shadows {
+            return -1;
+        }
+
+        if let Some(slot) = self.free_shadow_slots.pop() {
+            self.point_light_shadows[slot as usize] = *point_light;
+            return slot as _;
+        }
+
+        if self.point_light_shadows.len() < Self::MAX_SHADOWED_POINT_LIGHTS {
+            self.point_light_shadows.push(*point_light);
+            return (self.point_light_shadows.len() - 1) as _;
+        }
+
+        -1
+    }
+
+    fn release_shadow_slot(&mut self, shadow_slot: i32) {
+        if shadow_slot >= 0 {
+            self.free_shadow_slots.push(shadow_slot as _);
+        }
+    }
+
+    fn write_point_light(&self, queue: &wgpu::Queue, slot: u32) {
+        queue.write_buffer(
+            &self.point_lights,
+            slot as wgpu::BufferAddress * GpuPointLight::SIZE,
+            bytemuck::bytes_of(&self.point_lights_data[slot as usize].unwrap_or_default()),
+        );
+    }
+}
+
+impl Resource for LightsManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn photometric_units() {
+        let candela = 100.0;
+        let color = PhotometricUnits::candela_to_radiant(candela);
+        assert_eq!(color, 100.0 / 683.0);
+
+        // A light emitting 1 W uniformly over the sphere
+        let color = PhotometricUnits::watts_to_radiant(1.0);
+        assert!((color * 4.0 * std::f32::consts::PI - 1.0).abs() < 1e-6);
+
+        // The sun, roughly 100 000 lx, is about 146 W/m² of 555nm light
+        assert!((PhotometricUnits::lux_to_radiant(100_000.0) - 146.41).abs() < 0.01);
+
+        // Irradiance at the range is the cutoff
+        let color = glam::vec3(2.0, 8.0, 4.0);
+        let range = PhotometricUnits::point_light_range(color);
+        assert!(
+            (color.max_element() / (range * range) - PhotometricUnits::RANGE_CUTOFF).abs() < 1e-6
+        );
+    }
+
+    fn point_light(i: usize) -> PointLight {
+        PointLight {
+            position: glam::vec3(i as f32, 0.0, 0.0),
+            radius: 1.0,
+            color: glam::Vec3::ONE,
+            cast_shadows: i.is_multiple_of(10),
+        }
+    }
+
+    #[test]
+    fn streamed_point_lights() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = LightsManager::new(&device);
+
+        const CHUNK: usize = 100;
+        const LOADED_CHUNKS: usize = 5;
+
+        let mut chunks = std::collections::VecDeque::new();
+        for chunk in 0..50 {
+            if chunks.len() == LOADED_CHUNKS {
+                let ids: Vec<PointLightId> = chunks.pop_front().unwrap();
+                manager.remove_point_lights(&queue, &ids);
+                // Removing twice is a no-op
+                manager.remove_point_lights(&queue, &ids);
+            }
+
+            let lights = (0..CHUNK)
+                .map(|i| point_light(chunk * CHUNK + i))
+                .collect::<Vec<_>>();
+            chunks.push_back(manager.add_point_lights(&queue, &lights));
+
+            assert_eq!(manager.count_point_lights() as usize, chunks.len() * CHUNK);
+            assert!(manager.point_lights_high_water_mark() as usize <= LOADED_CHUNKS * CHUNK);
+            assert!(
+                manager.point_light_shadows().len() <= LightsManager::MAX_SHADOWED_POINT_LIGHTS
+            );
+        }
+
+        // Shadow slots of the unloaded chunks went to the loaded ones
+        let shadowed = manager
+            .point_lights_data
+            .iter()
+            .flatten()
+            .filter(|point_light| point_light.shadow_slot >= 0)
+            .count();
+        assert_eq!(shadowed, LightsManager::MAX_SHADOWED_POINT_LIGHTS);
+    }
+
+    #[test]
+    fn update_point_light() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut manager = LightsManager::new(&device);
+        let ids = manager.add_point_lights(&queue, &[point_light(1), point_light(2)]);
+
+        let moved = PointLight {
+            position: glam::vec3(4.0, 5.0, 6.0),
+            cast_shadows: true,
+            ..point_light(1)
+        };
+        manager.update_point_light(&queue, ids[0], moved);
+
+        let data = manager.point_lights_data[0].unwrap();
+        assert_eq!(data.position, moved.position);
+        assert_eq!(data.shadow_slot, 0);
+        assert_eq!(manager.point_light_shadows(), [moved]);
+
+        manager.update_point_light(&queue, ids[0], point_light(1));
+        assert_eq!(manager.point_lights_data[0].unwrap().shadow_slot, -1);
+        assert_eq!(manager.free_shadow_slots, [0]);
+
+        // Removed ids are ignored
+        manager.remove_point_lights(&queue, &ids[1..]);
+        manager.update_point_light(&queue, ids[1], moved);
+        assert_eq!(manager.point_lights_data[1], None);
+        assert_eq!(manager.count_point_lights(), 1);
+    }
+}
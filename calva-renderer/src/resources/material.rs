@@ -0,0 +1,230 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::{Resource, TextureId};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialId(u32);
+
+/// How the albedo alpha channel is interpreted
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Alpha is ignored
+    #[default]
+    Opaque,
+    /// Fragments with an alpha below [`Material::alpha_cutoff`] are discarded
+    Mask,
+    /// Needs a forward transparent pass which the deferred pipeline lacks,
+    /// rendered as [`AlphaMode::Mask`] meanwhile
+    Blend,
+}
+
+/// UV transform of a texture slot, from `KHR_texture_transform`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextureTransform {
+    pub offset: [f32; 2],
+    /// Counter-clockwise, in radians
+    pub rotation: f32,
+    pub scale: [f32; 2],
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0; 2],
+            rotation: 0.0,
+            scale: [1.0; 2],
+        }
+    }
+}
+
+impl TextureTransform {
+    /// Translation * rotation * scale, as the columns of a `mat3x2`
+    pub fn matrix(&self) -> [f32; 6] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let [x, y] = self.scale;
+        let [u, v] = self.offset;
+
+        [x * cos, -x * sin, y * sin, y * cos, u, v]
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Material {
+    pub albedo: TextureId,
+    pub normal: TextureId,
+    pub metallic_roughness: TextureId,
+    pub emissive: TextureId,
+    /// Linear emitted color, multiplied with the emissive texture if any
+    pub emissive_factor: [f32; 3],
+    /// HDR multiplier of the emitted color, from `KHR_materials_emissive_strength`
+    pub emissive_strength: f32,
+    /// Linear color, multiplied with the albedo texture if any
+    pub base_color_factor: [f32; 4],
+    /// Multiplied with the metallic texture channel if any
+    pub metallic_factor: f32,
+    /// Multiplied with the roughness texture channel if any
+    pub roughness_factor: f32,
+    pub alpha_mode: AlphaMode,
+    /// Alpha threshold of [`AlphaMode::Mask`] materials
+    pub alpha_cutoff: f32,
+    pub albedo_transform: TextureTransform,
+    pub normal_transform: TextureTransform,
+    pub metallic_roughness_transform: TextureTransform,
+    pub emissive_transform: TextureTransform,
+    /// Ambient occlusion in the red channel, only darkens the ambient light.
+    /// Often the same texture as [`Material::metallic_roughness`].
+    pub occlusion: TextureId,
+    /// Amount of the occlusion texture applied
+    pub occlusion_strength: f32,
+    pub occlusion_transform: TextureTransform,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            albedo: Default::default(),
+            normal: Default::default(),
+            metallic_roughness: Default::default(),
+            emissive: Default::default(),
+            emissive_factor: [0.0; 3],
+            emissive_strength: 1.0,
+            base_color_factor: [1.0; 4],
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            albedo_transform: Default::default(),
+            normal_transform: Default::default(),
+            metallic_roughness_transform: Default::default(),
+            emissive_transform: Default::default(),
+            occlusion: Default::default(),
+            occlusion_strength: 1.0,
+            occlusion_transform: Default::default(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuMaterial {
+    albedo: TextureId,
+    normal: TextureId,
+    metallic_roughness: TextureId,
+    emissive: TextureId,
+    emissive_factor: [f32; 3],
+    emissive_strength: f32,
+    base_color_factor: [f32; 4],
+    metallic_factor: f32,
+    roughness_factor: f32,
+    alpha_mode: u32,
+    alpha_cutoff: f32,
+    /// Albedo, normal, metallic roughness, emissive and occlusion
+    uv_transforms: [[f32; 6]; 5],
+    occlusion: TextureId,
+    occlusion_strength: f32,
+}
+
+impl GpuMaterial {
+    const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+}
+
+impl From<&Material> for GpuMaterial {
+    fn from(material: &Material) -> Self {
+        Self {
+            albedo: material.albedo,
+            normal: material.normal,
+            metallic_roughness: material.metallic_roughness,
+            emissive: material.emissive,
+            emissive_factor: material.emissive_factor,
+            emissive_strength: material.emissive_strength,
+            base_color_factor: material.base_color_factor,
+            metallic_factor: material.metallic_factor,
+            roughness_factor: material.roughness_factor,
+            alpha_mode: material.alpha_mode as _,
+            alpha_cutoff: material.alpha_cutoff,
+            uv_transforms: [
+                material.albedo_transform.matrix(),
+                material.normal_transform.matrix(),
+                material.metallic_roughness_transform.matrix(),
+                material.emissive_transform.matrix(),
+                material.occlusion_transform.matrix(),
+            ],
+            occlusion: material.occlusion,
+            occlusion_strength: material.occlusion_strength,
+        }
+    }
+}
+
+pub struct MaterialsManager {
+    material_index: AtomicU32,
+    buffer: wgpu::Buffer,
+
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl MaterialsManager {
+    const MAX_MATERIALS: usize = 256;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        use wgpu::util::DeviceExt;
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MaterialManager buffer"),
+            contents: bytemuck::cast_slice(
+                &[GpuMaterial::from(&Material::default()); Self::MAX_MATERIALS],
+            ),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("MaterialManager bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(GpuMaterial::SIZE),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("MaterialManager bind group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            material_index: AtomicU32::new(1),
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn add(&self, queue: &wgpu::Queue, material: Material) -> MaterialId {
+        let index = self.material_index.fetch_add(1, Ordering::Relaxed);
+        let offset = index as wgpu::BufferAddress * GpuMaterial::SIZE;
+
+        queue.write_buffer(
+            &self.buffer,
+            offset,
+            bytemuck::bytes_of(&GpuMaterial::from(&material)),
+        );
+
+        MaterialId(index)
+    }
+}
+
+impl Resource for MaterialsManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
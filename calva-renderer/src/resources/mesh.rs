@@ -1,11 +1,17 @@
 use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use parking_lot::RwLock;
 
-use crate::{Ressource, SkinIndex};
+use crate::{Instance, Resource, SkinIndex};
 
 #[repr(C)]
-#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct MeshId(u32);
 
+impl MeshId {
+    /// Mesh of the freed instances slots, matches `REMOVED_MESH` in the cull shaders
+    pub(crate) const REMOVED: Self = Self(u32::MAX);
+}
+
 impl From<MeshId> for u32 {
     fn from(value: MeshId) -> u32 {
         value.0
@@ -24,6 +30,22 @@ pub(crate) struct MeshBoundingSphere {
     radius: f32,
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct MeshLod {
+    vertex_count: u32,
+    base_index: u32,
+}
+
+/// Lower detail levels of a mesh, the mesh itself being LOD 0
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct MeshLods {
+    /// Levels set by [`MeshesManager::add_lod`], the mesh itself excluded
+    count: u32,
+    levels: [MeshLod; MeshesManager::MAX_LODS - 1],
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct MeshInfo {
@@ -32,15 +54,34 @@ pub(crate) struct MeshInfo {
     vertex_offset: i32,
     skin_offset: i32,
     bounding_sphere: MeshBoundingSphere,
+    lods: MeshLods,
+    _padding: u32,
 }
 impl MeshInfo {
     pub(crate) const SIZE: wgpu::BufferAddress = std::mem::size_of::<Self>() as _;
+
+    const LODS_OFFSET: wgpu::BufferAddress =
+        (std::mem::size_of::<[u32; 4]>() + std::mem::size_of::<MeshBoundingSphere>()) as _;
+}
+
+/// Range of a mesh in the shared vertex buffers of [`MeshesManager`]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MeshVertices {
+    pub offset: u32,
+    pub count: u32,
+    /// Tangents whose `w` (handedness) is neither `1.0` nor `-1.0`, which
+    /// flips or zeroes the bitangent. Usually a broken export, glTF requires
+    /// a unit sign.
+    pub invalid_tangents: u32,
 }
 
 pub struct MeshesManager {
     vertex_offset: AtomicI32,
     base_index: AtomicU32,
     mesh_index: AtomicU32,
+    /// CPU copy of the meshes bounding spheres
+    bounds: RwLock<Vec<(glam::Vec3, f32)>>,
+    vertices_ranges: RwLock<Vec<MeshVertices>>,
 
     pub(crate) meshes_info: wgpu::Buffer,
 
@@ -59,6 +100,10 @@ impl MeshesManager {
     pub const INDEX_SIZE: wgpu::BufferAddress = std::mem::size_of::<u32>() as _;
 
     pub const MAX_MESHES: usize = 1 << 12;
+    /// Levels of detail per mesh, the mesh itself included. The geometry pass
+    /// keeps a copy of the draw instances layout per LOD, so its draw instances
+    /// memory is `MAX_LODS` times the instances capacity.
+    pub const MAX_LODS: usize = 4;
     pub const MAX_VERTS: usize = 1 << 22;
 
     pub fn new(device: &wgpu::Device) -> Self {
@@ -110,6 +155,8 @@ impl MeshesManager {
             vertex_offset: AtomicI32::new(0),
             base_index: AtomicU32::new(0),
             mesh_index: AtomicU32::new(0),
+            bounds: Default::default(),
+            vertices_ranges: Default::default(),
 
             meshes_info,
 
@@ -125,6 +172,38 @@ impl MeshesManager {
         self.mesh_index.load(Ordering::Relaxed)
     }
 
+    /// Bounding sphere `(center, radius)` of a mesh, in model space
+    pub fn bounds(&self, mesh: MeshId) -> (glam::Vec3, f32) {
+        self.bounds.read()[mesh.0 as usize]
+    }
+
+    pub fn vertices(&self, mesh: MeshId) -> MeshVertices {
+        self.vertices_ranges.read()[mesh.0 as usize]
+    }
+
+    /// Number of tangents with a `w` component other than `1.0` or `-1.0`,
+    /// see [`MeshVertices::invalid_tangents`]
+    pub fn count_invalid_tangents(tangents: &[u8]) -> u32 {
+        // Vertex data may come unaligned straight from a glTF buffer
+        tangents
+            .chunks_exact(Self::TANGENT_SIZE as _)
+            .map(|tangent| bytemuck::pod_read_unaligned::<f32>(&tangent[12..]))
+            .filter(|w| w.abs() != 1.0)
+            .count() as _
+    }
+
+    /// Bounding sphere `(center, radius)` of an instance, in world space. Test
+    /// it against a [`Frustum`](crate::Frustum) to cull instances on the CPU.
+    pub fn instance_bounds(&self, instance: &Instance) -> (glam::Vec3, f32) {
+        let (center, radius) = self.bounds(instance.mesh);
+        let (scale, _, _) = instance.transform.to_scale_rotation_translation();
+
+        (
+            instance.transform.transform_point3(center),
+            radius * scale.abs().max_element(),
+        )
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn add(
         &self,
@@ -175,6 +254,27 @@ impl MeshesManager {
             .unwrap_or_default();
 
         let mesh_index = self.mesh_index.fetch_add(1, Ordering::Relaxed);
+
+        {
+            let mut bounds = self.bounds.write();
+            if bounds.len() <= mesh_index as usize {
+                bounds.resize(mesh_index as usize + 1, Default::default());
+            }
+            bounds[mesh_index as usize] = bounding_sphere;
+        }
+
+        {
+            let mut vertices_ranges = self.vertices_ranges.write();
+            if vertices_ranges.len() <= mesh_index as usize {
+                vertices_ranges.resize(mesh_index as usize + 1, Default::default());
+            }
+            vertices_ranges[mesh_index as usize] = MeshVertices {
+                offset: vertex_offset as _,
+                count: vertex_len as _,
+                invalid_tangents: Self::count_invalid_tangents(tangents),
+            };
+        }
+
         queue.write_buffer(
             &self.meshes_info,
             mesh_index as wgpu::BufferAddress * MeshInfo::SIZE,
@@ -187,15 +287,75 @@ impl MeshesManager {
                     center: bounding_sphere.0.to_array(),
                     radius: bounding_sphere.1,
                 },
+                lods: Default::default(),
+                _padding: 0,
             }),
         );
 
         MeshId(mesh_index)
     }
+
+    /// Registers lower detail levels for a mesh, from the most to the least
+    /// detailed, replacing the previous ones. LODs share the mesh vertices and
+    /// bounding sphere, only their indices differ: each LOD is a triangle list
+    /// indexing into the mesh vertices, bounded by its vertex count.
+    ///
+    /// The geometry pass picks the LOD from the mesh size on screen, see
+    /// [`GeometryConfig::lod`](crate::GeometryConfig::lod).
+    pub fn add_lod(&self, queue: &wgpu::Queue, mesh: MeshId, lods: &[&[u32]]) {
+        assert!(
+            lods.len() < Self::MAX_LODS,
+            "Meshes can have at most {} LODs, the mesh included",
+            Self::MAX_LODS
+        );
+
+        let vertices = self.vertices(mesh);
+        for (level, indices) in lods.iter().enumerate() {
+            assert!(
+                !indices.is_empty() && indices.len() % 3 == 0,
+                "LOD {} of {mesh:?} has {} indices, not a triangle list",
+                level + 1,
+                indices.len()
+            );
+            assert!(
+                indices.iter().all(|index| *index < vertices.count),
+                "LOD {} of {mesh:?} indexes past its {} vertices",
+                level + 1,
+                vertices.count
+            );
+        }
+
+        let mut mesh_lods = MeshLods {
+            count: lods.len() as _,
+            ..Default::default()
+        };
+
+        for (level, indices) in mesh_lods.levels.iter_mut().zip(lods) {
+            let vertex_count = indices.len() as u32;
+            let base_index = self.base_index.fetch_add(vertex_count, Ordering::Relaxed);
+
+            queue.write_buffer(
+                &self.indices,
+                base_index as wgpu::BufferAddress * Self::INDEX_SIZE,
+                bytemuck::cast_slice(indices),
+            );
+
+            *level = MeshLod {
+                vertex_count,
+                base_index,
+            };
+        }
+
+        queue.write_buffer(
+            &self.meshes_info,
+            mesh.0 as wgpu::BufferAddress * MeshInfo::SIZE + MeshInfo::LODS_OFFSET,
+            bytemuck::bytes_of(&mesh_lods),
+        );
+    }
 }
 
-impl Ressource for MeshesManager {
-    fn instanciate(device: &wgpu::Device) -> Self {
+impl Resource for MeshesManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
         Self::new(device)
     }
 }
@@ -0,0 +1,142 @@
+mod animation;
+mod camera;
+mod decal;
+mod instance;
+mod light;
+mod material;
+mod mesh;
+mod skin;
+mod skybox;
+mod texture;
+
+pub use animation::*;
+pub use camera::*;
+pub use decal::*;
+pub use instance::*;
+pub use light::*;
+pub use material::*;
+pub use mesh::*;
+pub use skin::*;
+pub use skybox::*;
+pub use texture::*;
+
+use parking_lot::RwLock;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+pub trait Resource: Sized {
+    fn instantiate(device: &wgpu::Device) -> Self;
+}
+
+pub struct ResourceRef<T>(Arc<RwLock<T>>)
+where
+    T: ?Sized;
+
+// Not derived, the resource itself doesn't need to be `Clone`
+impl<T: ?Sized> Clone for ResourceRef<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> ResourceRef<T> {
+    pub fn get(&self) -> impl std::ops::Deref<Target = T> + '_ {
+        self.0.as_ref().read()
+    }
+
+    pub fn get_mut(&self) -> impl std::ops::DerefMut<Target = T> + '_ {
+        self.0.as_ref().write()
+    }
+}
+
+pub struct ResourcesManager {
+    device: Arc<wgpu::Device>,
+    resources: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+impl ResourcesManager {
+    pub fn new(device: Arc<wgpu::Device>) -> Self {
+        Self {
+            device,
+            resources: Default::default(),
+        }
+    }
+
+    /// Shared instance of `T`, instantiated from the device on first use
+    pub fn get<T>(&self) -> ResourceRef<T>
+    where
+        T: Resource + Send + Sync + 'static,
+    {
+        self.get_or_insert_with(|| <T as Resource>::instantiate(&self.device))
+    }
+
+    /// Shared instance of `T`, built by `f` on first use. Allows resources
+    /// which need more than the device, like game-side singletons.
+    pub fn get_or_insert_with<T>(&self, f: impl FnOnce() -> T) -> ResourceRef<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let read = self.resources.read();
+
+        let arc = match read.get(&TypeId::of::<T>()) {
+            Some(arc) => arc.clone(),
+            None => {
+                drop(read); // prevent deadlock
+
+                self.resources
+                    .write()
+                    .entry(TypeId::of::<T>())
+                    .or_insert_with(|| Arc::new(RwLock::new(f())))
+                    .clone()
+            }
+        };
+
+        ResourceRef(arc.downcast::<RwLock<T>>().unwrap())
+    }
+
+    /// Sets the shared instance of `T`. References obtained before see the
+    /// new value.
+    pub fn insert<T>(&self, value: T) -> ResourceRef<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let mut value = Some(value);
+        let resource = self.get_or_insert_with(|| value.take().unwrap());
+
+        if let Some(value) = value {
+            *resource.get_mut() = value;
+        }
+
+        resource
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[derive(Debug, PartialEq)]
+    struct Score(u32);
+
+    #[test]
+    fn user_resources() {
+        let Some((device, _)) = test_utils::device() else {
+            return;
+        };
+
+        let resources = ResourcesManager::new(Arc::new(device));
+
+        let score = resources.get_or_insert_with(|| Score(1));
+        assert_eq!(*resources.get_or_insert_with(|| Score(2)).get(), Score(1));
+
+        resources.insert(Score(3));
+        assert_eq!(*score.get(), Score(3));
+
+        score.get_mut().0 += 1;
+        assert_eq!(*resources.get_or_insert_with(|| Score(0)).get(), Score(4));
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::atomic::{AtomicU32, Ordering};
 
-use crate::{MeshesManager, Ressource};
+use crate::{MeshesManager, Resource};
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
@@ -12,10 +12,25 @@ impl SkinIndex {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkeletonId(u32);
+
+/// Joints hierarchy of a skin, in the order of the baked animations joints.
+/// Only used for debugging, see [`SkeletonDebugPass`](crate::SkeletonDebugPass).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Skeleton {
+    /// Index of each joint parent, `None` for the roots
+    pub parents: Vec<Option<u32>>,
+    /// Position of each joint in the mesh space, in bind pose
+    pub bind_positions: Vec<glam::Vec3>,
+}
+
 pub struct SkinsManager {
     offset: AtomicU32,
     joints: wgpu::Buffer,
     weights: wgpu::Buffer,
+    skeletons: Vec<Skeleton>,
 
     pub(crate) bind_group_layout: wgpu::BindGroupLayout,
     pub(crate) bind_group: wgpu::BindGroup,
@@ -89,6 +104,7 @@ impl SkinsManager {
             offset: AtomicU32::new(1),
             joints,
             weights,
+            skeletons: vec![],
 
             bind_group_layout,
             bind_group,
@@ -113,10 +129,19 @@ impl SkinsManager {
 
         SkinIndex(offset)
     }
+
+    pub fn add_skeleton(&mut self, skeleton: Skeleton) -> SkeletonId {
+        self.skeletons.push(skeleton);
+        SkeletonId(self.skeletons.len() as u32 - 1)
+    }
+
+    pub fn skeleton(&self, id: SkeletonId) -> Option<&Skeleton> {
+        self.skeletons.get(id.0 as usize)
+    }
 }
 
-impl Ressource for SkinsManager {
-    fn instanciate(device: &wgpu::Device) -> Self {
+impl Resource for SkinsManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
         Self::new(device)
     }
 }
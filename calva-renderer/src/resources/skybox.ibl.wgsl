@@ -0,0 +1,164 @@
+const PI: f32 = 3.14159265359;
+
+const PREFILTER_SIZE: f32 = 128.0;
+const PREFILTER_MIPS: u32 = 5u;
+const PREFILTER_SAMPLES: u32 = 256u;
+const BRDF_LUT_SAMPLES: u32 = 512u;
+
+@group(0) @binding(0) var t_skybox: texture_cube<f32>;
+@group(0) @binding(1) var t_sampler: sampler;
+
+@group(1) @binding(0) var t_output: texture_storage_2d_array<rgba16float, write>;
+@group(1) @binding(1) var t_brdf_lut: texture_storage_2d<rgba16float, write>;
+
+// World space direction of a cube face texel
+fn cube_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let c = uv * 2.0 - 1.0;
+
+    switch face {
+        case 0u: { return normalize(vec3<f32>(1.0, -c.y, -c.x)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -c.y, c.x)); }
+        case 2u: { return normalize(vec3<f32>(c.x, 1.0, c.y)); }
+        case 3u: { return normalize(vec3<f32>(c.x, -1.0, -c.y)); }
+        case 4u: { return normalize(vec3<f32>(c.x, -c.y, 1.0)); }
+        default: { return normalize(vec3<f32>(-c.x, -c.y, -1.0)); }
+    }
+}
+
+fn tangent_frame(n: vec3<f32>) -> mat3x3<f32> {
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(n.y) > 0.999 {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+
+    return mat3x3<f32>(tangent, bitangent, n);
+}
+
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    let radical_inverse = f32(reverseBits(i)) * 2.3283064365386963e-10;
+    return vec2<f32>(f32(i) / f32(count), radical_inverse);
+}
+
+// Half vector around the normal, distributed as the GGX lobe
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+    let h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    return normalize(tangent_frame(n) * h);
+}
+
+fn geometry_schlick_ggx(NdotV: f32, roughness: f32) -> f32 {
+    // IBL remapping of k
+    let k = (roughness * roughness) / 2.0;
+
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+fn texel_uv(id: vec2<u32>, size: vec2<u32>) -> vec2<f32> {
+    return (vec2<f32>(id) + 0.5) / vec2<f32>(size);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn irradiance(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(t_output);
+    if any(global_id.xy >= size) { return; }
+
+    let n = cube_direction(global_id.z, texel_uv(global_id.xy, size));
+    let frame = tangent_frame(n);
+
+    let sample_delta = 0.05;
+    var irradiance = vec3<f32>(0.0);
+    var samples = 0.0;
+
+    for (var phi = 0.0; phi < 2.0 * PI; phi += sample_delta) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += sample_delta) {
+            let tangent_sample = vec3<f32>(
+                sin(theta) * cos(phi),
+                sin(theta) * sin(phi),
+                cos(theta),
+            );
+
+            let color = textureSampleLevel(t_skybox, t_sampler, frame * tangent_sample, 0.0).rgb;
+            irradiance += color * cos(theta) * sin(theta);
+            samples += 1.0;
+        }
+    }
+
+    irradiance = PI * irradiance / samples;
+
+    textureStore(t_output, global_id.xy, global_id.z, vec4<f32>(irradiance, 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn prefilter(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(t_output);
+    if any(global_id.xy >= size) { return; }
+
+    // Each mip is dispatched separately, roughness is deduced from its size
+    let roughness = log2(PREFILTER_SIZE / f32(size.x)) / f32(PREFILTER_MIPS - 1u);
+
+    let n = cube_direction(global_id.z, texel_uv(global_id.xy, size));
+    let v = n;
+
+    var color = vec3<f32>(0.0);
+    var weight = 0.0;
+
+    for (var i = 0u; i < PREFILTER_SAMPLES; i++) {
+        let h = importance_sample_ggx(hammersley(i, PREFILTER_SAMPLES), n, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+
+        let NdotL = dot(n, l);
+        if NdotL > 0.0 {
+            color += textureSampleLevel(t_skybox, t_sampler, l, 0.0).rgb * NdotL;
+            weight += NdotL;
+        }
+    }
+
+    textureStore(t_output, global_id.xy, global_id.z, vec4<f32>(color / max(weight, 0.0001), 1.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn brdf_lut(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let size = textureDimensions(t_brdf_lut);
+    if any(global_id.xy >= size) { return; }
+
+    let uv = texel_uv(global_id.xy, size);
+    let NdotV = max(uv.x, 0.001);
+    let roughness = uv.y;
+
+    let v = vec3<f32>(sqrt(1.0 - NdotV * NdotV), 0.0, NdotV);
+    let n = vec3<f32>(0.0, 0.0, 1.0);
+
+    var scale = 0.0;
+    var bias = 0.0;
+
+    for (var i = 0u; i < BRDF_LUT_SAMPLES; i++) {
+        let h = importance_sample_ggx(hammersley(i, BRDF_LUT_SAMPLES), n, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+
+        let NdotL = max(l.z, 0.0);
+        let NdotH = max(h.z, 0.0);
+        let VdotH = max(dot(v, h), 0.0);
+
+        if NdotL > 0.0 {
+            let g = geometry_schlick_ggx(NdotV, roughness) * geometry_schlick_ggx(NdotL, roughness);
+            let g_vis = (g * VdotH) / max(NdotH * NdotV, 0.0001);
+            let fc = pow(1.0 - VdotH, 5.0);
+
+            scale += (1.0 - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+
+    let brdf = vec2<f32>(scale, bias) / f32(BRDF_LUT_SAMPLES);
+
+    textureStore(t_brdf_lut, global_id.xy, vec4<f32>(brdf, 0.0, 1.0));
+}
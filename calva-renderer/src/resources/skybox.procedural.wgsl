@@ -0,0 +1,106 @@
+const PI: f32 = 3.14159265359;
+
+// Preetham luminances are in kcd/m², brought to the range of image skyboxes
+const EXPOSURE: f32 = 0.1;
+
+const SUN_ANGULAR_RADIUS: f32 = 0.02;
+const SUN_INTENSITY: f32 = 20.0;
+
+struct SkyParams {
+    sun_dir: vec3<f32>,
+    turbidity: f32,
+    ground_color: vec3<f32>,
+}
+@group(0) @binding(0) var<uniform> params: SkyParams;
+
+@group(1) @binding(0) var t_output: texture_storage_2d_array<rgba16float, write>;
+
+// World space direction of a cube face texel
+fn cube_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let c = uv * 2.0 - 1.0;
+
+    switch face {
+        case 0u: { return normalize(vec3<f32>(1.0, -c.y, -c.x)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -c.y, c.x)); }
+        case 2u: { return normalize(vec3<f32>(c.x, 1.0, c.y)); }
+        case 3u: { return normalize(vec3<f32>(c.x, -1.0, -c.y)); }
+        case 4u: { return normalize(vec3<f32>(c.x, -c.y, 1.0)); }
+        default: { return normalize(vec3<f32>(-c.x, -c.y, -1.0)); }
+    }
+}
+
+// Perez sky distribution for the luminance and the two chromaticities at once,
+// from "A Practical Analytic Model for Daylight" (Preetham et al. 1999)
+fn perez(t: f32, cos_theta: f32, gamma: f32) -> vec3<f32> {
+    let a = vec3<f32>(0.1787, -0.0193, -0.0167) * t + vec3<f32>(-1.4630, -0.2592, -0.2608);
+    let b = vec3<f32>(-0.3554, -0.0665, -0.0950) * t + vec3<f32>(0.4275, 0.0008, 0.0092);
+    let c = vec3<f32>(-0.0227, -0.0004, -0.0079) * t + vec3<f32>(5.3251, 0.2125, 0.2102);
+    let d = vec3<f32>(0.1206, -0.0641, -0.0441) * t + vec3<f32>(-2.5771, -0.8989, -1.6537);
+    let e = vec3<f32>(-0.0670, -0.0033, -0.0109) * t + vec3<f32>(0.3703, 0.0452, 0.0529);
+
+    let cos_gamma = cos(gamma);
+    return (1.0 + a * exp(b / cos_theta)) * (1.0 + c * exp(d * gamma) + e * cos_gamma * cos_gamma);
+}
+
+// Zenith luminance and chromaticities for a sun at `theta_s` from the zenith
+fn zenith(t: f32, theta_s: f32) -> vec3<f32> {
+    let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+    let luminance = (4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192;
+
+    let th = vec3<f32>(theta_s * theta_s * theta_s, theta_s * theta_s, theta_s);
+    let x = t * t * dot(vec3<f32>(0.00166, -0.00375, 0.00209), th)
+        + t * (dot(vec3<f32>(-0.02903, 0.06377, -0.03202), th) + 0.00394)
+        + dot(vec3<f32>(0.11693, -0.21196, 0.06052), th) + 0.25886;
+    let y = t * t * dot(vec3<f32>(0.00275, -0.00610, 0.00317), th)
+        + t * (dot(vec3<f32>(-0.04214, 0.08970, -0.04153), th) + 0.00516)
+        + dot(vec3<f32>(0.15346, -0.26756, 0.06670), th) + 0.26688;
+
+    return vec3<f32>(max(luminance, 0.0), x, y);
+}
+
+fn xyY_to_rgb(xyY: vec3<f32>) -> vec3<f32> {
+    let luminance = xyY.x;
+    let x = xyY.y;
+    let y = max(xyY.z, 1e-4);
+
+    let xyz = vec3<f32>(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+
+    // XYZ to linear sRGB
+    return max(mat3x3<f32>(
+        3.2406, -0.9689, 0.0557,
+        -1.5372, 1.8758, -0.2040,
+        -0.4986, 0.0415, 1.0570,
+    ) * xyz, vec3<f32>(0.0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn generate(@builtin(global_invocation_id) gid: vec3<u32>) {
+    let size = textureDimensions(t_output);
+    if gid.x >= size.x || gid.y >= size.y {
+        return;
+    }
+
+    let uv = (vec2<f32>(gid.xy) + 0.5) / vec2<f32>(size);
+    let dir = cube_direction(gid.z, uv);
+    let sun_dir = normalize(params.sun_dir);
+    let t = params.turbidity;
+
+    // The model doesn't hold below the horizon, the sky fades out instead
+    let theta_s = acos(clamp(sun_dir.y, 0.0, 1.0));
+    let day = smoothstep(-0.1, 0.05, sun_dir.y);
+
+    let cos_theta = max(dir.y, 0.01);
+    let gamma = acos(clamp(dot(dir, sun_dir), -1.0, 1.0));
+
+    let zenith_xyY = zenith(t, theta_s);
+    let xyY = zenith_xyY * perez(t, cos_theta, gamma) / perez(t, 1.0, theta_s);
+    var sky = xyY_to_rgb(vec3<f32>(xyY.x * EXPOSURE * day, xyY.yz));
+
+    let sun = smoothstep(SUN_ANGULAR_RADIUS, SUN_ANGULAR_RADIUS * 0.8, gamma);
+    sky *= 1.0 + sun * SUN_INTENSITY;
+
+    let ground = params.ground_color * zenith_xyY.x * EXPOSURE * day;
+
+    let color = mix(ground, sky, smoothstep(-0.01, 0.01, dir.y));
+    textureStore(t_output, vec2<i32>(gid.xy), i32(gid.z), vec4<f32>(color, 1.0));
+}
@@ -0,0 +1,692 @@
+use wgpu::util::DeviceExt;
+
+use crate::{Resource, UniformBuffer, UniformData};
+
+/// Parameters of [`SkyboxManager::set_procedural_sky`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SkyParams {
+    /// Direction towards the sun, i.e. the opposite of the
+    /// [`DirectionalLight`](crate::DirectionalLight) direction. The sky fades
+    /// to black as the sun sets below the horizon.
+    pub sun_dir: glam::Vec3,
+    /// Haze of the atmosphere, from 2 for a clear sky to 10 for a hazy one
+    pub turbidity: f32,
+    /// Linear color below the horizon, lit by the sky
+    pub ground_color: [f32; 3],
+}
+
+impl Default for SkyParams {
+    fn default() -> Self {
+        Self {
+            sun_dir: glam::vec3(-0.5, 1.0, -0.5),
+            turbidity: 2.5,
+            ground_color: [0.3, 0.25, 0.2],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GpuSkyParams {
+    sun_dir: glam::Vec3,
+    turbidity: f32,
+    ground_color: glam::Vec3,
+    _padding: u32,
+}
+
+impl UniformData for SkyParams {
+    type GpuType = GpuSkyParams;
+
+    fn as_gpu_type(&self) -> Self::GpuType {
+        GpuSkyParams {
+            sun_dir: self.sun_dir.normalize_or_zero(),
+            turbidity: self.turbidity.max(1.0),
+            ground_color: glam::Vec3::from_array(self.ground_color),
+            _padding: 0,
+        }
+    }
+}
+
+pub struct SkyboxManager {
+    sampler: wgpu::Sampler,
+    procedural: Option<ProceduralSky>,
+
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: Option<wgpu::BindGroup>,
+
+    ibl: Ibl,
+    pub ibl_bind_group_layout: wgpu::BindGroupLayout,
+    pub ibl_bind_group: wgpu::BindGroup,
+}
+
+impl SkyboxManager {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Skybox sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skybox bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let ibl = Ibl::new(device);
+
+        let ibl_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox[ibl] bind group layout"),
+                entries: &[
+                    // Irradiance
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    // Prefiltered specular
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    // BRDF LUT
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    // Sampler
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let cube_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                array_layer_count: Some(6),
+                ..Default::default()
+            })
+        };
+
+        let ibl_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox[ibl] bind group"),
+            layout: &ibl_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&cube_view(&ibl.irradiance)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cube_view(&ibl.prefiltered)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &ibl.brdf_lut.create_view(&Default::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&ibl.sampler),
+                },
+            ],
+        });
+
+        Self {
+            sampler,
+            procedural: None,
+
+            bind_group_layout,
+            bind_group: None,
+
+            ibl,
+            ibl_bind_group_layout,
+            ibl_bind_group,
+        }
+    }
+
+    pub fn set_skybox(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pixels: &[u8]) {
+        let size = (pixels.len() as f32 / (4.0 * 6.0)).sqrt() as _;
+
+        let view = device
+            .create_texture_with_data(
+                queue,
+                &wgpu::TextureDescriptor {
+                    label: Some("Skybox texture"),
+                    size: wgpu::Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 6,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb],
+                },
+                pixels,
+            )
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Skybox texture view"),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                array_layer_count: Some(6),
+                ..Default::default()
+            });
+
+        self.set_skybox_view(device, queue, &view);
+    }
+
+    /// Skybox computed from an atmosphere model instead of cubemap images.
+    ///
+    /// The cubemap and the image based lighting maps are generated on the GPU
+    /// at each call, which is too slow to do every frame: when animating the
+    /// sun, only update the sky once it moved noticeably.
+    pub fn set_procedural_sky(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        params: SkyParams,
+    ) {
+        let procedural = self
+            .procedural
+            .get_or_insert_with(|| ProceduralSky::new(device));
+
+        procedural.generate(device, queue, params);
+
+        let view = procedural
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Skybox[procedural] texture view"),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                array_layer_count: Some(6),
+                ..Default::default()
+            });
+
+        self.set_skybox_view(device, queue, &view);
+    }
+
+    fn set_skybox_view(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+    ) {
+        self.ibl.generate(device, queue, view);
+
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        }));
+    }
+}
+
+impl Resource for SkyboxManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+/// Cubemap filled by a compute pass evaluating the Preetham sky model, kept
+/// around so that moving the sun doesn't reallocate it.
+struct ProceduralSky {
+    texture: wgpu::Texture,
+    params: UniformBuffer<SkyParams>,
+    output_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl ProceduralSky {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    const SIZE: u32 = 256;
+    const WORKGROUP_SIZE: u32 = 8;
+
+    fn new(device: &wgpu::Device) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Skybox[procedural] texture"),
+            size: wgpu::Extent3d {
+                width: Self::SIZE,
+                height: Self::SIZE,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+            view_formats: &[Self::FORMAT],
+        });
+
+        let params = UniformBuffer::new(device, SkyParams::default());
+
+        let output_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox[procedural] output bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: Self::FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                }],
+            });
+
+        let output_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox[procedural] output bind group"),
+            layout: &output_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.create_view(
+                    &wgpu::TextureViewDescriptor {
+                        dimension: Some(wgpu::TextureViewDimension::D2Array),
+                        ..Default::default()
+                    },
+                )),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("skybox.procedural.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox[procedural] pipeline layout"),
+            bind_group_layouts: &[&params.bind_group_layout, &output_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Skybox[procedural] pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "generate",
+        });
+
+        Self {
+            texture,
+            params,
+            output_bind_group,
+            pipeline,
+        }
+    }
+
+    fn generate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, params: SkyParams) {
+        *self.params = params;
+        self.params.update(queue);
+
+        let workgroups = Self::SIZE.div_ceil(Self::WORKGROUP_SIZE);
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Skybox[procedural] encoder"),
+        });
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Skybox[procedural]"),
+        });
+
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.params.bind_group, &[]);
+        cpass.set_bind_group(1, &self.output_bind_group, &[]);
+        cpass.dispatch_workgroups(workgroups, workgroups, 6);
+
+        drop(cpass);
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+/// Image based lighting maps derived from the skybox: a diffuse irradiance
+/// cubemap, a specular cubemap prefiltered per roughness mip, and the split-sum
+/// BRDF lookup table.
+struct Ibl {
+    irradiance: wgpu::Texture,
+    prefiltered: wgpu::Texture,
+    brdf_lut: wgpu::Texture,
+    brdf_lut_ready: bool,
+
+    sampler: wgpu::Sampler,
+
+    source_bind_group_layout: wgpu::BindGroupLayout,
+    output_bind_group_layout: wgpu::BindGroupLayout,
+    brdf_lut_bind_group_layout: wgpu::BindGroupLayout,
+
+    irradiance_pipeline: wgpu::ComputePipeline,
+    prefilter_pipeline: wgpu::ComputePipeline,
+    brdf_lut_pipeline: wgpu::ComputePipeline,
+}
+
+impl Ibl {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    const IRRADIANCE_SIZE: u32 = 32;
+    const PREFILTER_SIZE: u32 = 128;
+    const PREFILTER_MIPS: u32 = 5;
+    const BRDF_LUT_SIZE: u32 = 256;
+
+    const WORKGROUP_SIZE: u32 = 8;
+
+    fn new(device: &wgpu::Device) -> Self {
+        let make_texture = |label: &str, size: u32, layers: u32, mip_level_count: u32| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: layers,
+                },
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+                view_formats: &[Self::FORMAT],
+            })
+        };
+
+        let irradiance = make_texture("Skybox[ibl] irradiance", Self::IRRADIANCE_SIZE, 6, 1);
+        let prefiltered = make_texture(
+            "Skybox[ibl] prefiltered",
+            Self::PREFILTER_SIZE,
+            6,
+            Self::PREFILTER_MIPS,
+        );
+        let brdf_lut = make_texture("Skybox[ibl] brdf lut", Self::BRDF_LUT_SIZE, 1, 1);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Skybox[ibl] sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let source_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox[ibl] source bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let output_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox[ibl] output bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: Self::FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                    },
+                    count: None,
+                }],
+            });
+
+        let brdf_lut_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Skybox[ibl] brdf lut bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: Self::FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                }],
+            });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("skybox.ibl.wgsl"));
+
+        let cube_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox[ibl] cube pipeline layout"),
+            bind_group_layouts: &[&source_bind_group_layout, &output_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let brdf_lut_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Skybox[ibl] brdf lut pipeline layout"),
+                bind_group_layouts: &[&source_bind_group_layout, &brdf_lut_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let irradiance_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Skybox[ibl] irradiance pipeline"),
+                layout: Some(&cube_pipeline_layout),
+                module: &shader,
+                entry_point: "irradiance",
+            });
+
+        let prefilter_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Skybox[ibl] prefilter pipeline"),
+            layout: Some(&cube_pipeline_layout),
+            module: &shader,
+            entry_point: "prefilter",
+        });
+
+        let brdf_lut_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Skybox[ibl] brdf lut pipeline"),
+            layout: Some(&brdf_lut_pipeline_layout),
+            module: &shader,
+            entry_point: "brdf_lut",
+        });
+
+        Self {
+            irradiance,
+            prefiltered,
+            brdf_lut,
+            brdf_lut_ready: false,
+
+            sampler,
+
+            source_bind_group_layout,
+            output_bind_group_layout,
+            brdf_lut_bind_group_layout,
+
+            irradiance_pipeline,
+            prefilter_pipeline,
+            brdf_lut_pipeline,
+        }
+    }
+
+    fn generate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, skybox: &wgpu::TextureView) {
+        let source_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox[ibl] source bind group"),
+            layout: &self.source_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(skybox),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+
+        let output_bind_group = |texture: &wgpu::Texture, mip: u32| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Skybox[ibl] output bind group"),
+                layout: &self.output_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.create_view(
+                        &wgpu::TextureViewDescriptor {
+                            dimension: Some(wgpu::TextureViewDimension::D2Array),
+                            base_mip_level: mip,
+                            mip_level_count: Some(1),
+                            ..Default::default()
+                        },
+                    )),
+                }],
+            })
+        };
+
+        let irradiance_bind_group = output_bind_group(&self.irradiance, 0);
+        let prefilter_bind_groups = (0..Self::PREFILTER_MIPS)
+            .map(|mip| output_bind_group(&self.prefiltered, mip))
+            .collect::<Vec<_>>();
+        let brdf_lut_bind_group = (!self.brdf_lut_ready).then(|| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Skybox[ibl] brdf lut bind group"),
+                layout: &self.brdf_lut_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &self.brdf_lut.create_view(&Default::default()),
+                    ),
+                }],
+            })
+        });
+
+        let workgroups = |size: u32| (size as f32 / Self::WORKGROUP_SIZE as f32).ceil() as u32;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Skybox[ibl] encoder"),
+        });
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Skybox[ibl]"),
+        });
+
+        cpass.set_bind_group(0, &source_bind_group, &[]);
+
+        cpass.set_pipeline(&self.irradiance_pipeline);
+        cpass.set_bind_group(1, &irradiance_bind_group, &[]);
+        cpass.dispatch_workgroups(
+            workgroups(Self::IRRADIANCE_SIZE),
+            workgroups(Self::IRRADIANCE_SIZE),
+            6,
+        );
+
+        cpass.set_pipeline(&self.prefilter_pipeline);
+        for (mip, bind_group) in prefilter_bind_groups.iter().enumerate() {
+            let size = (Self::PREFILTER_SIZE >> mip).max(1);
+
+            cpass.set_bind_group(1, bind_group, &[]);
+            cpass.dispatch_workgroups(workgroups(size), workgroups(size), 6);
+        }
+
+        if let Some(bind_group) = &brdf_lut_bind_group {
+            cpass.set_pipeline(&self.brdf_lut_pipeline);
+            cpass.set_bind_group(1, bind_group, &[]);
+            cpass.dispatch_workgroups(
+                workgroups(Self::BRDF_LUT_SIZE),
+                workgroups(Self::BRDF_LUT_SIZE),
+                1,
+            );
+        }
+
+        drop(cpass);
+
+        queue.submit(Some(encoder.finish()));
+
+        self.brdf_lut_ready = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn procedural_sky() {
+        let Some((device, queue)) = test_utils::device() else {
+            return;
+        };
+
+        let mut skybox = SkyboxManager::new(&device);
+        assert!(skybox.bind_group.is_none());
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        skybox.set_procedural_sky(&device, &queue, Default::default());
+        // The cubemap is reused when the sun moves
+        skybox.set_procedural_sky(
+            &device,
+            &queue,
+            SkyParams {
+                sun_dir: glam::vec3(1.0, 0.1, 0.0),
+                ..Default::default()
+            },
+        );
+
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{error:?}");
+        assert!(skybox.bind_group.is_some());
+    }
+}
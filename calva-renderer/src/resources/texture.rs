@@ -0,0 +1,670 @@
+use anyhow::Result;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+
+use crate::Resource;
+
+/// Texture view and sampler indices, the view in the low 24 bits and the
+/// sampler in the high 8 bits
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextureId(u32);
+
+impl TextureId {
+    const SAMPLER_SHIFT: u32 = 24;
+
+    /// Same texture sampled with `sampler`, the null texture stays null
+    pub fn with_sampler(self, sampler: SamplerId) -> Self {
+        if self == Self::default() {
+            return self;
+        }
+
+        let view = self.0 & ((1 << Self::SAMPLER_SHIFT) - 1);
+        Self(view | sampler.0 << Self::SAMPLER_SHIFT)
+    }
+
+    pub fn sampler(self) -> SamplerId {
+        SamplerId(self.0 >> Self::SAMPLER_SHIFT)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct SamplerId(u32);
+
+/// Wrapping and filtering of a texture, see [`TexturesManager::sampler`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SamplerConfig {
+    pub address_mode_u: wgpu::AddressMode,
+    pub address_mode_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+    pub mipmap_filter: wgpu::FilterMode,
+}
+
+impl Default for SamplerConfig {
+    fn default() -> Self {
+        Self {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+pub struct TexturesManager {
+    mipmaps: MipmapGenerator,
+
+    views: Vec<wgpu::TextureView>,
+    /// Deduplicated, the first one is the default sampler
+    samplers: Vec<(SamplerConfig, wgpu::Sampler)>,
+    anisotropy: u16,
+
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) bind_group: wgpu::BindGroup,
+    /// Views or samplers changed since the last [`TexturesManager::flush`]
+    dirty: bool,
+}
+
+impl TexturesManager {
+    /// Highest anisotropic filtering level
+    pub const MAX_ANISOTROPY: u16 = 16;
+    /// Distinct samplers, textures added past it use the default one
+    pub const MAX_SAMPLERS: usize = 8;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let mipmaps = MipmapGenerator::new(device);
+
+        let max_textures = device.limits().max_sampled_textures_per_shader_stage;
+        let mut views = Vec::with_capacity(max_textures as _);
+
+        views.push(
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("TexturesManager null texture"),
+                    size: Default::default(),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::R8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[wgpu::TextureFormat::R8Unorm],
+                })
+                .create_view(&Default::default()),
+        );
+
+        let anisotropy = 1;
+        let samplers = vec![(
+            SamplerConfig::default(),
+            Self::create_sampler(device, &SamplerConfig::default(), anisotropy),
+        )];
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("TexturesManager bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: core::num::NonZeroU32::new(max_textures),
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: core::num::NonZeroU32::new(Self::MAX_SAMPLERS as _),
+                },
+            ],
+        });
+
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &views, &samplers);
+
+        Self {
+            mipmaps,
+
+            views,
+            samplers,
+            anisotropy,
+
+            bind_group_layout,
+            bind_group,
+            dirty: false,
+        }
+    }
+
+    /// Registers a texture view, usable by the shaders once the bind group is
+    /// recreated by the next [`TexturesManager::flush`]
+    pub fn add(&mut self, view: wgpu::TextureView) -> TextureId {
+        self.add_all([view])[0]
+    }
+
+    /// Same as [`TexturesManager::add`] for many views
+    pub fn add_all(
+        &mut self,
+        views: impl IntoIterator<Item = wgpu::TextureView>,
+    ) -> Vec<TextureId> {
+        let start = self.views.len() as u32;
+        self.views.extend(views);
+        self.dirty = true;
+
+        (start..self.views.len() as u32).map(TextureId).collect()
+    }
+
+    /// Recreates the bind group after views or samplers were added, called by
+    /// [`Engine::update`](crate::Engine::update). Textures loaded over a
+    /// frame share a single bind group creation, however they were added.
+    pub fn flush(&mut self, device: &wgpu::Device) {
+        if !std::mem::take(&mut self.dirty) {
+            return;
+        }
+
+        self.bind_group =
+            Self::create_bind_group(device, &self.bind_group_layout, &self.views, &self.samplers);
+    }
+
+    /// Sampler matching `config`, to be set on textures with
+    /// [`TextureId::with_sampler`]. Samplers are shared between textures.
+    pub fn sampler(&mut self, device: &wgpu::Device, config: SamplerConfig) -> SamplerId {
+        if let Some(index) = self.samplers.iter().position(|(c, _)| *c == config) {
+            return SamplerId(index as _);
+        }
+
+        if self.samplers.len() >= Self::MAX_SAMPLERS {
+            return SamplerId::default();
+        }
+
+        let sampler = Self::create_sampler(device, &config, self.anisotropy);
+        self.samplers.push((config, sampler));
+        self.dirty = true;
+
+        SamplerId(self.samplers.len() as u32 - 1)
+    }
+
+    pub fn generate_mipmaps(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        desc: &wgpu::TextureDescriptor,
+    ) -> Result<()> {
+        self.mipmaps.generate_mipmaps(device, queue, texture, desc)
+    }
+
+    /// Records the mipmaps generation in `encoder`, to upload many textures
+    /// with a single submit
+    pub fn encode_mipmaps(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        desc: &wgpu::TextureDescriptor,
+    ) -> Result<()> {
+        self.mipmaps.encode_mipmaps(device, encoder, texture, desc)
+    }
+
+    pub fn anisotropy(&self) -> u16 {
+        self.anisotropy
+    }
+
+    /// Sets the anisotropic filtering level of the textures, rounded down to
+    /// a power of two up to [`Self::MAX_ANISOTROPY`]. Backends without
+    /// anisotropic filtering keep sampling trilinearly, see
+    /// [`crate::Renderer::anisotropy_supported`].
+    pub fn set_anisotropy(&mut self, device: &wgpu::Device, anisotropy: u16) {
+        let anisotropy = 1 << anisotropy.clamp(1, Self::MAX_ANISOTROPY).ilog2();
+        if anisotropy == self.anisotropy {
+            return;
+        }
+
+        self.anisotropy = anisotropy;
+        for (config, sampler) in &mut self.samplers {
+            *sampler = Self::create_sampler(device, config, anisotropy);
+        }
+        self.dirty = true;
+    }
+
+    fn create_sampler(
+        device: &wgpu::Device,
+        config: &SamplerConfig,
+        anisotropy: u16,
+    ) -> wgpu::Sampler {
+        // Anisotropic filtering is only valid with linear filters
+        let linear = [config.mag_filter, config.min_filter, config.mipmap_filter]
+            .iter()
+            .all(|filter| *filter == wgpu::FilterMode::Linear);
+
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("TexturesManager sampler"),
+            address_mode_u: config.address_mode_u,
+            address_mode_v: config.address_mode_v,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: config.mag_filter,
+            min_filter: config.min_filter,
+            mipmap_filter: config.mipmap_filter,
+            anisotropy_clamp: if linear { anisotropy } else { 1 },
+            ..Default::default()
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        views: &[wgpu::TextureView],
+        samplers: &[(SamplerConfig, wgpu::Sampler)],
+    ) -> wgpu::BindGroup {
+        let max_textures = device.limits().max_sampled_textures_per_shader_stage;
+        let views = (0..max_textures as _)
+            .map(|i| views.get(i).unwrap_or(&views[0]))
+            .collect::<Vec<_>>();
+        let samplers = (0..Self::MAX_SAMPLERS)
+            .map(|i| &samplers.get(i).unwrap_or(&samplers[0]).1)
+            .collect::<Vec<_>>();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("TexturesManager bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureViewArray(
+                        &views,
+                        // &views.iter().collect::<Vec<_>>(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::SamplerArray(&samplers),
+                },
+            ],
+        })
+    }
+}
+
+impl Resource for TexturesManager {
+    fn instantiate(device: &wgpu::Device) -> Self {
+        Self::new(device)
+    }
+}
+
+struct MipmapGenerator {
+    bind_group_layout: wgpu::BindGroupLayout,
+
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipelines: RwLock<HashMap<wgpu::TextureFormat, wgpu::RenderPipeline>>,
+}
+
+impl MipmapGenerator {
+    /// Box filter over the input texels covered by each output texel,
+    /// weighted by their coverage so that odd sizes don't drop any texel.
+    /// Texels are loaded and stored through the texture format, so sRGB
+    /// textures are averaged in linear space.
+    const SHADER: &'static str = r#"
+        @vertex
+        fn vs_main(@builtin(vertex_index) vertex_index : u32) -> @builtin(position) vec4<f32> {
+            let tc = vec2<f32>(
+                f32(vertex_index >> 1u),
+                f32(vertex_index &  1u),
+            ) * 2.0;
+        
+            return vec4<f32>(tc * 2.0 - 1.0, 0.0, 1.0);
+        }
+        
+        @group(0) @binding(0) var t_input: texture_2d<f32>;
+        
+        @fragment
+        fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            let input_size = vec2<f32>(textureDimensions(t_input));
+            let output_size = max(floor(input_size / 2.0), vec2<f32>(1.0));
+            let scale = input_size / output_size;
+
+            let start = floor(position.xy) * scale;
+            let end = start + scale;
+
+            var color = vec4<f32>(0.0);
+            for (var y = floor(start.y); y < end.y; y += 1.0) {
+                let wy = min(end.y, y + 1.0) - max(start.y, y);
+
+                for (var x = floor(start.x); x < end.x; x += 1.0) {
+                    let wx = min(end.x, x + 1.0) - max(start.x, x);
+                    color += textureLoad(t_input, vec2<i32>(vec2<f32>(x, y)), 0) * wx * wy;
+                }
+            }
+
+            return color / (scale.x * scale.y);
+        }
+    "#;
+
+    fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("MipmapGenerator shader"),
+            source: wgpu::ShaderSource::Wgsl(Self::SHADER.into()),
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("MipmapGenerator bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("MipmapGenerator render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            bind_group_layout,
+
+            shader,
+            pipeline_layout,
+            pipelines: Default::default(),
+        }
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("MipmapGenerator render pipeline"),
+            layout: Some(&self.pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &self.shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &self.shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: Default::default(),
+            multiview: None,
+        })
+    }
+
+    pub fn generate_mipmaps(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        desc: &wgpu::TextureDescriptor,
+    ) -> Result<()> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("MipmapGenerator command encoder"),
+        });
+
+        self.encode_mipmaps(device, &mut encoder, texture, desc)?;
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(())
+    }
+
+    pub fn encode_mipmaps(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        desc: &wgpu::TextureDescriptor,
+    ) -> Result<()> {
+        let pipelines_read = self.pipelines.read();
+
+        let pipeline = match pipelines_read.get(&desc.format) {
+            Some(pipeline) => pipeline,
+            None => {
+                drop(pipelines_read);
+
+                self.pipelines
+                    .write()
+                    .insert(desc.format, self.create_pipeline(device, desc.format));
+
+                return self.encode_mipmaps(device, encoder, texture, desc);
+            }
+        };
+
+        let mips = (0..desc.mip_level_count)
+            .map(|mip_level| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for res in mips.windows(2).map(<&[_; 2]>::try_from) {
+            let [input, output] = res?;
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("MipmapGenerator bind group"),
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input),
+                }],
+            });
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("MipmapGenerator render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: output,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils;
+
+    /// Generates the mipmaps of a black and white checkerboard, returns the
+    /// last mip red value
+    fn checkerboard_mip(size: u32, format: wgpu::TextureFormat) -> Option<u8> {
+        let (device, queue) = test_utils::device()?;
+        let mipmaps = MipmapGenerator::new(&device);
+
+        let size = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        };
+        let desc = wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: size.max_mips(wgpu::TextureDimension::D2),
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        };
+        let texture = device.create_texture(&desc);
+
+        let pixels = (0..size.width * size.height)
+            .flat_map(|i| {
+                let (x, y) = (i % size.width, i / size.width);
+                [if (x + y) % 2 == 0 { 255 } else { 0 }; 4]
+            })
+            .collect::<Vec<u8>>();
+
+        queue.write_texture(
+            texture.as_image_copy(),
+            &pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        mipmaps
+            .generate_mipmaps(&device, &queue, &texture, &desc)
+            .unwrap();
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as _,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                mip_level: desc.mip_level_count - 1,
+                ..texture.as_image_copy()
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: Default::default(),
+            },
+            wgpu::Extent3d::default(),
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |res| res.unwrap());
+        device.poll(wgpu::Maintain::Wait);
+
+        let red = slice.get_mapped_range()[0];
+        Some(red)
+    }
+
+    #[test]
+    fn anisotropy_levels() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut textures = TexturesManager::new(&renderer.device);
+        assert_eq!(textures.anisotropy(), 1);
+
+        for (requested, level) in [(8, 8), (5, 4), (0, 1), (100, 16)] {
+            textures.set_anisotropy(&renderer.device, requested);
+            assert_eq!(textures.anisotropy(), level);
+        }
+    }
+
+    #[test]
+    fn add_all_views() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut textures = TexturesManager::new(&renderer.device);
+        let view = || {
+            renderer
+                .device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: None,
+                    size: Default::default(),
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&Default::default())
+        };
+
+        // The null texture comes first
+        assert_eq!(textures.add(view()), TextureId(1));
+        assert_eq!(
+            textures.add_all([view(), view()]),
+            [TextureId(2), TextureId(3)]
+        );
+        assert!(textures.dirty);
+
+        textures.flush(&renderer.device);
+        assert!(!textures.dirty);
+    }
+
+    #[test]
+    fn shared_samplers() {
+        let Some(renderer) = test_utils::renderer((64, 64)) else {
+            return;
+        };
+
+        let mut textures = TexturesManager::new(&renderer.device);
+        let clamp = SamplerConfig {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            textures.sampler(&renderer.device, Default::default()),
+            SamplerId(0)
+        );
+        assert_eq!(textures.sampler(&renderer.device, clamp), SamplerId(1));
+        assert_eq!(textures.sampler(&renderer.device, clamp), SamplerId(1));
+
+        let texture = TextureId(3).with_sampler(SamplerId(1));
+        assert_eq!(texture.sampler(), SamplerId(1));
+        assert_eq!(texture.with_sampler(SamplerId(2)), TextureId(3 | 2 << 24));
+        assert_eq!(
+            TextureId::default().with_sampler(SamplerId(1)),
+            TextureId::default()
+        );
+    }
+
+    #[test]
+    fn mipmaps_srgb() {
+        let Some(gray) = checkerboard_mip(4, wgpu::TextureFormat::Rgba8UnormSrgb) else {
+            return;
+        };
+
+        // Half the linear intensity, not half the encoded value
+        assert!(gray.abs_diff(188) <= 1, "{gray}");
+    }
+
+    #[test]
+    fn mipmaps_npot() {
+        let Some(gray) = checkerboard_mip(3, wgpu::TextureFormat::Rgba8Unorm) else {
+            return;
+        };
+
+        // 5 white texels out of 9
+        assert!(gray.abs_diff(142) <= 1, "{gray}");
+    }
+}
@@ -0,0 +1,62 @@
+//! Helpers shared by the tests needing a GPU.
+//!
+//! Without a compatible adapter these tests are skipped. Setting
+//! `CALVA_REQUIRE_GPU` turns the skip into a failure, so that a CI runner
+//! expected to have a GPU can't pass them vacuously.
+
+use crate::Renderer;
+
+const REQUIRE_GPU: &str = "CALVA_REQUIRE_GPU";
+
+fn skip(reason: &str) {
+    assert!(
+        std::env::var_os(REQUIRE_GPU).is_none(),
+        "{reason} ({REQUIRE_GPU} is set)"
+    );
+    eprintln!("{reason}, skipping");
+}
+
+/// Device of the default adapter, with the default features and limits
+pub fn device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let device = pollster::block_on(async {
+        let adapter = instance.request_adapter(&Default::default()).await?;
+        adapter.request_device(&Default::default(), None).await.ok()
+    });
+
+    if device.is_none() {
+        skip("no adapter available");
+    }
+    device
+}
+
+/// Offscreen renderer, see [`Renderer::new_headless`]
+pub fn renderer(size: (u32, u32)) -> Option<Renderer> {
+    let renderer = pollster::block_on(Renderer::new_headless(size)).ok();
+
+    if renderer.is_none() {
+        skip("no compatible adapter available");
+    }
+    renderer
+}
+
+/// Blocking copy of a buffer content, the buffer needs `COPY_SRC`
+pub fn read_buffer(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer) -> Vec<u8> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: buffer.size(),
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |res| res.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+
+    let data = slice.get_mapped_range().to_vec();
+    data
+}
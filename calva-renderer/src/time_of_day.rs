@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use crate::{DirectionalLight, Engine, Renderer, SkyParams, SkyboxManager};
+
+/// Day cycle driving the sun of an [`Engine`].
+///
+/// The sun rises in the +X direction at `0.25`, culminates at `0.5` and sets
+/// in the -X direction at `0.75`. Its light turns warm near the horizon and
+/// fades out at night. Optionally the ambient color is tinted along, and the
+/// skybox replaced by a [`SkyboxManager::set_procedural_sky`] following the
+/// sun.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeOfDay {
+    /// Normalized time of the day, `0.0` and `1.0` at midnight
+    pub time: f32,
+    /// Advance [`Self::time`] in [`Self::advance`]
+    pub playing: bool,
+    /// Days per second
+    pub speed: f32,
+    /// Tilt of the sun path towards -Z, in radians. `0.0` passes through the
+    /// zenith at noon
+    pub tilt: f32,
+    /// Sun irradiance at noon, see [`DirectionalLight::intensity`]
+    pub intensity: f32,
+    /// Update the [`AmbientLightPass`](crate::AmbientLightPass) color from
+    /// night to day
+    pub tint_ambient: bool,
+    /// Regenerate the procedural sky with the sun direction, other
+    /// parameters are taken from [`Self::sky`]
+    pub update_sky: bool,
+    pub sky: SkyParams,
+
+    /// Sun direction of the last generated sky
+    sky_sun_dir: Option<glam::Vec3>,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            time: 0.4,
+            playing: false,
+            // Two minutes days
+            speed: 1.0 / 120.0,
+            tilt: 0.5,
+            intensity: DirectionalLight::default().intensity,
+            tint_ambient: false,
+            update_sky: false,
+            sky: Default::default(),
+
+            sky_sun_dir: None,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// The procedural sky is only regenerated once the sun moved by this
+    /// angle, in radians
+    pub const SKY_UPDATE_ANGLE: f32 = 0.01;
+
+    const DAWN_COLOR: glam::Vec3 = glam::Vec3::new(1.0, 0.45, 0.2);
+    const NOON_COLOR: glam::Vec3 = glam::Vec3::new(0.9, 0.95, 1.0);
+
+    const NIGHT_AMBIENT: glam::Vec3 = glam::Vec3::new(0.005, 0.007, 0.015);
+    const DAY_AMBIENT: glam::Vec3 = glam::Vec3::new(0.4, 0.5, 0.7);
+
+    pub fn advance(&mut self, dt: Duration) {
+        if self.playing {
+            self.time = (self.time + self.speed * dt.as_secs_f32()).rem_euclid(1.0);
+        }
+    }
+
+    /// Direction towards the sun
+    pub fn sun_dir(&self) -> glam::Vec3 {
+        let angle = (self.time - 0.25) * std::f32::consts::TAU;
+
+        glam::Quat::from_rotation_x(-self.tilt) * glam::vec3(angle.cos(), angle.sin(), 0.0)
+    }
+
+    /// `0.0` at night up to `1.0` with the sun high enough
+    fn daylight(&self) -> f32 {
+        smoothstep(-0.05, 0.15, self.sun_dir().y)
+    }
+
+    pub fn sun_light(&self) -> DirectionalLight {
+        let sun_dir = self.sun_dir();
+        let color = Self::DAWN_COLOR.lerp(Self::NOON_COLOR, smoothstep(0.0, 0.5, sun_dir.y));
+
+        DirectionalLight {
+            direction: -sun_dir,
+            color: color.to_array(),
+            intensity: self.intensity * self.daylight(),
+        }
+    }
+
+    pub fn ambient_color(&self) -> [f32; 3] {
+        Self::NIGHT_AMBIENT
+            .lerp(Self::DAY_AMBIENT, self.daylight())
+            .to_array()
+    }
+
+    /// Writes the sun into the engine lights, to be called every frame before
+    /// [`Engine::update`]
+    pub fn apply(&mut self, renderer: &Renderer, engine: &mut Engine) {
+        engine.directional_light.uniform.light = self.sun_light();
+
+        if self.tint_ambient {
+            engine.ambient_light.config.color = self.ambient_color();
+        }
+
+        if !self.update_sky {
+            self.sky_sun_dir = None;
+            return;
+        }
+
+        let sun_dir = self.sun_dir();
+        let moved = self
+            .sky_sun_dir
+            .is_none_or(|last| last.angle_between(sun_dir) >= Self::SKY_UPDATE_ANGLE);
+
+        if moved {
+            engine
+                .resources
+                .get::<SkyboxManager>()
+                .get_mut()
+                .set_procedural_sky(
+                    &renderer.device,
+                    &renderer.queue,
+                    SkyParams {
+                        sun_dir,
+                        ..self.sky
+                    },
+                );
+            self.sky_sun_dir = Some(sun_dir);
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(feature = "egui")]
+impl egui::Widget for &mut TimeOfDay {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        egui::CollapsingHeader::new("Time of day")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let label = if self.playing { "Pause" } else { "Play" };
+                    if ui.button(label).clicked() {
+                        self.playing = !self.playing;
+                    }
+                    ui.add(egui::Slider::new(&mut self.time, 0.0..=1.0).text("Time"));
+                });
+
+                ui.add(
+                    egui::Slider::new(&mut self.speed, 0.0..=0.1)
+                        .logarithmic(true)
+                        .text("Speed (days/s)"),
+                );
+                ui.add(egui::Slider::new(&mut self.tilt, -1.5..=1.5).text("Tilt"));
+                ui.add(egui::Slider::new(&mut self.intensity, 0.0..=50.0).text("Intensity"));
+                ui.checkbox(&mut self.tint_ambient, "Tint ambient");
+                ui.checkbox(&mut self.update_sky, "Procedural sky");
+                ui.add(egui::Slider::new(&mut self.sky.turbidity, 2.0..=10.0).text("Turbidity"));
+            })
+            .header_response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_path() {
+        let mut time_of_day = TimeOfDay {
+            time: 0.5,
+            tilt: 0.0,
+            ..Default::default()
+        };
+
+        assert!(time_of_day.sun_dir().abs_diff_eq(glam::Vec3::Y, 1e-5));
+        let noon = time_of_day.sun_light();
+        assert_eq!(noon.intensity, time_of_day.intensity);
+        assert!(noon.direction.abs_diff_eq(-glam::Vec3::Y, 1e-5));
+
+        time_of_day.time = 0.25;
+        assert!(time_of_day.sun_dir().abs_diff_eq(glam::Vec3::X, 1e-5));
+        // Warmer at dawn
+        let dawn = time_of_day.sun_light();
+        assert!(dawn.color[2] < noon.color[2]);
+
+        time_of_day.time = 0.0;
+        assert_eq!(time_of_day.sun_light().intensity, 0.0);
+
+        // Paused
+        time_of_day.advance(Duration::from_secs(10));
+        assert_eq!(time_of_day.time, 0.0);
+
+        // Wraps around midnight
+        time_of_day.time = 0.9;
+        time_of_day.playing = true;
+        time_of_day.speed = 0.1;
+        time_of_day.advance(Duration::from_secs(2));
+        assert!((time_of_day.time - 0.1).abs() < 1e-5);
+    }
+}
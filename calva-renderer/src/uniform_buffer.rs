@@ -76,6 +76,42 @@ impl<T: Copy + PartialEq + UniformData> UniformBuffer<T> {
             queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.gpu.as_gpu_type()));
         }
     }
+
+    /// Modifies the value with `f` and uploads only the bytes that changed
+    /// since the last upload, for uniforms where a single field changes per
+    /// frame.
+    ///
+    /// The written range is widened to [`wgpu::COPY_BUFFER_ALIGNMENT`], so
+    /// the size of `T::GpuType` must be a multiple of it, as for
+    /// [`UniformBuffer::update`]. Its fields must follow the WGSL uniform
+    /// layout, the bytes are uploaded at their offset in `T::GpuType`.
+    pub fn update_with(&mut self, queue: &wgpu::Queue, f: impl FnOnce(&mut T)) {
+        f(&mut self.cpu);
+
+        if self.gpu == self.cpu {
+            return;
+        }
+
+        let old = self.gpu.as_gpu_type();
+        let new = self.cpu.as_gpu_type();
+        self.gpu = self.cpu;
+
+        let new = bytemuck::bytes_of(&new);
+        if let Some(range) = changed_range(bytemuck::bytes_of(&old), new) {
+            queue.write_buffer(&self.buffer, range.start as _, &new[range]);
+        }
+    }
+}
+
+/// Bytes differing between `old` and `new`, aligned for buffer writes
+fn changed_range(old: &[u8], new: &[u8]) -> Option<std::ops::Range<usize>> {
+    let align = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+    let differ = |(a, b): (&u8, &u8)| a != b;
+
+    let start = old.iter().zip(new).position(differ)?;
+    let end = new.len() - old.iter().rev().zip(new.iter().rev()).position(differ)?;
+
+    Some(start / align * align..(end.div_ceil(align) * align).min(new.len()))
 }
 
 impl<T> std::ops::Deref for UniformBuffer<T> {
@@ -91,3 +127,37 @@ impl<T> std::ops::DerefMut for UniformBuffer<T> {
         &mut self.cpu
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Camera {
+        view: [f32; 4],
+        proj: [f32; 4],
+        near: f32,
+        _padding: [u32; 3],
+    }
+
+    #[test]
+    fn changed_bytes_only() {
+        let old = Camera::default();
+        let bytes = |camera: &Camera| bytemuck::bytes_of(camera).to_vec();
+
+        let mut new = old;
+        new.proj[1] = 1.0;
+        assert_eq!(changed_range(&bytes(&old), &bytes(&new)), Some(20..24));
+
+        new.proj[3] = 2.0;
+        assert_eq!(changed_range(&bytes(&old), &bytes(&new)), Some(20..32));
+
+        assert_eq!(changed_range(&bytes(&old), &bytes(&old)), None);
+
+        // Byte changes are widened to the copy alignment
+        let (old, mut new) = ([0u8; 8], [0u8; 8]);
+        new[5] = 1;
+        assert_eq!(changed_range(&old, &new), Some(4..8));
+    }
+}
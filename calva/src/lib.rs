@@ -2,3 +2,32 @@
 pub use gltf;
 
 pub use renderer;
+
+/// Commonly used types of both the renderer and the glTF loader.
+///
+/// ```
+/// use calva::prelude::*;
+///
+/// let camera = Camera::default();
+/// let light = PointLight {
+///     radius: 5.0,
+///     cast_shadows: true,
+///     ..Default::default()
+/// };
+/// let instance = Instance::default();
+/// #[cfg(feature = "gltf")]
+/// let options = GltfOptions::default();
+///
+/// // Needs a GPU, only compiled
+/// async fn setup() -> Result<(Renderer, Engine), Box<dyn std::error::Error>> {
+///     let renderer = Renderer::new_headless((800, 600)).await?;
+///     let engine = Engine::new(&renderer);
+///     assert_eq!(engine.resources.get::<InstancesManager>().get().count(), 0);
+///     Ok((renderer, engine))
+/// }
+/// ```
+pub mod prelude {
+    #[cfg(feature = "gltf")]
+    pub use gltf::prelude::*;
+    pub use renderer::prelude::*;
+}
@@ -3,37 +3,144 @@ use std::time::Duration;
 use calva::renderer::Camera;
 use winit::{dpi::PhysicalSize, event::WindowEvent};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective {
+        fovy: f32, // rad
+        aspect: f32,
+        znear: f32,
+        zfar: f32,
+    },
+    Orthographic {
+        height: f32, // world units
+        aspect: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl Projection {
+    fn set_aspect(&mut self, value: f32) {
+        match self {
+            Self::Perspective { aspect, .. } | Self::Orthographic { aspect, .. } => *aspect = value,
+        }
+    }
+
+    pub fn matrix(&self) -> glam::Mat4 {
+        match *self {
+            Self::Perspective {
+                fovy,
+                aspect,
+                znear,
+                zfar,
+            } => glam::Mat4::perspective_rh(fovy, aspect, znear, zfar),
+            Self::Orthographic {
+                height,
+                aspect,
+                znear,
+                zfar,
+            } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect;
+
+                glam::Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    znear,
+                    zfar,
+                )
+            }
+        }
+    }
+}
+
 pub struct MyCamera {
-    pub aspect: f32,
-    pub fovy: f32, // rad
-    pub znear: f32,
-    pub zfar: f32,
+    pub projection: Projection,
 
     pub controller: FlyingCamera,
+    pub animator: CameraAnimator,
 }
 
 impl MyCamera {
     pub fn new(size: PhysicalSize<u32>) -> Self {
         Self {
-            aspect: size.width as f32 / size.height as f32,
-            fovy: 45.0_f32.to_radians(),
-            znear: 0.1,
-            zfar: 380.0,
+            projection: Projection::Perspective {
+                fovy: 45.0_f32.to_radians(),
+                aspect: size.width as f32 / size.height as f32,
+                znear: 0.1,
+                zfar: 380.0,
+            },
 
             controller: FlyingCamera::default(),
+            animator: CameraAnimator::default(),
         }
     }
 
+    /// Switches projection, keeping the aspect ratio and depth range
+    pub fn set_orthographic(&mut self, orthographic: bool) {
+        self.projection = match (self.projection, orthographic) {
+            (
+                Projection::Perspective {
+                    aspect,
+                    znear,
+                    zfar,
+                    ..
+                },
+                true,
+            ) => Projection::Orthographic {
+                height: 64.0,
+                aspect,
+                znear,
+                zfar,
+            },
+            (
+                Projection::Orthographic {
+                    aspect,
+                    znear,
+                    zfar,
+                    ..
+                },
+                false,
+            ) => Projection::Perspective {
+                fovy: 45.0_f32.to_radians(),
+                aspect,
+                znear,
+                zfar,
+            },
+            (projection, _) => projection,
+        };
+    }
+
     pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
         self.controller.handle_event(event)
     }
 
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
-        self.aspect = size.width as f32 / size.height as f32;
+        self.projection
+            .set_aspect(size.width as f32 / size.height as f32);
     }
 
     pub fn update(&mut self, dt: Duration) {
-        self.controller.update(dt);
+        match self.animator.update(dt) {
+            Some(transform) => self.controller.transform = transform,
+            None => self.controller.update(dt),
+        }
+    }
+
+    /// Top-down view centered on the camera, for a square viewport
+    pub fn minimap(&self) -> Camera {
+        let position = self.controller.transform.w_axis.truncate();
+
+        Camera {
+            view: glam::Mat4::look_at_rh(
+                position + glam::Vec3::Y * 50.0,
+                position,
+                glam::Vec3::NEG_Z,
+            ),
+            proj: glam::Mat4::orthographic_rh(-20.0, 20.0, -20.0, 20.0, 0.1, 100.0),
+        }
     }
 }
 
@@ -41,7 +148,271 @@ impl From<&MyCamera> for Camera {
     fn from(camera: &MyCamera) -> Camera {
         Camera {
             view: camera.controller.transform.inverse(),
-            proj: glam::Mat4::perspective_rh(camera.fovy, camera.aspect, camera.znear, camera.zfar),
+            proj: camera.projection.matrix(),
+        }
+    }
+}
+
+pub use animator::*;
+mod animator {
+    use std::time::Duration;
+
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub enum Easing {
+        #[default]
+        Linear,
+        EaseInOut,
+    }
+
+    impl Easing {
+        fn apply(&self, t: f32) -> f32 {
+            match self {
+                Self::Linear => t,
+                Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Waypoint {
+        pub position: glam::Vec3,
+        pub rotation: glam::Quat,
+        /// Time to reach the next waypoint, the last waypoint of a non
+        /// looping path is held for that long
+        pub duration: f32,
+        pub easing: Easing,
+    }
+
+    impl Waypoint {
+        pub fn look_at(eye: glam::Vec3, target: glam::Vec3, duration: f32) -> Self {
+            let transform = glam::Mat4::look_at_rh(eye, target, glam::Vec3::Y).inverse();
+
+            Self {
+                position: eye,
+                rotation: glam::Quat::from_mat4(&transform),
+                duration,
+                easing: Easing::default(),
+            }
+        }
+    }
+
+    /// Moves the camera along a path of waypoints, positions follow a
+    /// Catmull-Rom spline and rotations are slerped.
+    #[derive(Debug, Default)]
+    pub struct CameraAnimator {
+        waypoints: Vec<Waypoint>,
+        looping: bool,
+
+        segment: usize,
+        time: f32,
+        playing: bool,
+        finished: bool,
+    }
+
+    impl CameraAnimator {
+        pub fn play(&mut self, waypoints: Vec<Waypoint>, looping: bool) {
+            *self = Self {
+                playing: !waypoints.is_empty(),
+                waypoints,
+                looping,
+                ..Default::default()
+            };
+        }
+
+        pub fn pause(&mut self) {
+            self.playing = false;
+        }
+
+        pub fn resume(&mut self) {
+            self.playing = !self.finished && !self.waypoints.is_empty();
+        }
+
+        pub fn is_playing(&self) -> bool {
+            self.playing
+        }
+
+        /// Set once a non looping path reached its end
+        pub fn is_finished(&self) -> bool {
+            self.finished
+        }
+
+        /// Advances the path, returns the camera transform while playing
+        pub fn update(&mut self, dt: Duration) -> Option<glam::Mat4> {
+            if !self.playing {
+                return None;
+            }
+
+            let len = self.waypoints.len();
+
+            self.time += dt.as_secs_f32();
+            while self.time >= self.waypoints[self.segment].duration {
+                let is_last = self.segment + 1 == len;
+                if is_last && !self.looping {
+                    self.playing = false;
+                    self.finished = true;
+                    return Some(self.transform(self.segment, 0.0));
+                }
+
+                self.time -= self.waypoints[self.segment].duration;
+                self.segment = (self.segment + 1) % len;
+
+                // Zero duration looping paths would never end
+                if self
+                    .waypoints
+                    .iter()
+                    .all(|waypoint| waypoint.duration <= 0.0)
+                {
+                    self.time = 0.0;
+                    break;
+                }
+            }
+
+            let waypoint = &self.waypoints[self.segment];
+            let holding = self.segment + 1 == len && !self.looping;
+            let t = if waypoint.duration > 0.0 && !holding {
+                waypoint.easing.apply(self.time / waypoint.duration)
+            } else {
+                0.0
+            };
+
+            Some(self.transform(self.segment, t))
+        }
+
+        fn waypoint(&self, index: isize) -> &Waypoint {
+            let len = self.waypoints.len() as isize;
+            let index = if self.looping {
+                index.rem_euclid(len)
+            } else {
+                index.clamp(0, len - 1)
+            };
+
+            &self.waypoints[index as usize]
+        }
+
+        fn transform(&self, segment: usize, t: f32) -> glam::Mat4 {
+            let i = segment as isize;
+            let (p0, p1, p2, p3) = (
+                self.waypoint(i - 1),
+                self.waypoint(i),
+                self.waypoint(i + 1),
+                self.waypoint(i + 2),
+            );
+
+            let position = catmull_rom(p0.position, p1.position, p2.position, p3.position, t);
+            let rotation = p1.rotation.slerp(p2.rotation, t);
+
+            glam::Mat4::from_rotation_translation(rotation, position)
+        }
+    }
+
+    fn catmull_rom(
+        p0: glam::Vec3,
+        p1: glam::Vec3,
+        p2: glam::Vec3,
+        p3: glam::Vec3,
+        t: f32,
+    ) -> glam::Vec3 {
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        0.5 * ((2.0 * p1)
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn waypoint(x: f32, duration: f32) -> Waypoint {
+            Waypoint {
+                position: glam::vec3(x, 0.0, 0.0),
+                rotation: glam::Quat::IDENTITY,
+                duration,
+                easing: Easing::Linear,
+            }
+        }
+
+        fn position(animator: &mut CameraAnimator, dt: f32) -> Option<f32> {
+            animator
+                .update(Duration::from_secs_f32(dt))
+                .map(|transform| transform.w_axis.x)
+        }
+
+        #[test]
+        fn keyframe_boundaries() {
+            let mut animator = CameraAnimator::default();
+            animator.play(vec![waypoint(0.0, 1.0), waypoint(10.0, 1.0)], false);
+
+            assert_eq!(position(&mut animator, 0.0), Some(0.0));
+
+            let halfway = position(&mut animator, 0.5).unwrap();
+            assert!(halfway > 0.0 && halfway < 10.0, "{halfway}");
+
+            // Exactly on the next waypoint
+            assert_eq!(position(&mut animator, 0.5), Some(10.0));
+            assert!(animator.is_playing());
+        }
+
+        #[test]
+        fn holds_last_waypoint() {
+            let mut animator = CameraAnimator::default();
+            animator.play(vec![waypoint(0.0, 1.0), waypoint(10.0, 2.0)], false);
+
+            // Held for the last waypoint duration, then finished on it
+            assert_eq!(position(&mut animator, 1.5), Some(10.0));
+            assert_eq!(position(&mut animator, 1.0), Some(10.0));
+            assert!(animator.is_playing());
+            assert_eq!(position(&mut animator, 1.0), Some(10.0));
+            assert!(animator.is_finished());
+
+            assert_eq!(position(&mut animator, 1.0), None);
+
+            // Finished paths can't be resumed
+            animator.resume();
+            assert!(!animator.is_playing());
+        }
+
+        #[test]
+        fn past_the_end() {
+            let mut animator = CameraAnimator::default();
+            animator.play(vec![waypoint(0.0, 1.0), waypoint(10.0, 1.0)], false);
+
+            assert_eq!(position(&mut animator, 100.0), Some(10.0));
+            assert!(animator.is_finished());
+            assert!(!animator.is_playing());
+        }
+
+        #[test]
+        fn loop_wrap() {
+            let waypoints = vec![waypoint(0.0, 1.0), waypoint(10.0, 1.0)];
+
+            let mut animator = CameraAnimator::default();
+            animator.play(waypoints.clone(), true);
+            let first_lap = position(&mut animator, 0.25).unwrap();
+
+            // Last waypoint leads back to the first one
+            assert_eq!(position(&mut animator, 0.75), Some(10.0));
+            let back = position(&mut animator, 0.5).unwrap();
+            assert!(back > 0.0 && back < 10.0, "{back}");
+
+            assert_eq!(position(&mut animator, 0.5), Some(0.0));
+
+            let mut animator = CameraAnimator::default();
+            animator.play(waypoints, true);
+            let second_lap = position(&mut animator, 2.25).unwrap();
+            assert!((first_lap - second_lap).abs() < 1e-4);
+            assert!(!animator.is_finished());
+        }
+
+        #[test]
+        fn zero_duration_loop() {
+            let mut animator = CameraAnimator::default();
+            animator.play(vec![waypoint(0.0, 0.0), waypoint(10.0, 0.0)], true);
+
+            assert!(position(&mut animator, 1.0).is_some());
+            assert!(animator.is_playing());
         }
     }
 }
@@ -2,14 +2,16 @@
 
 use anyhow::Result;
 use calva::{
-    gltf::GltfModel,
+    gltf::{GltfModel, GltfOptions},
     renderer::{
         egui::{self},
-        CameraManager, EguiWinitPass, Engine, InstancesManager, LightsManager, Renderer,
-        SkyboxManager,
+        wgpu, AnimationPlayback, AntiAliasing, Camera, CameraManager, DepthConfig,
+        DirectionalLightUniform, EguiWinitPass, Engine, Instance, InstancesManager, LightsManager,
+        MeshesManager, NormalsDebug, RenderError, Renderer, SkeletonDebug, SkyboxManager,
+        TexturesManager, TimeOfDay, Viewport,
     },
 };
-use std::time::Instant;
+use std::{sync::Arc, time::Instant};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
@@ -18,8 +20,6 @@ use winit::{
 
 mod camera;
 mod worldgen;
-// mod navmesh;
-// mod fog;
 
 #[async_std::main]
 async fn main() -> Result<()> {
@@ -36,43 +36,67 @@ async fn main() -> Result<()> {
     .inverse();
 
     let mut renderer: Renderer = Renderer::new(&window, window.inner_size().into()).await?;
-    let mut engine = Engine::new(&renderer);
+    // The camera far plane is far enough for z-fighting on distant walls
+    let depth = DepthConfig::reversed_z(&renderer.device);
+    let mut engine = Engine::builder().depth(depth).build(&renderer);
 
     engine.ambient_light.config.color = [0.106535, 0.061572, 0.037324];
     engine.ambient_light.config.strength = 0.1;
 
-    engine
-        .ressources
-        .get::<SkyboxManager>()
-        .get_mut()
-        .set_skybox(
-            &renderer.device,
-            &renderer.queue,
-            &[
-                "./demo/assets/sky/right.jpg",
-                "./demo/assets/sky/left.jpg",
-                "./demo/assets/sky/top.jpg",
-                "./demo/assets/sky/bottom.jpg",
-                "./demo/assets/sky/front.jpg",
-                "./demo/assets/sky/back.jpg",
-            ]
-            .iter()
-            .try_fold(vec![], |mut bytes, filepath| {
-                let image = image::open(filepath)?;
-                bytes.append(&mut image.to_rgba8().to_vec());
-                Ok::<_, image::ImageError>(bytes)
-            })?,
-        );
+    {
+        let skybox = engine.resources.get::<SkyboxManager>();
+        let mut skybox = skybox.get_mut();
+
+        let pixels = [
+            "./demo/assets/sky/right.jpg",
+            "./demo/assets/sky/left.jpg",
+            "./demo/assets/sky/top.jpg",
+            "./demo/assets/sky/bottom.jpg",
+            "./demo/assets/sky/front.jpg",
+            "./demo/assets/sky/back.jpg",
+        ]
+        .iter()
+        .try_fold(vec![], |mut bytes, filepath| {
+            let image = image::open(filepath)?;
+            bytes.append(&mut image.to_rgba8().to_vec());
+            Ok::<_, image::ImageError>(bytes)
+        });
+
+        match pixels {
+            Ok(pixels) => skybox.set_skybox(&renderer.device, &renderer.queue, &pixels),
+            // Missing sky images are not worth failing over
+            Err(e) => {
+                eprintln!("{e:?}");
+                skybox.set_procedural_sky(&renderer.device, &renderer.queue, Default::default());
+            }
+        }
+    }
 
-    let mut egui = EguiWinitPass::new(&renderer.device, &renderer.surface_config, &event_loop);
+    // Overlays are drawn aliased when 4x MSAA is not supported
+    renderer.set_sample_count(4, depth).ok();
+    let mut egui = EguiWinitPass::new(
+        &renderer.device,
+        &renderer.surface_config,
+        renderer.sample_count(),
+        &event_loop,
+    );
 
     use std::io::Read;
     let mut dungeon_buffer = Vec::new();
     std::fs::File::open("./demo/assets/dungeon.glb")?.read_to_end(&mut dungeon_buffer)?;
     let (doc, buffers, images) = gltf::import_slice(&dungeon_buffer)?;
-    let dungeon = GltfModel::new(&renderer, &mut engine, doc, &buffers, &images)?;
-
-    let tile_builder = worldgen::tile::TileBuilder::new(&renderer.device);
+    let dungeon = Arc::new(GltfModel::new(
+        &renderer,
+        &mut engine,
+        doc,
+        &buffers,
+        &images,
+    )?);
+
+    let tile_builder = worldgen::tile::TileBuilder::new(
+        &renderer.device,
+        worldgen::tile::Tile::DEFAULT_WORLD_SIZE,
+    );
 
     let tiles = [
         "module01", "module03", "module07", "module08", "module09", "module10", "module11",
@@ -94,50 +118,48 @@ async fn main() -> Result<()> {
     let navmesh = worldgen::navmesh::NavMesh::new(tile);
     let mut navmesh_debug = worldgen::navmesh::NavMeshDebug::new(
         &renderer.device,
-        &engine.ressources.get::<CameraManager>().get(),
+        &engine.resources.get::<CameraManager>().get(),
         &navmesh,
         renderer.surface_config.format,
+        renderer.sample_count(),
         worldgen::navmesh::NavMeshDebugInput {
             depth: &engine.geometry.outputs.depth,
         },
     );
 
     {
-        let (instances, point_lights) =
+        let (instances, mut point_lights) =
             dungeon.node_instances(dungeon.doc.nodes().nth(tile.node_id).unwrap(), None, None);
+        for point_light in &mut point_lights {
+            point_light.cast_shadows = true;
+        }
+        engine.resources.get::<InstancesManager>().get_mut().add(
+            &renderer.device,
+            &renderer.queue,
+            instances,
+        );
         engine
-            .ressources
-            .get::<InstancesManager>()
-            .get_mut()
-            .add(&renderer.queue, instances);
-        engine
-            .ressources
+            .resources
             .get::<LightsManager>()
             .get_mut()
             .add_point_lights(&renderer.queue, &point_lights);
     }
 
-    // let worldgen = worldgen::WorldGenerator::new(
-    //     "Calva!533d", // rand::random::<u32>(),
-    //     &tiles,
-    // );
-
-    // const DIM: i32 = 3;
-    // for x in -DIM..=DIM {
-    //     for y in -DIM..=DIM {
-    //         let res = worldgen.chunk(&dungeon, glam::ivec2(x, y));
-    //         engine
-    //             .ressources
-    //             .get::<InstancesManager>()
-    //             .get_mut()
-    //             .add(&renderer.queue, res.0);
-    //         engine
-    //             .ressources
-    //             .get::<LightsManager>()
-    //             .get_mut()
-    //             .add_point_lights(&renderer.queue, &res.1);
-    //     }
-    // }
+    // Procedural dungeon around the tile, generated in the background once
+    // enabled from the UI
+    let chunk_loader = {
+        let dungeon = dungeon.clone();
+        worldgen::loader::ChunkLoader::new(
+            Arc::new(worldgen::WorldGenerator::new(
+                "Calva!533d", // rand::random::<u32>(),
+                &tiles,
+            )),
+            2,
+            move |chunk| chunk.instances(&dungeon),
+        )
+    };
+    let mut worldgen_enabled = false;
+    let mut worldgen_requested = false;
 
     let ennemies = [
         "./demo/assets/zombies/zombie-boss.glb",
@@ -159,7 +181,7 @@ async fn main() -> Result<()> {
     .iter()
     .take(0)
     .map(|s| GltfModel::from_path(&renderer, &mut engine, s))
-    .collect::<Result<Vec<_>>>()?;
+    .collect::<Result<Vec<_>, _>>()?;
 
     let mut instances = vec![];
     for (z, ennemy) in ennemies.iter().enumerate() {
@@ -180,15 +202,58 @@ async fn main() -> Result<()> {
             }
         }
     }
-    engine
-        .ressources
-        .get::<InstancesManager>()
-        .get_mut()
-        .add(&renderer.queue, instances);
+    engine.resources.get::<InstancesManager>().get_mut().add(
+        &renderer.device,
+        &renderer.queue,
+        instances,
+    );
 
-    // let fog = fog::FogPass::new(&renderer, &engine.camera);
+    // Walks forward, moved by the motion extracted from its walk animation
+    let walker = {
+        let (doc, buffers, images) = gltf::import("./demo/assets/zombies/zombie-common.glb")?;
+        let options = GltfOptions {
+            root_motion_node: Some("Hips".into()),
+            ..Default::default()
+        };
+        GltfModel::new_with_options(&renderer, &mut engine, doc, &buffers, &images, options)?
+    };
+    let walk = walker
+        .animations
+        .keys()
+        .find(|name| name.to_lowercase().contains("walk"))
+        .and_then(|name| Some((walker.animations[name], walker.root_motions.get(name)?)))
+        .map(|(animation, root_motion)| (animation, root_motion.clone()));
+    let idle = walker
+        .animations
+        .iter()
+        .find(|(name, _)| name.to_lowercase().contains("idle"))
+        .map(|(_, animation)| *animation);
+
+    let mut walker_transform = glam::Mat4::from_translation(glam::vec3(0.0, 8.0, 0.0));
+    let mut walker_time = 0.0;
+    let mut walker_walking = true;
+    let walker_instances = walker
+        .scene_instances(None, None, walk.as_ref().map(|(animation, _)| *animation))
+        .map(|(instances, _)| instances)
+        .unwrap_or_default();
+    let walker_ids = engine.resources.get::<InstancesManager>().get_mut().add(
+        &renderer.device,
+        &renderer.queue,
+        walker_instances.iter().map(|instance| Instance {
+            transform: walker_transform * instance.transform,
+            ..*instance
+        }),
+    );
 
     let mut kb_modifiers = ModifiersState::empty();
+    let mut screenshot = None;
+    let mut minimap = false;
+    let mut walker_skeleton = false;
+    let mut walker_normals = false;
+    let mut normals_debug = NormalsDebug::default();
+    let mut navmesh_overlay = false;
+    let mut time_of_day_enabled = false;
+    let mut time_of_day = TimeOfDay::default();
     // let time = Instant::now();
     let mut render_time = Instant::now();
     event_loop.run(move |event, _, control_flow| {
@@ -225,13 +290,131 @@ async fn main() -> Result<()> {
                             ui.add(&renderer);
                             ui.add(&*renderer.profiler.try_borrow().unwrap());
 
+                            let mut orthographic = matches!(
+                                camera.projection,
+                                camera::Projection::Orthographic { .. }
+                            );
+                            if ui
+                                .checkbox(&mut orthographic, "Orthographic camera")
+                                .changed()
+                            {
+                                camera.set_orthographic(orthographic);
+                            }
+
+                            ui.checkbox(&mut minimap, "Minimap");
+                            ui.add_enabled(
+                                walker.skeleton.is_some(),
+                                egui::Checkbox::new(&mut walker_skeleton, "Walker skeleton"),
+                            );
+                            if let Some(instance) = walker_instances.first() {
+                                ui.checkbox(&mut walker_normals, "Walker normals");
+                                if walker_normals {
+                                    let invalid_tangents = engine
+                                        .resources
+                                        .get::<MeshesManager>()
+                                        .get()
+                                        .vertices(instance.mesh)
+                                        .invalid_tangents;
+                                    ui.label(format!("Invalid tangents: {invalid_tangents}"));
+                                    ui.add(&mut normals_debug);
+                                }
+                            }
+                            ui.checkbox(&mut navmesh_overlay, "Navmesh (N)");
+
+                            ui.horizontal(|ui| {
+                                ui.add_enabled(
+                                    !worldgen_requested,
+                                    egui::Checkbox::new(&mut worldgen_enabled, "World generation"),
+                                );
+                                let pending = chunk_loader.pending();
+                                if pending > 0 {
+                                    ui.label(format!("{pending} chunks pending"));
+                                }
+                            });
+
+                            ui.horizontal(|ui| {
+                                if ui.button("Flythrough").clicked() {
+                                    camera.animator.play(flythrough(), false);
+                                }
+                                if camera.animator.is_playing() {
+                                    if ui.button("Pause").clicked() {
+                                        camera.animator.pause();
+                                    }
+                                } else if camera.animator.is_finished() {
+                                    ui.label("Done");
+                                } else if ui.button("Resume").clicked() {
+                                    camera.animator.resume();
+                                }
+                            });
+
+                            ui.add_enabled(
+                                engine.geometry.wireframe_supported(),
+                                egui::Checkbox::new(&mut engine.wireframe_enabled, "Wireframe"),
+                            );
+
+                            let textures = engine.resources.get::<TexturesManager>();
+                            let mut anisotropy = textures.get().anisotropy().ilog2();
+                            if ui
+                                .add_enabled(
+                                    renderer.anisotropy_supported(),
+                                    egui::Slider::new(&mut anisotropy, 0..=4)
+                                        .custom_formatter(|n, _| format!("{}x", 1 << n as u32))
+                                        .text("Anisotropic filtering"),
+                                )
+                                .changed()
+                            {
+                                textures
+                                    .get_mut()
+                                    .set_anisotropy(&renderer.device, 1 << anisotropy);
+                            }
+
+                            ui.add(&mut *engine.debug_blit.config);
+                            ui.add(&mut *engine.geometry.config);
                             ui.add(&mut *engine.ambient_light.config);
-                            ui.add(&mut *engine.ssao.config);
+                            ui.checkbox(&mut engine.point_lights_enabled, "Point lights");
+                            ui.checkbox(&mut engine.shadows_enabled, "Shadows");
+                            ui.add(&mut *engine.point_lights.config);
+                            if let Some(ssao) = &mut engine.ssao {
+                                ui.checkbox(&mut engine.ssao_enabled, "SSAO");
+                                ui.add(&mut *ssao.config);
+                            }
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut engine.skybox_enabled, "Skybox");
+                                egui::color_picker::color_edit_button_rgb(
+                                    ui,
+                                    &mut engine.geometry.clear_color,
+                                );
+                                ui.label("Background");
+                            });
+                            ui.add(&mut *engine.ssr.config);
+                            ui.checkbox(&mut engine.fog_enabled, "Fog");
+                            ui.add(&mut *engine.fog.config);
+                            ui.add(&mut *engine.bloom.config);
+                            ui.add(&mut engine.anti_aliasing);
+                            match engine.anti_aliasing {
+                                AntiAliasing::Off => {}
+                                AntiAliasing::Fxaa => {
+                                    ui.add(&mut *engine.fxaa.config);
+                                }
+                                AntiAliasing::Taa => {
+                                    ui.add(&mut *engine.taa.config);
+                                }
+                            }
+                            ui.checkbox(&mut engine.dof_enabled, "Depth of field");
+                            ui.add(&mut *engine.dof.config);
+                            ui.add(&mut *engine.auto_exposure.config);
                             ui.add(&mut *engine.tone_mapping.config);
+                            ui.checkbox(&mut engine.screen_effects_enabled, "Screen effects");
+                            ui.add(&mut *engine.screen_effects.config);
+
+                            // Overrides the directional light settings below
+                            ui.checkbox(&mut time_of_day_enabled, "Animate sun");
+                            ui.add(&mut time_of_day);
 
                             egui::CollapsingHeader::new("Directional light")
                                 .default_open(true)
                                 .show(ui, |ui| {
+                                    ui.checkbox(&mut engine.directional_light_enabled, "Enabled");
                                     ui.horizontal(|ui| {
                                         egui::color_picker::color_edit_button_rgb(
                                             ui,
@@ -277,30 +460,211 @@ async fn main() -> Result<()> {
                                             .text("Z"),
                                         );
                                     });
+
+                                    ui.checkbox(
+                                        &mut engine.directional_light.uniform.occlusion_culling,
+                                        "Occlusion culling",
+                                    );
+
+                                    ui.add(
+                                        egui::Slider::new(
+                                            &mut engine.directional_light.uniform.shadow_softness,
+                                            0.0..=DirectionalLightUniform::MAX_SHADOW_SOFTNESS,
+                                        )
+                                        .text("Shadow softness"),
+                                    );
+
+                                    let shadow_size = engine.directional_light.shadow_size();
+                                    ui.label(format!("Shadow map: {shadow_size}x{shadow_size}"));
                                 });
                         });
                 });
 
-                ***engine.ressources.get::<CameraManager>().get_mut() = (&camera).into();
+                let view: Camera = (&camera).into();
+                ***engine.resources.get::<CameraManager>().get_mut() = view;
                 **engine.animate.uniform = dt;
+
+                if time_of_day_enabled {
+                    time_of_day.advance(dt);
+                    time_of_day.apply(&renderer, &mut engine);
+                }
+
+                if worldgen_enabled && !worldgen_requested {
+                    const DIM: i32 = 3;
+                    for x in -DIM..=DIM {
+                        for y in -DIM..=DIM {
+                            chunk_loader.request(glam::ivec2(x, y));
+                        }
+                    }
+                    worldgen_requested = true;
+                }
+                chunk_loader.set_focus(camera.controller.transform.w_axis.truncate());
+                for chunk in chunk_loader.loaded() {
+                    let Some((instances, point_lights)) = chunk.data else {
+                        eprintln!("Cannot generate chunk {}", chunk.coord);
+                        continue;
+                    };
+
+                    engine.resources.get::<InstancesManager>().get_mut().add(
+                        &renderer.device,
+                        &renderer.queue,
+                        instances,
+                    );
+                    engine
+                        .resources
+                        .get::<LightsManager>()
+                        .get_mut()
+                        .add_point_lights(&renderer.queue, &point_lights);
+                }
+
+                // Only walks when seen
+                let frustum = view.frustum();
+                let walker_visible = walker_instances.iter().any(|instance| {
+                    let (center, radius) = engine
+                        .resources
+                        .get::<MeshesManager>()
+                        .get()
+                        .instance_bounds(&Instance {
+                            transform: walker_transform * instance.transform,
+                            ..*instance
+                        });
+                    frustum.contains_sphere(center, radius)
+                });
+
+                // Stands idle out of view, fading between both animations
+                if let (Some((walk, _)), Some(idle)) = (walk.as_ref(), idle) {
+                    if walker_visible != walker_walking {
+                        walker_walking = walker_visible;
+
+                        // Walking resumes in sync with the root motion
+                        let (animation, start_time) = if walker_walking {
+                            (*walk, walker_time)
+                        } else {
+                            (idle, 0.0)
+                        };
+                        let playback = AnimationPlayback {
+                            start_time,
+                            ..Default::default()
+                        };
+
+                        let instances = engine.resources.get::<InstancesManager>();
+                        let mut instances = instances.get_mut();
+                        for id in &walker_ids {
+                            instances.crossfade_animation(
+                                &renderer.queue,
+                                *id,
+                                animation,
+                                playback,
+                                0.3,
+                            );
+                        }
+                    }
+                }
+
+                if let Some((_, root_motion)) = walk.as_ref().filter(|_| walker_visible) {
+                    // Mirrors the animate pass looping, the last frame blending into the first
+                    let period = root_motion.deltas.len() as f32 / root_motion.samples_per_sec;
+                    let time = (walker_time + dt.as_secs_f32()) % period;
+
+                    walker_transform *= root_motion.delta(walker_time, time);
+                    walker_time = time;
+
+                    let instances = engine.resources.get::<InstancesManager>();
+                    for (id, instance) in walker_ids.iter().zip(&walker_instances) {
+                        instances
+                            .get_mut()
+                            .set_transform(*id, walker_transform * instance.transform);
+                    }
+                }
+
+                let skinned_transform = walker_instances
+                    .first()
+                    .map_or(walker_transform, |instance| {
+                        walker_transform * instance.transform
+                    });
+                engine.skeleton_debug.update(
+                    &renderer.queue,
+                    walker
+                        .skeleton
+                        .filter(|_| walker_skeleton)
+                        .map(|skeleton| SkeletonDebug {
+                            skeleton,
+                            transform: skinned_transform,
+                            animation: walk
+                                .as_ref()
+                                .map(|(animation, _)| *animation)
+                                .unwrap_or_default(),
+                            time: walker_time,
+                            color: [1.0, 1.0, 0.0],
+                        }),
+                );
+                engine.normals_debug.update(
+                    &renderer.queue,
+                    walker_instances
+                        .first()
+                        .filter(|_| walker_normals)
+                        .map(|instance| NormalsDebug {
+                            mesh: instance.mesh,
+                            transform: skinned_transform,
+                            ..normals_debug
+                        }),
+                );
                 engine.update(&renderer);
 
                 let result = renderer.render(|ctx| {
                     engine.render(ctx);
-                    // fog.render(ctx, &engine.ressources.camera, &time);
-                    navmesh_debug.render(ctx, &engine.ressources.get::<CameraManager>().get());
+                    if minimap {
+                        let side = size.width.min(size.height) / 4;
+                        let viewport = Viewport {
+                            x: 10,
+                            y: 10,
+                            width: side,
+                            height: side,
+                        };
+                        engine.render_viewport(ctx, &camera.minimap(), viewport);
+                    }
+                    if navmesh_overlay {
+                        ctx.overlay(Some(&engine.geometry.outputs.depth), |ctx| {
+                            navmesh_debug
+                                .render(ctx, &engine.resources.get::<CameraManager>().get());
+                        });
+                    }
                     egui.render(ctx);
                 });
 
                 match result {
                     Ok(_) => {}
-                    // // Reconfigure the surface if lost
-                    // Err(wgpu::SurfaceError::Lost) => renderer.resize(0, 0),
-                    // // The system is out of memory, we should probably quit
-                    // Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                    // All other errors (Outdated, Timeout) should be resolved by the next frame
+                    // Reconfigure the surface if lost
+                    Err(RenderError::Surface(
+                        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                    )) => renderer.reconfigure(),
+                    // The system is out of memory, we should probably quit
+                    Err(RenderError::Surface(wgpu::SurfaceError::OutOfMemory)) => {
+                        eprintln!("Out of GPU memory");
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    // All other errors (Timeout) should be resolved by the next frame
                     Err(e) => eprintln!("{e:?}"),
                 }
+
+                // Saved with egui, screenshots are taken after the whole frame
+                if let Some(receiver) = &screenshot {
+                    match receiver.try_recv() {
+                        Ok(image) => {
+                            std::thread::spawn(move || {
+                                if let Err(e) = image.save("screenshot.png") {
+                                    eprintln!("{e:?}");
+                                }
+                            });
+                            screenshot = None;
+                        }
+                        Err(std::sync::mpsc::TryRecvError::Empty) => {}
+                        Err(e) => {
+                            eprintln!("{e:?}");
+                            screenshot = None;
+                        }
+                    }
+                }
             }
 
             Event::WindowEvent { ref event, .. } => {
@@ -336,6 +700,16 @@ async fn main() -> Result<()> {
                                 _ => None,
                             });
                         }
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F12),
+                            ..
+                        } => screenshot = Some(renderer.request_screenshot()),
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::N),
+                            ..
+                        } => navmesh_overlay = !navmesh_overlay,
                         _ => {}
                     },
                     _ => {}
@@ -346,3 +720,25 @@ async fn main() -> Result<()> {
         }
     });
 }
+
+/// Orbits around the dungeon tile, back to the starting point
+fn flythrough() -> Vec<camera::Waypoint> {
+    let target = glam::Vec3::Y;
+    let mut waypoints = (0..=8)
+        .map(|i| {
+            let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+            let eye = glam::vec3(angle.sin() * 12.0, 4.0, angle.cos() * 12.0);
+
+            camera::Waypoint::look_at(eye, target, 2.0)
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(last) = waypoints.last_mut() {
+        last.duration = 0.0;
+    }
+    if let Some(first) = waypoints.first_mut() {
+        first.easing = camera::Easing::EaseInOut;
+    }
+
+    waypoints
+}
@@ -0,0 +1,181 @@
+use glam::Vec3Swizzles;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+
+use super::{Chunk, WorldGenerator};
+
+/// Chunk built on a worker, with the coordinate it was requested for
+pub struct LoadedChunk<T> {
+    pub coord: glam::IVec2,
+    /// `None` when the chunk could not be generated
+    pub data: Option<T>,
+}
+
+#[derive(Default)]
+struct Queue {
+    coords: Vec<glam::IVec2>,
+    focus: glam::Vec2,
+    closed: bool,
+}
+
+/// Generates chunks on background threads so that the wave function collapse
+/// doesn't stall the frames.
+///
+/// Each generated chunk is turned into `T` on the worker, usually the
+/// instances and lights of [`Chunk::instances`], so that the main thread only
+/// has to upload them. Pending chunks nearest to the focus are built first.
+pub struct ChunkLoader<T> {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    receiver: mpsc::Receiver<LoadedChunk<T>>,
+    workers: Vec<std::thread::JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> ChunkLoader<T> {
+    pub fn new(
+        generator: Arc<WorldGenerator>,
+        workers: usize,
+        build: impl Fn(Chunk) -> T + Send + Sync + 'static,
+    ) -> Self {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (sender, receiver) = mpsc::channel();
+        let build = Arc::new(build);
+
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let generator = generator.clone();
+                let queue = queue.clone();
+                let sender = sender.clone();
+                let build = build.clone();
+
+                std::thread::spawn(move || {
+                    while let Some(coord) = Self::next(&generator, &queue) {
+                        let data = generator.generate_chunk(coord).map(&*build);
+
+                        if sender.send(LoadedChunk { coord, data }).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            queue,
+            receiver,
+            workers,
+        }
+    }
+
+    /// Queues the chunk at `coord` for generation
+    pub fn request(&self, coord: glam::IVec2) {
+        let (queue, condvar) = &*self.queue;
+
+        queue.lock().unwrap().coords.push(coord);
+        condvar.notify_one();
+    }
+
+    /// Sets the world position around which pending chunks are prioritized,
+    /// usually the camera one
+    pub fn set_focus(&self, position: glam::Vec3) {
+        self.queue.0.lock().unwrap().focus = position.xz();
+    }
+
+    /// Number of chunks requested but not generated yet
+    pub fn pending(&self) -> usize {
+        self.queue.0.lock().unwrap().coords.len()
+    }
+
+    /// Chunks generated since the last call, without blocking
+    pub fn loaded(&self) -> impl Iterator<Item = LoadedChunk<T>> + '_ {
+        self.receiver.try_iter()
+    }
+
+    /// Waits for a requested chunk nearest to the focus, `None` once the
+    /// loader is dropped
+    fn next(generator: &WorldGenerator, queue: &(Mutex<Queue>, Condvar)) -> Option<glam::IVec2> {
+        let (queue, condvar) = queue;
+
+        let mut queue = condvar
+            .wait_while(queue.lock().unwrap(), |queue| {
+                queue.coords.is_empty() && !queue.closed
+            })
+            .unwrap();
+
+        if queue.closed {
+            return None;
+        }
+
+        let focus = queue.focus;
+        let (index, _) = queue.coords.iter().enumerate().min_by(|(_, a), (_, b)| {
+            let a = generator.chunk_center(**a).distance_squared(focus);
+            let b = generator.chunk_center(**b).distance_squared(focus);
+            a.total_cmp(&b)
+        })?;
+
+        Some(queue.coords.swap_remove(index))
+    }
+}
+
+impl<T> Drop for ChunkLoader<T> {
+    fn drop(&mut self) {
+        let (queue, condvar) = &*self.queue;
+
+        queue.lock().unwrap().closed = true;
+        condvar.notify_all();
+
+        for worker in self.workers.drain(..) {
+            worker.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worldgen::tests::{generator, options};
+
+    #[test]
+    fn background_chunks() {
+        let generator = Arc::new(generator(options()));
+        let coords = [glam::ivec2(0, 0), glam::ivec2(1, 0), glam::ivec2(-1, 2)];
+
+        let loader = ChunkLoader::new(generator.clone(), 2, |chunk| chunk.to_save());
+        for coord in coords {
+            loader.request(coord);
+        }
+
+        let mut loaded = loader
+            .receiver
+            .iter()
+            .take(coords.len())
+            .collect::<Vec<_>>();
+        loaded.sort_by_key(|chunk| chunk.coord.to_array());
+
+        for (chunk, coord) in loaded.iter().zip([coords[2], coords[0], coords[1]]) {
+            let expected = generator.generate_chunk(coord).map(|chunk| chunk.to_save());
+            assert_eq!(chunk.coord, coord);
+            assert_eq!(chunk.data, expected);
+        }
+        assert_eq!(loader.pending(), 0);
+    }
+
+    #[test]
+    fn nearest_first() {
+        let generator = Arc::new(generator(options()));
+
+        // No workers, the queue is polled directly
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        {
+            let mut queue = queue.0.lock().unwrap();
+            queue.coords = vec![glam::ivec2(4, 4), glam::ivec2(-3, 0), glam::ivec2(0, 1)];
+            queue.focus = generator.chunk_center(glam::ivec2(-2, 0));
+        }
+
+        let next = || ChunkLoader::<()>::next(&generator, &queue);
+        assert_eq!(next(), Some(glam::ivec2(-3, 0)));
+        assert_eq!(next(), Some(glam::ivec2(0, 1)));
+        assert_eq!(next(), Some(glam::ivec2(4, 4)));
+
+        queue.0.lock().unwrap().closed = true;
+        assert_eq!(next(), None);
+    }
+}
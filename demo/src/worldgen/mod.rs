@@ -1,26 +1,37 @@
 use noise::NoiseFn;
 use rand::prelude::*;
 use rand_seeder::SipHasher;
-use std::{cell::RefCell, collections::BTreeSet, hash::Hash};
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::Hash,
+};
 
 use calva::{
     gltf::GltfModel,
     renderer::{Instance, PointLight},
 };
 
+pub mod loader;
 pub mod navmesh;
 pub mod tile;
 
+use navmesh::NavMesh;
 use tile::{Face, Tile};
 
 #[allow(unused)]
 pub struct WorldGenerator {
     seed: u32,
-    noise: Box<dyn NoiseFn<f64, 2>>,
+    noise: Box<dyn NoiseFn<f64, 2> + Send + Sync>,
     options: BTreeSet<SlotOption>,
+    layers: usize,
+    chunk_size: usize,
+    tile_size: f32,
 }
 
 impl WorldGenerator {
+    pub const DEFAULT_CHUNK_SIZE: usize = 3;
+
+    /// Tiles are expected to share the same [`Tile::world_size`]
     #[allow(unused)]
     pub fn new(seed: impl Hash, tiles: &[Tile]) -> Self {
         let seed = SipHasher::from(seed).into_rng().gen();
@@ -35,85 +46,212 @@ impl WorldGenerator {
         );
 
         let options = tiles.iter().flat_map(SlotOption::permutations).collect();
+        let tile_size = tiles
+            .first()
+            .map(|tile| tile.world_size)
+            .unwrap_or(Tile::DEFAULT_WORLD_SIZE);
 
         Self {
             seed,
             noise,
             options,
+            layers: 1,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            tile_size,
         }
     }
 
+    /// Chunks are `chunk_size` by `chunk_size` slots,
+    /// [`Self::DEFAULT_CHUNK_SIZE`] by default
     #[allow(unused)]
-    pub fn chunk(&self, model: &GltfModel, coord: glam::IVec2) -> (Vec<Instance>, Vec<PointLight>) {
-        let chunk = Chunk::new(self.seed, coord, self.noise.as_ref(), &self.options);
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
 
-        let mut instances = vec![];
-        let mut point_lights = vec![];
+    /// Stacks `layers` grids of slots, each [`Tile::MAX_HEIGHT`] tall, for
+    /// multi-floor dungeons. Tiles reaching above their layer, like stairs,
+    /// must be continued by the tiles placed over them.
+    ///
+    /// Layers above the ground are left empty at the chunks borders, so the
+    /// tiles set should include an empty tile.
+    #[allow(unused)]
+    pub fn with_layers(mut self, layers: usize) -> Self {
+        self.layers = layers.max(1);
+        self
+    }
+
+    /// Navmeshes of the chunk slots placed with their rotation and elevation,
+    /// to be joined with [`NavMesh::stitch`] and [`Self::tile_size`].
+    /// `navmeshes` are the tiles ones, by node id.
+    #[allow(unused)]
+    pub fn chunk_navmeshes(
+        &self,
+        coord: glam::IVec2,
+        navmeshes: &HashMap<usize, NavMesh>,
+    ) -> Vec<(glam::IVec2, NavMesh)> {
+        let Some(chunk) = self.generate_chunk(coord) else {
+            eprintln!("Cannot generate chunk {coord}");
+            return vec![];
+        };
+
+        chunk.navmeshes(navmeshes)
+    }
 
-        let offset = coord * (Chunk::SIZE as i32);
+    /// Width of the slots, in world units
+    #[allow(unused)]
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
 
-        for y in 0..Chunk::SIZE {
-            for x in 0..Chunk::SIZE {
-                let slot = chunk.grid[y][x].borrow();
+    /// Center of the chunk at `coord`, in world units on the XZ plane
+    pub fn chunk_center(&self, coord: glam::IVec2) -> glam::Vec2 {
+        let size = self.chunk_size as f32;
+        (coord.as_vec2() * size + (size - 1.0) / 2.0) * self.tile_size
+    }
 
-                if let Some(opt) = slot.options.first() {
-                    let res = model.node_instances(
-                        model.doc.nodes().nth(opt.id).unwrap(),
-                        Some(opt.transform(offset + glam::ivec2(x as _, y as _))),
-                        None,
-                    );
+    /// Collapses the chunk at `coord`, see [`Chunk::to_save`] to skip the
+    /// generation next time
+    #[allow(unused)]
+    pub fn generate_chunk(&self, coord: glam::IVec2) -> Option<Chunk> {
+        let grid = Chunk::collapse(
+            self.seed,
+            coord,
+            (self.layers, self.chunk_size),
+            self.noise.as_ref(),
+            &self.options,
+        )?;
+
+        Some(Chunk {
+            coord,
+            tile_size: self.tile_size,
+            grid,
+        })
+    }
 
-                    instances.extend(res.0);
-                    point_lights.extend(res.1);
-                };
-            }
+    /// Rebuilds a chunk saved with [`Chunk::to_save`], the saved slots must
+    /// be permutations of this generator tiles
+    #[allow(unused)]
+    pub fn load_chunk(&self, save: &ChunkSave) -> anyhow::Result<Chunk> {
+        if save.version != ChunkSave::VERSION {
+            anyhow::bail!(
+                "Unsupported chunk save version {} (expected {})",
+                save.version,
+                ChunkSave::VERSION
+            );
         }
 
-        (instances, point_lights)
+        let slot = |saved: &SlotSave| {
+            let option = self
+                .options
+                .iter()
+                .find(|opt| {
+                    opt.id == saved.id
+                        && opt.elevation == saved.elevation
+                        && opt.rotation == saved.rotation
+                })
+                .ok_or_else(|| anyhow::anyhow!("Unknown slot option {saved:?}"))?;
+
+            Ok(Slot {
+                options: [*option].into(),
+            })
+        };
+
+        let size = self.chunk_size;
+        if save.layers.is_empty()
+            || save
+                .layers
+                .iter()
+                .any(|layer| layer.len() != size || layer.iter().any(|row| row.len() != size))
+        {
+            anyhow::bail!("Chunk save size doesn't match the chunk size {size}");
+        }
+
+        let grid = save
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .iter()
+                    .map(|row| row.iter().map(slot).collect())
+                    .collect()
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Chunk {
+            coord: glam::IVec2::from_array(save.coord),
+            tile_size: self.tile_size,
+            grid,
+        })
     }
 }
 
-type ChunkGrid = [[RefCell<Slot>; Chunk::SIZE]; Chunk::SIZE];
-struct Chunk {
+/// Layers of square grids of slots, indexed by `[z][y][x]`
+type ChunkGrid = Vec<Vec<Vec<Slot>>>;
+pub struct Chunk {
+    coord: glam::IVec2,
+    tile_size: f32,
     grid: ChunkGrid,
 }
 
-impl Chunk {
-    const SIZE: usize = 3;
+/// Collapsed slots of a chunk, to be written to disk and reloaded with
+/// [`WorldGenerator::load_chunk`]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChunkSave {
+    pub version: u32,
+    pub coord: [i32; 2],
+    /// Slots indexed by `[z][y][x]`
+    pub layers: Vec<Vec<Vec<SlotSave>>>,
+}
+
+impl ChunkSave {
+    pub const VERSION: u32 = 1;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SlotSave {
+    /// Tile node id
+    pub id: usize,
+    pub elevation: u8,
+    pub rotation: u8,
+}
 
-    fn new(
+impl Chunk {
+    /// Collapses the chunk slots, the result only depends on `seed`, `coord`
+    /// and the `(layers, size)` dimensions. Returns `None` when the borders
+    /// constraints cannot be satisfied by any combination of options.
+    fn collapse(
         seed: impl Hash,
         coord: glam::IVec2,
+        (layers, size): (usize, usize),
         noise: &dyn NoiseFn<f64, 2>,
         options: &BTreeSet<SlotOption>,
-    ) -> Self {
+    ) -> Option<ChunkGrid> {
         let mut rng = SipHasher::from((seed, coord)).into_rng();
 
-        let grid = std::array::from_fn(|_| {
-            std::array::from_fn(|_| {
-                RefCell::new(Slot {
-                    options: options.clone(),
-                })
-            })
-        });
+        let slot = || Slot {
+            options: options.clone(),
+        };
+        let mut grid: ChunkGrid = vec![vec![vec![slot(); size]; size]; layers];
 
-        for face in Face::all() {
-            for i in 0..Self::SIZE {
+        for face in Face::horizontal() {
+            for i in 0..size {
                 let (x, y) = match face {
                     Face::North => (i, 0),
-                    Face::East => (Self::SIZE - 1, i),
-                    Face::South => (Self::SIZE - 1 - i, Self::SIZE - 1),
-                    Face::West => (0, Self::SIZE - 1 - i),
+                    Face::East => (size - 1, i),
+                    Face::South => (size - 1 - i, size - 1),
+                    Face::West => (0, size - 1 - i),
+                    Face::Up | Face::Down => unreachable!(),
                 };
 
-                let nx = coord.x as f64 * Self::SIZE as f64
+                let nx = coord.x as f64 * size as f64
                     + x as f64
                     + match face {
                         Face::East | Face::South => 1.0,
                         _ => 0.0,
                     };
 
-                let ny = coord.y as f64 * Self::SIZE as f64
+                let ny = coord.y as f64 * size as f64
                     + y as f64
                     + match face {
                         Face::South | Face::West => 1.0,
@@ -147,69 +285,176 @@ impl Chunk {
                 ];
                 constraint.reverse();
 
-                grid[y][x]
-                    .borrow_mut()
-                    .apply_constraints(face, &[constraint]);
+                if grid[0][y][x].apply_constraints(face, &[constraint]) {
+                    Self::propagate(&mut grid, (0, y, x));
+                }
 
-                Self::propagate(&grid, x, y);
+                for z in 1..layers {
+                    let empty = [None; SlotOption::WFC_SAMPLES];
+
+                    if grid[z][y][x].apply_constraints(face, &[empty]) {
+                        Self::propagate(&mut grid, (z, y, x));
+                    }
+                }
             }
         }
 
-        while let Some((x, y)) = Self::min_entropy_slot(&grid) {
-            let mut slot = grid[y][x].borrow_mut();
-            slot.options = [*slot.options.iter().choose(&mut rng).unwrap()].into();
-            drop(slot);
+        // Grid before each choice, with the slot and the option picked, to
+        // backtrack when a choice leads to a contradiction
+        let mut history: Vec<(ChunkGrid, (usize, usize, usize), SlotOption)> = vec![];
+
+        loop {
+            if Self::contradiction(&grid) {
+                let (previous, (z, y, x), option) = history.pop()?;
+
+                grid = previous;
+                grid[z][y][x].options.remove(&option);
+                Self::propagate(&mut grid, (z, y, x));
+
+                continue;
+            }
+
+            let Some((z, y, x)) = Self::min_entropy_slot(&grid) else {
+                break;
+            };
 
-            Self::propagate(&grid, x, y);
+            let option = *grid[z][y][x].options.iter().choose(&mut rng)?;
+            history.push((grid.clone(), (z, y, x), option));
+
+            grid[z][y][x].options = [option].into();
+            Self::propagate(&mut grid, (z, y, x));
         }
 
-        Self { grid }
+        Some(grid)
     }
 
-    fn min_entropy_slot(grid: &ChunkGrid) -> Option<(usize, usize)> {
-        (0..Self::SIZE)
-            .flat_map(|y| {
-                (0..Self::SIZE).filter_map(move |x| {
-                    let slot = grid[y][x].borrow();
-
-                    if slot.collapsed() {
-                        None
-                    } else {
-                        Some((slot.entropy(), (x, y)))
-                    }
+    #[allow(unused)]
+    pub fn to_save(&self) -> ChunkSave {
+        ChunkSave {
+            version: ChunkSave::VERSION,
+            coord: self.coord.to_array(),
+            layers: self
+                .grid
+                .iter()
+                .map(|layer| {
+                    layer
+                        .iter()
+                        .map(|row| {
+                            row.iter()
+                                .map(|slot| {
+                                    let opt = slot.options.first().unwrap();
+
+                                    SlotSave {
+                                        id: opt.id,
+                                        elevation: opt.elevation,
+                                        rotation: opt.rotation,
+                                    }
+                                })
+                                .collect()
+                        })
+                        .collect()
                 })
-            })
-            .min_by_key(|(entropy, _)| *entropy)
-            .map(|(_, coord)| coord)
-    }
-
-    fn propagate(grid: &ChunkGrid, x: usize, y: usize) {
-        for face in Face::all() {
-            let (xx, yy) = match face {
-                Face::North if y > 0 => (x, y - 1),
-                Face::East if x < Self::SIZE - 1 => (x + 1, y),
-                Face::South if y < Self::SIZE - 1 => (x, y + 1),
-                Face::West if x > 0 => (x - 1, y),
-                _ => continue,
+                .collect(),
+        }
+    }
+
+    pub fn instances(&self, model: &GltfModel) -> (Vec<Instance>, Vec<PointLight>) {
+        let mut instances = vec![];
+        let mut point_lights = vec![];
+
+        let offset = self.coord * self.size() as i32;
+
+        for (z, y, x) in Self::slots(&self.grid) {
+            let slot = &self.grid[z][y][x];
+
+            if let Some(opt) = slot.options.first() {
+                let pos = glam::ivec3(offset.x + x as i32, z as _, offset.y + y as i32);
+                let res = model.node_instances(
+                    model.doc.nodes().nth(opt.id).unwrap(),
+                    Some(opt.transform(pos, self.tile_size)),
+                    None,
+                );
+
+                instances.extend(res.0);
+                point_lights.extend(res.1);
             };
+        }
+
+        (instances, point_lights)
+    }
 
-            let slot = grid[y][x].borrow();
-            let mut neighbour = grid[yy][xx].borrow_mut();
+    /// See [`WorldGenerator::chunk_navmeshes`]
+    pub fn navmeshes(&self, navmeshes: &HashMap<usize, NavMesh>) -> Vec<(glam::IVec2, NavMesh)> {
+        let offset = self.coord * self.size() as i32;
 
-            let constraints = slot.constraints(face).collect::<Vec<_>>();
-            let has_changed = neighbour.apply_constraints(face.opposite(), &constraints);
+        Self::slots(&self.grid)
+            .filter_map(|(z, y, x)| {
+                let opt = self.grid[z][y][x].options.first()?;
+                let navmesh = navmeshes.get(&opt.id)?;
 
-            if has_changed {
-                drop(slot);
-                drop(neighbour);
+                Some((
+                    offset + glam::ivec2(x as _, y as _),
+                    navmesh.transformed(opt.transform(glam::ivec3(0, z as _, 0), self.tile_size)),
+                ))
+            })
+            .collect()
+    }
 
-                Self::propagate(grid, xx, yy);
+    /// Width of the chunk, in slots
+    pub fn size(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    /// Slots coordinates, as `(z, y, x)`, layer by layer
+    fn slots(grid: &ChunkGrid) -> impl Iterator<Item = (usize, usize, usize)> {
+        let size = grid[0].len();
+
+        (0..grid.len())
+            .flat_map(move |z| (0..size).flat_map(move |y| (0..size).map(move |x| (z, y, x))))
+    }
+
+    fn contradiction(grid: &ChunkGrid) -> bool {
+        grid.iter()
+            .flatten()
+            .flatten()
+            .any(|slot| slot.entropy() == 0)
+    }
+
+    /// Uncollapsed slot with the fewest options, ties are broken by layer,
+    /// row then column so that the collapse order is stable
+    fn min_entropy_slot(grid: &ChunkGrid) -> Option<(usize, usize, usize)> {
+        Self::slots(grid)
+            .filter(|&(z, y, x)| !grid[z][y][x].collapsed())
+            .min_by_key(|&(z, y, x)| (grid[z][y][x].entropy(), z, y, x))
+    }
+
+    fn propagate(grid: &mut ChunkGrid, slot: (usize, usize, usize)) {
+        let size = grid[0].len();
+        let mut stack = vec![slot];
+
+        while let Some((z, y, x)) = stack.pop() {
+            for face in Face::all() {
+                let (zz, yy, xx) = match face {
+                    Face::North if y > 0 => (z, y - 1, x),
+                    Face::East if x < size - 1 => (z, y, x + 1),
+                    Face::South if y < size - 1 => (z, y + 1, x),
+                    Face::West if x > 0 => (z, y, x - 1),
+                    Face::Up if z < grid.len() - 1 => (z + 1, y, x),
+                    Face::Down if z > 0 => (z - 1, y, x),
+                    _ => continue,
+                };
+
+                let slot = grid[z][y][x].clone();
+
+                if grid[zz][yy][xx].apply_neighbour(face.opposite(), &slot) {
+                    stack.push((zz, yy, xx));
+                }
             }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Slot {
     options: BTreeSet<SlotOption>,
 }
@@ -227,6 +472,33 @@ impl Slot {
         self.options.iter().map(move |opt| opt.constraint(face))
     }
 
+    /// Removes the options that cannot touch any of the `neighbour` ones by
+    /// their `face`, returns whether some were removed
+    fn apply_neighbour(&mut self, face: Face, neighbour: &Slot) -> bool {
+        match face {
+            Face::Up | Face::Down => {
+                if self.collapsed() {
+                    return false;
+                }
+
+                let prev_entropy = self.entropy();
+
+                self.options.retain(|opt| {
+                    neighbour.options.iter().any(|other| match face {
+                        Face::Up => opt.supports(other),
+                        _ => other.supports(opt),
+                    })
+                });
+
+                prev_entropy > self.entropy()
+            }
+            _ => {
+                let constraints = neighbour.constraints(face.opposite()).collect::<Vec<_>>();
+                self.apply_constraints(face, &constraints)
+            }
+        }
+    }
+
     fn apply_constraints(&mut self, face: Face, constraints: &[ModuleConstraint]) -> bool {
         if self.collapsed() {
             return false;
@@ -253,6 +525,8 @@ struct SlotOption {
     elevation: u8,
     rotation: u8,
     constraints: [ModuleConstraint; 4], // north east south west
+    /// Floor levels sampled over the tile, by rows from north to south
+    floors: [ModuleConstraint; SlotOption::WFC_SAMPLES],
 }
 
 impl Eq for SlotOption {}
@@ -291,42 +565,65 @@ impl SlotOption {
 
     const ELEVATION_MAX: usize = 4;
 
+    /// Floors in a layer of stacked slots
+    const LAYER_FLOORS: u8 = (Tile::MAX_HEIGHT / Self::FLOOR_HEIGHT) as u8;
+
     fn constraint(&self, face: Face) -> ModuleConstraint {
         match face {
             Face::North => self.constraints[0],
             Face::East => self.constraints[1],
             Face::South => self.constraints[2],
             Face::West => self.constraints[3],
+            Face::Up | Face::Down => unreachable!("Stacked slots are matched by their floors"),
         }
     }
 
+    /// Whether `above` can be stacked over `self`: where `self` reaches the
+    /// layer above, like stairs, `above` floor has to continue it
+    fn supports(&self, above: &Self) -> bool {
+        let below = self.floors.iter().flatten();
+        let above = above.floors.iter().flatten();
+
+        below.zip(above).all(|(below, above)| match below {
+            Some(level) if *level >= Self::LAYER_FLOORS => {
+                *above == Some(level - Self::LAYER_FLOORS)
+            }
+            _ => true,
+        })
+    }
+
     fn permutations(tile: &Tile) -> impl Iterator<Item = Self> + '_ {
-        fn wfc_to_world(i: usize) -> f32 {
-            const STEP: f32 = Tile::WORLD_SIZE / SlotOption::WFC_SAMPLES as f32;
-            const HALF: f32 = STEP / 2.0;
+        let wfc_to_world = |i: usize| {
+            let step = tile.world_size / Self::WFC_SAMPLES as f32;
+            i as f32 * step + step / 2.0
+        };
 
-            i as f32 * STEP + HALF
-        }
+        let floor_level = |pos: glam::Vec2| {
+            let floor_level = (tile.get_height(pos) / Self::FLOOR_HEIGHT).round();
+            u8::try_from(floor_level as i32).ok()
+        };
 
-        let mut constraints = Face::all().map(|face| {
+        let mut constraints = Face::horizontal().map(|face| {
             std::array::from_fn(|i| {
                 let reverse = |i: usize| Self::WFC_SAMPLES - 1 - i;
 
-                let height = tile.get_height(
+                floor_level(
                     match face {
                         Face::North => [wfc_to_world(i), 0.0],
-                        Face::East => [Tile::WORLD_SIZE, wfc_to_world(i)],
-                        Face::South => [wfc_to_world(reverse(i)), Tile::WORLD_SIZE],
+                        Face::East => [tile.world_size, wfc_to_world(i)],
+                        Face::South => [wfc_to_world(reverse(i)), tile.world_size],
                         Face::West => [0.0, wfc_to_world(reverse(i))],
+                        Face::Up | Face::Down => unreachable!(),
                     }
                     .into(),
-                );
-
-                let floor_level = (height / Self::FLOOR_HEIGHT).round();
-                u8::try_from(floor_level as i32).ok()
+                )
             })
         });
 
+        let mut floors: [ModuleConstraint; Self::WFC_SAMPLES] = std::array::from_fn(|z| {
+            std::array::from_fn(|x| floor_level(glam::vec2(wfc_to_world(x), wfc_to_world(z))))
+        });
+
         (0..4).flat_map(move |rotation| {
             let it = (0..=Self::ELEVATION_MAX as u8).map(move |elevation| Self {
                 id: tile.node_id,
@@ -334,6 +631,7 @@ impl SlotOption {
                 rotation,
                 constraints: constraints
                     .map(|constraint| constraint.map(|value| value.map(|i| i + elevation))),
+                floors: floors.map(|row| row.map(|value| value.map(|i| i + elevation))),
             });
 
             // Rotate faces
@@ -344,16 +642,207 @@ impl SlotOption {
                 constraints[2],
             ];
 
+            // Rotate floors the same way, the west column becomes the north row
+            floors = std::array::from_fn(|z| {
+                std::array::from_fn(|x| floors[Self::WFC_SAMPLES - 1 - x][z])
+            });
+
             it
         })
     }
 
-    fn transform(&self, pos: glam::IVec2) -> glam::Mat4 {
+    /// `pos` is the slot position, with the layer as `y`, on a grid of
+    /// `tile_size` cells
+    fn transform(&self, pos: glam::IVec3, tile_size: f32) -> glam::Mat4 {
         let quat = glam::Quat::from_rotation_y(self.rotation as f32 * -std::f32::consts::FRAC_PI_2);
 
-        let translation = glam::vec3(pos.x as f32, self.elevation as f32, pos.y as f32)
-            * glam::vec3(Tile::WORLD_SIZE, Self::FLOOR_HEIGHT, Tile::WORLD_SIZE);
+        let floor = pos.y as f32 * Self::LAYER_FLOORS as f32 + self.elevation as f32;
+        let translation = glam::vec3(pos.x as f32, floor, pos.z as f32)
+            * glam::vec3(tile_size, Self::FLOOR_HEIGHT, tile_size);
 
         glam::Mat4::from_rotation_translation(quat, translation)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(node_id: usize, height: impl Fn(usize, usize) -> f32) -> Tile {
+        Tile {
+            node_id,
+            world_size: Tile::DEFAULT_WORLD_SIZE,
+            height_map: std::array::from_fn(|y| std::array::from_fn(|x| height(x, y))),
+        }
+    }
+
+    /// Marching squares like set, enough to connect any one step elevation
+    /// changes between borders
+    pub(super) fn options() -> BTreeSet<SlotOption> {
+        const HALF: usize = Tile::TEXTURE_SIZE / 2;
+        let step = |raised: bool| {
+            if raised {
+                SlotOption::FLOOR_HEIGHT * 2.0
+            } else {
+                0.0
+            }
+        };
+
+        let tiles = [
+            tile(0, |_, _| step(false)),
+            tile(1, |_, _| step(true)),
+            tile(2, |x, _| step(x >= HALF)),
+            tile(3, |x, y| step(x >= HALF && y >= HALF)),
+            tile(4, |x, y| step(x >= HALF || y >= HALF)),
+        ];
+
+        tiles.iter().flat_map(SlotOption::permutations).collect()
+    }
+
+    fn tall_tile() -> Tile {
+        tile(5, |x, _| {
+            if x >= Tile::TEXTURE_SIZE / 2 {
+                Tile::MAX_HEIGHT
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn empty_tile() -> Tile {
+        tile(6, |_, _| -Tile::MAX_HEIGHT)
+    }
+
+    fn layout(chunk: &Chunk) -> Vec<(usize, u8, u8)> {
+        chunk
+            .grid
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|slot| {
+                assert!(slot.collapsed());
+                let opt = slot.options.first().unwrap();
+                (opt.id, opt.elevation, opt.rotation)
+            })
+            .collect()
+    }
+
+    pub(super) fn generator(options: BTreeSet<SlotOption>) -> WorldGenerator {
+        WorldGenerator {
+            options,
+            ..WorldGenerator::new(42, &[])
+        }
+    }
+
+    #[test]
+    fn deterministic_chunks() {
+        let generator = generator(options());
+
+        for coord in [glam::ivec2(0, 0), glam::ivec2(3, -2), glam::ivec2(-5, 7)] {
+            let generate = || {
+                generator
+                    .generate_chunk(coord)
+                    .expect("Cannot generate chunk")
+            };
+
+            assert_eq!(layout(&generate()), layout(&generate()));
+        }
+    }
+
+    #[test]
+    fn chunk_size() {
+        let generator = generator(options()).with_chunk_size(5);
+
+        let chunk = generator.generate_chunk(glam::ivec2(1, -2)).unwrap();
+        assert_eq!(chunk.size(), 5);
+        assert_eq!(layout(&chunk).len(), 25);
+
+        let save = chunk.to_save();
+        assert_eq!(generator.load_chunk(&save).unwrap().to_save(), save);
+
+        let generator = generator.with_chunk_size(WorldGenerator::DEFAULT_CHUNK_SIZE);
+        assert!(generator.load_chunk(&save).is_err());
+    }
+
+    #[test]
+    fn stacked_slots() {
+        let first = |tile: &Tile| SlotOption::permutations(tile).next().unwrap();
+
+        let tall = first(&tall_tile());
+        let floor = first(&tile(0, |_, _| 0.0));
+        let empty = first(&empty_tile());
+
+        assert!(tall.supports(&floor));
+        assert!(!tall.supports(&empty));
+        assert!(floor.supports(&empty));
+        assert!(empty.supports(&tall));
+
+        // Rotated a quarter turn the raised half is to the south, and has to
+        // be continued by a floor on the same side
+        let south_floor = first(&tile(7, |_, y| {
+            if y >= Tile::TEXTURE_SIZE / 2 {
+                0.0
+            } else {
+                -Tile::MAX_HEIGHT
+            }
+        }));
+        let rotated = SlotOption::permutations(&tall_tile())
+            .find(|opt| opt.rotation == 1 && opt.elevation == 0)
+            .unwrap();
+
+        assert!(!tall.supports(&south_floor));
+        assert!(rotated.supports(&south_floor));
+    }
+
+    #[test]
+    fn stacked_chunks() {
+        let mut options = options();
+        options.extend(SlotOption::permutations(&tall_tile()));
+        options.extend(SlotOption::permutations(&empty_tile()));
+        let generator = generator(options).with_layers(2);
+
+        for coord in [glam::ivec2(0, 0), glam::ivec2(3, -2), glam::ivec2(-5, 7)] {
+            let generate = || {
+                generator
+                    .generate_chunk(coord)
+                    .expect("Cannot generate chunk")
+            };
+
+            let chunk = generate();
+            assert_eq!(layout(&chunk), layout(&generate()));
+
+            for (_, y, x) in Chunk::slots(&chunk.grid).filter(|&(z, _, _)| z == 0) {
+                let below = chunk.grid[0][y][x].options.first().unwrap();
+                let above = chunk.grid[1][y][x].options.first().unwrap();
+
+                assert!(below.supports(above));
+            }
+        }
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let generator = generator(options());
+
+        let chunk = generator.generate_chunk(glam::ivec2(3, -2)).unwrap();
+        let save = chunk.to_save();
+
+        let json = serde_json::to_string(&save).unwrap();
+        let loaded = serde_json::from_str::<ChunkSave>(&json).unwrap();
+        assert_eq!(loaded, save);
+
+        let chunk = generator.load_chunk(&loaded).unwrap();
+        assert_eq!(chunk.coord, glam::ivec2(3, -2));
+        assert_eq!(chunk.to_save(), save);
+
+        let unknown = ChunkSave {
+            version: ChunkSave::VERSION + 1,
+            ..save.clone()
+        };
+        assert!(generator.load_chunk(&unknown).is_err());
+
+        let mut edited = save;
+        edited.layers[0][1][1].id = 42;
+        assert!(generator.load_chunk(&edited).is_err());
+    }
+}
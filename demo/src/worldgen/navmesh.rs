@@ -3,15 +3,27 @@ use calva::renderer::{
     CameraManager, RenderContext,
 };
 use glam::Vec3Swizzles;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
 
 use super::tile::Tile;
 
 pub struct NavMesh {
     triangles: Vec<[glam::Vec3; 3]>,
+    /// Triangle sharing the edge `(i, i + 1)` of each triangle
+    neighbors: Vec<[Option<usize>; 3]>,
 }
 
 impl NavMesh {
+    /// Highest walkable height difference between neighbor cells
+    const MAX_STEP: f32 = 0.5;
+
     pub fn new(tile: &Tile) -> Self {
+        let pixel_size = tile.pixel_size();
+        let half_size = tile.world_size / 2.0;
+
         let get_height = |x: i32, y: i32| {
             let y = y.max(0).min(Tile::TEXTURE_SIZE as i32 - 1) as usize;
             let x = x.max(0).min(Tile::TEXTURE_SIZE as i32 - 1) as usize;
@@ -23,8 +35,6 @@ impl NavMesh {
             .flat_map(|y| {
                 (0..Tile::TEXTURE_SIZE as i32)
                     .filter_map(move |x| {
-                        const MAX_STEP: f32 = 0.5;
-
                         let height = get_height(x, y);
 
                         if height < 0.0 {
@@ -35,7 +45,7 @@ impl NavMesh {
                         let r: i32 = 3;
                         for yy in -r..=r {
                             for xx in -r..=r {
-                                if (get_height(x + xx, y + yy) - height).abs() > MAX_STEP {
+                                if (get_height(x + xx, y + yy) - height).abs() > Self::MAX_STEP {
                                     continue;
                                 }
 
@@ -52,7 +62,7 @@ impl NavMesh {
                         for xx in -r..=r {
                             let a = get_height(x + xx, y);
                             let b = height; // get_height(x + xx - xx.signum(), y);
-                            if (a - b).abs() < MAX_STEP {
+                            if (a - b).abs() < Self::MAX_STEP {
                                 c += 1;
                             }
                         }
@@ -64,7 +74,7 @@ impl NavMesh {
                         for yy in -r..=r {
                             let a = get_height(x, y + yy);
                             let b = height; // get_height(x, y + yy - yy.signum());
-                            if (a - b).abs() < MAX_STEP {
+                            if (a - b).abs() < Self::MAX_STEP {
                                 c += 1;
                             }
                         }
@@ -77,10 +87,10 @@ impl NavMesh {
                         let mut bl = tl + glam::Vec2::Y;
                         let mut br = bl + glam::Vec2::X;
 
-                        tl = tl * Tile::PIXEL_SIZE - Tile::WORLD_SIZE / 2.0;
-                        tr = tr * Tile::PIXEL_SIZE - Tile::WORLD_SIZE / 2.0;
-                        bl = bl * Tile::PIXEL_SIZE - Tile::WORLD_SIZE / 2.0;
-                        br = br * Tile::PIXEL_SIZE - Tile::WORLD_SIZE / 2.0;
+                        tl = tl * pixel_size - half_size;
+                        tr = tr * pixel_size - half_size;
+                        bl = bl * pixel_size - half_size;
+                        br = br * pixel_size - half_size;
 
                         let tlh = height
                             .max(get_height(x - 1, y - 1))
@@ -99,10 +109,10 @@ impl NavMesh {
                             .max(get_height(x, y + 1))
                             .max(get_height(x + 1, y));
 
-                        let tlh = ((tlh - height).abs() < MAX_STEP).then_some(tlh);
-                        let trh = ((trh - height).abs() < MAX_STEP).then_some(trh);
-                        let blh = ((blh - height).abs() < MAX_STEP).then_some(blh);
-                        let brh = ((brh - height).abs() < MAX_STEP).then_some(brh);
+                        let tlh = ((tlh - height).abs() < Self::MAX_STEP).then_some(tlh);
+                        let trh = ((trh - height).abs() < Self::MAX_STEP).then_some(trh);
+                        let blh = ((blh - height).abs() < Self::MAX_STEP).then_some(blh);
+                        let brh = ((brh - height).abs() < Self::MAX_STEP).then_some(brh);
                         // let tlh = Some(tlh);
                         // let trh = Some(trh);
                         // let blh = Some(blh);
@@ -143,10 +153,337 @@ impl NavMesh {
             })
             .collect::<Vec<_>>();
 
-        Self { triangles }
+        Self::from_triangles(triangles)
+    }
+
+    /// Triangles are linked when they share an edge, i.e. the exact same two
+    /// vertices
+    pub fn from_triangles(triangles: Vec<[glam::Vec3; 3]>) -> Self {
+        let key = |v: glam::Vec3| v.to_array().map(f32::to_bits);
+        let edge_key = |a: glam::Vec3, b: glam::Vec3| {
+            let (a, b) = (key(a), key(b));
+            (a.min(b), a.max(b))
+        };
+
+        let mut edges = HashMap::<_, Vec<(usize, usize)>>::new();
+        for (t, triangle) in triangles.iter().enumerate() {
+            for i in 0..3 {
+                edges
+                    .entry(edge_key(triangle[i], triangle[(i + 1) % 3]))
+                    .or_default()
+                    .push((t, i));
+            }
+        }
+
+        let mut neighbors = vec![[None; 3]; triangles.len()];
+        for shared in edges.values() {
+            if let [(a, i), (b, j)] = shared[..] {
+                neighbors[a][i] = Some(b);
+                neighbors[b][j] = Some(a);
+            }
+        }
+
+        Self {
+            triangles,
+            neighbors,
+        }
+    }
+
+    pub fn transformed(&self, transform: glam::Mat4) -> Self {
+        Self::from_triangles(
+            self.triangles
+                .iter()
+                .map(|triangle| triangle.map(|v| transform.transform_point3(v)))
+                .collect(),
+        )
+    }
+
+    /// Welds the navmeshes of neighbor tiles into a single one, each navmesh
+    /// being in the frame of its tile at `coord` on a grid of `tile_size`
+    /// cells, rotation and elevation included (see [`NavMesh::transformed`]).
+    ///
+    /// Vertices at the same position on the tiles height map grid are merged
+    /// when their heights are less than a step apart, so that stairs and
+    /// ramps connect across tiles but vertical gaps don't.
+    pub fn stitch(meshes: &[(glam::IVec2, NavMesh)], tile_size: f32) -> Self {
+        let pixel_size = tile_size / Tile::TEXTURE_SIZE as f32;
+
+        let triangles = meshes
+            .iter()
+            .flat_map(|(coord, mesh)| {
+                let offset = glam::vec3(coord.x as f32, 0.0, coord.y as f32) * tile_size;
+                mesh.triangles
+                    .iter()
+                    .map(move |triangle| triangle.map(|v| v + offset))
+            })
+            .collect::<Vec<_>>();
+
+        let key = |v: glam::Vec3| v.to_array().map(f32::to_bits);
+        let grid_position = |v: glam::Vec3| (v.xz() / pixel_size).round().as_ivec2();
+
+        let mut columns = HashMap::<_, Vec<glam::Vec3>>::new();
+        for &v in triangles.iter().flatten() {
+            columns.entry(grid_position(v)).or_default().push(v);
+        }
+
+        // Each vertex is replaced by the lowest one of its run of close heights
+        let mut welded = HashMap::new();
+        for column in columns.values_mut() {
+            column.sort_by(|a, b| a.y.total_cmp(&b.y));
+
+            let mut base = column[0];
+            let mut previous = column[0];
+            for &v in column.iter() {
+                if v.y - previous.y >= Self::MAX_STEP {
+                    base = v;
+                }
+                welded.insert(key(v), base);
+                previous = v;
+            }
+        }
+
+        Self::from_triangles(
+            triangles
+                .into_iter()
+                .map(|triangle| triangle.map(|v| welded[&key(v)]))
+                .collect(),
+        )
+    }
+
+    /// Closest point on the navmesh, with the triangle it belongs to
+    pub fn nearest_point(&self, point: glam::Vec3) -> Option<(usize, glam::Vec3)> {
+        self.triangles
+            .iter()
+            .map(|&triangle| closest_point_on_triangle(point, triangle))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(point)
+                    .total_cmp(&b.distance_squared(point))
+            })
+    }
+
+    /// Shortest path between the closest navmesh points to `start` and
+    /// `goal`, `None` when they are not connected.
+    ///
+    /// The triangles corridor is found with A* then straightened by pulling
+    /// the path taut along the shared edges (simple stupid funnel algorithm).
+    pub fn find_path(&self, start: glam::Vec3, goal: glam::Vec3) -> Option<Vec<glam::Vec3>> {
+        let (start_triangle, start) = self.nearest_point(start)?;
+        let (goal_triangle, goal) = self.nearest_point(goal)?;
+
+        let corridor = self.find_corridor((start_triangle, start), (goal_triangle, goal))?;
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let edge = self.neighbors[from].iter().position(|&n| n == Some(to))?;
+
+            let a = self.triangles[from][edge];
+            let b = self.triangles[from][(edge + 1) % 3];
+
+            // Left and right as seen when walking through the edge
+            let direction = self.centroid(to) - self.centroid(from);
+            let side = |p: glam::Vec3| cross_xz(direction, p - self.centroid(from));
+            portals.push(if side(a) > side(b) { (a, b) } else { (b, a) });
+        }
+        portals.push((goal, goal));
+
+        Some(string_pull(&portals))
+    }
+
+    fn centroid(&self, triangle: usize) -> glam::Vec3 {
+        let [a, b, c] = self.triangles[triangle];
+        (a + b + c) / 3.0
+    }
+
+    /// A* over the triangles adjacency, triangles are entered at the middle of
+    /// the shared edge which is closer to the final path than their centroid
+    fn find_corridor(
+        &self,
+        (start, start_point): (usize, glam::Vec3),
+        (goal, goal_point): (usize, glam::Vec3),
+    ) -> Option<Vec<usize>> {
+        #[derive(PartialEq)]
+        struct Node {
+            cost: f32,
+            triangle: usize,
+        }
+        impl Eq for Node {}
+        impl Ord for Node {
+            // Min-heap on the estimated cost
+            fn cmp(&self, other: &Self) -> Ordering {
+                other.cost.total_cmp(&self.cost)
+            }
+        }
+        impl PartialOrd for Node {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        let mut came_from = HashMap::new();
+        // Cost to reach each triangle, with the point it is entered at
+        let mut costs = HashMap::from([(start, (0.0, start_point))]);
+        let mut open = BinaryHeap::from([Node {
+            cost: 0.0,
+            triangle: start,
+        }]);
+
+        while let Some(Node { triangle, .. }) = open.pop() {
+            if triangle == goal {
+                let mut corridor = vec![goal];
+                while let Some(&previous) = came_from.get(corridor.last()?) {
+                    corridor.push(previous);
+                }
+                corridor.reverse();
+
+                return Some(corridor);
+            }
+
+            let (cost, point) = costs[&triangle];
+            for (edge, neighbor) in self.neighbors[triangle].into_iter().enumerate() {
+                let Some(neighbor) = neighbor else {
+                    continue;
+                };
+
+                let vertices = self.triangles[triangle];
+                let entry = (vertices[edge] + vertices[(edge + 1) % 3]) / 2.0;
+                let neighbor_cost = cost + point.distance(entry);
+
+                let shorter = costs
+                    .get(&neighbor)
+                    .map(|&(c, _)| neighbor_cost < c)
+                    .unwrap_or(true);
+
+                if shorter {
+                    costs.insert(neighbor, (neighbor_cost, entry));
+                    came_from.insert(neighbor, triangle);
+                    open.push(Node {
+                        cost: neighbor_cost + entry.distance(goal_point),
+                        triangle: neighbor,
+                    });
+                }
+            }
+        }
+
+        None
     }
 }
 
+/// Signed area of the xz parallelogram, positive when `b` is on the left of `a`
+fn cross_xz(a: glam::Vec3, b: glam::Vec3) -> f32 {
+    a.x * b.z - a.z * b.x
+}
+
+/// https://digestingduck.blogspot.com/2010/03/simple-stupid-funnel-algorithm.html
+fn string_pull(portals: &[(glam::Vec3, glam::Vec3)]) -> Vec<glam::Vec3> {
+    // Positive when `c` is on the right of `a -> b`
+    let triarea2 = |a: glam::Vec3, b: glam::Vec3, c: glam::Vec3| -cross_xz(b - a, c - a);
+
+    let (mut apex, _) = portals[0];
+    let (mut left, mut right) = portals[0];
+    let (mut left_index, mut right_index) = (0, 0);
+
+    let mut path = vec![apex];
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (portal_left, portal_right) = portals[i];
+
+        // Narrow the funnel from the right, the closest of collinear points
+        // is kept as it would be the path corner
+        if apex == right || triarea2(apex, right, portal_right) < 0.0 {
+            if triarea2(apex, left, portal_right) >= 0.0 {
+                right = portal_right;
+                right_index = i;
+            } else {
+                // Right crossed over left, left is a corner of the path
+                path.push(left);
+                apex = left;
+
+                right = apex;
+                right_index = left_index;
+
+                i = left_index + 1;
+                continue;
+            }
+        }
+
+        // Narrow the funnel from the left
+        if apex == left || triarea2(apex, left, portal_left) > 0.0 {
+            if triarea2(apex, right, portal_left) <= 0.0 {
+                left = portal_left;
+                left_index = i;
+            } else {
+                // Left crossed over right, right is a corner of the path
+                path.push(right);
+                apex = right;
+
+                left = apex;
+                left_index = right_index;
+
+                i = right_index + 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    if let Some(&(goal, _)) = portals.last() {
+        if path.last() != Some(&goal) {
+            path.push(goal);
+        }
+    }
+
+    path
+}
+
+/// Real-Time Collision Detection, Christer Ericson, 5.1.5
+fn closest_point_on_triangle(p: glam::Vec3, [a, b, c]: [glam::Vec3; 3]) -> glam::Vec3 {
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
 pub struct NavMeshDebugInput<'a> {
     pub depth: &'a wgpu::Texture,
 }
@@ -165,6 +502,7 @@ impl NavMeshDebug {
         camera: &CameraManager,
         navmesh: &NavMesh,
         format: wgpu::TextureFormat,
+        sample_count: u32,
         input: NavMeshDebugInput,
     ) -> Self {
         let vertices = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -247,22 +585,25 @@ impl NavMeshDebug {
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
+            // Translucent overlay, occluded by the geometry
             primitive: wgpu::PrimitiveState {
-                polygon_mode: wgpu::PolygonMode::Line,
                 cull_mode: Some(wgpu::Face::Back),
                 ..Default::default()
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: input.depth.format(),
                 depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::LessEqual,
+                depth_compare: camera.depth().compare(wgpu::CompareFunction::LessEqual),
                 stencil: Default::default(),
                 bias: wgpu::DepthBiasState {
-                    constant: -10,
+                    constant: camera.depth().near_bias(10),
                     ..Default::default()
                 },
             }),
-            multisample: Default::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
         });
 
         Self {
@@ -290,7 +631,7 @@ impl NavMeshDebug {
                 },
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_view,
+                view: ctx.depth.unwrap_or(&self.depth_view),
                 depth_ops: None,
                 stencil_ops: None,
             }),
@@ -304,3 +645,110 @@ impl NavMeshDebug {
         rpass.draw(0..self.vertices_count, 0..1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit cells on the xz plane, two triangles each
+    fn grid(cells: &[(i32, i32)]) -> NavMesh {
+        let triangles = cells
+            .iter()
+            .flat_map(|&(x, z)| {
+                let v = |dx: i32, dz: i32| glam::vec3((x + dx) as f32, 0.0, (z + dz) as f32);
+                [[v(0, 0), v(1, 0), v(1, 1)], [v(0, 0), v(1, 1), v(0, 1)]]
+            })
+            .collect();
+
+        NavMesh::from_triangles(triangles)
+    }
+
+    fn room(x: i32) -> impl Iterator<Item = (i32, i32)> {
+        (x..x + 2).flat_map(|x| (0..3).map(move |z| (x, z)))
+    }
+
+    #[test]
+    fn path_through_doorway() {
+        // Two 2x3 rooms linked by a single cell doorway at z = 1
+        let cells = room(0)
+            .chain(room(3))
+            .chain(std::iter::once((2, 1)))
+            .collect::<Vec<_>>();
+        let navmesh = grid(&cells);
+
+        let start = glam::vec3(0.5, 0.0, 0.5);
+        let goal = glam::vec3(4.5, 0.0, 0.5);
+        let path = navmesh.find_path(start, goal).unwrap();
+
+        assert_eq!(
+            path,
+            [
+                start,
+                glam::vec3(2.0, 0.0, 1.0),
+                glam::vec3(3.0, 0.0, 1.0),
+                goal,
+            ]
+        );
+    }
+
+    #[test]
+    fn off_mesh_endpoints() {
+        let navmesh = grid(&room(0).collect::<Vec<_>>());
+
+        // Snapped to the room, straight line inside a convex room
+        let path = navmesh
+            .find_path(glam::vec3(-1.0, 2.0, 0.5), glam::vec3(1.5, 0.0, 5.0))
+            .unwrap();
+
+        assert_eq!(path, [glam::vec3(0.0, 0.0, 0.5), glam::vec3(1.5, 0.0, 3.0)]);
+    }
+
+    /// Flat tile at `height`, two triangles
+    fn tile(height: f32) -> NavMesh {
+        let half = Tile::DEFAULT_WORLD_SIZE / 2.0;
+        let v = |x: f32, z: f32| glam::vec3(x * half, height, z * half);
+
+        NavMesh::from_triangles(vec![
+            [v(-1.0, -1.0), v(1.0, -1.0), v(1.0, 1.0)],
+            [v(-1.0, -1.0), v(1.0, 1.0), v(-1.0, 1.0)],
+        ])
+    }
+
+    #[test]
+    fn stitch_tiles() {
+        let start = glam::vec3(0.0, 0.0, 0.0);
+        let goal = glam::vec3(Tile::DEFAULT_WORLD_SIZE, 0.0, 0.0);
+
+        // Rotated tiles don't land exactly on the grid
+        let rotation = glam::Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        let ramp = NavMesh::stitch(
+            &[
+                (glam::ivec2(0, 0), tile(0.0)),
+                (glam::ivec2(1, 0), tile(0.2).transformed(rotation)),
+            ],
+            Tile::DEFAULT_WORLD_SIZE,
+        );
+        let path = ramp.find_path(start, goal).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert!(path.last().unwrap().distance(goal) < 0.5);
+
+        // One floor up
+        let gap = NavMesh::stitch(
+            &[
+                (glam::ivec2(0, 0), tile(0.0)),
+                (glam::ivec2(1, 0), tile(4.0)),
+            ],
+            Tile::DEFAULT_WORLD_SIZE,
+        );
+        assert!(gap.find_path(start, goal).is_none());
+    }
+
+    #[test]
+    fn disconnected_rooms() {
+        let cells = room(0).chain(room(3)).collect::<Vec<_>>();
+        let navmesh = grid(&cells);
+
+        let path = navmesh.find_path(glam::vec3(0.5, 0.0, 0.5), glam::vec3(4.5, 0.0, 0.5));
+        assert!(path.is_none());
+    }
+}
@@ -5,13 +5,16 @@ struct Triangle(glam::Vec3, glam::Vec3, glam::Vec3);
 use calva::renderer::wgpu::{self, util::DeviceExt};
 
 pub struct TileBuilder {
+    world_size: f32,
     depth: wgpu::Texture,
     depth_view: wgpu::TextureView,
     pipeline: wgpu::RenderPipeline,
 }
 
 impl TileBuilder {
-    pub fn new(device: &wgpu::Device) -> Self {
+    /// Builds tiles spanning `world_size` units, usually
+    /// [`Tile::DEFAULT_WORLD_SIZE`]
+    pub fn new(device: &wgpu::Device, world_size: f32) -> Self {
         let depth = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("TileBuilder depth"),
             size: wgpu::Extent3d {
@@ -49,7 +52,7 @@ impl TileBuilder {
                             );
                         }}
                     "#,
-                    tile_half_size = Tile::WORLD_SIZE / 2.0,
+                    tile_half_size = world_size / 2.0,
                     tile_max_height = Tile::MAX_HEIGHT,
                 )
                 .into(),
@@ -82,6 +85,7 @@ impl TileBuilder {
         });
 
         Self {
+            world_size,
             depth,
             depth_view,
             pipeline,
@@ -253,6 +257,7 @@ impl TileBuilder {
 
         Tile {
             node_id: node.index(),
+            world_size: self.world_size,
             height_map,
         }
     }
@@ -260,20 +265,26 @@ impl TileBuilder {
 
 pub struct Tile {
     pub node_id: usize,
+    /// Width of the tile, in world units
+    pub world_size: f32,
     pub height_map: [[f32; Self::TEXTURE_SIZE]; Self::TEXTURE_SIZE],
 }
 
 impl Tile {
-    pub const WORLD_SIZE: f32 = 5.0 * 6.0;
+    pub const DEFAULT_WORLD_SIZE: f32 = 5.0 * 6.0;
 
     pub const MAX_HEIGHT: f32 = 40.0;
 
     pub const TEXTURE_SIZE: usize =
         wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize / std::mem::size_of::<u16>();
-    pub const PIXEL_SIZE: f32 = Self::WORLD_SIZE / Self::TEXTURE_SIZE as f32;
+
+    /// Width of a height map texel, in world units
+    pub fn pixel_size(&self) -> f32 {
+        self.world_size / Self::TEXTURE_SIZE as f32
+    }
 
     pub fn get_height(&self, pos: glam::Vec2) -> f32 {
-        let coord = (pos / Self::WORLD_SIZE * Self::TEXTURE_SIZE as f32).clamp(
+        let coord = (pos / self.world_size * Self::TEXTURE_SIZE as f32).clamp(
             glam::vec2(0.0, 0.0),
             glam::vec2(
                 (Self::TEXTURE_SIZE - 1) as f32,
@@ -291,10 +302,24 @@ pub enum Face {
     East,
     South,
     West,
+    Up,
+    Down,
 }
 
 impl Face {
-    pub const fn all() -> [Self; 4] {
+    pub const fn all() -> [Self; 6] {
+        [
+            Self::North,
+            Self::East,
+            Self::South,
+            Self::West,
+            Self::Up,
+            Self::Down,
+        ]
+    }
+
+    /// Faces turned by the tiles rotations, clockwise from north
+    pub const fn horizontal() -> [Self; 4] {
         [Self::North, Self::East, Self::South, Self::West]
     }
 
@@ -304,6 +329,8 @@ impl Face {
             Self::East => Self::West,
             Self::South => Self::North,
             Self::West => Self::East,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
         }
     }
 }